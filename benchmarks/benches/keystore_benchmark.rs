@@ -0,0 +1,64 @@
+#[macro_use]
+extern crate bencher;
+extern crate holochain_conductor_api;
+
+use bencher::Bencher;
+use holochain_conductor_api::{
+    key_loaders::mock_passphrase_manager,
+    keystore::{test_hash_config, Keystore, DEFAULT_SIGN_BUFFER_POOL_SIZE},
+};
+
+const SIGN_KEY: &str = "primary_keybundle:sign_key";
+
+fn keystore_with_sign_buffer_pool(size: usize) -> Keystore {
+    let (keystore, _) = Keystore::new_standalone(
+        mock_passphrase_manager("bench".to_string()),
+        test_hash_config(),
+    )
+    .unwrap();
+    keystore.set_sign_buffer_pool_size(size).unwrap();
+    keystore
+}
+
+/// signs data of the same length over and over, like entry addresses
+fn sign_same_length(b: &mut Bencher, pool_size: usize) {
+    let keystore = keystore_with_sign_buffer_pool(pool_size);
+    let address = String::from("QmeQVNGBZ7ARBbQ6zWNjpYkPzT6AnnhxAkJXAZKKSWyuY1");
+    b.iter(|| keystore.sign(SIGN_KEY, address.clone()).unwrap())
+}
+
+/// signs data of lengths that keep changing, so that pooled buffers rarely fit
+fn sign_varying_length(b: &mut Bencher, pool_size: usize) {
+    let keystore = keystore_with_sign_buffer_pool(pool_size);
+    let messages: Vec<String> = (1..=64).map(|length| "x".repeat(length * 4)).collect();
+    let mut next = 0;
+    b.iter(|| {
+        next = (next + 1) % messages.len();
+        keystore.sign(SIGN_KEY, messages[next].clone()).unwrap()
+    })
+}
+
+fn bench_sign_same_length_pooled(b: &mut Bencher) {
+    sign_same_length(b, DEFAULT_SIGN_BUFFER_POOL_SIZE)
+}
+
+fn bench_sign_same_length_unpooled(b: &mut Bencher) {
+    sign_same_length(b, 0)
+}
+
+fn bench_sign_varying_length_pooled(b: &mut Bencher) {
+    sign_varying_length(b, DEFAULT_SIGN_BUFFER_POOL_SIZE)
+}
+
+fn bench_sign_varying_length_unpooled(b: &mut Bencher) {
+    sign_varying_length(b, 0)
+}
+
+benchmark_group!(
+    benches,
+    bench_sign_same_length_pooled,
+    bench_sign_same_length_unpooled,
+    bench_sign_varying_length_pooled,
+    bench_sign_varying_length_unpooled
+);
+benchmark_main!(benches);
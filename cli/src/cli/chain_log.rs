@@ -1,31 +1,238 @@
 use crate::error::DefaultResult;
 use colored::*;
 use holochain_core::agent::{
-    chain_store::ChainStore,
+    chain_store::{ChainStore, ChainStoreIterator},
     state::{AgentState, AgentStateSnapshot},
 };
-use holochain_core_types::{chain_header::ChainHeader, entry::Entry};
-use holochain_persistence_api::cas::content::Address;
+use holochain_core_types::{
+    chain_header::ChainHeader,
+    entry::{cap_entries::CapTokenGrant, entry_type::EntryType, Entry},
+};
+use holochain_persistence_api::cas::{content::Address, storage::ContentAddressableStorage};
 use holochain_persistence_file::cas::file::FilesystemStorage;
-use std::{convert::TryFrom, fs, path::PathBuf};
+use std::{
+    convert::TryFrom,
+    fs,
+    path::PathBuf,
+    str::FromStr,
+    sync::{Arc, RwLock},
+};
 
 // TODO: use system-agnostic default path
 const DEFAULT_CHAIN_PATH: &str = "TODO";
 
-pub fn chain_log(storage_path: Option<PathBuf>, instance_id: String) -> DefaultResult<()> {
+/// Printed instead of capability tokens, which must not end up in logs: the token of a
+/// transferable grant is all it takes to exercise it.
+/// As a grant's token is its address, grant addresses get redacted as well.
+const REDACTED_TOKEN: &str = "<redacted>";
+
+/// Prints the chain entry by entry, starting `offset` entries below the latest one and
+/// stopping after `limit` entries if given
+pub fn chain_log(
+    storage_path: Option<PathBuf>,
+    instance_id: String,
+    limit: Option<usize>,
+    offset: usize,
+) -> DefaultResult<()> {
+    let (cas_path, mut chain) = open_chain(storage_path, instance_id)?;
+
+    println!(
+        "\nChain entries for '{}' (latest on top):\n",
+        cas_path.to_string_lossy()
+    );
+    chain.skip_headers(offset);
+    for item in chain.take(limit.unwrap_or(usize::max_value())) {
+        let (header, entry) = item?;
+        display_header(&header, &entry);
+    }
+
+    Ok(())
+}
+
+/// Prints only those entries of the given type, e.g. `%cap_token_grant` or an app entry type
+pub fn dump_chain(
+    storage_path: Option<PathBuf>,
+    instance_id: String,
+    entry_type_name: String,
+) -> DefaultResult<()> {
+    let entry_type = parse_entry_type(&entry_type_name)?;
+    let (cas_path, chain) = open_chain(storage_path, instance_id)?;
+
+    let mut match_count = 0;
+    for item in filter_by_entry_type(chain, &entry_type) {
+        let (header, entry) = item?;
+        if match_count == 0 {
+            println!(
+                "\nEntries of type '{}' in '{}' (latest on top):\n",
+                entry_type,
+                cas_path.to_string_lossy()
+            );
+        }
+        display_header(&header, &entry);
+        match_count += 1;
+    }
+
+    if match_count == 0 {
+        println!(
+            "\nNo entries of type '{}' found in '{}'",
+            entry_type,
+            cas_path.to_string_lossy()
+        );
+    } else {
+        println!("\n{} entries of type '{}'", match_count, entry_type);
+    }
+    Ok(())
+}
+
+/// Prints the capability grant committed at the given address in a readable form
+pub fn grant_info(
+    storage_path: Option<PathBuf>,
+    instance_id: String,
+    address: Address,
+) -> DefaultResult<()> {
+    let (_, chain) = open_chain(storage_path, instance_id)?;
+    let grant = find_grant(chain, &address)?;
+    println!("\n{}", format_grant(&grant));
+    Ok(())
+}
+
+/// Looks the grant up like core does when checking capabilities: the entry has to be
+/// on the chain and be a CapTokenGrant
+fn find_grant<I>(chain: I, address: &Address) -> DefaultResult<CapTokenGrant>
+where
+    I: Iterator<Item = DefaultResult<(ChainHeader, Entry)>>,
+{
+    for item in chain {
+        let (header, entry) = item?;
+        if header.entry_address() != address {
+            continue;
+        }
+        match entry {
+            Entry::CapTokenGrant(grant) => return Ok(grant),
+            _ => bail!(
+                "{} is not a capability grant but an entry of type '{}'",
+                address,
+                header.entry_type()
+            ),
+        }
+    }
+    bail!("No entry with address {} in the chain", address)
+}
+
+fn format_grant(grant: &CapTokenGrant) -> String {
+    let mut lines = vec![
+        "Capability grant".yellow().to_string(),
+        format!("  id:         {}", grant.id()),
+        format!("  type:       {:?}", grant.cap_type()),
+        format!("  token:      {}", REDACTED_TOKEN),
+        String::from("  functions:"),
+    ];
+    for (zome, functions) in grant.functions() {
+        lines.push(format!("    {}: {}", zome, functions.join(", ")));
+    }
+    match grant.assignees() {
+        Some(ref assignees) if !assignees.is_empty() => {
+            lines.push(String::from("  assignees:"));
+            for assignee in assignees {
+                lines.push(format!("    {}", assignee));
+            }
+        }
+        _ => lines.push(String::from("  assignees:  none")),
+    }
+    if let Some(rate_limit) = grant.rate_limit() {
+        lines.push(format!(
+            "  rate limit: {} calls per {} seconds",
+            rate_limit.max_calls, rate_limit.period_secs
+        ));
+    }
+    if let Some(policy) = grant.policy() {
+        lines.push(format!("  policy:     {}", policy));
+    }
+    lines.join("\n")
+}
+
+fn parse_entry_type(entry_type_name: &str) -> DefaultResult<EntryType> {
+    // parsing never fails, unknown names just end up as app entry types
+    let entry_type = EntryType::from_str(entry_type_name).unwrap();
+    if entry_type.is_app() && !EntryType::has_valid_app_name(entry_type_name) {
+        bail!("'{}' is not a valid entry type name", entry_type_name);
+    }
+    Ok(entry_type)
+}
+
+/// Keeps the entries of the given type, passing on errors reading the chain
+fn filter_by_entry_type<'a, I>(
+    chain: I,
+    entry_type: &'a EntryType,
+) -> impl Iterator<Item = DefaultResult<(ChainHeader, Entry)>> + 'a
+where
+    I: Iterator<Item = DefaultResult<(ChainHeader, Entry)>> + 'a,
+{
+    chain.filter(move |item| match item {
+        Ok((header, _)) => header.entry_type() == entry_type,
+        Err(_) => true,
+    })
+}
+
+/// Streams a source chain from CAS, latest entry first.
+/// Headers and entries are only read once the iterator gets to them, so printing a chain
+/// doesn't need to hold all of it in memory.
+/// Yields an error for each header whose entry is missing from CAS or can't be read.
+pub(crate) struct ChainReader {
+    headers: ChainStoreIterator,
+    cas: Arc<RwLock<dyn ContentAddressableStorage>>,
+}
+
+impl ChainReader {
+    /// Moves past the next `count` headers without reading their entries
+    pub(crate) fn skip_headers(&mut self, count: usize) {
+        for _ in 0..count {
+            if self.headers.next().is_none() {
+                return;
+            }
+        }
+    }
+
+    fn read_entry(&self, header: &ChainHeader) -> DefaultResult<Entry> {
+        let address = header.entry_address();
+        let content =
+            self.cas.read().unwrap().fetch(address)?.ok_or_else(|| {
+                format_err!("{} referenced in header but not found in CAS", address)
+            })?;
+        Entry::try_from(content)
+            .map_err(|err| format_err!("Invalid content at {}: {}", address, err))
+    }
+}
+
+impl Iterator for ChainReader {
+    type Item = DefaultResult<(ChainHeader, Entry)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let header = self.headers.next()?;
+        Some(self.read_entry(&header).map(|entry| (header, entry)))
+    }
+}
+
+/// Opens the source chain of the given instance for reading it with a [ChainReader]
+pub(crate) fn open_chain(
+    storage_path: Option<PathBuf>,
+    instance_id: String,
+) -> DefaultResult<(PathBuf, ChainReader)> {
     // let storage_path = storage_path.unwrap_or_else(|| PathBuf::from(DEFAULT_CHAIN_PATH));
     let storage_path = storage_path.ok_or(format_err!(
         "Please specify the path to CAS storage with the --path option."
     ))?;
     let cas_path = storage_path.join(instance_id).join("cas");
-    let chain_store = ChainStore::new(std::sync::Arc::new(std::sync::RwLock::new(
+    let chain_store = ChainStore::new(Arc::new(RwLock::new(
         FilesystemStorage::new(cas_path.clone()).expect("Could not create chain store".into()),
     )));
     let cas_lock = chain_store.content_storage();
-    let cas = cas_lock.read().unwrap();
 
-    let agent = cas
-        .fetch(&Address::from("AgentState"))?
+    let agent_state = cas_lock
+        .read()
+        .unwrap()
+        .fetch(&Address::from("AgentState"))?;
+    let agent = agent_state
         .ok_or("Chain does not exist or has not been initialized")
         .and_then(|snapshot_json| {
             AgentStateSnapshot::from_json_str(&snapshot_json.to_string())
@@ -43,26 +250,12 @@ pub fn chain_log(storage_path: Option<PathBuf>, instance_id: String) -> DefaultR
             )
         })?;
 
-    println!(
-        "\nChain entries for '{}' (latest on top):\n",
-        cas_path.to_string_lossy()
-    );
-    for ref header in agent.iter_chain() {
-        let content = cas
-            .fetch(header.entry_address())
-            .expect("Panic while fetching from CAS!")
-            .ok_or_else(|| {
-                println!(
-                    "{:?} referenced in header but not found in CAS!",
-                    header.entry_address(),
-                )
-            })
-            .unwrap();
-        let entry = Entry::try_from(content).expect("Invalid content");
-        display_header(&header, &entry);
-    }
+    let chain = ChainReader {
+        headers: agent.iter_chain(),
+        cas: cas_lock,
+    };
 
-    Ok(())
+    Ok((cas_path, chain))
 }
 
 pub fn chain_list(path: Option<PathBuf>) {
@@ -76,11 +269,240 @@ pub fn chain_list(path: Option<PathBuf>) {
 }
 
 fn display_header(header: &ChainHeader, entry: &Entry) {
-    println!(
-        "{} {}",
+    println!("{}", format_header(header, entry));
+}
+
+fn format_header(header: &ChainHeader, entry: &Entry) -> String {
+    let address = match entry {
+        Entry::CapTokenGrant(_) => REDACTED_TOKEN.to_string(),
+        _ => header.entry_address().to_string(),
+    };
+    format!(
+        "{} {}\n{:#?}",
         header.timestamp().to_string().bright_black(),
         // format!("{:?}", header.entry_type()).blue().bold(),
-        header.entry_address().to_string().yellow(),
-    );
-    println!("{:#?}", entry);
+        address.yellow(),
+        entry
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate tempfile;
+    use self::tempfile::tempdir;
+    use super::*;
+    use holochain_core_types::{
+        chain_header::test_provenances,
+        entry::{
+            cap_entries::{CapFunctions, CapabilityType, RateLimit},
+            entry_type::test_app_entry_type,
+            test_entry, test_entry_b, test_sys_entry,
+        },
+        time::test_iso_8601,
+    };
+    use holochain_json_api::json::{JsonString, RawString};
+    use holochain_persistence_api::cas::{
+        content::AddressableContent, storage::ContentAddressableStorage,
+    };
+
+    /// writes a chain made up of the given entries (oldest first) for instance "test-instance"
+    fn write_fixture_chain(storage_path: &PathBuf, entries: Vec<Entry>) {
+        write_partial_fixture_chain(storage_path, entries, &|_| true)
+    }
+
+    /// like `write_fixture_chain`, but only stores the entries at the indexes `stored` selects.
+    /// Their headers are all stored, so reading any other entry fails.
+    fn write_partial_fixture_chain(
+        storage_path: &PathBuf,
+        entries: Vec<Entry>,
+        stored: &dyn Fn(usize) -> bool,
+    ) {
+        let cas_path = storage_path.join("test-instance").join("cas");
+        fs::create_dir_all(&cas_path).unwrap();
+        let mut cas = FilesystemStorage::new(cas_path).unwrap();
+
+        let mut top_header: Option<ChainHeader> = None;
+        for (index, entry) in entries.into_iter().enumerate() {
+            if stored(index) {
+                cas.add(&entry).unwrap();
+            }
+            let header = ChainHeader::new(
+                &entry.entry_type(),
+                &entry.address(),
+                &test_provenances("sig"),
+                &top_header.as_ref().map(|header| header.address()),
+                &None,
+                &None,
+                &test_iso_8601(),
+            );
+            cas.add(&header).unwrap();
+            top_header = Some(header);
+        }
+        cas.add(&AgentStateSnapshot::new(top_header)).unwrap();
+    }
+
+    fn cap_grant_entry(id: &str) -> Entry {
+        let mut functions = CapFunctions::new();
+        functions.insert("zome".to_string(), vec!["some_fn".to_string()]);
+        Entry::CapTokenGrant(
+            CapTokenGrant::create(id, CapabilityType::Transferable, None, functions).unwrap(),
+        )
+    }
+
+    #[test]
+    fn filters_chain_by_entry_type() {
+        let dir = tempdir().unwrap();
+        let storage_path = dir.path().to_path_buf();
+        write_fixture_chain(
+            &storage_path,
+            vec![
+                test_sys_entry(),
+                cap_grant_entry("first"),
+                test_entry(),
+                cap_grant_entry("second"),
+                test_entry_b(),
+            ],
+        );
+
+        let entries_of_type = |entry_type_name: &str| {
+            let (_, chain) =
+                open_chain(Some(storage_path.clone()), "test-instance".into()).unwrap();
+            let entry_type = parse_entry_type(entry_type_name).unwrap();
+            filter_by_entry_type(chain, &entry_type)
+                .map(|item| item.unwrap().1)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            entries_of_type("%cap_token_grant"),
+            vec![cap_grant_entry("second"), cap_grant_entry("first")]
+        );
+        assert_eq!(
+            entries_of_type(&String::from(test_entry().entry_type())),
+            vec![test_entry()]
+        );
+        assert!(entries_of_type("%link_add").is_empty());
+        assert!(dump_chain(
+            Some(storage_path),
+            "test-instance".into(),
+            "%link_add".into()
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn prints_grant_info() {
+        let dir = tempdir().unwrap();
+        let storage_path = dir.path().to_path_buf();
+        let agent = Address::from("HcScAgent");
+        let mut functions = CapFunctions::new();
+        let blog_functions = vec!["create_post".to_string(), "get_post".to_string()];
+        functions.insert("blog".to_string(), blog_functions);
+        let grant = CapTokenGrant::create(
+            "writers",
+            CapabilityType::Assigned,
+            Some(vec![agent.clone()]),
+            functions,
+        )
+        .unwrap()
+        .with_rate_limit(RateLimit::new(10, 60));
+        let grant_entry = Entry::CapTokenGrant(grant.clone());
+        write_fixture_chain(&storage_path, vec![test_sys_entry(), grant_entry.clone()]);
+
+        let chain = || {
+            open_chain(Some(storage_path.clone()), "test-instance".into())
+                .unwrap()
+                .1
+        };
+        let address = grant_entry.address();
+        assert_eq!(find_grant(chain(), &address).unwrap(), grant);
+
+        let info = format_grant(&grant);
+        assert!(info.contains("id:         writers"));
+        assert!(info.contains("type:       Assigned"));
+        assert!(info.contains("token:      <redacted>"));
+        assert!(!info.contains(&grant.token().to_string()));
+        assert!(info.contains("blog: create_post, get_post"));
+        assert!(info.contains(&format!("    {}", agent)));
+        assert!(info.contains("rate limit: 10 calls per 60 seconds"));
+        assert!(grant_info(Some(storage_path.clone()), "test-instance".into(), address).is_ok());
+
+        // other entries and unknown addresses aren't grants
+        assert!(find_grant(chain(), &test_sys_entry().address()).is_err());
+        assert!(find_grant(chain(), &Address::from("QmMissing")).is_err());
+    }
+
+    #[test]
+    fn redacts_grant_addresses() {
+        let dir = tempdir().unwrap();
+        let storage_path = dir.path().to_path_buf();
+        write_fixture_chain(&storage_path, vec![test_entry(), cap_grant_entry("secret")]);
+
+        let (_, chain) = open_chain(Some(storage_path), "test-instance".into()).unwrap();
+        let printed: Vec<String> = chain
+            .map(|item| {
+                let (header, entry) = item.unwrap();
+                format_header(&header, &entry)
+            })
+            .collect();
+        let token = cap_grant_entry("secret").address().to_string();
+        assert!(!printed[0].contains(&token));
+        assert!(printed[0].contains("<redacted>"));
+        assert!(printed[1].contains(&test_entry().address().to_string()));
+    }
+
+    #[test]
+    fn pages_through_chain_lazily() {
+        let dir = tempdir().unwrap();
+        let storage_path = dir.path().to_path_buf();
+        let numbered_entry = |number: usize| {
+            Entry::App(
+                test_app_entry_type(),
+                JsonString::from(RawString::from(format!("entry {}", number))),
+            )
+        };
+        // with 100 entries, skipping the latest 20 and reading 10 hits entries 79 to 70
+        let entries = (0..100).map(numbered_entry).collect();
+        write_partial_fixture_chain(&storage_path, entries, &|index| index >= 70 && index < 80);
+
+        let (_, mut chain) = open_chain(Some(storage_path), "test-instance".into()).unwrap();
+        chain.skip_headers(20);
+        let window: Vec<Entry> = chain
+            .by_ref()
+            .take(10)
+            .map(|item| item.unwrap().1)
+            .collect();
+        assert_eq!(
+            window,
+            (70..80).rev().map(numbered_entry).collect::<Vec<_>>()
+        );
+
+        // entries missing from CAS are reported instead of panicking
+        let missing = numbered_entry(69).address();
+        assert_eq!(
+            chain.next().unwrap().unwrap_err().to_string(),
+            format!("{} referenced in header but not found in CAS", missing)
+        );
+        let storage_path = dir.path().to_path_buf();
+        assert!(dump_chain(
+            Some(storage_path),
+            "test-instance".into(),
+            "%link_add".into()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_entry_type_names() {
+        assert!(parse_entry_type("").is_err());
+        assert!(parse_entry_type("%not_a_sys_type").is_err());
+        assert_eq!(parse_entry_type("%agent_id").unwrap(), EntryType::AgentId);
+        assert_eq!(
+            parse_entry_type("post").unwrap(),
+            EntryType::App("post".into())
+        );
+        assert_eq!(
+            parse_entry_type("évent").unwrap(),
+            EntryType::App("évent".into())
+        );
+    }
 }
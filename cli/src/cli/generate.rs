@@ -8,13 +8,26 @@ use crate::{
 };
 use serde_json;
 use std::{
+    env,
     fs::{self, File},
     path::PathBuf,
 };
 
 pub const ZOME_CONFIG_FILE_NAME: &str = "zome.json";
 
-pub fn generate(zome_name: &PathBuf, language: &str) -> DefaultResult<()> {
+pub fn generate(
+    zome_name: &PathBuf,
+    language: &str,
+    proc_macro: bool,
+    quiet: bool,
+    target_dir: Option<PathBuf>,
+) -> DefaultResult<()> {
+    ensure!(
+        !proc_macro || language == "rust" || language == "rust-proc",
+        "--proc-macro only applies to rust zomes, not to {}",
+        language
+    );
+
     if !zome_name.exists() {
         fs::create_dir_all(&zome_name)?;
     }
@@ -43,26 +56,37 @@ pub fn generate(zome_name: &PathBuf, language: &str) -> DefaultResult<()> {
         .to_string()
         .replace("/", "_")
         .replace("zomes_", "");
+    // the zome gets built from inside its code directory, so a relative target
+    // directory has to be resolved against where hc was called from
+    let target_dir = match target_dir {
+        Some(target_dir) => Some(env::current_dir()?.join(target_dir)),
+        None => None,
+    };
+    let rust_scaffold = |macro_style| match target_dir {
+        Some(ref target_dir) => scaffold::rust::RustScaffold::with_target_dir(
+            &zome_name_string,
+            macro_style,
+            target_dir.clone(),
+        ),
+        None => scaffold::rust::RustScaffold::new(&zome_name_string, macro_style),
+    };
 
     // match against all supported languages
     match language {
-        "rust" => scaffold(
-            &scaffold::rust::RustScaffold::new(
-                &zome_name_string,
-                scaffold::rust::HdkMacroStyle::Declarative,
-            ),
+        "rust" if !proc_macro => scaffold(
+            &rust_scaffold(scaffold::rust::HdkMacroStyle::Declarative),
             code_dir,
+            quiet,
         )?,
-        "rust-proc" => scaffold(
-            &scaffold::rust::RustScaffold::new(
-                &zome_name_string,
-                scaffold::rust::HdkMacroStyle::Procedural,
-            ),
+        "rust" | "rust-proc" => scaffold(
+            &rust_scaffold(scaffold::rust::HdkMacroStyle::Procedural),
             code_dir,
+            quiet,
         )?,
         "assemblyscript" => scaffold(
             &scaffold::assemblyscript::AssemblyScriptScaffold::new(),
             code_dir,
+            quiet,
         )?,
         // TODO: supply zome name for AssemblyScriptScaffold as well
         _ => bail!("unsupported language: {}", language),
@@ -71,17 +95,19 @@ pub fn generate(zome_name: &PathBuf, language: &str) -> DefaultResult<()> {
     Ok(())
 }
 
-fn scaffold<S: Scaffold>(tooling: &S, base_path: PathBuf) -> DefaultResult<()> {
-    tooling.gen(base_path)
+fn scaffold<S: Scaffold>(tooling: &S, base_path: PathBuf, quiet: bool) -> DefaultResult<()> {
+    tooling.gen(base_path, quiet)
 }
 
 #[cfg(test)]
-// too slow!
-#[cfg(feature = "broken-tests")]
 mod tests {
-    use assert_cmd::prelude::*;
-    use std::process::Command;
-    use tempfile::{Builder, TempDir};
+    extern crate assert_cmd;
+    extern crate tempfile;
+    use self::{
+        assert_cmd::prelude::*,
+        tempfile::{Builder, TempDir},
+    };
+    use std::{fs, process::Command};
 
     const HOLOCHAIN_TEST_PREFIX: &str = "org.holochain.test";
 
@@ -93,6 +119,8 @@ mod tests {
     }
 
     #[test]
+    // too slow!
+    #[cfg(feature = "broken-tests")]
     fn can_generate_scaffolds() {
         let tmp = gen_dir();
 
@@ -118,4 +146,86 @@ mod tests {
         //   .assert()
         //   .success();
     }
+
+    #[test]
+    fn quiet_generate_produces_no_stdout() {
+        let tmp = gen_dir();
+
+        let output = Command::main_binary()
+            .unwrap()
+            .current_dir(&tmp.path())
+            .args(&["g", "zomes/bubblechat", "rust", "--quiet"])
+            .output()
+            .expect("should run");
+
+        assert!(output.status.success());
+        assert!(output.stdout.is_empty());
+        assert!(tmp.path().join("zomes/bubblechat/code/src/lib.rs").exists());
+    }
+
+    #[test]
+    fn proc_macro_flag_generates_procedural_zome() {
+        let tmp = gen_dir();
+
+        Command::main_binary()
+            .unwrap()
+            .current_dir(&tmp.path())
+            .args(&["g", "zomes/bubblechat", "rust", "--proc-macro", "--quiet"])
+            .assert()
+            .success();
+
+        let code_dir = tmp.path().join("zomes/bubblechat/code");
+        let lib_rs = fs::read_to_string(code_dir.join("src/lib.rs")).unwrap();
+        assert_eq!(lib_rs, include_str!("scaffold/rust-proc-macro/lib.rs"));
+        let cargo_toml = fs::read_to_string(code_dir.join("Cargo.toml")).unwrap();
+        assert!(cargo_toml.contains("hdk-proc-macros"));
+    }
+
+    #[test]
+    fn target_dir_flag_ends_up_in_the_build_file() {
+        let tmp = gen_dir();
+        let target_dir = tmp.path().join("shared-target");
+
+        Command::main_binary()
+            .unwrap()
+            .current_dir(&tmp.path())
+            .args(&["g", "zomes/bubblechat", "rust", "--quiet", "--target-dir"])
+            .arg(&target_dir)
+            .assert()
+            .success();
+
+        let build_file =
+            fs::read_to_string(tmp.path().join("zomes/bubblechat/code/.hcbuild")).unwrap();
+        assert!(build_file.contains(&format!("--target-dir={}", target_dir.display())));
+    }
+
+    #[test]
+    fn relative_target_dir_is_resolved_against_the_working_directory() {
+        let tmp = gen_dir();
+
+        Command::main_binary()
+            .unwrap()
+            .current_dir(&tmp.path())
+            .args(&["g", "zomes/bubblechat", "rust", "--quiet"])
+            .args(&["--target-dir", "shared-target"])
+            .assert()
+            .success();
+
+        let build_file =
+            fs::read_to_string(tmp.path().join("zomes/bubblechat/code/.hcbuild")).unwrap();
+        let target_dir = tmp.path().canonicalize().unwrap().join("shared-target");
+        assert!(build_file.contains(&format!("--target-dir={}", target_dir.display())));
+    }
+
+    #[test]
+    fn proc_macro_flag_is_rejected_for_other_languages() {
+        let tmp = gen_dir();
+
+        Command::main_binary()
+            .unwrap()
+            .current_dir(&tmp.path())
+            .args(&["g", "zomes/bubblechat", "assemblyscript", "--proc-macro"])
+            .assert()
+            .failure();
+    }
 }
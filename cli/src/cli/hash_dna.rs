@@ -1,9 +1,71 @@
-use error::DefaultResult;
+use error::{DefaultResult, InvalidDna};
+use failure::Error;
 use holochain_conductor_api::conductor::Conductor;
+use holochain_core_types::error::HolochainError;
 use holochain_persistence_api::cas::content::{Address, AddressableContent};
-use std::path::PathBuf;
+use std::{fs, path::PathBuf};
 
 pub fn hash_dna(dna_file_path: &PathBuf) -> DefaultResult<Address> {
-    let dna = Conductor::load_dna(dna_file_path)?;
+    let dna = Conductor::load_dna(dna_file_path).map_err(|err| match err {
+        HolochainError::IoError(_) => err.into(),
+        // the file could be read, so it's its contents that are wrong
+        _ => Error::from(InvalidDna(err.to_string())),
+    })?;
     Ok(dna.address())
 }
+
+/// Writes the given DNA hash to `output_path`, creating any missing parent directories
+pub fn write_dna_hash(dna_hash: &Address, output_path: &PathBuf) -> DefaultResult<()> {
+    if let Some(parent) = output_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| {
+            format_err!(
+                "Could not create directory {} for DNA hash: {}",
+                parent.display(),
+                e
+            )
+        })?;
+    }
+    fs::write(output_path, String::from(dna_hash.clone())).map_err(|e| {
+        format_err!(
+            "Could not write DNA hash to {}: {}",
+            output_path.display(),
+            e
+        )
+    })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate tempfile;
+    use self::tempfile::tempdir;
+    use super::*;
+    use holochain_core_types::dna::Dna;
+    use holochain_json_api::json::JsonString;
+
+    #[test]
+    fn write_dna_hash_to_file() {
+        let dir = tempdir().expect("Could not get tempdir");
+        let dna_path = dir.path().join("test.dna.json");
+        fs::write(&dna_path, JsonString::from(Dna::new()).to_string()).unwrap();
+
+        let dna_hash = hash_dna(&dna_path).expect("Could not hash DNA");
+        let output_path = dir.path().join("hashes").join("test.dna.hash");
+        write_dna_hash(&dna_hash, &output_path).expect("Could not write DNA hash");
+
+        let written = fs::read_to_string(&output_path).unwrap();
+        assert_eq!(Address::from(written), dna_hash);
+    }
+
+    #[test]
+    fn write_dna_hash_to_unwritable_path_fails() {
+        let dir = tempdir().expect("Could not get tempdir");
+        // a regular file can't be used as a parent directory
+        let blocker = dir.path().join("blocker");
+        fs::write(&blocker, "").unwrap();
+
+        let result = write_dna_hash(&Address::from("QmFoo"), &blocker.join("hash"));
+        let err = result.expect_err("writing below a file should fail");
+        assert!(err.to_string().contains("Could not create directory"));
+    }
+}
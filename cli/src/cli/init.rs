@@ -16,6 +16,14 @@ use std::{
     path::PathBuf,
 };
 
+/// The starter templates `hc init` can scaffold a project from:
+/// * minimal: the DNA config, an empty zomes folder and ignore files
+/// * with-tests: minimal plus a JS test suite and ops files
+/// * with-ui: with-tests plus a web page talking to the conductor
+pub const TEMPLATES: [&str; 3] = ["minimal", "with-tests", "with-ui"];
+pub const DEFAULT_TEMPLATE: &str = "with-tests";
+const UI_DIR_NAME: &str = "ui";
+
 fn create_test_file(
     test_folder_path: &PathBuf,
     test_file_name: &str,
@@ -88,7 +96,32 @@ fn setup_ops(path: &PathBuf) -> DefaultResult<()> {
     Ok(())
 }
 
-pub fn init(path: &PathBuf) -> DefaultResult<()> {
+fn setup_ui_folder(path: &PathBuf) -> DefaultResult<()> {
+    let dir_name = path.file_name().expect("directory to be a file");
+    let project_name = dir_name.to_string_lossy().to_string();
+
+    let ui_path = path.join(UI_DIR_NAME);
+    fs::create_dir_all(ui_path.clone())?;
+    create_test_file(
+        &ui_path,
+        "index.html",
+        include_str!("ui-scaffold/index.html"),
+        Some(hashmap!["<<DNA_NAME>>" =>  project_name.as_str()]),
+    )?;
+    Ok(())
+}
+
+pub fn init(path: &PathBuf, template: &str) -> DefaultResult<()> {
+    if !TEMPLATES.contains(&template) {
+        bail!(
+            "unknown template '{}', available templates are: {}",
+            template,
+            TEMPLATES.join(", ")
+        );
+    }
+    let with_tests = template != "minimal";
+    let with_ui = template == "with-ui";
+
     if !path.exists() {
         fs::create_dir_all(&path)?;
     } else {
@@ -116,15 +149,24 @@ pub fn init(path: &PathBuf) -> DefaultResult<()> {
     gitignore_file.write_all(gitignore_starter.as_bytes())?;
 
     // create a default .hcignore file with good defaults
-    let ignores = [&DIST_DIR_NAME, &TEST_DIR_NAME, "README.md"].join("\n");
+    let mut ignores = vec![DIST_DIR_NAME, TEST_DIR_NAME, "README.md"];
+    if with_ui {
+        ignores.push(UI_DIR_NAME);
+    }
     let mut hcignore_file = File::create(path.join(&IGNORE_FILE_NAME))?;
-    hcignore_file.write_all(ignores.as_bytes())?;
+    hcignore_file.write_all(ignores.join("\n").as_bytes())?;
 
-    // create a test folder with useful files
-    setup_test_folder(&path, &TEST_DIR_NAME)?;
+    if with_tests {
+        // create a test folder with useful files
+        setup_test_folder(&path, &TEST_DIR_NAME)?;
 
-    // create basic ops files
-    setup_ops(&path)?;
+        // create basic ops files
+        setup_ops(&path)?;
+    }
+
+    if with_ui {
+        setup_ui_folder(&path)?;
+    }
 
     // CLI feedback
     println!(
@@ -155,7 +197,7 @@ pub mod tests {
     fn init_test() {
         let dir = gen_dir();
         let dir_path_buf = &dir.path().to_path_buf();
-        let result = init(dir_path_buf);
+        let result = init(dir_path_buf, DEFAULT_TEMPLATE);
 
         assert!(result.is_ok());
         assert!(dir_path_buf.join("zomes").exists());
@@ -163,6 +205,47 @@ pub mod tests {
         assert!(dir_path_buf.join(IGNORE_FILE_NAME).exists());
         assert!(dir_path_buf.join(GITIGNORE_FILE_NAME).exists());
         assert!(dir_path_buf.join(TEST_DIR_NAME).exists());
+        assert!(!dir_path_buf.join(UI_DIR_NAME).exists());
+    }
+
+    #[test]
+    fn init_with_template_test() {
+        let dir = gen_dir();
+        let dir_path_buf = &dir.path().to_path_buf();
+        init(dir_path_buf, "with-ui").expect("init with-ui failed");
+
+        assert!(dir_path_buf.join(TEST_DIR_NAME).join("index.js").exists());
+        let ui_index_path = dir_path_buf.join(UI_DIR_NAME).join("index.html");
+        let ui_index = fs::read_to_string(ui_index_path).unwrap();
+        let project_name = dir_path_buf.file_name().unwrap().to_string_lossy();
+        assert!(ui_index.contains(&format!("<title>{}</title>", project_name)));
+        let ignores = fs::read_to_string(dir_path_buf.join(IGNORE_FILE_NAME)).unwrap();
+        assert!(ignores.lines().any(|line| line == UI_DIR_NAME));
+
+        let dir = gen_dir();
+        let dir_path_buf = &dir.path().to_path_buf();
+        init(dir_path_buf, "minimal").expect("init minimal failed");
+        assert!(dir_path_buf.join("app.json").exists());
+        assert!(dir_path_buf.join("zomes").exists());
+        assert!(!dir_path_buf.join(TEST_DIR_NAME).exists());
+        assert!(!dir_path_buf.join("Vagrantfile").exists());
+        assert!(!dir_path_buf.join(UI_DIR_NAME).exists());
+    }
+
+    #[test]
+    fn init_unknown_template_test() {
+        let dir = gen_dir();
+        let dir_path_buf = &dir.path().to_path_buf();
+        let message = init(dir_path_buf, "with-everything")
+            .expect_err("unknown template should fail")
+            .to_string();
+
+        assert_eq!(
+            message,
+            "unknown template 'with-everything', available templates are: \
+             minimal, with-tests, with-ui"
+        );
+        assert_eq!(fs::read_dir(dir_path_buf).unwrap().count(), 0);
     }
 
     #[test]
@@ -1,14 +1,55 @@
 use error::DefaultResult;
 use holochain_common::paths::keys_directory;
-use holochain_conductor_api::{key_loaders::mock_passphrase_manager, keystore::Keystore};
+use holochain_conductor_api::{
+    key_loaders::mock_passphrase_manager,
+    keystore::{Keystore, PRIMARY_KEYBUNDLE_ID},
+};
 use rpassword;
+use serde_json;
 use std::{
     fs::create_dir_all,
     io::{self, Write},
     path::PathBuf,
 };
 
-pub fn keygen(path: Option<PathBuf>, passphrase: Option<String>, quiet: bool) -> DefaultResult<()> {
+/// Parses a hex encoded seed as given to `hc keygen --seed`
+fn parse_hex_seed(seed: &str) -> DefaultResult<Vec<u8>> {
+    ensure!(
+        seed.len() % 2 == 0 && seed.is_ascii(),
+        "seed must be an even number of hex digits"
+    );
+    (0..seed.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&seed[i..i + 2], 16)
+                .map_err(|e| format_err!("invalid hex in seed: {}", e))
+        })
+        .collect()
+}
+
+/// The formats `hc keygen --format` can report the created keystore in
+const KEYGEN_FORMATS: &[&str] = &["human", "json"];
+
+pub fn keygen(
+    path: Option<PathBuf>,
+    passphrase: Option<String>,
+    quiet: bool,
+    seed: Option<String>,
+    format: &str,
+) -> DefaultResult<()> {
+    ensure!(
+        KEYGEN_FORMATS.contains(&format),
+        "unknown output format {}, expected one of: {}",
+        format,
+        KEYGEN_FORMATS.join(", ")
+    );
+    let json = format == "json";
+    // keep stdout parseable as a single JSON object
+    let quiet = quiet || json;
+    let seed = match seed {
+        Some(seed) => Some(parse_hex_seed(&seed)?),
+        None => None,
+    };
     let passphrase = passphrase.unwrap_or_else(|| {
         if !quiet {
             println!(
@@ -40,7 +81,15 @@ when unlocking the keybundle to use within a Holochain conductor."
     if !quiet {
         println!("Generating keystore (this will take a few moments)...");
     }
-    let (keystore, pub_key) = Keystore::new_standalone(mock_passphrase_manager(passphrase), None)?;
+    let (keystore, pub_key) = match seed {
+        Some(seed) => {
+            if !quiet {
+                println!("WARNING: keys derived from a fixed seed are insecure, use them for testing only!");
+            }
+            Keystore::new_standalone_from_seed(mock_passphrase_manager(passphrase), None, &seed)?
+        }
+        None => Keystore::new_standalone(mock_passphrase_manager(passphrase), None)?,
+    };
 
     let path = if None == path {
         let p = keys_directory();
@@ -53,7 +102,15 @@ when unlocking the keybundle to use within a Holochain conductor."
     keystore.save(path.clone())?;
     let path_str = path.to_str().unwrap();
 
-    if quiet {
+    if json {
+        let public_keys = keystore.export_public_bundle(PRIMARY_KEYBUNDLE_ID)?;
+        let report = json!({
+            "agent_address": pub_key,
+            "keystore_path": path_str,
+            "public_keys": public_keys,
+        });
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else if quiet {
         println!("{}", pub_key);
         println!("{}", path_str);
     } else {
@@ -68,23 +125,77 @@ when unlocking the keybundle to use within a Holochain conductor."
     Ok(())
 }
 
+/// Derives a new signing key from the seed `seed_id` of the keystore at `keystore_path`
+/// and saves the keystore with it, keeping all keys it had before.
+pub fn rotate_key(
+    keystore_path: PathBuf,
+    seed_id: &str,
+    passphrase: Option<String>,
+    quiet: bool,
+) -> DefaultResult<()> {
+    let passphrase = match passphrase {
+        Some(passphrase) => passphrase,
+        None => {
+            print!("Keystore passphrase: ");
+            io::stdout().flush()?;
+            rpassword::read_password()?
+        }
+    };
+    let keystore = Keystore::new_from_file(
+        keystore_path.clone(),
+        mock_passphrase_manager(passphrase),
+        None,
+    )?;
+    let rotated = keystore.rotate_signing_key(seed_id)?;
+    keystore.save(keystore_path.clone())?;
+
+    if quiet {
+        println!("{}", rotated.public_key);
+    } else {
+        println!("");
+        println!(
+            "Derived a new signing key from {} at index {}.",
+            seed_id, rotated.index
+        );
+        println!("");
+        println!("Public key: {}", rotated.public_key);
+        println!("Keystore identifier: {}", rotated.key_id);
+        println!("Keystore written to: {}", keystore_path.display());
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 pub mod test {
+    extern crate assert_cmd;
+    extern crate tempfile;
+    use self::{assert_cmd::prelude::*, tempfile::tempdir};
     use super::*;
+    use holochain_common::DEFAULT_PASSPHRASE;
     use holochain_conductor_api::{
         key_loaders::mock_passphrase_manager,
-        keystore::{Keystore, PRIMARY_KEYBUNDLE_ID},
+        keystore::{Keystore, PRIMARY_KEYBUNDLE_ID, STANDALONE_ROOT_SEED},
     };
-    use std::{fs::remove_file, path::PathBuf};
+    use holochain_dpki::utils::verify;
+    use holochain_persistence_api::cas::content::Address;
+    use serde_json::Value;
+    use std::{fs::remove_file, path::PathBuf, process::Command};
 
     #[test]
     fn keygen_roundtrip() {
         let path = PathBuf::new().join("test.key");
         let passphrase = String::from("secret");
 
-        keygen(Some(path.clone()), Some(passphrase.clone()), true).expect("Keygen should work");
+        keygen(
+            Some(path.clone()),
+            Some(passphrase.clone()),
+            true,
+            None,
+            "human",
+        )
+        .expect("Keygen should work");
 
-        let mut keystore =
+        let keystore =
             Keystore::new_from_file(path.clone(), mock_passphrase_manager(passphrase), None)
                 .unwrap();
 
@@ -94,4 +205,156 @@ pub mod test {
 
         let _ = remove_file(path);
     }
+
+    #[test]
+    fn keygen_with_seed_is_deterministic() {
+        let seed = "2a".repeat(32);
+        let passphrase = String::from("secret");
+        let agent_address = |path: &PathBuf| {
+            keygen(
+                Some(path.clone()),
+                Some(passphrase.clone()),
+                true,
+                Some(seed.clone()),
+                "human",
+            )
+            .expect("Keygen should work");
+            let keystore = Keystore::new_from_file(
+                path.clone(),
+                mock_passphrase_manager(passphrase.clone()),
+                None,
+            )
+            .unwrap();
+            let address = keystore
+                .get_keybundle(PRIMARY_KEYBUNDLE_ID)
+                .unwrap()
+                .get_id();
+            let _ = remove_file(path);
+            address
+        };
+
+        let first = agent_address(&PathBuf::new().join("test_seed_1.key"));
+        let second = agent_address(&PathBuf::new().join("test_seed_2.key"));
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn keygen_rejects_bad_seeds() {
+        let path = PathBuf::new().join("test_bad_seed.key");
+        let passphrase = Some(String::from("secret"));
+        for seed in &["not hex", "2a2", "2a2a"] {
+            assert!(keygen(
+                Some(path.clone()),
+                passphrase.clone(),
+                true,
+                Some(seed.to_string()),
+                "human"
+            )
+            .is_err());
+        }
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn keygen_rejects_unknown_formats() {
+        let path = PathBuf::new().join("test_bad_format.key");
+        assert!(keygen(
+            Some(path.clone()),
+            Some(String::from("secret")),
+            true,
+            None,
+            "yaml"
+        )
+        .is_err());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn keygen_rotate_adds_new_keys() {
+        let tmp = tempdir().unwrap();
+        let path = tmp.path().join("test_rotate.key");
+        let passphrase = String::from(DEFAULT_PASSPHRASE);
+        keygen(
+            Some(path.clone()),
+            Some(passphrase.clone()),
+            true,
+            None,
+            "human",
+        )
+        .expect("Keygen should work");
+        let load = || {
+            Keystore::new_from_file(
+                path.clone(),
+                mock_passphrase_manager(passphrase.clone()),
+                None,
+            )
+            .unwrap()
+        };
+        let agent_address = load().get_keybundle(PRIMARY_KEYBUNDLE_ID).unwrap().get_id();
+
+        let rotate = || {
+            let output = Command::main_binary()
+                .unwrap()
+                .args(&["keygen", "--rotate", "--nullpass", "--quiet", "--seed-id"])
+                .arg(STANDALONE_ROOT_SEED)
+                .arg("--keystore")
+                .arg(&path)
+                .output()
+                .expect("should run");
+            assert!(output.status.success());
+            String::from_utf8(output.stdout).unwrap().trim().to_string()
+        };
+        let first = rotate();
+        let second = rotate();
+        assert_ne!(first, second);
+
+        let keystore = load();
+        assert!(keystore.contains(STANDALONE_ROOT_SEED));
+        assert_eq!(
+            keystore
+                .get_keybundle(PRIMARY_KEYBUNDLE_ID)
+                .unwrap()
+                .get_id(),
+            agent_address
+        );
+        let data = String::from("rotated");
+        for (index, public_key) in [first, second].iter().enumerate() {
+            let key_id = format!("{}:rotated_key_{}", STANDALONE_ROOT_SEED, index + 1);
+            let signature = keystore.sign(&key_id, data.clone()).unwrap();
+            assert_eq!(
+                verify(Address::from(public_key.clone()), data.clone(), signature),
+                Ok(true)
+            );
+        }
+    }
+
+    #[test]
+    fn keygen_prints_json() {
+        let tmp = tempdir().unwrap();
+        let path = tmp.path().join("test_json.key");
+
+        let output = Command::main_binary()
+            .unwrap()
+            .args(&["keygen", "--nullpass", "--format", "json", "--path"])
+            .arg(&path)
+            .output()
+            .expect("should run");
+        assert!(output.status.success());
+
+        let report: Value = serde_json::from_slice(&output.stdout).expect("output should be JSON");
+        let keystore = Keystore::new_from_file(
+            path.clone(),
+            mock_passphrase_manager(String::from(DEFAULT_PASSPHRASE)),
+            None,
+        )
+        .unwrap();
+        let public_keys = keystore.export_public_bundle(PRIMARY_KEYBUNDLE_ID).unwrap();
+        assert_eq!(report["agent_address"], json!(public_keys.sign_key));
+        assert_eq!(report["keystore_path"], json!(path.to_str().unwrap()));
+        assert_eq!(
+            report["public_keys"]["sign_key"],
+            json!(public_keys.sign_key)
+        );
+        assert_eq!(report["public_keys"]["enc_key"], json!(public_keys.enc_key));
+    }
 }
@@ -0,0 +1,165 @@
+use error::DefaultResult;
+use holochain_conductor_api::{
+    key_loaders::mock_passphrase_manager,
+    keystore::{Keystore, KEYSTORE_FORMAT_VERSION},
+};
+use holochain_dpki::password_encryption::PwHashConfig;
+use rpassword;
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+};
+
+/// Rewrites the keystore file at `input` in the current keystore format to `output`,
+/// asking for the keystore's passphrase unless it is given.
+/// `input` can also be an agent key file of the format used before keystores existed.
+pub fn migrate_keystore(
+    input: PathBuf,
+    output: PathBuf,
+    passphrase: Option<String>,
+    hash_config: Option<PwHashConfig>,
+) -> DefaultResult<()> {
+    let passphrase = match passphrase {
+        Some(passphrase) => passphrase,
+        None => {
+            print!("Keystore passphrase: ");
+            io::stdout().flush()?;
+            rpassword::read_password()?
+        }
+    };
+    let old_version = Keystore::migrate_file(
+        input.clone(),
+        output.clone(),
+        mock_passphrase_manager(passphrase),
+        hash_config,
+    )?;
+    if old_version == KEYSTORE_FORMAT_VERSION {
+        println!(
+            "{} already is in format version {}, copied it to {}",
+            input.display(),
+            KEYSTORE_FORMAT_VERSION,
+            output.display()
+        );
+    } else {
+        println!(
+            "Migrated {} from format version {} to {}, written to {}",
+            input.display(),
+            old_version,
+            KEYSTORE_FORMAT_VERSION,
+            output.display()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod test {
+    extern crate tempfile;
+    use self::tempfile::tempdir;
+    use super::*;
+    use holochain_conductor_api::keystore::{test_hash_config, PRIMARY_KEYBUNDLE_ID};
+    use holochain_dpki::{utils::verify, CODEC_HCS0};
+    use holochain_persistence_api::cas::content::Address;
+    use serde_json::{self, Value};
+    use std::fs;
+
+    /// agent key file written before keystores existed, see `Keystore::import_legacy`
+    fn legacy_agent_key_file() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../conductor_api/test_legacy_agent.key")
+    }
+
+    const LEGACY_PASSPHRASE: &str = "legacy passphrase";
+
+    /// the public signing key held by the legacy agent key file
+    const LEGACY_SIGN_KEY: &str =
+        "ea4a6c63e29c520abef5507b132ec5f9954776aebebe7b92421eea691446d22c";
+
+    #[test]
+    fn migrate_keystore_keeps_all_keys() {
+        let tmp = tempdir().unwrap();
+        let output = tmp.path().join("new.keystore");
+        assert_eq!(
+            Keystore::file_format_version(&legacy_agent_key_file()).unwrap(),
+            0
+        );
+
+        migrate_keystore(
+            legacy_agent_key_file(),
+            output.clone(),
+            Some(LEGACY_PASSPHRASE.to_string()),
+            test_hash_config(),
+        )
+        .expect("migration should work");
+        assert_eq!(
+            Keystore::file_format_version(&output).unwrap(),
+            KEYSTORE_FORMAT_VERSION
+        );
+
+        let new = Keystore::new_from_file(
+            output,
+            mock_passphrase_manager(LEGACY_PASSPHRASE.into()),
+            test_hash_config(),
+        )
+        .unwrap();
+        assert_eq!(
+            new.list(),
+            vec!["primary_keybundle:enc_key", "primary_keybundle:sign_key"]
+        );
+        let raw_key: Vec<u8> = (0..LEGACY_SIGN_KEY.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&LEGACY_SIGN_KEY[i..i + 2], 16).unwrap())
+            .collect();
+        let public_key = CODEC_HCS0.encode(&raw_key).unwrap();
+        let data = String::from("some data to sign");
+        let signature = new
+            .sign(&format!("{}:sign_key", PRIMARY_KEYBUNDLE_ID), data.clone())
+            .unwrap();
+        assert_eq!(verify(Address::from(public_key), data, signature), Ok(true));
+    }
+
+    #[test]
+    fn migrate_keystore_needs_the_passphrase() {
+        let tmp = tempdir().unwrap();
+        let output = tmp.path().join("new.keystore");
+
+        assert!(migrate_keystore(
+            legacy_agent_key_file(),
+            output.clone(),
+            Some("wrong".to_string()),
+            test_hash_config(),
+        )
+        .is_err());
+        assert!(!output.exists());
+    }
+
+    #[test]
+    fn migrate_keystore_refuses_to_downgrade() {
+        let tmp = tempdir().unwrap();
+        let input = tmp.path().join("future.keystore");
+        let output = tmp.path().join("new.keystore");
+        let passphrase = "secret";
+        let (keystore, _) = Keystore::new_standalone(
+            mock_passphrase_manager(passphrase.to_string()),
+            test_hash_config(),
+        )
+        .unwrap();
+        keystore.save(input.clone()).unwrap();
+
+        let mut json: Value = serde_json::from_str(&fs::read_to_string(&input).unwrap()).unwrap();
+        {
+            let fields = json.as_object_mut().unwrap();
+            fields.remove("checksum");
+            fields.insert("version".to_string(), json!(KEYSTORE_FORMAT_VERSION + 1));
+        }
+        fs::write(&input, serde_json::to_string(&json).unwrap()).unwrap();
+
+        let result = migrate_keystore(
+            input,
+            output.clone(),
+            Some(passphrase.to_string()),
+            test_hash_config(),
+        );
+        assert!(result.is_err());
+        assert!(!output.exists());
+    }
+}
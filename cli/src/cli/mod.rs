@@ -3,18 +3,24 @@ mod generate;
 mod hash_dna;
 mod init;
 mod keygen;
+mod migrate_keystore;
 pub mod package;
 mod run;
 mod scaffold;
 pub mod test;
+mod verify_chain;
+mod watch;
 
 pub use self::{
-    chain_log::{chain_list, chain_log},
+    chain_log::{chain_list, chain_log, dump_chain, grant_info},
     generate::generate,
-    hash_dna::hash_dna,
-    init::init,
-    keygen::keygen,
+    hash_dna::{hash_dna, write_dna_hash},
+    init::{init, DEFAULT_TEMPLATE},
+    keygen::{keygen, rotate_key},
+    migrate_keystore::migrate_keystore,
     package::{package, unpack},
     run::{get_interface_type_string, hc_run_configuration, run},
     test::{test, TEST_DIR_NAME},
+    verify_chain::verify_chain,
+    watch::Watch,
 };
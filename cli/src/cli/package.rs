@@ -1,4 +1,8 @@
-use crate::{config_files::Build, error::DefaultResult, util};
+use crate::{
+    config_files::Build,
+    error::{BuildFailed, DefaultResult},
+    util,
+};
 use base64;
 use colored::*;
 use holochain_core::nucleus::ribosome::{run_dna, WasmCallData};
@@ -15,7 +19,7 @@ use std::{
     sync::Arc,
 };
 
-use cli::scaffold::rust::CARGO_FILE_NAME;
+use cli::{generate::ZOME_CONFIG_FILE_NAME, scaffold::rust::CARGO_FILE_NAME};
 
 use holochain_core_types::hdk_version::{HDKVersion, HDK_VERSION};
 
@@ -56,16 +60,54 @@ fn hdk_version_compare(hdk_version: &HDKVersion, cargo_toml: &str) -> DefaultRes
     Ok(hdk_version == &hdk_version_from_toml)
 }
 
+/// Lists the paths inside `dir`, sorted so that lookups don't depend on file system order
+fn sorted_dir_entries(dir: &PathBuf) -> DefaultResult<Vec<PathBuf>> {
+    let mut entries: Vec<PathBuf> = dir
+        .read_dir()?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .collect();
+    entries.sort();
+    Ok(entries)
+}
+
+/// The stages of packaging a DNA, as reported to the observer of `package_with_observer`
+#[derive(Clone, Debug, PartialEq)]
+pub enum PackagePhase {
+    Started,
+    /// The build steps for the code in this directory are running
+    Compiling(PathBuf),
+    /// The code in this directory has been built into its artifact
+    ArtifactProduced(PathBuf),
+    /// The DNA package has been written to this file
+    Done(PathBuf),
+}
+
+pub type PackageObserver = Box<dyn Fn(&PackagePhase)>;
+
 struct Packager {
     strip_meta: bool,
+    observer: PackageObserver,
 }
 
 impl Packager {
     fn new(strip_meta: bool) -> Packager {
-        Packager { strip_meta }
+        Packager {
+            strip_meta,
+            observer: Box::new(|_| ()),
+        }
     }
 
-    pub fn package(strip_meta: bool, output: PathBuf) -> DefaultResult<()> {
+    fn with_observer(mut self, observer: PackageObserver) -> Packager {
+        self.observer = observer;
+        self
+    }
+
+    pub fn package(
+        strip_meta: bool,
+        output: PathBuf,
+        observer: PackageObserver,
+    ) -> DefaultResult<()> {
         // First, check whether they have `cargo` installed, since it will be needed for packaging
         // TODO: in the future, don't check for this here, since other build tools and languages
         // could be used
@@ -82,17 +124,17 @@ impl Packager {
             return Ok(());
         }
 
-        Packager::new(strip_meta).run(&output)
+        Packager::new(strip_meta)
+            .with_observer(observer)
+            .run(&std::env::current_dir()?, &output)
     }
 
-    fn run(&self, output: &PathBuf) -> DefaultResult<()> {
-        let current_dir = std::env::current_dir()?;
-        let dir_obj_bundle = Value::from(self.bundle_recurse(&current_dir).map_err(|e| {
-            format_err!(
-                "Couldn't traverse DNA in directory {:?}: {}",
-                &current_dir,
-                e
-            )
+    fn run(&self, source_dir: &PathBuf, output: &PathBuf) -> DefaultResult<()> {
+        (self.observer)(&PackagePhase::Started);
+        let dir_obj_bundle = Value::from(self.bundle_recurse(source_dir).map_err(|e| {
+            // as context, so the cause still tells what kind of error it is
+            let message = format!("Couldn't traverse DNA in directory {:?}: {}", source_dir, e);
+            e.context(message)
         })?);
 
         let dna_str =
@@ -119,10 +161,19 @@ impl Packager {
             output
         );
         println!("DNA hash: {}", dna.address());
+        (self.observer)(&PackagePhase::Done(output.clone()));
 
         Ok(())
     }
 
+    /// runs the build, letting the observer know when it starts and when it's done
+    fn build(&self, build: &Build, dir: &PathBuf) -> DefaultResult<String> {
+        (self.observer)(&PackagePhase::Compiling(dir.clone()));
+        let artifact = build.run(dir).map_err(|err| BuildFailed(err.to_string()))?;
+        (self.observer)(&PackagePhase::ArtifactProduced(dir.clone()));
+        Ok(artifact)
+    }
+
     fn bundle_recurse(&self, path: &PathBuf) -> DefaultResult<Object> {
         let root_dir = WalkBuilder::new(path)
             .max_depth(Some(1))
@@ -130,10 +181,14 @@ impl Packager {
             .build()
             .skip(1);
 
-        let root: Vec<_> = root_dir
+        let mut root: Vec<_> = root_dir
             .filter_map(|e| e.ok())
             .map(|e| e.path().to_path_buf())
             .collect();
+        // The bundle keeps insertion order, so walk in path order to make packaging
+        // reproducible. The bundle carries no timestamps, leaving ordering as the only
+        // thing that could differ between runs over the same inputs.
+        root.sort();
 
         let root_json_files: Vec<&PathBuf> = root
             .iter()
@@ -199,17 +254,15 @@ impl Packager {
             } else if node.is_dir() {
                 // a folder within this folder has a .hcbuild in it, meaning this node
                 // should build the json and insert it for this zome
-                if let Some(dir_with_code) = node
-                    .read_dir()?
-                    .filter_map(|e| e.ok())
-                    .map(|e| e.path())
+                if let Some(dir_with_code) = sorted_dir_entries(&node)?
+                    .into_iter()
                     .filter(|path| path.is_dir())
                     .find(|path| path.join(BUILD_CONFIG_FILE_NAME).exists())
                 {
                     meta_tree.insert(file_name.clone(), META_DIR_ID.into());
 
                     let build = Build::from_file(dir_with_code.join(BUILD_CONFIG_FILE_NAME))?;
-                    let wasm = build.run(&dir_with_code)?;
+                    let wasm = self.build(&build, &dir_with_code)?;
                     let wasm_binary = Arc::new(base64::decode(&wasm)?);
 
                     let json_string = run_dna(
@@ -232,10 +285,8 @@ impl Packager {
                     // here insert json generated by the wasm, alongside the rest of the sub-tree
                     main_tree.insert(file_name.clone(), sub_tree_content.into());
                 // this is the code folder itself, with a .hcbuild file in it
-                } else if let Some(build_config) = node
-                    .read_dir()?
-                    .filter_map(|e| e.ok())
-                    .map(|e| e.path())
+                } else if let Some(build_config) = sorted_dir_entries(&node)?
+                    .into_iter()
                     .find(|path| path.ends_with(BUILD_CONFIG_FILE_NAME))
                 {
                     meta_tree.insert(file_name.clone(), META_BIN_ID.into());
@@ -272,7 +323,7 @@ impl Packager {
                         });
                     }
 
-                    let wasm = build.run(&node)?;
+                    let wasm = self.build(&build, &node)?;
 
                     // here insert the wasm itself
                     main_tree.insert(file_name.clone(), json!({ "code": wasm }));
@@ -301,7 +352,119 @@ impl Packager {
 }
 
 pub fn package(strip_meta: bool, output: PathBuf) -> DefaultResult<()> {
-    Packager::package(strip_meta, output)
+    package_with_observer(strip_meta, output, Box::new(|_| ()))
+}
+
+/// Same as `package`, but calls `observer` whenever packaging enters another phase,
+/// so that tools can show the progress of a build.
+pub fn package_with_observer(
+    strip_meta: bool,
+    output: PathBuf,
+    observer: PackageObserver,
+) -> DefaultResult<()> {
+    Packager::package(strip_meta, output, observer)
+}
+
+/// Checks that the project in `source_dir` is laid out so that it can be packaged, without
+/// running any of its builds. Fails listing everything that is wrong with the project.
+pub fn check(source_dir: &PathBuf) -> DefaultResult<()> {
+    let problems = project_problems(source_dir)?;
+    if problems.is_empty() {
+        println!(
+            "{} {:?} is ready to be packaged",
+            "Checked".green().bold(),
+            source_dir
+        );
+        Ok(())
+    } else {
+        bail!(
+            "Found {} problem(s) in {:?}:\n  - {}",
+            problems.len(),
+            source_dir,
+            problems.join("\n  - ")
+        )
+    }
+}
+
+fn contains_json_object(path: &PathBuf) -> DefaultResult<bool> {
+    Ok(serde_json::from_str::<Object>(&fs::read_to_string(path)?).is_ok())
+}
+
+fn project_problems(source_dir: &PathBuf) -> DefaultResult<Vec<String>> {
+    let mut problems = Vec::new();
+    let entries = sorted_dir_entries(source_dir)?;
+
+    let root_json_files: Vec<&PathBuf> = entries
+        .iter()
+        .filter(|e| e.is_file())
+        .filter(|e| e.to_string_lossy().ends_with(".json"))
+        .collect();
+    match root_json_files.len() {
+        // as when packaging, a root json file is optional
+        0 => (),
+        1 => {
+            if !contains_json_object(root_json_files[0])? {
+                problems.push(format!(
+                    "{:?} doesn't contain a JSON object",
+                    root_json_files[0]
+                ));
+            }
+        }
+        _ => problems.push(format!(
+            "multiple .json files in the project root, {:?}",
+            root_json_files
+        )),
+    }
+
+    let zomes_dir = source_dir.join("zomes");
+    if !zomes_dir.is_dir() {
+        problems.push("no zomes directory".into());
+        return Ok(problems);
+    }
+    for zome_dir in sorted_dir_entries(&zomes_dir)?
+        .into_iter()
+        .filter(|path| path.is_dir())
+    {
+        let zome = util::file_name_string(&zome_dir)?;
+
+        let zome_config = zome_dir.join(ZOME_CONFIG_FILE_NAME);
+        if !zome_config.is_file() {
+            problems.push(format!(
+                "zome {} is missing its {}",
+                zome, ZOME_CONFIG_FILE_NAME
+            ));
+        } else if !contains_json_object(&zome_config)? {
+            problems.push(format!(
+                "{} of zome {} doesn't contain a JSON object",
+                ZOME_CONFIG_FILE_NAME, zome
+            ));
+        }
+
+        // same lookup as when packaging: the first sub-directory with a build file
+        let build_config = sorted_dir_entries(&zome_dir)?
+            .into_iter()
+            .filter(|path| path.is_dir())
+            .map(|path| path.join(BUILD_CONFIG_FILE_NAME))
+            .find(|path| path.exists());
+        match build_config {
+            None => problems.push(format!(
+                "zome {} has no code directory with a {} build file",
+                zome, BUILD_CONFIG_FILE_NAME
+            )),
+            Some(build_config) => match Build::from_file(&build_config) {
+                Err(e) => problems.push(format!(
+                    "{:?} is not a valid build file: {}",
+                    build_config, e
+                )),
+                Ok(ref build) if build.artifact.as_os_str().is_empty() => {
+                    problems.push(format!("{:?} doesn't name an artifact", build_config))
+                }
+                Ok(_) => (),
+            },
+        }
+    }
+
+    Ok(problems)
 }
 
 pub fn unpack(path: &PathBuf, to: &PathBuf) -> DefaultResult<()> {
@@ -384,7 +547,123 @@ fn unpack_recurse(mut obj: Object, to: &PathBuf) -> DefaultResult<()> {
 #[cfg(test)]
 // too slow!
 mod tests {
+    extern crate tempfile;
+    use self::tempfile::tempdir;
     use super::*;
+    use std::{cell::RefCell, rc::Rc};
+
+    #[test]
+    fn package_is_reproducible() {
+        // Lay out the same project twice, creating files in opposite orders
+        fn write_project(path: &PathBuf, file_names: &[&str]) {
+            fs::write(path.join("app.json"), r#"{"name": "test", "zomes": {}}"#).unwrap();
+            fs::create_dir_all(path.join("extra")).unwrap();
+            for name in file_names {
+                fs::write(path.join(name), name.as_bytes()).unwrap();
+                fs::write(path.join("extra").join(name), name.as_bytes()).unwrap();
+            }
+        }
+        let file_names = ["b.txt", "a.txt", "d.txt", "c.txt"];
+        let mut reversed_names = file_names.to_vec();
+        reversed_names.reverse();
+
+        let first = tempdir().unwrap();
+        let second = tempdir().unwrap();
+        write_project(&first.path().to_path_buf(), &file_names);
+        write_project(&second.path().to_path_buf(), &reversed_names);
+
+        let packager = Packager::new(false);
+        let package = |path: &PathBuf| {
+            serde_json::to_vec_pretty(&Value::from(packager.bundle_recurse(path).unwrap())).unwrap()
+        };
+        let first_bundle = package(&first.path().to_path_buf());
+        let second_bundle = package(&second.path().to_path_buf());
+        assert_eq!(first_bundle, second_bundle);
+
+        let bundle: Object = serde_json::from_slice(&first_bundle).unwrap();
+        let tree = bundle[META_SECTION_NAME][META_TREE_SECTION_NAME]
+            .as_object()
+            .unwrap();
+        let keys: Vec<&String> = tree.keys().collect();
+        assert_eq!(keys, vec!["a.txt", "b.txt", "c.txt", "d.txt", "extra"]);
+    }
+
+    #[test]
+    fn package_reports_its_phases() {
+        let project = tempdir().unwrap();
+        let project_path = project.path().to_path_buf();
+        fs::write(
+            project_path.join("app.json"),
+            r#"{"name": "test", "zomes": {}}"#,
+        )
+        .unwrap();
+        let code_path = project_path.join("bin");
+        fs::create_dir_all(&code_path).unwrap();
+        fs::write(code_path.join("artifact.wasm"), b"not really wasm").unwrap();
+        Build::with_artifact("artifact.wasm")
+            .save_as(code_path.join(BUILD_CONFIG_FILE_NAME))
+            .unwrap();
+        let output_dir = tempdir().unwrap();
+        let output = output_dir.path().join("test.dna.json");
+
+        let phases = Rc::new(RefCell::new(Vec::new()));
+        let recorded_phases = phases.clone();
+        Packager::new(true)
+            .with_observer(Box::new(move |phase| {
+                recorded_phases.borrow_mut().push(phase.clone())
+            }))
+            .run(&project_path, &output)
+            .unwrap();
+
+        assert_eq!(
+            *phases.borrow(),
+            vec![
+                PackagePhase::Started,
+                PackagePhase::Compiling(code_path.clone()),
+                PackagePhase::ArtifactProduced(code_path),
+                PackagePhase::Done(output.clone()),
+            ]
+        );
+        assert!(output.is_file());
+    }
+
+    #[test]
+    fn check_finds_missing_build_files() {
+        let project = tempdir().unwrap();
+        let project_path = project.path().to_path_buf();
+        fs::write(
+            project_path.join("app.json"),
+            r#"{"name": "test", "zomes": {}}"#,
+        )
+        .unwrap();
+        for zome in &["first", "second"] {
+            let zome_path = project_path.join("zomes").join(zome);
+            let code_path = zome_path.join(CODE_DIR_NAME);
+            fs::create_dir_all(&code_path).unwrap();
+            fs::write(
+                zome_path.join(ZOME_CONFIG_FILE_NAME),
+                r#"{"description": ""}"#,
+            )
+            .unwrap();
+            Build::with_artifact("target/zome.wasm")
+                .cmd("cargo", &["build"])
+                .save_as(code_path.join(BUILD_CONFIG_FILE_NAME))
+                .unwrap();
+        }
+        assert!(check(&project_path).is_ok());
+
+        fs::remove_file(
+            project_path
+                .join("zomes")
+                .join("second")
+                .join(CODE_DIR_NAME)
+                .join(BUILD_CONFIG_FILE_NAME),
+        )
+        .unwrap();
+        let message = check(&project_path).unwrap_err().to_string();
+        assert!(message.contains("Found 1 problem(s)"));
+        assert!(message.contains("zome second has no code directory with a .hcbuild build file"));
+    }
 
     #[test]
     #[cfg(feature = "broken-tests")]
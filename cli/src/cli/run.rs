@@ -1,4 +1,4 @@
-use cli;
+use cli::{self, watch::Watch};
 use colored::*;
 use error::DefaultResult;
 use holochain_common::env_vars::EnvVar;
@@ -11,36 +11,47 @@ use holochain_conductor_api::{
 };
 use holochain_core_types::agent::AgentId;
 use holochain_persistence_api::cas::content::AddressableContent;
-use std::{fs, path::PathBuf};
+use std::{fs, net::SocketAddr, path::PathBuf, time::Duration};
 
-/// Starts a minimal configuration Conductor with the current application running
+/// How long `run` waits for the instances to initialize before it gives up announcing them ready
+const READINESS_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Starts a minimal configuration Conductor with the current application running.
+/// With `watch` given, the conductor gets restarted whenever the watched files change,
+/// packaging the DNA first if `package` is set.
 pub fn run(
     dna_path: PathBuf,
     package: bool,
     port: u16,
     interface_type: String,
     conductor_config: Configuration,
+    watch: Option<Watch>,
 ) -> DefaultResult<()> {
     if package {
-        cli::package(true, dna_path)?;
+        cli::package(true, dna_path.clone())?;
     }
 
-    mount_conductor_from_config(conductor_config);
-    let mut conductor_guard = CONDUCTOR.lock().unwrap();
-    let conductor = conductor_guard.as_mut().expect("Conductor must be mounted");
-    conductor.key_loader = test_keystore_loader();
-
-    conductor
-        .boot_from_config()
-        .map_err(|err| format_err!("{}", err))?;
-
-    conductor.start_all_interfaces();
-    conductor.start_all_instances()?;
+    start_conductor(conductor_config)?;
 
     println!(
         "Holochain development conductor started. Running {} server on port {}",
         interface_type, port
     );
+    announce_readiness();
+
+    if let Some(Watch { paths, configure }) = watch {
+        cli::watch::spawn_watcher(paths, move || {
+            println!("{} changes, reloading...", "Detected".yellow().bold());
+            match reload(&dna_path, package, &*configure) {
+                Ok(()) => {
+                    println!("{} conductor", "Reloaded".green().bold());
+                    announce_readiness();
+                }
+                Err(err) => eprintln!("{} {}", "Reload failed:".red().bold(), err),
+            }
+        })?;
+        println!("Watching for changes to reload the conductor");
+    }
     println!("Type 'exit' to stop the conductor and exit the program");
 
     let mut rl = rustyline::Editor::<()>::new();
@@ -61,6 +72,62 @@ pub fn run(
     Ok(())
 }
 
+fn start_conductor(conductor_config: Configuration) -> DefaultResult<()> {
+    mount_conductor_from_config(conductor_config);
+    let mut conductor_guard = CONDUCTOR.lock().unwrap();
+    let conductor = conductor_guard.as_mut().expect("Conductor must be mounted");
+    conductor.key_loader = test_keystore_loader();
+
+    conductor
+        .boot_from_config()
+        .map_err(|err| format_err!("{}", err))?;
+
+    conductor.start_all_interfaces();
+    conductor.start_all_instances()?;
+    Ok(())
+}
+
+/// Prints "ready" once all instances of the running conductor have initialized,
+/// so that scripts know when they can start making calls
+fn announce_readiness() {
+    let (readiness, instance_ids) = {
+        let conductor_guard = CONDUCTOR.lock().unwrap();
+        let conductor = conductor_guard.as_ref().expect("Conductor must be mounted");
+        let instance_ids: Vec<String> = conductor
+            .config()
+            .instances
+            .iter()
+            .map(|instance| instance.id.clone())
+            .collect();
+        (conductor.readiness(), instance_ids)
+    };
+    if readiness.wait_for(&instance_ids, READINESS_TIMEOUT) {
+        println!("{}", "ready".green().bold());
+    } else {
+        eprintln!(
+            "{} not all instances initialized within {} seconds",
+            "Warning:".yellow().bold(),
+            READINESS_TIMEOUT.as_secs()
+        );
+    }
+}
+
+/// Shuts the running conductor down and starts a new one with a fresh configuration
+fn reload(
+    dna_path: &PathBuf,
+    package: bool,
+    configure: &dyn Fn() -> DefaultResult<Configuration>,
+) -> DefaultResult<()> {
+    if package {
+        cli::package(true, dna_path.clone())?;
+    }
+    let conductor_config = configure()?;
+    if let Some(conductor) = CONDUCTOR.lock().unwrap().as_mut() {
+        conductor.shutdown()?;
+    }
+    start_conductor(conductor_config)
+}
+
 pub fn get_interface_type_string(given_type: String) -> String {
     // note that this behaviour is documented within
     // holochain_common::env_vars module and should be updated
@@ -69,25 +136,92 @@ pub fn get_interface_type_string(given_type: String) -> String {
     EnvVar::Interface.value().ok().unwrap_or_else(|| given_type)
 }
 
+/// Builds the driver for an interface of the given type, serving on `port`
+fn interface_driver(interface_type: &str, port: u16) -> DefaultResult<InterfaceDriver> {
+    match interface_type {
+        "websocket" => Ok(InterfaceDriver::Websocket { port }),
+        "http" => Ok(InterfaceDriver::Http { port }),
+        _ => Err(format_err!(
+            "unknown interface type: {}, known types are: http, websocket",
+            interface_type
+        )),
+    }
+}
+
+/// Builds the configuration for running the given DNAs, each in an instance of its own.
+/// All instances are exposed through the one interface, served at `address`.
 pub fn hc_run_configuration(
-    dna_path: &PathBuf,
-    port: u16,
+    dna_paths: &[PathBuf],
+    address: SocketAddr,
     persist: bool,
     networked: bool,
     interface_type: &String,
     logging: bool,
 ) -> DefaultResult<Configuration> {
+    let ids = run_ids(dna_paths)?;
+    let mut dnas = Vec::new();
+    let mut instances = Vec::new();
+    for (dna_path, (dna_id, instance_id)) in dna_paths.iter().zip(ids.iter()) {
+        // persisted instances can't share a storage directory
+        let storage_path = if dna_paths.len() == 1 {
+            LOCAL_STORAGE_PATH.to_string()
+        } else {
+            format!("{}/{}", LOCAL_STORAGE_PATH, instance_id)
+        };
+        dnas.push(dna_configuration(dna_path, dna_id));
+        instances.push(instance_configuration(
+            instance_id,
+            dna_id,
+            storage_configuration(persist, &storage_path)?,
+        ));
+    }
+    let instance_ids: Vec<String> = ids
+        .into_iter()
+        .map(|(_, instance_id)| instance_id)
+        .collect();
     Ok(Configuration {
         agents: vec![agent_configuration()],
-        dnas: vec![dna_configuration(&dna_path)],
-        instances: vec![instance_configuration(storage_configuration(persist)?)],
-        interfaces: vec![interface_configuration(&interface_type, port)?],
+        dnas,
+        instances,
+        interfaces: vec![interface_configuration(
+            &interface_type,
+            address,
+            &instance_ids,
+        )?],
         network: networking_configuration(networked),
         logger: logger_configuration(logging),
         ..Default::default()
     })
 }
 
+/// Returns the ids of the DNA and the instance to configure for each of the given DNA files.
+/// A single DNA gets the fixed ids `hc run` has always used, several ones get ids
+/// derived from their file names, which therefore need to differ.
+fn run_ids(dna_paths: &[PathBuf]) -> DefaultResult<Vec<(String, String)>> {
+    if dna_paths.len() == 1 {
+        return Ok(vec![(DNA_CONFIG_ID.into(), INSTANCE_CONFIG_ID.into())]);
+    }
+    let mut ids: Vec<(String, String)> = Vec::new();
+    for (index, dna_path) in dna_paths.iter().enumerate() {
+        let file_name = dna_path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| format_err!("Invalid DNA file path {}", dna_path.display()))?;
+        let name = file_name.trim_end_matches(".json").trim_end_matches(".dna");
+        let instance_id = format!("{}-{}", INSTANCE_CONFIG_ID, name);
+        if let Some(other) = ids.iter().position(|(_, id)| *id == instance_id) {
+            bail!(
+                "DNA files {} and {} would both run as instance '{}', please rename one of them",
+                dna_paths[other].display(),
+                dna_paths[index].display(),
+                instance_id
+            );
+        }
+        ids.push((format!("{}-{}", DNA_CONFIG_ID, name), instance_id));
+    }
+    Ok(ids)
+}
+
 // AGENT
 const AGENT_NAME_DEFAULT: &str = "testAgent";
 const AGENT_CONFIG_ID: &str = "hc-run-agent";
@@ -100,7 +234,7 @@ fn agent_configuration() -> AgentConfiguration {
         .value()
         .ok()
         .unwrap_or_else(|| String::from(AGENT_NAME_DEFAULT));
-    let mut keystore = test_keystore(&agent_name);
+    let keystore = test_keystore(&agent_name);
     let pub_key = keystore
         .get_keybundle(PRIMARY_KEYBUNDLE_ID)
         .expect("should be able to get keybundle")
@@ -119,13 +253,13 @@ fn agent_configuration() -> AgentConfiguration {
 // DNA
 const DNA_CONFIG_ID: &str = "hc-run-dna";
 
-fn dna_configuration(dna_path: &PathBuf) -> DnaConfiguration {
+fn dna_configuration(dna_path: &PathBuf, id: &str) -> DnaConfiguration {
     let dna = Conductor::load_dna(dna_path).expect(&format!(
         "Could not load DNA file {}",
         dna_path.to_str().expect("No DNA file path given")
     ));
     DnaConfiguration {
-        id: DNA_CONFIG_ID.into(),
+        id: id.into(),
         file: dna_path
             .to_str()
             .expect("Expected DNA path to be valid unicode")
@@ -137,13 +271,11 @@ fn dna_configuration(dna_path: &PathBuf) -> DnaConfiguration {
 // STORAGE
 const LOCAL_STORAGE_PATH: &str = ".hc";
 
-fn storage_configuration(persist: bool) -> DefaultResult<StorageConfiguration> {
+fn storage_configuration(persist: bool, path: &str) -> DefaultResult<StorageConfiguration> {
     if persist {
-        fs::create_dir_all(LOCAL_STORAGE_PATH)?;
+        fs::create_dir_all(path)?;
 
-        Ok(StorageConfiguration::Pickle {
-            path: LOCAL_STORAGE_PATH.into(),
-        })
+        Ok(StorageConfiguration::Pickle { path: path.into() })
     } else {
         Ok(StorageConfiguration::Memory)
     }
@@ -152,10 +284,14 @@ fn storage_configuration(persist: bool) -> DefaultResult<StorageConfiguration> {
 // INSTANCE
 const INSTANCE_CONFIG_ID: &str = "test-instance";
 
-fn instance_configuration(storage: StorageConfiguration) -> InstanceConfiguration {
+fn instance_configuration(
+    id: &str,
+    dna_id: &str,
+    storage: StorageConfiguration,
+) -> InstanceConfiguration {
     InstanceConfiguration {
-        id: INSTANCE_CONFIG_ID.into(),
-        dna: DNA_CONFIG_ID.into(),
+        id: id.into(),
+        dna: dna_id.into(),
         agent: AGENT_CONFIG_ID.into(),
         storage,
     }
@@ -166,24 +302,21 @@ const INTERFACE_CONFIG_ID: &str = "websocket-interface";
 
 fn interface_configuration(
     interface_type: &String,
-    port: u16,
+    address: SocketAddr,
+    instance_ids: &[String],
 ) -> DefaultResult<InterfaceConfiguration> {
-    let driver = if interface_type == &String::from("websocket") {
-        InterfaceDriver::Websocket { port }
-    } else if interface_type == &String::from("http") {
-        InterfaceDriver::Http { port }
-    } else {
-        return Err(format_err!("unknown interface type: {}", interface_type));
-    };
-
     Ok(InterfaceConfiguration {
         id: INTERFACE_CONFIG_ID.into(),
-        driver,
+        driver: interface_driver(interface_type, address.port())?,
         admin: true,
-        instances: vec![InstanceReferenceConfiguration {
-            id: INSTANCE_CONFIG_ID.into(),
-            alias: None,
-        }],
+        instances: instance_ids
+            .iter()
+            .map(|id| InstanceReferenceConfiguration {
+                id: id.clone(),
+                alias: None,
+            })
+            .collect(),
+        bind_address: Some(address.ip().to_string()),
     })
 }
 
@@ -238,14 +371,23 @@ fn networking_configuration(networked: bool) -> Option<NetworkConfig> {
 #[cfg(test)]
 mod tests {
     extern crate tempfile;
-    // use crate::cli::init::{init, tests::gen_dir};
+    // use crate::cli::init::{init, tests::gen_dir, DEFAULT_TEMPLATE};
     // use assert_cmd::prelude::*;
     // use std::{env, process::Command, path::PathBuf};
     use self::tempfile::tempdir;
     use holochain_conductor_api::config::*;
     use holochain_core_types::dna::Dna;
     use holochain_persistence_api::cas::content::AddressableContent;
-    use std::fs::{create_dir, File};
+    use std::{
+        fs::{create_dir, create_dir_all, File},
+        net::{Ipv4Addr, SocketAddr},
+        path::{Path, PathBuf},
+    };
+
+    /// the given port on all IPv4 network interfaces, where `hc run` serves by default
+    fn default_address(port: u16) -> SocketAddr {
+        SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), port)
+    }
 
     #[test]
     // flagged as broken for:
@@ -261,7 +403,7 @@ mod tests {
         let mut run_cmd = Command::main_binary().unwrap();
         let mut run2_cmd = Command::main_binary().unwrap();
 
-        let _ = init(&temp_dir_path_buf);
+        let _ = init(&temp_dir_path_buf, DEFAULT_TEMPLATE);
 
         assert!(env::set_current_dir(&temp_dir_path).is_ok());
 
@@ -306,7 +448,7 @@ mod tests {
         let out_file = File::create(&temp_path).expect("Could not create temp file for test DNA");
         serde_json::to_writer_pretty(&out_file, &dna).expect("Could not write test DNA to file");
 
-        let dna_config = super::dna_configuration(&temp_path);
+        let dna_config = super::dna_configuration(&temp_path, super::DNA_CONFIG_ID);
         assert_eq!(
             dna_config,
             DnaConfiguration {
@@ -319,10 +461,10 @@ mod tests {
 
     #[test]
     fn test_storage_configuration() {
-        let storage = super::storage_configuration(false).unwrap();
+        let storage = super::storage_configuration(false, super::LOCAL_STORAGE_PATH).unwrap();
         assert_eq!(storage, StorageConfiguration::Memory);
 
-        let persist_store = super::storage_configuration(true).unwrap();
+        let persist_store = super::storage_configuration(true, super::LOCAL_STORAGE_PATH).unwrap();
         assert_eq!(
             persist_store,
             StorageConfiguration::Pickle {
@@ -333,8 +475,9 @@ mod tests {
 
     #[test]
     fn test_instance_configuration() {
-        let storage = super::storage_configuration(false).unwrap();
-        let instance = super::instance_configuration(storage);
+        let storage = super::storage_configuration(false, super::LOCAL_STORAGE_PATH).unwrap();
+        let instance =
+            super::instance_configuration(super::INSTANCE_CONFIG_ID, super::DNA_CONFIG_ID, storage);
         assert_eq!(
             instance,
             InstanceConfiguration {
@@ -348,7 +491,13 @@ mod tests {
 
     #[test]
     fn test_interface_configuration() {
-        let http_interface = super::interface_configuration(&"http".to_string(), 4444).unwrap();
+        let instance_ids = vec![super::INSTANCE_CONFIG_ID.to_string()];
+        let http_interface = super::interface_configuration(
+            &"http".to_string(),
+            default_address(4444),
+            &instance_ids,
+        )
+        .unwrap();
         assert_eq!(
             http_interface,
             InterfaceConfiguration {
@@ -359,11 +508,16 @@ mod tests {
                     id: "test-instance".to_string(),
                     alias: None,
                 }],
+                bind_address: Some("0.0.0.0".to_string()),
             }
         );
 
-        let websocket_interface =
-            super::interface_configuration(&"websocket".to_string(), 5555).unwrap();
+        let websocket_interface = super::interface_configuration(
+            &"websocket".to_string(),
+            default_address(5555),
+            &instance_ids,
+        )
+        .unwrap();
         assert_eq!(
             websocket_interface,
             InterfaceConfiguration {
@@ -374,11 +528,112 @@ mod tests {
                     id: "test-instance".to_string(),
                     alias: None,
                 }],
+                bind_address: Some("0.0.0.0".to_string()),
             }
         );
 
-        let invalid_type = super::interface_configuration(&"funny".to_string(), 4444);
-        assert!(invalid_type.is_err());
+        let invalid_type = super::interface_configuration(
+            &"funny".to_string(),
+            default_address(4444),
+            &instance_ids,
+        );
+        assert_eq!(
+            invalid_type.unwrap_err().to_string(),
+            "unknown interface type: funny, known types are: http, websocket"
+        );
+    }
+
+    #[test]
+    fn test_run_configuration_binds_to_given_host() {
+        let dir = tempdir().expect("Could not get tempdir");
+        let dna_paths = vec![write_dna(dir.path(), "app.dna.json")];
+        let config = super::hc_run_configuration(
+            &dna_paths,
+            "127.0.0.1:8888".parse().unwrap(),
+            false,
+            false,
+            &"websocket".to_string(),
+            false,
+        )
+        .unwrap();
+
+        let interface = &config.interfaces[0];
+        assert_eq!(interface.bind_address, Some("127.0.0.1".to_string()));
+        assert_eq!(interface.driver, InterfaceDriver::Websocket { port: 8888 });
+    }
+
+    /// writes an empty DNA to `dir/file_name` and returns its path
+    fn write_dna(dir: &Path, file_name: &str) -> PathBuf {
+        create_dir_all(dir).expect("Could not create DNA directory");
+        let path = dir.join(file_name);
+        let out_file = File::create(&path).expect("Could not create temp file for test DNA");
+        serde_json::to_writer_pretty(&out_file, &Dna::new()).expect("Could not write test DNA");
+        path
+    }
+
+    #[test]
+    fn test_run_configuration_with_several_dnas() {
+        let dir = tempdir().expect("Could not get tempdir");
+        let dna_paths = vec![
+            write_dna(dir.path(), "app.dna.json"),
+            write_dna(dir.path(), "other.dna.json"),
+        ];
+        let config = super::hc_run_configuration(
+            &dna_paths,
+            default_address(8888),
+            false,
+            false,
+            &"websocket".to_string(),
+            false,
+        )
+        .unwrap();
+
+        let dna_ids: Vec<_> = config.dnas.iter().map(|dna| dna.id.clone()).collect();
+        assert_eq!(dna_ids, vec!["hc-run-dna-app", "hc-run-dna-other"]);
+        assert_eq!(
+            config.instances,
+            vec![
+                InstanceConfiguration {
+                    id: "test-instance-app".to_string(),
+                    dna: "hc-run-dna-app".to_string(),
+                    agent: "hc-run-agent".to_string(),
+                    storage: StorageConfiguration::Memory,
+                },
+                InstanceConfiguration {
+                    id: "test-instance-other".to_string(),
+                    dna: "hc-run-dna-other".to_string(),
+                    agent: "hc-run-agent".to_string(),
+                    storage: StorageConfiguration::Memory,
+                },
+            ]
+        );
+        let interface_instances: Vec<_> = config.interfaces[0]
+            .instances
+            .iter()
+            .map(|instance| instance.id.clone())
+            .collect();
+        assert_eq!(
+            interface_instances,
+            vec!["test-instance-app", "test-instance-other"]
+        );
+
+        // the same file name in different directories would clash
+        let clashing = vec![
+            write_dna(&dir.path().join("a"), "app.dna.json"),
+            write_dna(&dir.path().join("b"), "app.dna.json"),
+        ];
+        let result = super::hc_run_configuration(
+            &clashing,
+            default_address(8888),
+            false,
+            false,
+            &"websocket".to_string(),
+            false,
+        );
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("would both run as instance 'test-instance-app'"));
     }
 
     #[test]
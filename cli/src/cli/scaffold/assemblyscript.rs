@@ -35,21 +35,23 @@ impl AssemblyScriptScaffold {
 }
 
 impl Scaffold for AssemblyScriptScaffold {
-    fn gen<P: AsRef<Path>>(&self, base_path: P) -> DefaultResult<()> {
+    fn gen<P: AsRef<Path>>(&self, base_path: P, quiet: bool) -> DefaultResult<()> {
         fs::create_dir_all(&base_path)?;
 
         // use npm to initialise a nodejs project
-        util::run_cmd(
+        util::run_cmd_with_verbosity(
             base_path.as_ref().to_path_buf(),
             "npm".into(),
             &["init", "-y"],
+            quiet,
         )?;
 
         // add hdk-assemblyscript as a dependency
-        util::run_cmd(
+        util::run_cmd_with_verbosity(
             base_path.as_ref().to_path_buf(),
             "npm".into(),
             &["install", "--save", "holochain/hdk-assemblyscript"],
+            quiet,
         )?;
 
         // create a index.ts file
@@ -5,5 +5,7 @@ use crate::error::DefaultResult;
 use std::path::Path;
 
 pub trait Scaffold {
-    fn gen<P: AsRef<Path>>(&self, base_path: P) -> DefaultResult<()>;
+    /// Generates the scaffold into `base_path`.
+    /// If `quiet` is set, success messages are not printed; errors are still returned.
+    fn gen<P: AsRef<Path>>(&self, base_path: P, quiet: bool) -> DefaultResult<()>;
 }
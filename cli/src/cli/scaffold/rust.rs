@@ -80,6 +80,16 @@ fn interpolate_cargo_template(
 impl RustScaffold {
     pub fn new(package_name: &str, macro_style: HdkMacroStyle) -> RustScaffold {
         let target_dir = wasm_target_dir(&package_name.into(), &String::new().into());
+        RustScaffold::with_target_dir(package_name, macro_style, target_dir)
+    }
+
+    /// Like `new`, but the zome gets built into `target_dir` instead of the default target
+    /// directory, e.g. to keep the zomes of a monorepo from building into the same one.
+    pub fn with_target_dir(
+        package_name: &str,
+        macro_style: HdkMacroStyle,
+        target_dir: PathBuf,
+    ) -> RustScaffold {
         let mut artifact_name = target_dir.clone();
         let artifact_path_component: PathBuf = [
             String::from("wasm32-unknown-unknown"),
@@ -150,7 +160,7 @@ impl RustScaffold {
 }
 
 impl Scaffold for RustScaffold {
-    fn gen<P: AsRef<Path>>(&self, base_path: P) -> DefaultResult<()> {
+    fn gen<P: AsRef<Path>>(&self, base_path: P, quiet: bool) -> DefaultResult<()> {
         // First, check whether they have `cargo` installed
         let should_continue = util::check_for_cargo(
             "Generating a Rust based Zome depends on having Rust installed.",
@@ -164,10 +174,11 @@ impl Scaffold for RustScaffold {
         fs::create_dir_all(&base_path)?;
 
         // use cargo to initialise a library Rust crate without any version control
-        util::run_cmd(
+        util::run_cmd_with_verbosity(
             base_path.as_ref().to_path_buf(),
             "cargo".into(),
             &["init", "--lib", "--vcs", "none"],
+            quiet,
         )?;
 
         // immediately rewrite the generated Cargo file, using some values
@@ -182,12 +193,42 @@ impl Scaffold for RustScaffold {
         self.build_template.save_as(build_file_path)?;
 
         // CLI feedback
-        println!(
-            "{} {:?} Zome",
-            "Generated".green().bold(),
-            self.package_name
-        );
+        if !quiet {
+            println!(
+                "{} {:?} Zome",
+                "Generated".green().bold(),
+                self.package_name
+            );
+        }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scaffold_builds_into_custom_target_dir() {
+        let target_dir = PathBuf::from("/tmp/monorepo-target/bubblechat");
+        let scaffold = RustScaffold::with_target_dir(
+            "bubblechat",
+            HdkMacroStyle::Declarative,
+            target_dir.clone(),
+        );
+
+        assert_eq!(
+            scaffold.build_template.artifact,
+            target_dir.join("wasm32-unknown-unknown/release/bubblechat.wasm")
+        );
+        let step = &scaffold.build_template.steps[0];
+        assert_eq!(step.command, "cargo");
+        assert_eq!(
+            step.arguments.last(),
+            Some(&String::from(
+                "--target-dir=/tmp/monorepo-target/bubblechat"
+            ))
+        );
+    }
+}
@@ -94,7 +94,7 @@ pub fn test(
 #[cfg(feature = "broken-tests")]
 pub mod tests {
     use super::*;
-    use crate::cli::init::{init, tests::gen_dir};
+    use crate::cli::init::{init, tests::gen_dir, DEFAULT_TEMPLATE};
     //    use assert_cmd::prelude::*;
     //    use std::{env, process::Command};
 
@@ -111,7 +111,7 @@ pub mod tests {
 
         let mut gen_cmd = Command::main_binary().unwrap();
 
-        let _ = init(&temp_dir_path_buf);
+        let _ = init(&temp_dir_path_buf, DEFAULT_TEMPLATE);
 
         assert!(env::set_current_dir(&temp_dir_path).is_ok());
 
@@ -146,7 +146,7 @@ pub mod tests {
         let temp_dir_path = temp_dir.path();
         let temp_dir_path_buf = temp_dir_path.to_path_buf();
 
-        let _ = init(&temp_dir_path_buf);
+        let _ = init(&temp_dir_path_buf, DEFAULT_TEMPLATE);
 
         let result = test(&temp_dir_path_buf, "west", "test/index.js", false);
 
@@ -0,0 +1,229 @@
+use cli::chain_log::open_chain;
+use error::DefaultResult;
+use holochain_conductor_api::{
+    key_loaders::mock_passphrase_manager,
+    keystore::{Keystore, PRIMARY_KEYBUNDLE_ID},
+};
+use holochain_core_types::{chain_header::ChainHeader, entry::Entry};
+use holochain_dpki::utils;
+use holochain_persistence_api::cas::content::{Address, AddressableContent};
+use rpassword;
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+};
+
+/// Checks that every header of the instance's source chain carries a valid signature
+/// of its entry by the agent whose keys are stored in the given keystore.
+/// Fails naming the first (oldest) invalid entry, counting from 0 at the start of the chain.
+pub fn verify_chain(
+    storage_path: Option<PathBuf>,
+    instance_id: String,
+    keystore_path: PathBuf,
+    passphrase: Option<String>,
+) -> DefaultResult<()> {
+    let passphrase = match passphrase {
+        Some(passphrase) => passphrase,
+        None => {
+            print!("Keystore passphrase: ");
+            io::stdout().flush()?;
+            rpassword::read_password()?
+        }
+    };
+    let keystore =
+        Keystore::new_from_file(keystore_path, mock_passphrase_manager(passphrase), None)?;
+    let agent_key = Address::from(keystore.get_keybundle(PRIMARY_KEYBUNDLE_ID)?.get_id());
+
+    let (cas_path, chain) = open_chain(storage_path, instance_id)?;
+    let (entry_count, first_invalid) = check_signatures(chain, &agent_key)?;
+    if let Some((index, entry_address)) = first_invalid {
+        bail!(
+            "Entry {} ({}) in chain '{}' is not validly signed by agent {}",
+            index,
+            entry_address,
+            cas_path.to_string_lossy(),
+            agent_key
+        );
+    }
+
+    println!(
+        "All {} entries in chain '{}' are validly signed by agent {}",
+        entry_count,
+        cas_path.to_string_lossy(),
+        agent_key
+    );
+    Ok(())
+}
+
+/// Walks the chain (given latest first, as read) and returns the number of its entries
+/// together with the index and address of the first entry from its start that doesn't verify.
+/// Only the entry at hand is kept in memory, so the first invalid entry is only known
+/// once the whole chain has been read.
+fn check_signatures<I>(
+    chain: I,
+    agent_key: &Address,
+) -> DefaultResult<(usize, Option<(usize, Address)>)>
+where
+    I: Iterator<Item = DefaultResult<(ChainHeader, Entry)>>,
+{
+    let mut entry_count = 0;
+    // counted from the latest entry, as that's the order they are read in
+    let mut oldest_invalid = None;
+    for item in chain {
+        let (header, entry) = item?;
+        if !is_validly_signed(&header, &entry, agent_key) {
+            oldest_invalid = Some((entry_count, header.entry_address().clone()));
+        }
+        entry_count += 1;
+    }
+    let first_invalid =
+        oldest_invalid.map(|(from_latest, address)| (entry_count - 1 - from_latest, address));
+    Ok((entry_count, first_invalid))
+}
+
+fn is_validly_signed(header: &ChainHeader, entry: &Entry, agent_key: &Address) -> bool {
+    // the header has to reference exactly this entry
+    if entry.address() != *header.entry_address() {
+        return false;
+    }
+    header
+        .provenances()
+        .iter()
+        .filter(|provenance| provenance.source() == *agent_key)
+        .any(|provenance| {
+            utils::verify(
+                agent_key.clone(),
+                header.entry_address().to_string(),
+                provenance.signature(),
+            )
+            .unwrap_or(false)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate tempfile;
+    use self::tempfile::tempdir;
+    use super::*;
+    use base64;
+    use holochain_core::agent::state::AgentStateSnapshot;
+    use holochain_core_types::{
+        entry::{test_entry, test_entry_b, test_sys_entry},
+        signature::{Provenance, Signature},
+        time::test_iso_8601,
+    };
+    use holochain_persistence_api::cas::storage::ContentAddressableStorage;
+    use holochain_persistence_file::cas::file::FilesystemStorage;
+    use lib3h_sodium::secbuf::SecBuf;
+    use std::fs;
+
+    const PASSPHRASE: &str = "secret";
+
+    /// writes a keystore to the given path and returns it together with the agent's key
+    fn write_keystore(path: &PathBuf) -> (Keystore, Address) {
+        let (keystore, pub_key) =
+            Keystore::new_standalone(mock_passphrase_manager(PASSPHRASE.into()), None).unwrap();
+        keystore.save(path.clone()).unwrap();
+        (keystore, Address::from(pub_key))
+    }
+
+    /// writes a chain of the given entries (oldest first) for instance "test-instance",
+    /// signing each entry's address with the keystore's agent key
+    /// except for the entry at `bad_signature_index`
+    fn write_signed_chain(
+        storage_path: &PathBuf,
+        keystore: &Keystore,
+        agent_key: &Address,
+        entries: Vec<Entry>,
+        bad_signature_index: Option<usize>,
+    ) {
+        let cas_path = storage_path.join("test-instance").join("cas");
+        fs::create_dir_all(&cas_path).unwrap();
+        let mut cas = FilesystemStorage::new(cas_path).unwrap();
+        let mut keybundle = keystore.get_keybundle(PRIMARY_KEYBUNDLE_ID).unwrap();
+
+        let mut top_header: Option<ChainHeader> = None;
+        for (index, entry) in entries.into_iter().enumerate() {
+            cas.add(&entry).unwrap();
+            let signed_data = if Some(index) == bad_signature_index {
+                String::from("something else")
+            } else {
+                entry.address().to_string()
+            };
+            let mut data = SecBuf::with_insecure_from_string(signed_data);
+            let mut signature = keybundle.sign(&mut data).unwrap();
+            let signature = Signature::from(base64::encode(&**signature.read_lock()));
+            let header = ChainHeader::new(
+                &entry.entry_type(),
+                &entry.address(),
+                &vec![Provenance::new(agent_key.clone(), signature)],
+                &top_header.as_ref().map(|header| header.address()),
+                &None,
+                &None,
+                &test_iso_8601(),
+            );
+            cas.add(&header).unwrap();
+            top_header = Some(header);
+        }
+        cas.add(&AgentStateSnapshot::new(top_header)).unwrap();
+    }
+
+    fn entries() -> Vec<Entry> {
+        vec![test_sys_entry(), test_entry(), test_entry_b()]
+    }
+
+    #[test]
+    fn verifies_valid_chain() {
+        let dir = tempdir().unwrap();
+        let keystore_path = dir.path().join("agent.keystore");
+        let (keystore, agent_key) = write_keystore(&keystore_path);
+        let storage_path = dir.path().join("storage");
+        write_signed_chain(&storage_path, &keystore, &agent_key, entries(), None);
+
+        assert!(verify_chain(
+            Some(storage_path),
+            "test-instance".into(),
+            keystore_path,
+            Some(PASSPHRASE.into())
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn reports_first_tampered_entry() {
+        let dir = tempdir().unwrap();
+        let keystore_path = dir.path().join("agent.keystore");
+        let (keystore, agent_key) = write_keystore(&keystore_path);
+        let storage_path = dir.path().join("storage");
+        write_signed_chain(&storage_path, &keystore, &agent_key, entries(), Some(1));
+
+        let result = verify_chain(
+            Some(storage_path.clone()),
+            "test-instance".into(),
+            keystore_path.clone(),
+            Some(PASSPHRASE.into()),
+        );
+        let message = result.expect_err("tampered chain must fail").to_string();
+        assert!(message.starts_with(&format!("Entry 1 ({})", test_entry().address())));
+
+        // a chain signed by someone else fails right at its start
+        let chain = || {
+            open_chain(Some(storage_path.clone()), "test-instance".into())
+                .unwrap()
+                .1
+        };
+        let other_agent = Address::from(
+            Keystore::new_standalone(mock_passphrase_manager(PASSPHRASE.into()), None)
+                .unwrap()
+                .1,
+        );
+        assert_eq!(
+            check_signatures(chain(), &other_agent).unwrap(),
+            (3, Some((0, test_sys_entry().address())))
+        );
+        assert_eq!(
+            check_signatures(chain(), &agent_key).unwrap(),
+            (3, Some((1, test_entry().address())))
+        );
+    }
+}
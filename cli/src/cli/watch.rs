@@ -0,0 +1,190 @@
+use cli::package::IGNORE_FILE_NAME;
+use error::DefaultResult;
+use holochain_conductor_api::config::Configuration;
+use ignore::WalkBuilder;
+use std::{
+    collections::BTreeMap,
+    path::PathBuf,
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+/// How often the watched files get checked for changes
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How long the watched files have to stay unchanged before reloading, so that saving
+/// several files at once leads to a single reload
+const DEBOUNCE_PERIOD: Duration = Duration::from_secs(1);
+
+/// What `hc run --watch` watches, and how it configures the conductor after a change
+pub struct Watch {
+    /// Files and directories whose changes trigger a reload. Directories are walked
+    /// skipping whatever .gitignore and .hcignore files exclude.
+    pub paths: Vec<PathBuf>,
+    /// Builds the configuration of the reloaded conductor, which picks up the changed DNA
+    pub configure: Box<dyn Fn() -> DefaultResult<Configuration> + Send>,
+}
+
+/// Turns a burst of changes into a single reload once the changes have settled
+struct Debouncer {
+    period: Duration,
+    last_change: Option<Instant>,
+}
+
+impl Debouncer {
+    fn new(period: Duration) -> Self {
+        Debouncer {
+            period,
+            last_change: None,
+        }
+    }
+
+    fn changed(&mut self, now: Instant) {
+        self.last_change = Some(now);
+    }
+
+    /// True once, as soon as nothing has changed for the whole period
+    fn should_fire(&mut self, now: Instant) -> bool {
+        match self.last_change {
+            Some(last_change) if now.duration_since(last_change) >= self.period => {
+                self.last_change = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+type Snapshot = BTreeMap<PathBuf, SystemTime>;
+
+/// Modification times of all files under the given paths
+fn snapshot(paths: &[PathBuf]) -> Snapshot {
+    let mut files = BTreeMap::new();
+    for path in paths {
+        let walker = WalkBuilder::new(path)
+            .add_custom_ignore_filename(IGNORE_FILE_NAME)
+            .build();
+        for entry in walker.filter_map(Result::ok) {
+            let modified = entry
+                .metadata()
+                .ok()
+                .and_then(|metadata| metadata.modified().ok());
+            if let Some(modified) = modified {
+                files.insert(entry.path().to_path_buf(), modified);
+            }
+        }
+    }
+    files
+}
+
+/// Compares successive snapshots of the watched files and tells when to reload
+struct ChangeTracker {
+    files: Snapshot,
+    debouncer: Debouncer,
+}
+
+impl ChangeTracker {
+    fn new(files: Snapshot, period: Duration) -> Self {
+        ChangeTracker {
+            files,
+            debouncer: Debouncer::new(period),
+        }
+    }
+
+    /// Takes the files as they are at `now` and returns whether to reload
+    fn update(&mut self, files: Snapshot, now: Instant) -> bool {
+        if files != self.files {
+            self.files = files;
+            self.debouncer.changed(now);
+        }
+        self.debouncer.should_fire(now)
+    }
+
+    /// Takes the files as they are after reloading, so that files the reload wrote
+    /// itself (like a freshly packaged DNA) don't trigger another one
+    fn reset(&mut self, files: Snapshot) {
+        self.files = files;
+    }
+}
+
+/// Watches the given paths in a thread of its own, calling `reload` after they changed.
+/// Runs until the process exits.
+pub fn spawn_watcher<F>(paths: Vec<PathBuf>, mut reload: F) -> DefaultResult<()>
+where
+    F: FnMut() + Send + 'static,
+{
+    thread::Builder::new()
+        .name("hc_run_watcher".to_string())
+        .spawn(move || {
+            let mut tracker = ChangeTracker::new(snapshot(&paths), DEBOUNCE_PERIOD);
+            loop {
+                thread::sleep(POLL_INTERVAL);
+                if tracker.update(snapshot(&paths), Instant::now()) {
+                    reload();
+                    tracker.reset(snapshot(&paths));
+                }
+            }
+        })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate tempfile;
+    use self::tempfile::tempdir;
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn reloads_once_after_changes_settle() {
+        let start = Instant::now();
+        let at = |millis| start + Duration::from_millis(millis);
+        let period = Duration::from_millis(1000);
+        let file = PathBuf::from("zomes/my_zome/code/src/lib.rs");
+        let version = |secs| {
+            let mut files = Snapshot::new();
+            files.insert(
+                file.clone(),
+                SystemTime::UNIX_EPOCH + Duration::from_secs(secs),
+            );
+            files
+        };
+        let mut tracker = ChangeTracker::new(version(0), period);
+
+        // nothing changed
+        assert!(!tracker.update(version(0), at(500)));
+
+        // three saves in quick succession
+        assert!(!tracker.update(version(1), at(1000)));
+        assert!(!tracker.update(version(2), at(1300)));
+        assert!(!tracker.update(version(3), at(1600)));
+        assert!(!tracker.update(version(3), at(2100)));
+
+        // a second after the last one the reload happens, and only once
+        assert!(tracker.update(version(3), at(2600)));
+        assert!(!tracker.update(version(3), at(3100)));
+        assert!(!tracker.update(version(3), at(5000)));
+
+        // new files count as changes too
+        let mut with_new_file = version(3);
+        with_new_file.insert(PathBuf::from("app.json"), SystemTime::UNIX_EPOCH);
+        assert!(!tracker.update(with_new_file.clone(), at(6000)));
+        assert!(tracker.update(with_new_file, at(7000)));
+    }
+
+    #[test]
+    fn snapshot_skips_ignored_files() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        fs::create_dir_all(root.join("zomes")).unwrap();
+        fs::write(root.join("app.json"), "{}").unwrap();
+        fs::write(root.join("zomes").join("lib.rs"), "").unwrap();
+        fs::write(root.join("README.md"), "").unwrap();
+        fs::write(root.join(IGNORE_FILE_NAME), "README.md").unwrap();
+
+        let files = snapshot(&[root.clone()]);
+        assert!(files.contains_key(&root.join("app.json")));
+        assert!(files.contains_key(&root.join("zomes").join("lib.rs")));
+        assert!(!files.contains_key(&root.join("README.md")));
+    }
+}
@@ -1,4 +1,8 @@
 use failure::Error;
+use holochain_core_types::error::HolochainError as CoreError;
+use serde_json::Value;
+use std::io;
+use structopt::clap;
 
 #[derive(Debug, Fail)]
 pub enum HolochainError {
@@ -6,5 +10,109 @@ pub enum HolochainError {
     Default(Error),
 }
 
+/// Building the code of a zome failed
+#[derive(Debug, Fail)]
+#[fail(display = "{}", _0)]
+pub struct BuildFailed(pub String);
+
+/// A DNA file can't be used, e.g. because it doesn't hold valid DNA JSON
+#[derive(Debug, Fail)]
+#[fail(display = "{}", _0)]
+pub struct InvalidDna(pub String);
+
+impl HolochainError {
+    /// A stable, machine-readable identifier for the kind of error, taken from the first
+    /// of its causes that is known: "io" for failed file access, including missing files,
+    /// "invalid_dna", "build_failed" and "invalid_arguments" for command lines that don't parse.
+    /// Anything else is "default".
+    pub fn code(&self) -> &'static str {
+        let HolochainError::Default(ref err) = *self;
+        for cause in err.iter_chain() {
+            if cause.downcast_ref::<io::Error>().is_some() {
+                return "io";
+            }
+            if cause.downcast_ref::<InvalidDna>().is_some() {
+                return "invalid_dna";
+            }
+            if cause.downcast_ref::<BuildFailed>().is_some() {
+                return "build_failed";
+            }
+            if cause.downcast_ref::<clap::Error>().is_some() {
+                return "invalid_arguments";
+            }
+            match cause.downcast_ref::<CoreError>() {
+                Some(CoreError::IoError(_)) => return "io",
+                Some(CoreError::Dna(_))
+                | Some(CoreError::DnaMissing(_))
+                | Some(CoreError::DnaHashMismatch(_, _)) => return "invalid_dna",
+                _ => (),
+            }
+        }
+        "default"
+    }
+
+    /// The error message without the human-oriented "Error: " prefix
+    pub fn message(&self) -> String {
+        match self {
+            HolochainError::Default(err) => err.to_string(),
+        }
+    }
+
+    /// Structured representation of this error, as emitted with `--json-errors`
+    pub fn to_json(&self) -> Value {
+        json!({
+            "error": {
+                "code": self.code(),
+                "message": self.message(),
+            }
+        })
+    }
+}
+
 pub type DefaultResult<T> = Result<T, Error>;
 pub type HolochainResult<T> = Result<T, HolochainError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use failure::Fail;
+    use holochain_core_types::error::DnaError;
+
+    #[test]
+    fn error_to_json_test() {
+        let err = HolochainError::Default(format_err!("something broke"));
+        assert_eq!(
+            err.to_json(),
+            json!({
+                "error": {
+                    "code": "default",
+                    "message": "something broke",
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn error_codes_follow_the_cause() {
+        let code = |err: Error| HolochainError::Default(err).code();
+        let missing = io::Error::new(io::ErrorKind::NotFound, "missing.dna.json");
+        assert_eq!(code(missing.into()), "io");
+        assert_eq!(code(CoreError::IoError("gone".into()).into()), "io");
+        assert_eq!(code(InvalidDna("not JSON".into()).into()), "invalid_dna");
+        let zome_not_found = DnaError::ZomeNotFound("zome".into());
+        assert_eq!(code(CoreError::Dna(zome_not_found).into()), "invalid_dna");
+        assert_eq!(
+            code(BuildFailed("cargo failed".into()).into()),
+            "build_failed"
+        );
+        assert_eq!(code(CoreError::Timeout.into()), "default");
+
+        // context added on the way up keeps the code
+        let err: Error = BuildFailed("cargo failed".into())
+            .context("Couldn't traverse DNA: cargo failed")
+            .into();
+        let err = HolochainError::Default(err);
+        assert_eq!(err.code(), "build_failed");
+        assert_eq!(err.message(), "Couldn't traverse DNA: cargo failed");
+    }
+}
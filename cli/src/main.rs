@@ -3,6 +3,7 @@ extern crate holochain_common;
 extern crate holochain_conductor_api;
 extern crate holochain_core;
 extern crate holochain_core_types;
+extern crate holochain_dpki;
 extern crate holochain_json_api;
 extern crate holochain_persistence_api;
 extern crate holochain_persistence_file;
@@ -28,11 +29,26 @@ mod error;
 mod util;
 
 use crate::error::{HolochainError, HolochainResult};
-use std::path::PathBuf;
-use structopt::StructOpt;
+use holochain_persistence_api::cas::content::Address;
+use std::{
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+};
+use structopt::{clap, StructOpt};
 
 #[derive(StructOpt)]
 #[structopt(about = "A command line for Holochain")]
+struct Opt {
+    #[structopt(
+        long = "json-errors",
+        help = "On failure, print a machine-readable JSON error object to stderr"
+    )]
+    json_errors: bool,
+    #[structopt(subcommand)]
+    cmd: Cli,
+}
+
+#[derive(StructOpt)]
 enum Cli {
     #[structopt(
         name = "package",
@@ -47,6 +63,11 @@ enum Cli {
         strip_meta: bool,
         #[structopt(long = "output", short = "o", parse(from_os_str))]
         output: Option<PathBuf>,
+        #[structopt(
+            long = "check",
+            help = "Only checks that the project structure, zome configs and build files are valid, without building anything"
+        )]
+        check: bool,
     },
     #[structopt(
         name = "unpack",
@@ -66,6 +87,12 @@ enum Cli {
     Init {
         #[structopt(parse(from_os_str))]
         path: PathBuf,
+        #[structopt(
+            long,
+            short,
+            help = "The starter template to use: minimal/with-tests/with-ui [default: with-tests]"
+        )]
+        template: Option<String>,
     },
     #[structopt(
         name = "generate",
@@ -80,6 +107,23 @@ enum Cli {
         zome: PathBuf,
         #[structopt(help = "The language of the generated zome", default_value = "rust")]
         language: String,
+        #[structopt(
+            long = "proc-macro",
+            help = "Scaffold a rust zome using the HDK's procedural macros instead of define_zome!"
+        )]
+        proc_macro: bool,
+        #[structopt(
+            long,
+            short,
+            help = "Don't print success messages; errors are still reported"
+        )]
+        quiet: bool,
+        #[structopt(
+            long = "target-dir",
+            help = "Directory rust zomes get built into, instead of their own target directory",
+            parse(from_os_str)
+        )]
+        target_dir: Option<PathBuf>,
     },
     #[structopt(
         name = "run",
@@ -94,6 +138,12 @@ enum Cli {
             default_value = "8888"
         )]
         port: u16,
+        #[structopt(
+            long,
+            help = "The IP address the interface binds to, e.g. 127.0.0.1 to only accept local connections",
+            default_value = "0.0.0.0"
+        )]
+        bind: IpAddr,
         #[structopt(
             long,
             short = "b",
@@ -103,9 +153,9 @@ enum Cli {
         #[structopt(
             long = "dna",
             short = "d",
-            help = "Absolute path to the .dna.json file to run. [default: ./dist/<dna-name>.dna.json]"
+            help = "Absolute path to a .dna.json file to run. Repeat to run several DNAs, each in an instance of its own. [default: ./dist/<dna-name>.dna.json]"
         )]
-        dna_path: Option<PathBuf>,
+        dna_paths: Vec<PathBuf>,
         #[structopt(long, help = "Produce logging output")]
         logging: bool,
         #[structopt(long, help = "Save generated data to file system")]
@@ -119,6 +169,11 @@ enum Cli {
             default_value = "websocket"
         )]
         interface: String,
+        #[structopt(
+            long,
+            help = "Rebuild and reload the conductor whenever the project or DNA files change"
+        )]
+        watch: bool,
     },
     #[structopt(
         name = "test",
@@ -165,6 +220,30 @@ enum Cli {
         quiet: bool,
         #[structopt(long, short, help = "Don't ask for passphrase")]
         nullpass: bool,
+        #[structopt(
+            long,
+            help = "INSECURE, for testing only: derive the keys from this hex encoded 32 byte seed instead of a random one"
+        )]
+        seed: Option<String>,
+        #[structopt(
+            long,
+            help = "Output format: human, or json for an object with the agent address, keystore path and public keys",
+            default_value = "human"
+        )]
+        format: String,
+        #[structopt(
+            long,
+            help = "Instead of creating a keystore, derive a new signing key from a seed of an existing one, keeping its other keys",
+            raw(conflicts_with_all = r#"&["seed", "format", "path"]"#)
+        )]
+        rotate: bool,
+        #[structopt(long, help = "Keystore file to add the rotated key to")]
+        keystore: Option<PathBuf>,
+        #[structopt(
+            long = "seed-id",
+            help = "Identifier of the seed to derive the rotated key from"
+        )]
+        seed_id: Option<String>,
     },
     #[structopt(name = "chain", about = "View the contents of a source chain")]
     ChainLog {
@@ -174,6 +253,72 @@ enum Cli {
         path: Option<PathBuf>,
         #[structopt(long, short, help = "List available instances")]
         list: bool,
+        #[structopt(long, help = "Print at most this many entries")]
+        limit: Option<usize>,
+        #[structopt(
+            long,
+            help = "Number of latest entries to skip before printing",
+            default_value = "0"
+        )]
+        offset: usize,
+    },
+    #[structopt(
+        name = "dump_chain",
+        about = "Print the entries of a single type from a source chain"
+    )]
+    DumpChain {
+        #[structopt(name = "INSTANCE", help = "Instance ID to view")]
+        instance_id: String,
+        #[structopt(long, short, help = "Location of chain storage")]
+        path: Option<PathBuf>,
+        #[structopt(
+            long = "entry-type",
+            short = "t",
+            help = "Entry type to select, e.g. %cap_token_grant, %link_add or an app entry type"
+        )]
+        entry_type: String,
+    },
+    #[structopt(
+        name = "grant_info",
+        about = "Print a capability grant committed to a source chain"
+    )]
+    GrantInfo {
+        #[structopt(name = "INSTANCE", help = "Instance ID of the chain holding the grant")]
+        instance_id: String,
+        #[structopt(long, short, help = "Location of chain storage")]
+        path: Option<PathBuf>,
+        #[structopt(long, short, help = "Address of the grant entry, i.e. its token")]
+        address: String,
+    },
+    #[structopt(
+        name = "verify_chain",
+        about = "Verifies the agent's signature on every entry of a source chain"
+    )]
+    VerifyChain {
+        #[structopt(name = "INSTANCE", help = "Instance ID of the chain to verify")]
+        instance_id: String,
+        #[structopt(long, short, help = "Location of chain storage")]
+        path: Option<PathBuf>,
+        #[structopt(
+            long,
+            short,
+            help = "Keystore file of the agent that authored the chain"
+        )]
+        keystore: PathBuf,
+        #[structopt(long, short, help = "Don't ask for passphrase")]
+        nullpass: bool,
+    },
+    #[structopt(
+        name = "migrate_keystore",
+        about = "Rewrites a keystore file of an older format version in the current format"
+    )]
+    MigrateKeystore {
+        #[structopt(long = "in", help = "Keystore file to migrate")]
+        input: PathBuf,
+        #[structopt(long = "out", help = "Where to write the migrated keystore")]
+        output: PathBuf,
+        #[structopt(long, short, help = "Don't ask for passphrase")]
+        nullpass: bool,
     },
     #[structopt(
         name = "hash",
@@ -186,65 +331,139 @@ enum Cli {
             help = "Path to .dna.json file [default: dist/<dna-name>.dna.json]"
         )]
         path: Option<PathBuf>,
+        #[structopt(
+            long,
+            short,
+            help = "Also write the hash to this file, creating parent directories as needed"
+        )]
+        output: Option<PathBuf>,
+        #[structopt(long, short, help = "Don't print the hash to stdout")]
+        quiet: bool,
     },
 }
 
 fn main() {
     lib3h_sodium::check_init();
-    run().unwrap_or_else(|err| {
-        eprintln!("{}", err);
+    // looked for before parsing, so that arguments that don't parse get reported as JSON too
+    let json_errors = std::env::args().any(|arg| arg == "--json-errors");
+    let opt = Opt::clap()
+        .get_matches_safe()
+        .map(|matches| Opt::from_clap(&matches))
+        .unwrap_or_else(|err| match err.kind {
+            // help and version get shown through errors as well
+            clap::ErrorKind::HelpDisplayed | clap::ErrorKind::VersionDisplayed => err.exit(),
+            _ if json_errors => {
+                eprintln!("{}", HolochainError::Default(err.into()).to_json());
+                ::std::process::exit(1);
+            }
+            _ => err.exit(),
+        });
+    run(opt.cmd).unwrap_or_else(|err| {
+        if json_errors {
+            eprintln!("{}", err.to_json());
+        } else {
+            eprintln!("{}", err);
+        }
 
         ::std::process::exit(1);
     });
 }
 
-fn run() -> HolochainResult<()> {
-    let args = Cli::from_args();
-
-    let project_path =
-        std::env::current_dir().map_err(|e| HolochainError::Default(format_err!("{}", e)))?;
+fn run(args: Cli) -> HolochainResult<()> {
+    let project_path = std::env::current_dir().map_err(|e| HolochainError::Default(e.into()))?;
     match args {
         // If using default path, we'll create if necessary; otherwise, target dir must exist
-        Cli::Package { strip_meta, output } => {
-            let output = if output.is_some() {
-                output.unwrap()
+        Cli::Package {
+            strip_meta,
+            output,
+            check,
+        } => {
+            if check {
+                cli::package::check(&project_path).map_err(HolochainError::Default)?
             } else {
-                util::std_package_path(&project_path).map_err(HolochainError::Default)?
-            };
-            cli::package(strip_meta, output).map_err(HolochainError::Default)?
+                let output = if output.is_some() {
+                    output.unwrap()
+                } else {
+                    util::std_package_path(&project_path).map_err(HolochainError::Default)?
+                };
+                cli::package(strip_meta, output).map_err(HolochainError::Default)?
+            }
         }
 
         Cli::Unpack { path, to } => cli::unpack(&path, &to).map_err(HolochainError::Default)?,
 
-        Cli::Init { path } => cli::init(&path).map_err(HolochainError::Default)?,
-
-        Cli::Generate { zome, language } => {
-            cli::generate(&zome, &language).map_err(HolochainError::Default)?
+        Cli::Init { path, template } => {
+            let template = template.unwrap_or_else(|| cli::DEFAULT_TEMPLATE.to_string());
+            cli::init(&path, &template).map_err(HolochainError::Default)?
         }
 
+        Cli::Generate {
+            zome,
+            language,
+            proc_macro,
+            quiet,
+            target_dir,
+        } => cli::generate(&zome, &language, proc_macro, quiet, target_dir)
+            .map_err(HolochainError::Default)?,
+
         Cli::Run {
             package,
             port,
-            dna_path,
+            bind,
+            dna_paths,
             persist,
             networked,
             interface,
             logging,
+            watch,
         } => {
-            let dna_path = dna_path
-                .unwrap_or(util::std_package_path(&project_path).map_err(HolochainError::Default)?);
+            let dna_paths = if dna_paths.is_empty() {
+                vec![util::std_package_path(&project_path).map_err(HolochainError::Default)?]
+            } else {
+                dna_paths
+            };
+            // packaging builds the project's DNA, which is the first one
+            let dna_path = dna_paths[0].clone();
             let interface_type = cli::get_interface_type_string(interface);
+            let address = SocketAddr::new(bind, port);
             let conductor_config = cli::hc_run_configuration(
-                &dna_path,
-                port,
+                &dna_paths,
+                address,
                 persist,
                 networked,
                 &interface_type,
                 logging,
             )
             .map_err(HolochainError::Default)?;
-            cli::run(dna_path, package, port, interface_type, conductor_config)
-                .map_err(HolochainError::Default)?
+            let watch = if watch {
+                let mut paths = dna_paths.clone();
+                paths.push(project_path.clone());
+                let interface_type = interface_type.clone();
+                Some(cli::Watch {
+                    paths,
+                    configure: Box::new(move || {
+                        cli::hc_run_configuration(
+                            &dna_paths,
+                            address,
+                            persist,
+                            networked,
+                            &interface_type,
+                            logging,
+                        )
+                    }),
+                })
+            } else {
+                None
+            };
+            cli::run(
+                dna_path,
+                package,
+                port,
+                interface_type,
+                conductor_config,
+                watch,
+            )
+            .map_err(HolochainError::Default)?
         }
 
         Cli::Test {
@@ -253,8 +472,8 @@ fn run() -> HolochainResult<()> {
             skip_build,
             show_npm_output,
         } => {
-            let current_path = std::env::current_dir()
-                .map_err(|e| HolochainError::Default(format_err!("{}", e)))?;
+            let current_path =
+                std::env::current_dir().map_err(|e| HolochainError::Default(e.into()))?;
             cli::test(&current_path, &dir, &testfile, skip_build, show_npm_output)
         }
         .map_err(HolochainError::Default)?,
@@ -263,40 +482,204 @@ fn run() -> HolochainResult<()> {
             path,
             quiet,
             nullpass,
+            seed,
+            format,
+            rotate,
+            keystore,
+            seed_id,
         } => {
             let passphrase = if nullpass {
                 Some(String::from(holochain_common::DEFAULT_PASSPHRASE))
             } else {
                 None
             };
-            cli::keygen(path, passphrase, quiet)
-                .map_err(|e| HolochainError::Default(format_err!("{}", e)))?
+            if rotate {
+                let keystore = keystore.ok_or_else(|| {
+                    HolochainError::Default(format_err!("--rotate needs a --keystore"))
+                })?;
+                let seed_id = seed_id.ok_or_else(|| {
+                    HolochainError::Default(format_err!("--rotate needs a --seed-id"))
+                })?;
+                cli::rotate_key(keystore, &seed_id, passphrase, quiet)
+                    .map_err(HolochainError::Default)?
+            } else {
+                cli::keygen(path, passphrase, quiet, seed, &format)
+                    .map_err(HolochainError::Default)?
+            }
         }
 
         Cli::ChainLog {
             instance_id,
             list,
             path,
+            limit,
+            offset,
         } => match (list, instance_id) {
             (true, _) => cli::chain_list(path),
             (false, None) => {
-                Cli::clap().print_help().expect("Couldn't print help!");
+                Opt::clap().print_help().expect("Couldn't print help!");
                 println!("\n\nTry `hc help chain` for more info");
             }
             (false, Some(instance_id)) => {
-                cli::chain_log(path, instance_id)
-                    .map_err(|e| HolochainError::Default(format_err!("{}", e)))?;
+                cli::chain_log(path, instance_id, limit, offset)
+                    .map_err(HolochainError::Default)?;
             }
         },
-        Cli::HashDna { path } => {
+        Cli::DumpChain {
+            instance_id,
+            path,
+            entry_type,
+        } => {
+            cli::dump_chain(path, instance_id, entry_type).map_err(HolochainError::Default)?;
+        }
+        Cli::GrantInfo {
+            instance_id,
+            path,
+            address,
+        } => {
+            cli::grant_info(path, instance_id, Address::from(address))
+                .map_err(HolochainError::Default)?;
+        }
+        Cli::VerifyChain {
+            instance_id,
+            path,
+            keystore,
+            nullpass,
+        } => {
+            let passphrase = if nullpass {
+                Some(String::from(holochain_common::DEFAULT_PASSPHRASE))
+            } else {
+                None
+            };
+            cli::verify_chain(path, instance_id, keystore, passphrase)
+                .map_err(HolochainError::Default)?;
+        }
+        Cli::MigrateKeystore {
+            input,
+            output,
+            nullpass,
+        } => {
+            let passphrase = if nullpass {
+                Some(String::from(holochain_common::DEFAULT_PASSPHRASE))
+            } else {
+                None
+            };
+            cli::migrate_keystore(input, output, passphrase, None)
+                .map_err(HolochainError::Default)?;
+        }
+        Cli::HashDna {
+            path,
+            output,
+            quiet,
+        } => {
             let dna_path = path
                 .unwrap_or(util::std_package_path(&project_path).map_err(HolochainError::Default)?);
 
-            let dna_hash = cli::hash_dna(&dna_path)
-                .map_err(|e| HolochainError::Default(format_err!("{}", e)))?;
-            println!("DNA Hash: {}", dna_hash);
+            let dna_hash = cli::hash_dna(&dna_path).map_err(HolochainError::Default)?;
+            if let Some(output_path) = output {
+                cli::write_dna_hash(&dna_hash, &output_path).map_err(HolochainError::Default)?;
+            }
+            if !quiet {
+                println!("DNA Hash: {}", dna_hash);
+            }
         }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    extern crate assert_cmd;
+    extern crate tempfile;
+    use self::{assert_cmd::prelude::*, tempfile::tempdir};
+    use super::Opt;
+    use serde_json::Value;
+    use std::process::Command;
+    use structopt::{clap, StructOpt};
+
+    #[test]
+    fn json_errors_flag_emits_structured_error() {
+        let dir = tempdir().expect("Could not get tempdir");
+        let missing_dna = dir.path().join("missing.dna.json");
+
+        let output = Command::main_binary()
+            .unwrap()
+            .current_dir(dir.path())
+            .args(&[
+                "--json-errors",
+                "hash",
+                "--path",
+                missing_dna.to_str().unwrap(),
+            ])
+            .output()
+            .expect("should run");
+
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let error: Value = serde_json::from_str(stderr.trim()).expect("stderr should be JSON");
+        assert_eq!(error["error"]["code"], "io");
+        assert!(error["error"]["message"].is_string());
+    }
+
+    #[test]
+    fn json_errors_flag_covers_argument_errors() {
+        let output = Command::main_binary()
+            .unwrap()
+            .args(&["--json-errors", "hash", "--no-such-flag"])
+            .output()
+            .expect("should run");
+
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let error: Value = serde_json::from_str(stderr.trim()).expect("stderr should be JSON");
+        assert_eq!(error["error"]["code"], "invalid_arguments");
+        assert!(error["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("--no-such-flag"));
+    }
+
+    #[test]
+    fn errors_are_human_readable_by_default() {
+        let dir = tempdir().expect("Could not get tempdir");
+        let missing_dna = dir.path().join("missing.dna.json");
+
+        let output = Command::main_binary()
+            .unwrap()
+            .current_dir(dir.path())
+            .args(&["hash", "--path", missing_dna.to_str().unwrap()])
+            .output()
+            .expect("should run");
+
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.starts_with("Error: "));
+        assert!(serde_json::from_str::<Value>(stderr.trim()).is_err());
+    }
+
+    #[test]
+    fn keygen_rotate_rejects_options_for_new_keystores() {
+        let rotate = vec![
+            "hc",
+            "keygen",
+            "--rotate",
+            "--keystore",
+            "agent.keystore",
+            "--seed-id",
+            "root_seed",
+        ];
+        assert!(Opt::clap().get_matches_from_safe(&rotate).is_ok());
+
+        for option in &[
+            ["--seed", "00"],
+            ["--format", "json"],
+            ["--path", "out.keystore"],
+        ] {
+            let mut args = rotate.clone();
+            args.extend(option);
+            let err = Opt::clap().get_matches_from_safe(&args).unwrap_err();
+            assert_eq!(err.kind, clap::ErrorKind::ArgumentConflict);
+        }
+    }
+}
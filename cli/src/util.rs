@@ -9,14 +9,29 @@ use std::{
 };
 
 pub fn run_cmd(base_path: PathBuf, bin: String, args: &[&str]) -> DefaultResult<()> {
+    run_cmd_with_verbosity(base_path, bin, args, false)
+}
+
+/// Same as `run_cmd`, but if `quiet` is set neither the command itself nor its
+/// stdout get printed. Its stderr is left untouched so that errors still surface.
+pub fn run_cmd_with_verbosity(
+    base_path: PathBuf,
+    bin: String,
+    args: &[&str],
+    quiet: bool,
+) -> DefaultResult<()> {
     let pretty_command = format!("{} {}", bin.green(), args.join(" ").cyan());
 
-    println!("> {}", pretty_command);
+    let mut command = Command::new(bin);
+    command.args(args).current_dir(base_path);
+
+    if quiet {
+        command.stdout(Stdio::null());
+    } else {
+        println!("> {}", pretty_command);
+    }
 
-    let status = Command::new(bin)
-        .args(args)
-        .current_dir(base_path)
-        .status()?;
+    let status = command.status()?;
 
     ensure!(
         status.success(),
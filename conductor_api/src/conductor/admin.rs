@@ -420,7 +420,7 @@ impl ConductorAdmin for Conductor {
 
                 // try to create the keystore first so that if the passphrase fails we don't have
                 // to clean-up any dkpi calls
-                let mut keystore =
+                let keystore =
                     Keystore::new(self.passphrase_manager.clone(), self.hash_config.clone())?;
                 {
                     let instance = self.instances.get(&dpki_instance_id)?;
@@ -434,7 +434,7 @@ impl ConductorAdmin for Conductor {
                 // the agent in the conductor, so we should do something...
                 let dpki_config = self.config.instance_by_id(&dpki_instance_id)?;
                 let dpki_keystore = self.get_keystore_for_agent(&dpki_config.agent)?;
-                let mut dpki_keystore = dpki_keystore.lock().unwrap();
+                let dpki_keystore = dpki_keystore.lock().unwrap();
                 let mut keybundle = dpki_keystore.get_keybundle(&id)?;
                 keystore.add_keybundle(PRIMARY_KEYBUNDLE_ID, &mut keybundle)?;
                 (keystore, keybundle.get_id())
@@ -1277,6 +1277,7 @@ type = 'websocket'"#,
             driver: InterfaceDriver::Http { port: 8080 },
             admin: false,
             instances: Vec::new(),
+            bind_address: None,
         };
 
         assert_eq!(conductor.add_interface(interface_config), Ok(()),);
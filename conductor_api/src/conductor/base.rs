@@ -32,6 +32,7 @@ use std::{
     convert::TryFrom,
     fs::{self, File},
     io::prelude::*,
+    net::Ipv4Addr,
     option::NoneError,
     path::PathBuf,
     sync::{Arc, Mutex, RwLock},
@@ -42,8 +43,11 @@ use std::{
 use boolinator::Boolinator;
 #[cfg(unix)]
 use conductor::passphrase_manager::PassphraseServiceUnixSocket;
-use conductor::passphrase_manager::{
-    PassphraseManager, PassphraseService, PassphraseServiceCmd, PassphraseServiceMock,
+use conductor::{
+    passphrase_manager::{
+        PassphraseManager, PassphraseService, PassphraseServiceCmd, PassphraseServiceMock,
+    },
+    readiness::Readiness,
 };
 use config::{AgentConfiguration, PassphraseServiceConfig};
 use holochain_core_types::dna::bridges::BridgePresence;
@@ -100,6 +104,7 @@ pub struct Conductor {
     pub(in crate::conductor) interface_threads: HashMap<String, Sender<()>>,
     pub(in crate::conductor) interface_broadcasters: Arc<RwLock<HashMap<String, Broadcaster>>>,
     signal_multiplexer_kill_switch: Option<Sender<()>>,
+    readiness: Readiness,
     pub key_loader: KeyLoader,
     pub(in crate::conductor) dna_loader: DnaLoader,
     pub(in crate::conductor) ui_dir_copier: UiDirCopier,
@@ -217,6 +222,7 @@ impl Conductor {
             static_servers: HashMap::new(),
             interface_broadcasters: Arc::new(RwLock::new(HashMap::new())),
             signal_multiplexer_kill_switch: None,
+            readiness: Readiness::new(),
             config,
             key_loader: Arc::new(Box::new(Self::load_key)),
             dna_loader: Arc::new(Box::new(Self::load_dna)),
@@ -257,6 +263,12 @@ impl Conductor {
         self.config.clone()
     }
 
+    /// Tells which instances have finished initializing, so that callers can wait for them
+    /// before making zome calls
+    pub fn readiness(&self) -> Readiness {
+        self.readiness.clone()
+    }
+
     /// Starts a new thread which monitors each instance's signal channel and pushes signals out
     /// all interfaces the according instance is part of.
     pub fn start_signal_multiplexer(&mut self) -> thread::JoinHandle<()> {
@@ -264,6 +276,7 @@ impl Conductor {
         let broadcasters = self.interface_broadcasters.clone();
         let instance_signal_receivers = self.instance_signal_receivers.clone();
         let signal_tx = self.signal_tx.clone();
+        let readiness = self.readiness.clone();
         let config = self.config.clone();
         let (kill_switch_tx, kill_switch_rx) = unbounded();
         self.signal_multiplexer_kill_switch = Some(kill_switch_tx);
@@ -276,6 +289,11 @@ impl Conductor {
                     for (instance_id, receiver) in instance_signal_receivers.read().unwrap().iter()
                     {
                         if let Ok(signal) = receiver.try_recv() {
+                            if let Signal::Consistency(ref consistency_signal) = signal {
+                                if consistency_signal.is_initialization_complete() {
+                                    readiness.mark_initialized(instance_id);
+                                }
+                            }
                             signal_tx.clone().map(|s| s.send(signal.clone()));
                             let broadcasters = broadcasters.read().unwrap();
                             let interfaces_with_instance: Vec<&InterfaceConfiguration> =
@@ -418,14 +436,12 @@ impl Conductor {
         notify(format!("Starting instance \"{}\"...", id));
 
         // Get instance DNA so we can read out required bridge definitions:
-        let dna =
-            instance
-                .state()?
-                .nucleus()
-                .dna()
-                .ok_or(HolochainInstanceError::InternalFailure(
-                    HolochainError::DnaMissing,
-                ))?;
+        let dna = instance.state()?.nucleus().dna().ok_or_else(|| {
+            HolochainInstanceError::InternalFailure(HolochainError::DnaMissing(format!(
+                "can't start instance \"{}\" without one",
+                id
+            )))
+        })?;
 
         // Make sure required bridges are configured and started:
         for zome in dna.zomes.values() {
@@ -638,6 +654,7 @@ impl Conductor {
 
         let mut config = self.config.clone();
         self.shutdown().map_err(|e| e.to_string())?;
+        self.readiness.reset();
 
         self.start_signal_multiplexer();
         self.dpki_bootstrap()?;
@@ -656,6 +673,16 @@ impl Conductor {
                         )
                     })?;
 
+                // instances whose chain got initialized in an earlier run don't initialize
+                // again, so there is no signal to wait for
+                if instance
+                    .state()
+                    .map(|state| state.nucleus().has_initialized())
+                    .unwrap_or(false)
+                {
+                    self.readiness.mark_initialized(&id);
+                }
+
                 self.instances
                     .insert(id.clone(), Arc::new(RwLock::new(instance)));
             }
@@ -899,6 +926,9 @@ impl Conductor {
         }
 
         // Bridges:
+        // zome calls through this API are bridge calls made by this instance's agent
+        api_builder =
+            api_builder.with_bridge_caller(self.agent_config_to_id(&agent_config)?.address());
         let id = instance_config.id.clone();
         for bridge in config.bridge_dependencies(id.clone()) {
             assert_eq!(bridge.caller_id, id.clone());
@@ -1053,7 +1083,7 @@ impl Conductor {
                 return Err("agent is holo_remote, no keystore".to_string());
             }
 
-            let mut keystore = match agent_config.test_agent {
+            let keystore = match agent_config.test_agent {
                 Some(true) => test_keystore(&agent_config.name),
                 _ => {
                     let keystore_file_path = PathBuf::from(agent_config.keystore_file.clone());
@@ -1106,7 +1136,7 @@ impl Conductor {
         let keystore = self
             .get_keystore_for_agent(agent_id)
             .map_err(|err| format!("{}", err))?;
-        let mut keystore = keystore.lock().unwrap();
+        let keystore = keystore.lock().unwrap();
         let keybundle = keystore
             .get_keybundle(PRIMARY_KEYBUNDLE_ID)
             .map_err(|err| format!("{}", err))?;
@@ -1331,10 +1361,21 @@ impl Conductor {
 /// This can eventually be dependency injected for third party Interface definitions
 fn make_interface(interface_config: &InterfaceConfiguration) -> Box<dyn Interface> {
     use interface_impls::{http::HttpInterface, websocket::WebsocketInterface};
+    let bind_address = interface_config
+        .bind_address
+        .clone()
+        .unwrap_or_else(|| Ipv4Addr::UNSPECIFIED.to_string());
     match interface_config.driver {
-        InterfaceDriver::Websocket { port } => Box::new(WebsocketInterface::new(port)),
-        InterfaceDriver::Http { port } => Box::new(HttpInterface::new(port)),
-        _ => unimplemented!(),
+        InterfaceDriver::Websocket { port } => {
+            Box::new(WebsocketInterface::new(bind_address, port))
+        }
+        InterfaceDriver::Http { port } => Box::new(HttpInterface::new(bind_address, port)),
+        InterfaceDriver::DomainSocket { .. } => unimplemented!(),
+        InterfaceDriver::Custom(_) => {
+            unreachable!(
+                "custom interface drivers are rejected by Configuration::check_consistency"
+            )
+        }
     }
 }
 
@@ -1354,10 +1395,19 @@ pub mod tests {
     extern crate tempfile;
     use crate::config::load_configuration;
     use holochain_core::{
-        action::Action, nucleus::actions::call_zome_function::make_cap_request_for_call,
+        action::Action,
+        consistency::{ConsistencyEvent, ConsistencySignal},
+        nucleus::actions::call_zome_function::make_cap_request_for_call,
         signal::signal_channel,
+        workflows::author_entry::author_entry,
+    };
+    use holochain_core_types::{
+        dna,
+        entry::{
+            cap_entries::{CapFunctions, CapTokenGrant, CapabilityType},
+            Entry,
+        },
     };
-    use holochain_core_types::dna;
     use holochain_dpki::{key_bundle::KeyBundle, password_encryption::PwHashConfig, SEED_SIZE};
     use holochain_persistence_api::cas::content::Address;
     use holochain_wasm_utils::wasm_target_dir;
@@ -1424,7 +1474,7 @@ pub mod tests {
 
     pub fn test_keystore(index: u8) -> Keystore {
         let agent_name = format!("test-agent-{}", index);
-        let mut keystore = Keystore::new(
+        let keystore = Keystore::new(
             mock_passphrase_manager(agent_name.clone()),
             test_hash_config(),
         )
@@ -1446,7 +1496,7 @@ pub mod tests {
     }
 
     pub fn test_keybundle(index: u8) -> KeyBundle {
-        let mut keystore = test_keystore(index);
+        let keystore = test_keystore(index);
         keystore.get_keybundle(PRIMARY_KEYBUNDLE_ID).unwrap()
     }
 
@@ -1855,6 +1905,56 @@ pub mod tests {
         .unwrap();
     }
 
+    #[test]
+    fn test_instances_are_ready_after_initialization() {
+        let conductor = test_conductor(10071, 10072);
+        let instance_ids: Vec<String> = conductor
+            .config()
+            .instances
+            .iter()
+            .map(|instance| instance.id.clone())
+            .collect();
+        assert!(!instance_ids.is_empty());
+        assert!(conductor
+            .readiness()
+            .wait_for(&instance_ids, Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn test_initialization_complete_signal_makes_instance_ready() {
+        let config = load_configuration::<Configuration>(&test_toml(10081, 10082)).unwrap();
+        let mut conductor = Conductor::from_config(config);
+        let (instance_signal_tx, instance_signal_rx) = unbounded();
+        conductor
+            .instance_signal_receivers
+            .write()
+            .unwrap()
+            .insert(String::from("test-instance-1"), instance_signal_rx);
+        conductor.start_signal_multiplexer();
+        let readiness = conductor.readiness();
+        let instance_ids = vec![String::from("test-instance-1")];
+
+        // other signals don't make an instance ready
+        let other_signal = ConsistencySignal::new_terminal(ConsistencyEvent::UpdateAgentId(
+            Address::from("old"),
+            Address::from("new"),
+        ));
+        instance_signal_tx
+            .send(Signal::Consistency(other_signal.into()))
+            .unwrap();
+        assert!(!readiness.wait_for(&instance_ids, Duration::from_millis(100)));
+
+        let ready_signal =
+            ConsistencySignal::new_terminal(ConsistencyEvent::InitializationComplete);
+        instance_signal_tx
+            .send(Signal::Consistency(ready_signal.into()))
+            .unwrap();
+        assert!(readiness.wait_for(&instance_ids, Duration::from_secs(5)));
+        assert!(!readiness.is_initialized("test-instance-2"));
+
+        conductor.stop_signal_multiplexer();
+    }
+
     pub fn callee_wat() -> String {
         r#"
 (module
@@ -1965,10 +2065,7 @@ pub mod tests {
         FnDeclaration {
             name: String::from("hello"),
             inputs: vec![],
-            outputs: vec![dna::fn_declarations::FnParameter {
-                name: String::from("greeting"),
-                parameter_type: String::from("String"),
-            }],
+            outputs: vec![dna::fn_declarations::FnParameter::new("greeting", "String")],
         }
     }
 
@@ -2072,9 +2169,11 @@ pub mod tests {
             make_cap_request_for_call(
                 context.clone(),
                 Address::from(context.clone().agent_id.address()),
+                "test_zome",
                 "call_bridge",
                 JsonString::empty_object(),
             )
+            .unwrap()
         };
         let result = instance
             .call("test_zome", cap_call, "call_bridge", "{}")
@@ -2104,9 +2203,11 @@ pub mod tests {
             make_cap_request_for_call(
                 context.clone(),
                 Address::from(context.clone().agent_id.address()),
+                "test_zome",
                 "call_bridge_error",
                 JsonString::empty_object(),
             )
+            .unwrap()
         };
         let result = instance.call("test_zome", cap_call, "call_bridge_error", "{}");
 
@@ -2114,6 +2215,124 @@ pub mod tests {
         assert!(result.unwrap().to_string().contains("Holochain Instance Error: Zome function \'non-existent-function\' not found in Zome \'greeter\'"));
     }
 
+    /// the callee DNA with `hello` left out of its public trait, so that bridge callers need
+    /// a grant to call it
+    fn private_callee_dna() -> Dna {
+        let mut dna = callee_dna();
+        dna.zomes
+            .get_mut("greeter")
+            .unwrap()
+            .traits
+            .get_mut("hc_public")
+            .unwrap()
+            .functions
+            .retain(|function| function != "hello");
+        dna
+    }
+
+    #[test]
+    fn bridge_call_authorized_by_bridge_grant() {
+        // a second instance running the caller DNA, but with another agent
+        let toml = test_toml(10061, 10062).replace(
+            &callee_dna().address().to_string(),
+            &private_callee_dna().address().to_string(),
+        ) + r#"
+    [[instances]]
+    id = "bridge-caller-2"
+    dna = "bridge-caller"
+    agent = "test-agent-2"
+    [instances.storage]
+    type = "memory"
+
+    [[bridges]]
+    caller_id = "bridge-caller-2"
+    callee_id = "test-instance-1"
+    handle = "DPKI"
+
+    [[bridges]]
+    caller_id = "bridge-caller-2"
+    callee_id = "test-instance-2"
+    handle = "happ-store"
+
+    [[bridges]]
+    caller_id = "bridge-caller-2"
+    callee_id = "test-instance-1"
+    handle = "test-callee"
+    "#;
+        let config = load_configuration::<Configuration>(&toml).unwrap();
+        let mut conductor = Conductor::from_config(config.clone());
+        let loader = Box::new(|path: &PathBuf| {
+            Ok(match path.to_str().unwrap().as_ref() {
+                "bridge/callee.dna" => private_callee_dna(),
+                "bridge/caller.dna" => caller_dna(),
+                _ => Dna::try_from(JsonString::from_json(&example_dna_string())).unwrap(),
+            })
+        })
+            as Box<dyn FnMut(&PathBuf) -> Result<Dna, HolochainError> + Send + Sync>;
+        conductor.dna_loader = Arc::new(loader);
+        conductor.key_loader = test_key_loader();
+        conductor
+            .boot_from_config()
+            .expect("Test config must be sane");
+        conductor
+            .start_all_instances()
+            .expect("Instances must be spawnable");
+
+        let call_bridge = |conductor: &Conductor, caller_id: &str| {
+            let caller_instance = conductor.instances[caller_id].clone();
+            let mut instance = caller_instance.write().unwrap();
+            let cap_call = {
+                let context = instance.context().unwrap();
+                make_cap_request_for_call(
+                    context.clone(),
+                    context.agent_id.address(),
+                    "test_zome",
+                    "call_bridge",
+                    JsonString::empty_object(),
+                )
+                .unwrap()
+            };
+            instance
+                .call("test_zome", cap_call, "call_bridge", "{}")
+                .ok()
+        };
+        let greeting = Some(JsonString::from("Holo World"));
+
+        // the public grant doesn't cover `hello` anymore
+        assert_ne!(call_bridge(&conductor, "bridge-caller"), greeting);
+
+        // a bridge grant for the caller's agent lets its calls through the bridge
+        let caller_agent = conductor.instances["bridge-caller"]
+            .read()
+            .unwrap()
+            .context()
+            .unwrap()
+            .agent_id
+            .address();
+        let callee_context = conductor.instances["test-instance-1"]
+            .read()
+            .unwrap()
+            .context()
+            .unwrap();
+        let mut functions = CapFunctions::new();
+        functions.insert(String::from("greeter"), vec![String::from("hello")]);
+        let grant = CapTokenGrant::create(
+            "bridge",
+            CapabilityType::Bridge,
+            Some(vec![caller_agent]),
+            functions,
+        )
+        .unwrap();
+        let grant = Entry::CapTokenGrant(grant);
+        callee_context
+            .block_on(author_entry(&grant, None, &callee_context, &vec![]))
+            .unwrap();
+        assert_eq!(call_bridge(&conductor, "bridge-caller"), greeting);
+
+        // but not those of another instance running the same DNA
+        assert_ne!(call_bridge(&conductor, "bridge-caller-2"), greeting);
+    }
+
     #[test]
     fn error_if_required_bridge_missing() {
         let mut config = load_configuration::<Configuration>(&test_toml(10061, 10062)).unwrap();
@@ -2160,10 +2379,10 @@ pub mod tests {
             let mut fn_declaration = bridge_call_fn_declaration();
             fn_declaration
                 .inputs
-                .push(dna::fn_declarations::FnParameter {
-                    name: String::from("additional_parameter"),
-                    parameter_type: String::from("String"),
-                });
+                .push(dna::fn_declarations::FnParameter::new(
+                    "additional_parameter",
+                    "String",
+                ));
             bridge.reference = BridgeReference::Trait {
                 traits: btreemap! {
                     String::from("greetable") => Trait{
@@ -2272,7 +2491,7 @@ pub mod tests {
         println!("{:?}", result);
         assert_eq!(
             result.err().unwrap(),
-            "Bridge \'test-callee\' of instance \'bridge-caller\' requires callee to to implement trait \'greetable\' with functions: [FnDeclaration { name: \"hello\", inputs: [FnParameter { parameter_type: \"String\", name: \"additional_parameter\" }], outputs: [FnParameter { parameter_type: \"String\", name: \"greeting\" }] }]",
+            "Bridge \'test-callee\' of instance \'bridge-caller\' requires callee to to implement trait \'greetable\' with functions: [FnDeclaration { name: \"hello\", inputs: [FnParameter { parameter_type: \"String\", name: \"additional_parameter\", default: None }], outputs: [FnParameter { parameter_type: \"String\", name: \"greeting\", default: None }] }]",
         );
     }
 
@@ -2348,9 +2567,11 @@ pub mod tests {
                 make_cap_request_for_call(
                     context.clone(),
                     Address::from(context.clone().agent_id.address()),
+                    "test_zome",
                     "call_bridge",
                     JsonString::empty_object(),
                 )
+                .unwrap()
             };
             bridge_caller.call(
                 "test_zome",
@@ -3,6 +3,7 @@ pub mod base;
 pub mod broadcaster;
 pub mod debug;
 pub mod passphrase_manager;
+pub mod readiness;
 pub mod test_admin;
 pub mod ui_admin;
 
@@ -10,6 +11,7 @@ pub use self::{
     admin::ConductorAdmin,
     base::{mount_conductor_from_config, Conductor, CONDUCTOR},
     debug::ConductorDebug,
+    readiness::Readiness,
     test_admin::ConductorTestAdmin,
     ui_admin::ConductorUiAdmin,
 };
@@ -0,0 +1,54 @@
+use std::{
+    collections::HashSet,
+    sync::{Arc, Condvar, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Keeps track of which instances of a conductor have finished initializing and are ready
+/// to accept zome calls. The conductor marks instances when their `InitializationComplete`
+/// consistency signal comes through.
+/// Clones share the same state, so waiting on a clone doesn't need access to the conductor.
+#[derive(Clone, Default)]
+pub struct Readiness {
+    initialized: Arc<(Mutex<HashSet<String>>, Condvar)>,
+}
+
+impl Readiness {
+    pub fn new() -> Self {
+        Readiness::default()
+    }
+
+    pub(crate) fn mark_initialized(&self, instance_id: &str) {
+        let (ref initialized, ref changed) = *self.initialized;
+        initialized.lock().unwrap().insert(instance_id.to_string());
+        changed.notify_all();
+    }
+
+    /// Forgets about all instances, for when they get instantiated anew
+    pub(crate) fn reset(&self) {
+        self.initialized.0.lock().unwrap().clear();
+    }
+
+    pub fn is_initialized(&self, instance_id: &str) -> bool {
+        self.initialized.0.lock().unwrap().contains(instance_id)
+    }
+
+    /// Blocks until all of the given instances have initialized, returning false if that
+    /// doesn't happen within `timeout`
+    pub fn wait_for(&self, instance_ids: &[String], timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let (ref initialized, ref changed) = *self.initialized;
+        let mut initialized_ids = initialized.lock().unwrap();
+        while !instance_ids.iter().all(|id| initialized_ids.contains(id)) {
+            let now = Instant::now();
+            if now >= deadline {
+                return false;
+            }
+            initialized_ids = changed
+                .wait_timeout(initialized_ids, deadline - now)
+                .unwrap()
+                .0;
+        }
+        true
+    }
+}
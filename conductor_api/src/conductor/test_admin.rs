@@ -19,7 +19,7 @@ impl ConductorTestAdmin for Conductor {
                 id
             )));
         }
-        let mut keystore = test_keystore(&name);
+        let keystore = test_keystore(&name);
         let keybundle = keystore
             .get_keybundle(PRIMARY_KEYBUNDLE_ID)
             .expect("Couldn't get KeyBundle that was just added back from Keystore");
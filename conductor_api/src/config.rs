@@ -257,6 +257,12 @@ impl Configuration {
         }
 
         for ref interface in self.interfaces.iter() {
+            if let InterfaceDriver::Custom(_) = interface.driver {
+                return Err(format!(
+                    "Interface \"{}\" has a custom driver, which the conductor can't run",
+                    interface.id
+                ));
+            }
             for ref instance in interface.instances.iter() {
                 self.instance_by_id(&instance.id).is_some().ok_or_else(|| {
                     format!(
@@ -707,6 +713,11 @@ pub struct InterfaceConfiguration {
     pub admin: bool,
     #[serde(default)]
     pub instances: Vec<InstanceReferenceConfiguration>,
+    /// Address the interface binds to
+    /// Can be either IPv4 or IPv6
+    /// Default = "0.0.0.0", i.e. all IPv4 network interfaces
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bind_address: Option<String>,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
@@ -1260,6 +1271,49 @@ pub mod tests {
         );
     }
 
+    #[test]
+    fn test_inconsistent_config_custom_interface() {
+        let toml = r#"
+    [[agents]]
+    id = "test agent"
+    name = "Holo Tester 1"
+    public_address = "HoloTester1-------------------------------------------------------------------------AHi1"
+    keystore_file = "holo_tester.key"
+
+    [[dnas]]
+    id = "app spec rust"
+    file = "app_spec.dna.json"
+    hash = "Qm328wyq38924y"
+
+    [[instances]]
+    id = "app spec instance"
+    dna = "app spec rust"
+    agent = "test agent"
+        [instances.storage]
+        type = "memory"
+
+    [[interfaces]]
+    id = "app spec interface"
+        [interfaces.driver]
+        type = "websocket"
+        port = 8888
+        [[interfaces.instances]]
+        id = "app spec instance"
+    "#;
+
+        let mut config = load_configuration::<Configuration>(toml).unwrap();
+        assert_eq!(config.check_consistency(&mut test_dna_loader()), Ok(()));
+
+        config.interfaces[0].driver = InterfaceDriver::Custom(toml::Value::String("wss".into()));
+        assert_eq!(
+            config.check_consistency(&mut test_dna_loader()),
+            Err(
+                "Interface \"app spec interface\" has a custom driver, which the conductor can't run"
+                    .to_string()
+            )
+        );
+    }
+
     #[test]
     fn test_invalid_toml_1() {
         let toml = &format!(
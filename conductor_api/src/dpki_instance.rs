@@ -29,12 +29,13 @@ fn dpki_cap_request(
     parameters: &str,
 ) -> Result<CapabilityRequest, HolochainError> {
     let token = Address::from(context.agent_id.address());
-    Ok(make_cap_request_for_call(
+    make_cap_request_for_call(
         context.clone(),
         token,
+        DPKI_ZOME_NAME,
         function,
         JsonString::from_json(parameters),
-    ))
+    )
 }
 
 impl DpkiInstance for Holochain {
@@ -96,8 +96,10 @@ pub mod tests {
                 "Holochain instance is already active.",
             ),
             (
-                HolochainInstanceError::InternalFailure(HolochainError::DnaMissing),
-                "DNA is missing",
+                HolochainInstanceError::InternalFailure(HolochainError::DnaMissing(String::from(
+                    "reason",
+                ))),
+                "DNA is missing: reason",
             ),
             (
                 HolochainInstanceError::NoSuchInstance,
@@ -119,8 +121,10 @@ pub mod tests {
     /// show From<HolochainError> for HolochainInstanceError
     fn holochain_instance_error_from_holochain_error_test() {
         assert_eq!(
-            HolochainInstanceError::InternalFailure(HolochainError::DnaMissing),
-            HolochainInstanceError::from(HolochainError::DnaMissing),
+            HolochainInstanceError::InternalFailure(HolochainError::DnaMissing(String::from(
+                "reason"
+            ))),
+            HolochainInstanceError::from(HolochainError::DnaMissing(String::from("reason"))),
         );
     }
 
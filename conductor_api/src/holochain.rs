@@ -239,10 +239,29 @@ impl Holochain {
         fn_name: &str,
         params: &str,
     ) -> HolochainResult<JsonString> {
+        let zome_call = ZomeFnCall::new(&zome, cap, &fn_name, JsonString::from_json(&params));
+        self.call_zome_fn(zome_call)
+    }
+
+    /// call a function in a zome on behalf of another instance that is bridged to this one,
+    /// identified by the address of its agent, so that bridge grants made for it apply
+    pub fn bridge_call(
+        &mut self,
+        caller: Address,
+        zome: &str,
+        cap: CapabilityRequest,
+        fn_name: &str,
+        params: &str,
+    ) -> HolochainResult<JsonString> {
+        let zome_call = ZomeFnCall::new(&zome, cap, &fn_name, JsonString::from_json(&params))
+            .with_bridge_caller(caller);
+        self.call_zome_fn(zome_call)
+    }
+
+    fn call_zome_fn(&mut self, zome_call: ZomeFnCall) -> HolochainResult<JsonString> {
         self.check_instance()?;
         self.check_active()?;
 
-        let zome_call = ZomeFnCall::new(&zome, cap, &fn_name, JsonString::from_json(&params));
         let context = self.context()?;
         Ok(context.block_on(call_zome_function(zome_call, context.clone()))?)
     }
@@ -361,9 +380,11 @@ mod tests {
         make_cap_request_for_call(
             context.clone(),
             Address::from(context.clone().agent_id.address()),
+            "test_zome",
             fn_name,
             JsonString::from_json(params),
         )
+        .unwrap()
     }
 
     #[test]
@@ -471,9 +492,7 @@ mod tests {
         let result = Holochain::new(dna.clone(), context.clone());
         assert!(result.is_err());
         assert_eq!(
-            HolochainInstanceError::from(HolochainError::ErrorGeneric(
-                "Timeout while initializing".to_string()
-            )),
+            HolochainInstanceError::from(HolochainError::Timeout),
             result.err().unwrap(),
         );
     }
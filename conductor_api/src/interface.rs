@@ -7,7 +7,7 @@ use holochain_core::nucleus::actions::call_zome_function::make_cap_request_for_c
 use holochain_core_types::{
     agent::AgentId, dna::capabilities::CapabilityRequest, signature::Provenance,
 };
-use holochain_dpki::key_bundle::KeyBundle;
+use holochain_dpki::{key_bundle::KeyBundle, seed::check_seed_size};
 use holochain_json_api::json::JsonString;
 use holochain_persistence_api::cas::content::Address;
 use lib3h_sodium::secbuf::SecBuf;
@@ -99,6 +99,7 @@ pub struct ConductorApiBuilder {
     instances: InstanceMap,
     instance_ids_map: PublicInstanceMap,
     instance_configs: HashMap<String, InstanceConfiguration>,
+    bridge_caller: Option<Address>,
     io: Box<IoHandler>,
 }
 
@@ -108,6 +109,7 @@ impl ConductorApiBuilder {
             instances: HashMap::new(),
             instance_ids_map: HashMap::new(),
             instance_configs: HashMap::new(),
+            bridge_caller: None,
             io: Box::new(IoHandler::new()),
         }
     }
@@ -123,6 +125,7 @@ impl ConductorApiBuilder {
     fn setup_call_api(&mut self) {
         let instances = self.instances.clone();
         let instance_ids_map = self.instance_ids_map.clone();
+        let bridge_caller = self.bridge_caller.clone();
 
         // We need to place this one here in order to avoid compiler lifetime issue
         let default_call_args = json!({});
@@ -183,9 +186,15 @@ impl ConductorApiBuilder {
                     None => make_cap_request_for_call(
                         context.clone(),
                         token,
+                        &zome_name,
                         &func_name,
                         JsonString::from_json(&args_string.clone()),
-                    ),
+                    )
+                    .map_err(|e| {
+                        let mut error = jsonrpc_core::Error::internal_error();
+                        error.message = e.to_string();
+                        error
+                    })?,
                     Some(json_provenance) => {
                         let provenance: Provenance =
                             serde_json::from_value(json_provenance.to_owned()).map_err(|e| {
@@ -199,9 +208,17 @@ impl ConductorApiBuilder {
                 }
             };
 
-            let response = hc
-                .call(&zome_name, cap_request, &func_name, &args_string)
-                .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))?;
+            let response = match bridge_caller {
+                Some(ref caller) => hc.bridge_call(
+                    caller.clone(),
+                    &zome_name,
+                    cap_request,
+                    &func_name,
+                    &args_string,
+                ),
+                None => hc.call(&zome_name, cap_request, &func_name, &args_string),
+            }
+            .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))?;
             Ok(Value::String(response.to_string()))
         });
     }
@@ -257,6 +274,14 @@ impl ConductorApiBuilder {
         self
     }
 
+    /// Marks all zome calls made through this handler as bridge calls from the instance
+    /// whose agent has the given address.
+    /// This is for the handlers instances make bridge calls through.
+    pub fn with_bridge_caller(mut self, caller_agent_address: Address) -> Self {
+        self.bridge_caller = Some(caller_agent_address);
+        self
+    }
+
     /// Add a single instance and register it under the given name
     pub fn with_named_instance(
         mut self,
@@ -613,6 +638,7 @@ impl ConductorApiBuilder {
                     }
                 },
                 instances: Vec::new(),
+                bind_address: None,
             };
 
             conductor_call!(|c| c.add_interface(new_interface))?;
@@ -1094,6 +1120,9 @@ impl ConductorApiBuilder {
                 let params_map = Self::unwrap_params_map(params)?;
                 let id = Self::get_as_string("dst_id", &params_map)?;
                 let size = Self::get_as_int("size", &params_map)? as usize;
+                // any other size used to be accepted here and then panic when storing the seed
+                check_seed_size(size)
+                    .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))?;
                 k.lock()
                     .unwrap()
                     .add_random_seed(&id, size)
@@ -1216,7 +1245,10 @@ pub trait Interface {
 #[cfg(test)]
 pub mod tests {
     use super::*;
-    use crate::{conductor::tests::test_conductor, config::Configuration};
+    use crate::{
+        conductor::tests::test_conductor, config::Configuration,
+        key_loaders::mock_passphrase_manager, keystore::test_hash_config,
+    };
 
     fn example_config_and_instances() -> (Configuration, InstanceMap) {
         let conductor = test_conductor(7777, 7778);
@@ -1427,4 +1459,33 @@ pub mod tests {
             r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Holochain Instance Error: Holochain instance is not active yet."},"id":"0"}"#
         );
     }
+
+    #[test]
+    fn test_add_random_seed_checks_size() {
+        let keystore = Keystore::new(
+            mock_passphrase_manager("passphrase".to_string()),
+            test_hash_config(),
+        )
+        .unwrap();
+        let handler = ConductorApiBuilder::new()
+            .with_agent_keystore_functions(Arc::new(Mutex::new(keystore)))
+            .spawn();
+        let add_random_seed = |size: usize| {
+            handler
+                .handle_request_sync(&create_call_str(
+                    "agent/keystore/add_random_seed",
+                    Some(json!({"dst_id": format!("seed{}", size), "size": size})),
+                ))
+                .expect("Invalid call to handler")
+        };
+
+        assert_eq!(
+            add_random_seed(16),
+            r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid seed size: expected 32 bytes, got 16"},"id":"0"}"#
+        );
+        assert_eq!(
+            add_random_seed(32),
+            r#"{"jsonrpc":"2.0","result":{"success":true},"id":"0"}"#
+        );
+    }
 }
@@ -5,13 +5,16 @@ use jsonrpc_core::IoHandler;
 use jsonrpc_http_server::ServerBuilder;
 use std::thread;
 
+use super::socket_address;
+
 pub struct HttpInterface {
+    bind_address: String,
     port: u16,
 }
 
 impl HttpInterface {
-    pub fn new(port: u16) -> Self {
-        HttpInterface { port }
+    pub fn new(bind_address: String, port: u16) -> Self {
+        HttpInterface { bind_address, port }
     }
 }
 
@@ -21,14 +24,14 @@ impl Interface for HttpInterface {
         handler: IoHandler,
         kill_switch: Receiver<()>,
     ) -> Result<(Broadcaster, thread::JoinHandle<()>), String> {
-        let url = format!("0.0.0.0:{}", self.port);
+        let address = socket_address(&self.bind_address, self.port)?;
 
         let server = ServerBuilder::new(handler)
-            .start_http(&url.parse().expect("Invalid URL!"))
+            .start_http(&address)
             .map_err(|e| e.to_string())?;
         let broadcaster = Broadcaster::Noop;
         let handle = thread::Builder::new()
-            .name(format!("http_interface/{}", url))
+            .name(format!("http_interface/{}", address))
             .spawn(move || {
                 let _ = server; // move `server` into this thread
                 let _ = kill_switch.recv();
@@ -2,3 +2,13 @@ pub mod http;
 pub mod websocket;
 
 pub use self::{http::*, websocket::*};
+
+use std::net::{IpAddr, SocketAddr};
+
+/// The address an interface serves at, given the IP address (v4 or v6) it binds to
+fn socket_address(bind_address: &str, port: u16) -> Result<SocketAddr, String> {
+    bind_address
+        .parse::<IpAddr>()
+        .map(|ip| SocketAddr::new(ip, port))
+        .map_err(|_| format!("Invalid interface bind address: {}", bind_address))
+}
@@ -5,13 +5,16 @@ use jsonrpc_core::IoHandler;
 use jsonrpc_ws_server::ServerBuilder;
 use std::thread;
 
+use super::socket_address;
+
 pub struct WebsocketInterface {
+    bind_address: String,
     port: u16,
 }
 
 impl WebsocketInterface {
-    pub fn new(port: u16) -> Self {
-        WebsocketInterface { port }
+    pub fn new(bind_address: String, port: u16) -> Self {
+        WebsocketInterface { bind_address, port }
     }
 }
 
@@ -21,13 +24,13 @@ impl Interface for WebsocketInterface {
         handler: IoHandler,
         kill_switch: Receiver<()>,
     ) -> Result<(Broadcaster, thread::JoinHandle<()>), String> {
-        let url = format!("0.0.0.0:{}", self.port);
+        let address = socket_address(&self.bind_address, self.port)?;
         let server = ServerBuilder::new(handler)
-            .start(&url.parse().expect("Invalid URL!"))
+            .start(&address)
             .map_err(|e| e.to_string())?;
         let broadcaster = Broadcaster::Ws(server.broadcaster());
         let handle = thread::Builder::new()
-            .name(format!("websocket_interface/{}", url))
+            .name(format!("websocket_interface/{}", address))
             .spawn(move || {
                 let _ = server; // move `server` into this thread
                 let _ = kill_switch.recv();
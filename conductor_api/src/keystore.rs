@@ -7,49 +7,238 @@ use holochain_dpki::{
     key_blob::{BlobType, Blobbable, KeyBlob},
     key_bundle::KeyBundle,
     keypair::{EncryptingKeyPair, KeyPair, SigningKeyPair},
-    seed::Seed,
+    seed::{check_seed_size, Seed},
     utils::{
         decrypt_with_passphrase_buf, encrypt_with_passphrase_buf, generate_derived_seed_buf,
         generate_random_buf, SeedContext,
     },
-    SEED_SIZE,
+    AGENT_ID_CTX, DEVICE_CTX, SEED_SIZE,
 };
 
 use lib3h_sodium::{
-    pwhash::{ALG_ARGON2ID13, MEMLIMIT_INTERACTIVE, OPSLIMIT_INTERACTIVE},
+    hash::sha256,
+    pwhash::{self, ALG_ARGON2ID13, MEMLIMIT_INTERACTIVE, OPSLIMIT_INTERACTIVE},
     secbuf::SecBuf,
 };
+use serde_json::Value;
 
 use conductor::passphrase_manager::PassphraseManager;
-use holochain_dpki::{password_encryption::PwHashConfig, seed::SeedType};
+use holochain_dpki::{password_encryption::PwHashConfig, seed::SeedType, utils};
+use holochain_persistence_api::cas::content::{Address, AddressableContent};
 use std::{
     collections::{BTreeMap, HashMap},
     fs::File,
     io::prelude::*,
     path::PathBuf,
-    sync::{Arc, Mutex},
+    str::FromStr,
+    sync::{Arc, Mutex, RwLock},
 };
 
 const PCHECK_HEADER_SIZE: usize = 8;
 const PCHECK_HEADER: [u8; 8] = *b"PHCCHECK";
 const PCHECK_RANDOM_SIZE: usize = 32;
 const PCHECK_SIZE: usize = PCHECK_RANDOM_SIZE + PCHECK_HEADER_SIZE;
+const CHECKSUM_KEY: &str = "checksum";
+const VERSION_KEY: &str = "version";
+const CHECKSUM_SIZE: usize = 32;
 const KEYBUNDLE_SIGNKEY_SUFFIX: &str = ":sign_key";
 const KEYBUNDLE_ENCKEY_SUFFIX: &str = ":enc_key";
+const DEVICE_SEED_SUFFIX: &str = ":device_seed";
+const AGENT_SEED_SUFFIX: &str = ":agent_seed";
+const AGENT_KEYBUNDLE_SUFFIX: &str = ":agent_keybundle";
+const ROTATED_SEED_INFIX: &str = ":rotated_seed_";
+const ROTATED_KEY_INFIX: &str = ":rotated_key_";
+const ROTATION_CTX: [u8; 8] = *b"HCROTATE";
+/// Version of the keystore file format this build writes and reads up to.
+/// Agent key files of the format used before keystores existed count as version 0,
+/// see [Keystore::import_legacy].
+pub const KEYSTORE_FORMAT_VERSION: u64 = 1;
+/// Largest index seeds can be derived at. Indices come in over JSON, so they are kept to the
+/// integers JSON clients like JavaScript represent exactly, which also catches negative
+/// indices that got cast to `u64`.
+pub const MAX_DERIVATION_INDEX: u64 = (1 << 53) - 1;
 pub const PRIMARY_KEYBUNDLE_ID: &str = "primary_keybundle";
 pub const STANDALONE_ROOT_SEED: &str = "root_seed";
+/// How many buffers for data to sign a keystore keeps around for reuse, unless set otherwise
+/// with [Keystore::set_sign_buffer_pool_size]
+pub const DEFAULT_SIGN_BUFFER_POOL_SIZE: usize = 16;
+/// Fixed so that stretching the same seed with the same [KdfParams] always yields the same key
+const KDF_SALT: [u8; pwhash::SALTBYTES] = *b"HCKEYSTORE:SEED:";
 
 pub enum Secret {
     SigningKey(SigningKeyPair),
     EncryptingKey(EncryptingKeyPair),
     Seed(SecBuf),
+    /// A signing key that never leaves the device holding it, e.g. an HSM or a smartcard.
+    /// Signers only live in memory, they are not written to the keystore file.
+    Signer(Box<dyn Signer>),
 }
 
+/// Produces signatures without handing out the private key.
+/// Implement this to have [Keystore::sign] use hardware keys.
+pub trait Signer: Send {
+    fn sign(&self, data: &mut SecBuf) -> HcResult<SecBuf>;
+}
+
+/// Software keys sign in place, so they need to be locked to be shared as a [Signer]
+impl Signer for Mutex<SigningKeyPair> {
+    fn sign(&self, data: &mut SecBuf) -> HcResult<SecBuf> {
+        self.lock()?.sign(data)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum KeyType {
     Signing,
     Encrypting,
 }
 
+/// Where a key pair in the keystore was derived from, so that it can be derived again
+/// to check it for corruption with [Keystore::verify_integrity]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct KeyOrigin {
+    seed_id: String,
+    key_type: KeyType,
+    kdf_params: Option<KdfParams>,
+}
+
+/// Work factor of the Argon2id hash that seeds are stretched with before keys get derived
+/// from them. Raise these as hardware gets faster.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct KdfParams {
+    /// Number of passes over the memory (Argon2's ops limit)
+    pub iterations: u64,
+    /// Memory used for hashing, in bytes
+    pub memory: usize,
+}
+
+impl KdfParams {
+    /// Stretches `seed` into a new seed of the same size
+    fn stretch(&self, seed: &mut SecBuf) -> HcResult<SecBuf> {
+        let mut salt = SecBuf::with_insecure(pwhash::SALTBYTES);
+        salt.write(0, &KDF_SALT)?;
+        let mut stretched = SecBuf::with_secure(SEED_SIZE);
+        pwhash::hash(
+            seed,
+            self.iterations,
+            self.memory,
+            ALG_ARGON2ID13,
+            &mut salt,
+            &mut stretched,
+        )?;
+        Ok(stretched)
+    }
+}
+
+/// How the identifiers of one keystore differ from those of another, see [Keystore::diff].
+/// Only names identifiers, never anything about the secrets stored under them.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct KeystoreDiff {
+    /// Identifiers only the other keystore has
+    pub added: Vec<String>,
+    /// Identifiers only this keystore has
+    pub removed: Vec<String>,
+    /// Identifiers both keystores have, but for different types of secrets
+    pub type_changed: Vec<String>,
+}
+
+impl KeystoreDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.type_changed.is_empty()
+    }
+}
+
+/// The type of a secret as far as it can be told without decrypting it.
+/// Signers live outside of the keystore and have none.
+type SecretType = Option<(BlobType, SeedType)>;
+
+/// Identifiers and public key of the secrets added by [Keystore::rotate_signing_key]
+#[derive(Clone, Debug, PartialEq)]
+pub struct RotatedKey {
+    pub index: u64,
+    pub seed_id: String,
+    pub key_id: String,
+    pub public_key: Base32,
+}
+
+/// Identifiers and public keys of the secrets added by [Keystore::derive_agent_keys]
+#[derive(Clone, Debug, PartialEq)]
+pub struct AgentKeys {
+    pub device_seed_id: String,
+    pub agent_seed_id: String,
+    pub keybundle_id: String,
+    pub sign_public_key: Base32,
+    pub enc_public_key: Base32,
+}
+
+/// The public keys of a keybundle, safe to hand out to peers
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PublicKeyBundle {
+    pub sign_key: Base32,
+    pub enc_key: Base32,
+}
+
+/// The signature algorithms that [verify] can check signatures for.
+/// Agent keys are all Ed25519 for now, but this is where other algorithms would get added.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SignatureAlgorithm {
+    Ed25519,
+}
+
+impl Default for SignatureAlgorithm {
+    fn default() -> Self {
+        SignatureAlgorithm::Ed25519
+    }
+}
+
+impl FromStr for SignatureAlgorithm {
+    type Err = HolochainError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ed25519" => Ok(SignatureAlgorithm::Ed25519),
+            _ => Err(HolochainError::ErrorGeneric(format!(
+                "unsupported signature algorithm: {}",
+                s
+            ))),
+        }
+    }
+}
+
+/// verifies that `signature` is a signature of `data` by the public key `source`,
+/// using the given signature algorithm.
+/// Since [Keystore::sign] refuses to sign empty data, no signature holds for it.
+pub fn verify(
+    algorithm: &SignatureAlgorithm,
+    source: Address,
+    data: String,
+    signature: Signature,
+) -> HcResult<bool> {
+    if data.is_empty() {
+        return Ok(false);
+    }
+    match algorithm {
+        SignatureAlgorithm::Ed25519 => utils::verify(source, data, signature),
+    }
+}
+
+/// verifies a signature made with [Keystore::sign_with_context]: it only holds if `data` was
+/// signed for the very same `domain`
+pub fn verify_with_context(
+    algorithm: &SignatureAlgorithm,
+    source: Address,
+    data: String,
+    domain: &str,
+    signature: Signature,
+) -> HcResult<bool> {
+    verify(algorithm, source, domain_separated(domain, data), signature)
+}
+
+/// prepends a tag for `domain` to `data`; the length prefix keeps the boundary between
+/// domain and data unambiguous
+fn domain_separated(domain: &str, data: String) -> String {
+    format!("{}:{}:{}", domain.len(), domain, data)
+}
+
 /// A type for providing high-level crypto functions and managing secrets securely.
 /// Keystore can store an arbitrary number of named secrets such as key pairs and seeds.
 /// It can be serialized and deserialized with serde and stores secrets in encrypted [KeyBlob]s,
@@ -73,6 +262,11 @@ pub enum KeyType {
 ///
 /// and a [sign] function for using stored keys to create signatures.
 ///
+/// Apart from changing its passphrase or [KdfParams], all functions take `&self`, so a single
+/// keystore can be shared between threads without locking it as a whole: every secret has its
+/// own lock, and the maps holding them are only locked for looking up or adding a secret.
+/// Signing with different keys from several threads therefore happens concurrently.
+///
 #[derive(Serialize, Deserialize)]
 pub struct Keystore {
     /// This stores the cipher text of [PCHECK_HEADER] plus 32 random bytes encrypted
@@ -84,14 +278,14 @@ pub struct Keystore {
     passphrase_check: String,
 
     /// These are the secrets (keys/seeds) stored encrypted, by name.
-    secrets: BTreeMap<String, KeyBlob>,
+    secrets: RwLock<BTreeMap<String, KeyBlob>>,
 
     // The following fields are transient, i.e. not serialized to the keystore file:
     /// Using a secret from [secrets] will result in decrypting the secret and
     /// storing it in this cache.
     /// TODO: maybe clear the cache for certain (not agent keys) items after some time?
     #[serde(skip_serializing, skip_deserializing)]
-    cache: HashMap<String, Arc<Mutex<Secret>>>,
+    cache: RwLock<HashMap<String, Arc<Mutex<Secret>>>>,
 
     /// Requested for passphrases needed to decrypt secrets
     #[serde(skip_serializing, skip_deserializing)]
@@ -101,6 +295,57 @@ pub struct Keystore {
     /// Gets sets to non-default for quick tests.
     #[serde(skip_serializing, skip_deserializing)]
     hash_config: Option<PwHashConfig>,
+
+    /// Secure buffers [sign] copies the data to sign into, kept for reuse.
+    #[serde(skip_serializing, skip_deserializing)]
+    sign_buffers: Mutex<SignBufferPool>,
+
+    /// Parameters keys are derived from seeds with, see [KdfParams].
+    /// These are saved with the keystore so it keeps deriving keys the way it did when they
+    /// were set. Keystores saved without them derive keys from the seeds directly, as before.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    kdf_params: Option<KdfParams>,
+
+    /// The origins of the key pairs derived from seeds, by name.
+    /// Keystores saved before origins were recorded have none, like imported keys.
+    #[serde(default, skip_serializing_if = "has_no_key_origins")]
+    key_origins: RwLock<BTreeMap<String, KeyOrigin>>,
+}
+
+fn has_no_key_origins(key_origins: &RwLock<BTreeMap<String, KeyOrigin>>) -> bool {
+    key_origins
+        .read()
+        .map(|key_origins| key_origins.is_empty())
+        .unwrap_or(false)
+}
+
+/// derives a key pair of the given type from the seed, stretching the seed first if there are
+/// [KdfParams]. Returns the key pair as secret along with its public key.
+fn derive_key_pair(
+    seed_buf: &mut SecBuf,
+    key_type: &KeyType,
+    kdf_params: Option<&KdfParams>,
+) -> HcResult<(Secret, Base32)> {
+    let mut stretched_seed;
+    let seed_buf = match kdf_params {
+        Some(kdf_params) => {
+            stretched_seed = kdf_params.stretch(seed_buf)?;
+            &mut stretched_seed
+        }
+        None => seed_buf,
+    };
+    Ok(match key_type {
+        KeyType::Signing => {
+            let key_pair = SigningKeyPair::new_from_seed(seed_buf)?;
+            let public_key = key_pair.public();
+            (Secret::SigningKey(key_pair), public_key)
+        }
+        KeyType::Encrypting => {
+            let key_pair = EncryptingKeyPair::new_from_seed(seed_buf)?;
+            let public_key = key_pair.public();
+            (Secret::EncryptingKey(key_pair), public_key)
+        }
+    })
 }
 
 fn make_passphrase_check(
@@ -113,6 +358,147 @@ fn make_passphrase_check(
     encrypt_with_passphrase_buf(&mut check_buf, passphrase, hash_config)
 }
 
+/// sha256 over the serialized keystore (without the checksum itself), base64 encoded
+fn keystore_checksum(payload: &Value) -> HcResult<String> {
+    let mut payload_buf = SecBuf::with_insecure_from_string(payload.to_string());
+    let mut hash_buf = SecBuf::with_insecure(CHECKSUM_SIZE);
+    sha256(&mut payload_buf, &mut hash_buf)?;
+    let hash = hash_buf.read_lock();
+    Ok(base64::encode(&**hash))
+}
+
+/// reads a keystore file as JSON, checking its checksum.
+/// Files stating their format version always have one, only older keystores may lack it.
+fn read_keystore_json(path: &PathBuf) -> HcResult<Value> {
+    let mut file = File::open(path)?;
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+    let mut json: Value =
+        serde_json::from_str(&contents).map_err(|err| corrupt_keystore_error(err.to_string()))?;
+    let states_version = json.get(VERSION_KEY).is_some();
+    let maybe_checksum = json
+        .as_object_mut()
+        .and_then(|fields| fields.remove(CHECKSUM_KEY));
+    match maybe_checksum {
+        Some(checksum) => {
+            if checksum != Value::String(keystore_checksum(&json)?) {
+                return Err(corrupt_keystore_error("checksum mismatch".to_string()));
+            }
+        }
+        None if states_version => {
+            return Err(corrupt_keystore_error("checksum missing".to_string()));
+        }
+        None => (),
+    }
+    Ok(json)
+}
+
+/// the format version a keystore file's JSON states.
+/// Legacy agent key files are version 0, keystores saved before the version was recorded
+/// are version 1.
+fn format_version(json: &Value) -> HcResult<u64> {
+    match json.get(VERSION_KEY) {
+        None if serde_json::from_value::<KeyBlob>(json.clone()).is_ok() => Ok(0),
+        None => Ok(1),
+        Some(version) => version
+            .as_u64()
+            .ok_or_else(|| corrupt_keystore_error(format!("invalid version {}", version))),
+    }
+}
+
+fn corrupt_keystore_error(reason: String) -> HolochainError {
+    HolochainError::CorruptKeystore(reason)
+}
+
+/// overwrites the key material of a secret with zeros
+fn zero_secret(secret: &mut Secret) {
+    let buf = match secret {
+        Secret::SigningKey(keypair) => &mut keypair.private,
+        Secret::EncryptingKey(keypair) => &mut keypair.private,
+        Secret::Seed(buf) => buf,
+        // the keys of signers never are in the keystore's memory
+        Secret::Signer(_) => return,
+    };
+    zero_buf(buf);
+}
+
+fn zero_buf(buf: &mut SecBuf) {
+    let mut buf = buf.write_lock();
+    for byte in buf.iter_mut() {
+        *byte = 0;
+    }
+}
+
+/// Secure buffers for data to sign, pooled by length so that signing lots of data of the same
+/// size, like entry addresses, doesn't allocate and lock new memory every time.
+/// Buffers have to be exactly as long as the data since signing covers all of the buffer,
+/// see the keystore benchmark for how this compares to not pooling.
+/// Buffers get zeroed before they go back into the pool, so no data outlives its signature.
+struct SignBufferPool {
+    capacity: usize,
+    buffers: HashMap<usize, Vec<SecBuf>>,
+}
+
+impl Default for SignBufferPool {
+    fn default() -> Self {
+        SignBufferPool {
+            capacity: DEFAULT_SIGN_BUFFER_POOL_SIZE,
+            buffers: HashMap::new(),
+        }
+    }
+}
+
+impl SignBufferPool {
+    fn pooled(&self) -> usize {
+        self.buffers.values().map(Vec::len).sum()
+    }
+
+    /// a buffer holding `data`, reused from the pool if there is one of the right length
+    fn take(&mut self, data: &[u8]) -> HcResult<SecBuf> {
+        let mut buf = self
+            .buffers
+            .get_mut(&data.len())
+            .and_then(Vec::pop)
+            .unwrap_or_else(|| SecBuf::with_secure(data.len()));
+        buf.write(0, data)?;
+        Ok(buf)
+    }
+
+    /// zeroes `buf` and keeps it for reuse, unless the pool is full
+    fn give_back(&mut self, mut buf: SecBuf) {
+        zero_buf(&mut buf);
+        if self.pooled() < self.capacity {
+            let len = buf.len();
+            self.buffers.entry(len).or_insert_with(Vec::new).push(buf);
+        }
+    }
+
+    fn resize(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.pooled() > capacity {
+            if let Some(bufs) = self.buffers.values_mut().find(|bufs| !bufs.is_empty()) {
+                bufs.pop();
+            }
+        }
+        self.buffers.retain(|_, bufs| !bufs.is_empty());
+    }
+}
+
+/// derivation indices start at 1 and go up to [MAX_DERIVATION_INDEX]
+fn check_derivation_index(index: u64) -> HcResult<()> {
+    if index == 0 || index > MAX_DERIVATION_INDEX {
+        return Err(HolochainError::ErrorGeneric(format!(
+            "Derivation index {} is out of range, it has to be between 1 and {}",
+            index, MAX_DERIVATION_INDEX
+        )));
+    }
+    Ok(())
+}
+
+fn identifier_exists_error() -> HolochainError {
+    HolochainError::ErrorGeneric("identifier already exists".to_string())
+}
+
 impl Keystore {
     /// Create a new keystore.
     /// This will query `passphrase_manager` immediately to set a passphrase for the keystore.
@@ -125,10 +511,13 @@ impl Keystore {
                 &mut passphrase_manager.get_passphrase()?,
                 hash_config.clone(),
             )?,
-            secrets: BTreeMap::new(),
-            cache: HashMap::new(),
+            secrets: RwLock::new(BTreeMap::new()),
+            cache: RwLock::new(HashMap::new()),
             passphrase_manager: Some(passphrase_manager),
             hash_config,
+            sign_buffers: Mutex::new(SignBufferPool::default()),
+            kdf_params: None,
+            key_origins: RwLock::new(BTreeMap::new()),
         })
     }
 
@@ -137,30 +526,142 @@ impl Keystore {
         passphrase_manager: Arc<PassphraseManager>,
         hash_config: Option<PwHashConfig>,
     ) -> HcResult<(Self, Base32)> {
-        let mut keystore = Keystore::new(passphrase_manager, hash_config)?;
+        let keystore = Keystore::new(passphrase_manager, hash_config)?;
         keystore.add_random_seed(STANDALONE_ROOT_SEED, SEED_SIZE)?;
         let (pub_key, _) =
             keystore.add_keybundle_from_seed(STANDALONE_ROOT_SEED, PRIMARY_KEYBUNDLE_ID)?;
         Ok((keystore, pub_key))
     }
 
+    /// Create a standalone keystore whose root seed is the given bytes instead of a random one,
+    /// so the same seed always yields the same agent keys.
+    /// Only meant for tests: anyone knowing the seed can recreate the keys.
+    pub fn new_standalone_from_seed(
+        passphrase_manager: Arc<PassphraseManager>,
+        hash_config: Option<PwHashConfig>,
+        seed: &[u8],
+    ) -> HcResult<(Self, Base32)> {
+        check_seed_size(seed.len())?;
+        let keystore = Keystore::new(passphrase_manager, hash_config)?;
+        keystore.add_seed_from_bytes(STANDALONE_ROOT_SEED, seed)?;
+        let (pub_key, _) =
+            keystore.add_keybundle_from_seed(STANDALONE_ROOT_SEED, PRIMARY_KEYBUNDLE_ID)?;
+        Ok((keystore, pub_key))
+    }
+
     /// Load a keystore from file.
     /// This won't ask for a passphrase until a secret is used via the other functions.
     /// Secrets will get loaded to memory instantly but stay encrypted until requested.
+    /// Fails if the file's checksum doesn't match its contents, or if it was written in a newer
+    /// format than [KEYSTORE_FORMAT_VERSION].
     pub fn new_from_file(
         path: PathBuf,
         passphrase_manager: Arc<PassphraseManager>,
         hash_config: Option<PwHashConfig>,
     ) -> HcResult<Self> {
-        let mut file = File::open(path)?;
-        let mut contents = String::new();
-        file.read_to_string(&mut contents)?;
-        let mut keystore: Keystore = serde_json::from_str(&contents)?;
+        let mut json = read_keystore_json(&path)?;
+        let version = format_version(&json)?;
+        if version > KEYSTORE_FORMAT_VERSION {
+            return Err(HolochainError::ErrorGeneric(format!(
+                "Keystore file has format version {}, but only versions up to {} can be read",
+                version, KEYSTORE_FORMAT_VERSION
+            )));
+        }
+        if version == 0 {
+            return Err(HolochainError::ErrorGeneric(format!(
+                "{} is a legacy agent key file, it needs to be migrated to a keystore first",
+                path.display()
+            )));
+        }
+        if let Some(fields) = json.as_object_mut() {
+            fields.remove(VERSION_KEY);
+        }
+        let mut keystore: Keystore =
+            serde_json::from_value(json).map_err(|err| corrupt_keystore_error(err.to_string()))?;
         keystore.hash_config = hash_config;
         keystore.passphrase_manager = Some(passphrase_manager);
         Ok(keystore)
     }
 
+    /// The format version of the keystore file at `path`, see [KEYSTORE_FORMAT_VERSION]
+    pub fn file_format_version(path: &PathBuf) -> HcResult<u64> {
+        format_version(&read_keystore_json(path)?)
+    }
+
+    /// Reads the keystore file at `src` and writes it to `dst` in the current format,
+    /// returning the format version it had.
+    /// Legacy agent key files get imported with [Keystore::import_legacy], keystores keep all
+    /// their identifiers and secrets as they are. The passphrase from `passphrase_manager`
+    /// has to be the file's.
+    /// Files of a newer format than this build's are refused rather than downgraded.
+    pub fn migrate_file(
+        src: PathBuf,
+        dst: PathBuf,
+        passphrase_manager: Arc<PassphraseManager>,
+        hash_config: Option<PwHashConfig>,
+    ) -> HcResult<u64> {
+        let version = Keystore::file_format_version(&src)?;
+        if version > KEYSTORE_FORMAT_VERSION {
+            return Err(HolochainError::ErrorGeneric(format!(
+                "Refusing to downgrade keystore from format version {} to {}",
+                version, KEYSTORE_FORMAT_VERSION
+            )));
+        }
+        let keystore = if version == 0 {
+            // decrypting the legacy keys already checks the passphrase
+            Keystore::import_legacy(src, passphrase_manager, hash_config)?
+        } else {
+            let keystore = Keystore::new_from_file(src, passphrase_manager.clone(), hash_config)?;
+            if !keystore.check_passphrase(&mut passphrase_manager.get_passphrase()?)? {
+                return Err(HolochainError::ErrorGeneric("Bad passphrase".to_string()));
+            }
+            keystore
+        };
+        keystore.save(dst)?;
+        Ok(version)
+    }
+
+    /// Create a keystore from an agent key file of the format used before keystores existed,
+    /// i.e. a single [KeyBlob] of the agent's [KeyBundle].
+    /// The keys end up under [PRIMARY_KEYBUNDLE_ID], so the agent address stays the same.
+    /// The passphrase from `passphrase_manager` has to be the one the key file was written with
+    /// and becomes the passphrase of the new keystore.
+    pub fn import_legacy(
+        path: PathBuf,
+        passphrase_manager: Arc<PassphraseManager>,
+        hash_config: Option<PwHashConfig>,
+    ) -> HcResult<Self> {
+        let legacy_error = |reason: String| {
+            HolochainError::ErrorGeneric(format!(
+                "Can't import legacy key file {}: {}",
+                path.display(),
+                reason
+            ))
+        };
+        let mut file = File::open(&path)?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        let blob: KeyBlob =
+            serde_json::from_str(&contents).map_err(|err| legacy_error(err.to_string()))?;
+        if blob.blob_type != BlobType::KeyBundle {
+            return Err(legacy_error(format!(
+                "unsupported key type {:?}, expected {:?}",
+                blob.blob_type,
+                BlobType::KeyBundle
+            )));
+        }
+        let mut keybundle = KeyBundle::from_blob(
+            &blob,
+            &mut passphrase_manager.get_passphrase()?,
+            hash_config.clone(),
+        )
+        .map_err(|err| legacy_error(format!("could not decrypt keys: {}", err)))?;
+
+        let keystore = Keystore::new(passphrase_manager, hash_config)?;
+        keystore.add_keybundle(PRIMARY_KEYBUNDLE_ID, &mut keybundle)?;
+        Ok(keystore)
+    }
+
     /// This tries to decrypt `passphrase_check` with the given passphrase and
     /// expects to read `PCHECK_HEADER` from the decrypted text, ignoring the
     /// random bytes following the header.
@@ -221,11 +722,10 @@ impl Keystore {
         })
     }
 
-    /// This function expects the named secret in `secrets`, decrypts it and stores the decrypted
-    /// representation in `cache`.
-    fn decrypt(&mut self, id_str: &String) -> HcResult<()> {
-        let blob = self
-            .secrets
+    /// Decrypts the named secret from `secrets` without touching `cache`.
+    fn decrypt_uncached(&self, id_str: &str) -> HcResult<Secret> {
+        let secrets = self.secrets.read()?;
+        let blob = secrets
             .get(id_str)
             .ok_or(HolochainError::new("Secret not found"))?;
 
@@ -239,29 +739,20 @@ impl Keystore {
             self.inner_decrypt(blob, passphrase)
         };
 
-        let secret = maybe_secret.map_err(|err| {
+        maybe_secret.map_err(|err| {
             HolochainError::ErrorGeneric(format!("Could not decrypt '{}': {:?}", id_str, err))
-        })?;
-
-        self.cache
-            .insert(id_str.clone(), Arc::new(Mutex::new(secret)));
-        Ok(())
+        })
     }
 
-    /// This expects an unencrypted named secret in `cache`, encrypts it and stores the
-    /// encrypted representation in `secrets`.
-    fn encrypt(&mut self, id_str: &String) -> HcResult<()> {
-        let secret = self
-            .cache
-            .get(id_str)
-            .ok_or(HolochainError::new("Secret not found"))?;
+    /// Encrypts the given secret into the representation stored in `secrets`.
+    fn encrypt(&self, secret: &mut Secret) -> HcResult<KeyBlob> {
         let mut passphrase = self.passphrase_manager.as_ref()?.get_passphrase()?;
         self.check_passphrase(&mut passphrase)?;
-        let blob = match *secret.lock()? {
+        match *secret {
             Secret::Seed(ref mut buf) => {
                 let mut owned_buf = SecBuf::with_insecure(buf.len());
                 owned_buf.write(0, &*buf.read_lock())?;
-                Seed::new(owned_buf, SeedType::OneShot).as_blob(
+                Seed::new(owned_buf, SeedType::OneShot)?.as_blob(
                     &mut passphrase,
                     "".to_string(),
                     self.hash_config.clone(),
@@ -273,70 +764,251 @@ impl Keystore {
             Secret::EncryptingKey(ref mut key) => {
                 key.as_blob(&mut passphrase, "".to_string(), self.hash_config.clone())
             }
-        }?;
-        self.secrets.insert(id_str.clone(), blob);
+            Secret::Signer(_) => Err(HolochainError::ErrorGeneric(
+                "external signers can't be stored in the keystore".to_string(),
+            )),
+        }
+    }
+
+    /// Encrypts the secret into `secrets` and caches it in the clear under the given identifier.
+    /// The identifier is checked to be free again while holding the lock, so that threads
+    /// adding secrets at the same time can't overwrite each other's.
+    fn store(&self, dst_id: String, secret: Arc<Mutex<Secret>>) -> HcResult<()> {
+        let blob = self.encrypt(&mut *secret.lock()?)?;
+        let mut secrets = self.secrets.write()?;
+        let taken = secrets.contains_key(&dst_id) || self.cache.read()?.contains_key(&dst_id);
+        if taken {
+            return Err(identifier_exists_error());
+        }
+        secrets.insert(dst_id.clone(), blob);
+        self.cache.write()?.insert(dst_id, secret);
         Ok(())
     }
 
     /// Serialize the keystore to a file.
+    /// The file states the [KEYSTORE_FORMAT_VERSION] it's written in and a checksum over
+    /// the contents is added so that corruption is detected on load.
     pub fn save(&self, path: PathBuf) -> HcResult<()> {
-        let json_string = serde_json::to_string(self)?;
+        let mut json = serde_json::to_value(self)?;
+        if let Some(fields) = json.as_object_mut() {
+            fields.insert(
+                VERSION_KEY.to_string(),
+                Value::from(KEYSTORE_FORMAT_VERSION),
+            );
+        }
+        let checksum = keystore_checksum(&json)?;
+        if let Some(fields) = json.as_object_mut() {
+            fields.insert(CHECKSUM_KEY.to_string(), Value::String(checksum));
+        }
+        let json_string = serde_json::to_string(&json)?;
         let mut file = File::create(path)?;
         file.write_all(&json_string.as_bytes())?;
         Ok(())
     }
 
+    /// The parameters keys get derived from seeds with, if any
+    pub fn kdf_params(&self) -> Option<KdfParams> {
+        self.kdf_params.clone()
+    }
+
+    /// Sets the parameters for deriving keys from seeds from now on.
+    /// Keys already in the keystore stay as they are, but deriving them again from the same seed
+    /// with other parameters yields different keys, so save the keystore after changing these.
+    pub fn set_kdf_params(&mut self, kdf_params: Option<KdfParams>) {
+        self.kdf_params = kdf_params;
+    }
+
+    /// Sets how many buffers for data to sign are kept for reuse, see
+    /// [DEFAULT_SIGN_BUFFER_POOL_SIZE]. Size it to the number of threads signing concurrently;
+    /// 0 allocates a new buffer for every signature.
+    pub fn set_sign_buffer_pool_size(&self, size: usize) -> HcResult<()> {
+        self.sign_buffers.lock()?.resize(size);
+        Ok(())
+    }
+
     /// return a list of the identifiers stored in the keystore
     pub fn list(&self) -> Vec<String> {
-        self.secrets.keys().map(|k| k.to_string()).collect()
+        // the map is only ever changed by single inserts, so it's intact even if poisoned
+        let secrets = self
+            .secrets
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        secrets.keys().map(|k| k.to_string()).collect()
+    }
+
+    /// returns whether there is a secret or signer stored under the given identifier
+    pub fn contains(&self, id_str: &str) -> bool {
+        let in_secrets = self
+            .secrets
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .contains_key(id_str);
+        // signers are only ever cached
+        in_secrets
+            || self
+                .cache
+                .read()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .contains_key(id_str)
+    }
+
+    /// the types of all secrets and signers, by identifier
+    fn secret_types(&self) -> BTreeMap<String, SecretType> {
+        // both maps are only ever changed by single inserts, so they're intact even if poisoned
+        let mut types: BTreeMap<String, SecretType> = self
+            .secrets
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .iter()
+            .map(|(id, blob)| {
+                (
+                    id.clone(),
+                    Some((blob.blob_type.clone(), blob.seed_type.clone())),
+                )
+            })
+            .collect();
+        for (id, secret) in self
+            .cache
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .iter()
+        {
+            let secret = secret
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            if let Secret::Signer(_) = *secret {
+                types.insert(id.clone(), None);
+            }
+        }
+        types
+    }
+
+    /// compares the identifiers of this keystore with those of `other`, e.g. an older or
+    /// newer snapshot of it, to review changes before the keystore gets shared.
+    /// Only looks at identifiers and the types of the secrets stored under them, nothing
+    /// gets decrypted.
+    pub fn diff(&self, other: &Keystore) -> KeystoreDiff {
+        let ours = self.secret_types();
+        let theirs = other.secret_types();
+        let mut diff = KeystoreDiff::default();
+        for (id, secret_type) in ours.iter() {
+            match theirs.get(id) {
+                None => diff.removed.push(id.clone()),
+                Some(their_type) if their_type != secret_type => diff.type_changed.push(id.clone()),
+                Some(_) => (),
+            }
+        }
+        diff.added = theirs
+            .keys()
+            .filter(|id| !ours.contains_key(*id))
+            .cloned()
+            .collect();
+        diff
+    }
+
+    /// removes all secrets and signers whose identifier starts with `prefix`, such as a
+    /// hierarchy of derived keys, and returns their identifiers.
+    /// Secrets that were in use get overwritten with zeros.
+    pub fn remove_prefix(&mut self, prefix: &str) -> HcResult<Vec<String>> {
+        let mut secrets = self.secrets.write()?;
+        let mut cache = self.cache.write()?;
+        let mut removed: Vec<String> = secrets
+            .keys()
+            .chain(cache.keys())
+            .filter(|id| id.starts_with(prefix))
+            .cloned()
+            .collect();
+        removed.sort();
+        removed.dedup();
+        let mut key_origins = self.key_origins.write()?;
+        for id in removed.iter() {
+            key_origins.remove(id);
+            // the stored blobs are encrypted, only decrypted secrets need zeroing
+            secrets.remove(id);
+            if let Some(secret) = cache.remove(id) {
+                zero_secret(&mut *secret.lock()?);
+            }
+        }
+        Ok(removed)
     }
 
     /// adds a secret to the keystore
-    pub fn add(&mut self, dst_id_str: &str, secret: Arc<Mutex<Secret>>) -> HcResult<()> {
+    pub fn add(&self, dst_id_str: &str, secret: Arc<Mutex<Secret>>) -> HcResult<()> {
         let dst_id = self.check_dst_identifier(dst_id_str)?;
-        self.cache.insert(dst_id.clone(), secret);
-        self.encrypt(&dst_id)?;
+        self.store(dst_id, secret)
+    }
+
+    /// adds an external signer, such as a hardware key, under the given identifier.
+    /// It can then be used with [Keystore::sign] like any signing key, but as it only
+    /// lives in memory it has to be added again after loading the keystore from a file.
+    pub fn add_signer(&self, dst_id_str: &str, signer: Box<dyn Signer>) -> HcResult<()> {
+        let secrets = self.secrets.read()?;
+        let mut cache = self.cache.write()?;
+        if secrets.contains_key(dst_id_str) || cache.contains_key(dst_id_str) {
+            return Err(identifier_exists_error());
+        }
+        cache.insert(
+            dst_id_str.to_string(),
+            Arc::new(Mutex::new(Secret::Signer(signer))),
+        );
         Ok(())
     }
 
     /// adds a random root seed into the keystore
-    pub fn add_random_seed(&mut self, dst_id_str: &str, size: usize) -> HcResult<()> {
+    /// `size` has to be `SEED_SIZE`, seeds of any other size can't be derived from
+    pub fn add_random_seed(&self, dst_id_str: &str, size: usize) -> HcResult<()> {
+        check_seed_size(size)?;
         let dst_id = self.check_dst_identifier(dst_id_str)?;
         let seed_buf = generate_random_buf(size);
-        let secret = Arc::new(Mutex::new(Secret::Seed(seed_buf)));
-        self.cache.insert(dst_id.clone(), secret);
-        self.encrypt(&dst_id)?;
-        Ok(())
+        self.store(dst_id, Arc::new(Mutex::new(Secret::Seed(seed_buf))))
     }
 
+    /// adds a root seed with the given contents into the keystore
+    /// useful where keys need to be reproducible, i.e. tests and recovery
+    pub fn add_seed_from_bytes(&self, dst_id_str: &str, seed: &[u8]) -> HcResult<()> {
+        check_seed_size(seed.len())?;
+        let dst_id = self.check_dst_identifier(dst_id_str)?;
+        let mut seed_buf = SecBuf::with_secure(seed.len());
+        seed_buf.write(0, seed)?;
+        self.store(dst_id, Arc::new(Mutex::new(Secret::Seed(seed_buf))))
+    }
+
+    /// fails early if the identifier is taken, before any work goes into the new secret
     fn check_dst_identifier(&self, dst_id_str: &str) -> HcResult<String> {
-        let dst_id = dst_id_str.to_string();
-        if self.secrets.contains_key(&dst_id) {
-            return Err(HolochainError::ErrorGeneric(
-                "identifier already exists".to_string(),
-            ));
+        if self.contains(dst_id_str) {
+            return Err(identifier_exists_error());
         }
-        Ok(dst_id)
+        Ok(dst_id_str.to_string())
+    }
+
+    /// returns the secret if it has been decrypted already
+    fn cached(&self, id_str: &str) -> HcResult<Option<Arc<Mutex<Secret>>>> {
+        Ok(self.cache.read()?.get(id_str).cloned())
     }
 
     /// gets a secret from the keystore
-    pub fn get(&mut self, src_id_str: &str) -> HcResult<Arc<Mutex<Secret>>> {
-        let src_id = src_id_str.to_string();
-        if !self.secrets.contains_key(&src_id) {
+    pub fn get(&self, src_id_str: &str) -> HcResult<Arc<Mutex<Secret>>> {
+        // signers are only ever cached
+        if let Some(secret) = self.cached(src_id_str)? {
+            return Ok(secret);
+        }
+        if !self.secrets.read()?.contains_key(src_id_str) {
             return Err(HolochainError::ErrorGeneric(
                 "unknown source identifier".to_string(),
             ));
         }
 
-        if !self.cache.contains_key(&src_id) {
-            self.decrypt(&src_id)?;
-        }
-
-        Ok(self.cache.get(&src_id).unwrap().clone()) // unwrap ok because we made sure src exists
+        let secret = Arc::new(Mutex::new(self.decrypt_uncached(src_id_str)?));
+        // another thread might have decrypted it meanwhile, in which case its copy is kept
+        let mut cache = self.cache.write()?;
+        Ok(cache
+            .entry(src_id_str.to_string())
+            .or_insert(secret)
+            .clone())
     }
 
     fn check_identifiers(
-        &mut self,
+        &self,
         src_id_str: &str,
         dst_id_str: &str,
     ) -> HcResult<(Arc<Mutex<Secret>>, String)> {
@@ -347,15 +1019,16 @@ impl Keystore {
 
     /// adds a derived seed into the keystore
     pub fn add_seed_from_seed(
-        &mut self,
+        &self,
         src_id_str: &str,
         dst_id_str: &str,
         context: &SeedContext,
         index: u64,
     ) -> HcResult<()> {
+        check_derivation_index(index)?;
         let (src_secret, dst_id) = self.check_identifiers(src_id_str, dst_id_str)?;
         let secret = {
-            let mut src_secret = src_secret.lock().unwrap();
+            let mut src_secret = src_secret.lock()?;
             match *src_secret {
                 Secret::Seed(ref mut src) => {
                     let seed = generate_derived_seed_buf(src, context, index, SEED_SIZE)?;
@@ -368,24 +1041,21 @@ impl Keystore {
                 }
             }
         };
-        self.cache.insert(dst_id.clone(), secret);
-        self.encrypt(&dst_id)?;
-
-        Ok(())
+        self.store(dst_id, secret)
     }
 
     /// adds a keypair into the keystore based on a seed already in the keystore
     /// returns the public key
     pub fn add_key_from_seed(
-        &mut self,
+        &self,
         src_id_str: &str,
         dst_id_str: &str,
         key_type: KeyType,
     ) -> HcResult<Base32> {
         let (src_secret, dst_id) = self.check_identifiers(src_id_str, dst_id_str)?;
         let (secret, public_key) = {
-            let mut src_secret = src_secret.lock().unwrap();
-            let ref mut seed_buf = match *src_secret {
+            let mut src_secret = src_secret.lock()?;
+            let seed_buf = match *src_secret {
                 Secret::Seed(ref mut src) => src,
                 _ => {
                     return Err(HolochainError::ErrorGeneric(
@@ -393,35 +1063,57 @@ impl Keystore {
                     ));
                 }
             };
-            match key_type {
-                KeyType::Signing => {
-                    let key_pair = SigningKeyPair::new_from_seed(seed_buf)?;
-                    let public_key = key_pair.public();
-                    (
-                        Arc::new(Mutex::new(Secret::SigningKey(key_pair))),
-                        public_key,
-                    )
-                }
-                KeyType::Encrypting => {
-                    let key_pair = EncryptingKeyPair::new_from_seed(seed_buf)?;
-                    let public_key = key_pair.public();
-                    (
-                        Arc::new(Mutex::new(Secret::EncryptingKey(key_pair))),
-                        public_key,
-                    )
-                }
-            }
+            derive_key_pair(seed_buf, &key_type, self.kdf_params.as_ref())?
         };
-        self.cache.insert(dst_id.clone(), secret);
-        self.encrypt(&dst_id)?;
+        self.store(dst_id.clone(), Arc::new(Mutex::new(secret)))?;
+        self.key_origins.write()?.insert(
+            dst_id,
+            KeyOrigin {
+                seed_id: src_id_str.to_string(),
+                key_type,
+                kdf_params: self.kdf_params.clone(),
+            },
+        );
 
         Ok(public_key)
     }
 
+    /// derives the key pair stored under the given identifier again from the seed it was
+    /// derived from and tells whether the stored one still matches, to detect corruption.
+    /// Fails for keys that can't be traced back to a seed, because they were imported or
+    /// their seed has been removed.
+    pub fn verify_integrity(&self, id_str: &str) -> HcResult<bool> {
+        let origin = self
+            .key_origins
+            .read()?
+            .get(id_str)
+            .cloned()
+            .ok_or_else(|| {
+                HolochainError::ErrorGeneric(format!(
+                    "'{}' was not derived from a seed in this keystore, so it can't be verified",
+                    id_str
+                ))
+            })?;
+        let stored_public_key = self.public_key(id_str)?;
+        let seed = self.get(&origin.seed_id)?;
+        let mut seed = seed.lock()?;
+        let seed_buf = match *seed {
+            Secret::Seed(ref mut seed_buf) => seed_buf,
+            _ => {
+                return Err(HolochainError::ErrorGeneric(
+                    "source secret is not a seed".to_string(),
+                ));
+            }
+        };
+        let (_, public_key) =
+            derive_key_pair(seed_buf, &origin.key_type, origin.kdf_params.as_ref())?;
+        Ok(public_key == stored_public_key)
+    }
+
     /// adds a signing keypair into the keystore based on a seed already in the keystore
     /// returns the public key
     pub fn add_signing_key_from_seed(
-        &mut self,
+        &self,
         src_id_str: &str,
         dst_id_str: &str,
     ) -> HcResult<Base32> {
@@ -431,7 +1123,7 @@ impl Keystore {
     /// adds an encrypting keypair into the keystore based on a seed already in the keystore
     /// returns the public key
     pub fn add_encrypting_key_from_seed(
-        &mut self,
+        &self,
         src_id_str: &str,
         dst_id_str: &str,
     ) -> HcResult<Base32> {
@@ -442,7 +1134,7 @@ impl Keystore {
     /// adding two keypair secrets (signing and encrypting) under the named prefix
     /// returns the public keys of the secrets
     pub fn add_keybundle_from_seed(
-        &mut self,
+        &self,
         src_id_str: &str,
         dst_id_prefix_str: &str,
     ) -> HcResult<(Base32, Base32)> {
@@ -456,10 +1148,69 @@ impl Keystore {
         Ok((sign_pub_key, enc_pub_key))
     }
 
+    /// performs the canonical derivation of an agent's keys from a root seed already in the
+    /// keystore: root seed -> device seed -> agent seed -> keybundle.
+    /// The new secrets are stored under identifiers prefixed with the root seed's identifier.
+    pub fn derive_agent_keys(&self, root_id: &str) -> HcResult<AgentKeys> {
+        let device_seed_id = [root_id, DEVICE_SEED_SUFFIX].join("");
+        let agent_seed_id = [root_id, AGENT_SEED_SUFFIX].join("");
+        let keybundle_id = [root_id, AGENT_KEYBUNDLE_SUFFIX].join("");
+
+        self.add_seed_from_seed(root_id, &device_seed_id, &SeedContext::new(DEVICE_CTX), 1)?;
+        self.add_seed_from_seed(
+            &device_seed_id,
+            &agent_seed_id,
+            &SeedContext::new(AGENT_ID_CTX),
+            1,
+        )?;
+        let (sign_public_key, enc_public_key) =
+            self.add_keybundle_from_seed(&agent_seed_id, &keybundle_id)?;
+
+        Ok(AgentKeys {
+            device_seed_id,
+            agent_seed_id,
+            keybundle_id,
+            sign_public_key,
+            enc_public_key,
+        })
+    }
+
+    /// derives a new signing key from the root seed `root_id` at the lowest index that no
+    /// earlier rotation of that seed used, leaving all existing secrets in place.
+    /// The derived seed and key are stored under identifiers prefixed with the root seed's
+    /// identifier and ending in the index.
+    pub fn rotate_signing_key(&self, root_id: &str) -> HcResult<RotatedKey> {
+        let rotated_ids = |index: u64| {
+            (
+                format!("{}{}{}", root_id, ROTATED_SEED_INFIX, index),
+                format!("{}{}{}", root_id, ROTATED_KEY_INFIX, index),
+            )
+        };
+        let index = (1..=MAX_DERIVATION_INDEX)
+            .find(|index| {
+                let (seed_id, key_id) = rotated_ids(*index);
+                !self.contains(&seed_id) && !self.contains(&key_id)
+            })
+            .ok_or_else(|| {
+                HolochainError::ErrorGeneric(format!("No derivation index left for {}", root_id))
+            })?;
+        let (seed_id, key_id) = rotated_ids(index);
+
+        self.add_seed_from_seed(root_id, &seed_id, &SeedContext::new(ROTATION_CTX), index)?;
+        let public_key = self.add_signing_key_from_seed(&seed_id, &key_id)?;
+
+        Ok(RotatedKey {
+            index,
+            seed_id,
+            key_id,
+            public_key,
+        })
+    }
+
     /// adds a keybundle into the keystore based on an actual keybundle object by
     /// adding two keypair secrets (signing and encrypting) under the named prefix
     pub fn add_keybundle(
-        &mut self,
+        &self,
         dst_id_prefix_str: &str,
         keybundle: &mut KeyBundle,
     ) -> HcResult<()> {
@@ -478,12 +1229,12 @@ impl Keystore {
     /// adds a keybundle into the keystore based on a seed already in the keystore by
     /// adding two keypair secrets (signing and encrypting) under the named prefix
     /// returns the public keys of the secrets
-    pub fn get_keybundle(&mut self, src_id_prefix_str: &str) -> HcResult<KeyBundle> {
+    pub fn get_keybundle(&self, src_id_prefix_str: &str) -> HcResult<KeyBundle> {
         let src_sign_id_str = [src_id_prefix_str, KEYBUNDLE_SIGNKEY_SUFFIX].join("");
         let src_enc_id_str = [src_id_prefix_str, KEYBUNDLE_ENCKEY_SUFFIX].join("");
 
         let sign_secret = self.get(&src_sign_id_str)?;
-        let mut sign_secret = sign_secret.lock().unwrap();
+        let mut sign_secret = sign_secret.lock()?;
         let sign_key = match *sign_secret {
             Secret::SigningKey(ref mut key_pair) => key_pair.new_from_self()?,
             _ => {
@@ -494,7 +1245,7 @@ impl Keystore {
         };
 
         let enc_secret = self.get(&src_enc_id_str)?;
-        let mut enc_secret = enc_secret.lock().unwrap();
+        let mut enc_secret = enc_secret.lock()?;
         let enc_key = match *enc_secret {
             Secret::EncryptingKey(ref mut key_pair) => key_pair.new_from_self()?,
             _ => {
@@ -507,45 +1258,148 @@ impl Keystore {
         Ok(KeyBundle::new(sign_key, enc_key)?)
     }
 
+    /// returns the public keys of the keybundle stored under the given prefix, for sharing the
+    /// agent's identity. Nothing secret is part of the result.
+    pub fn export_public_bundle(&self, id: &str) -> HcResult<PublicKeyBundle> {
+        Ok(PublicKeyBundle {
+            sign_key: self.public_key(&[id, KEYBUNDLE_SIGNKEY_SUFFIX].join(""))?,
+            enc_key: self.public_key(&[id, KEYBUNDLE_ENCKEY_SUFFIX].join(""))?,
+        })
+    }
+
+    /// reads the public key of a keypair, decrypting it without caching if necessary
+    fn public_key(&self, id_str: &str) -> HcResult<Base32> {
+        let read_public_key = |secret: &Secret| match secret {
+            Secret::SigningKey(key_pair) => Ok(key_pair.public()),
+            Secret::EncryptingKey(key_pair) => Ok(key_pair.public()),
+            _ => Err(HolochainError::ErrorGeneric(format!(
+                "'{}' is not a key pair",
+                id_str
+            ))),
+        };
+        if let Some(secret) = self.cached(id_str)? {
+            return read_public_key(&*secret.lock()?);
+        }
+        if !self.secrets.read()?.contains_key(id_str) {
+            return Err(HolochainError::ErrorGeneric(
+                "unknown source identifier".to_string(),
+            ));
+        }
+        read_public_key(&self.decrypt_uncached(id_str)?)
+    }
+
     /// signs some data using a keypair in the keystore
-    /// returns the signature
-    pub fn sign(&mut self, src_id_str: &str, data: String) -> HcResult<Signature> {
+    /// returns the signature.
+    /// Empty data is rejected rather than signed, as it can't mean anything to a verifier.
+    pub fn sign(&self, src_id_str: &str, data: String) -> HcResult<Signature> {
+        if data.is_empty() {
+            return Err(HolochainError::ErrorGeneric(
+                "Cannot sign empty data".to_string(),
+            ));
+        }
         let src_secret = self.get(src_id_str)?;
-        let mut src_secret = src_secret.lock().unwrap();
-        match *src_secret {
-            Secret::SigningKey(ref mut key_pair) => {
-                let mut data_buf = SecBuf::with_insecure_from_string(data);
-
-                let mut signature_buf = key_pair.sign(&mut data_buf)?;
-                let buf = signature_buf.read_lock();
-                // Return as base64 encoded string
-                let signature_str = base64::encode(&**buf);
-                Ok(Signature::from(signature_str))
+        let mut src_secret = src_secret.lock()?;
+        let mut data_buf = self.sign_buffers.lock()?.take(data.as_bytes())?;
+        let signed = match *src_secret {
+            Secret::SigningKey(ref mut key_pair) => key_pair.sign(&mut data_buf),
+            Secret::Signer(ref signer) => signer.sign(&mut data_buf),
+            _ => Err(HolochainError::ErrorGeneric(
+                "source secret is not a signing key".to_string(),
+            )),
+        };
+        self.sign_buffers.lock()?.give_back(data_buf);
+        let mut signature_buf = signed?;
+        let buf = signature_buf.read_lock();
+        // Return as base64 encoded string
+        let signature_str = base64::encode(&**buf);
+        Ok(Signature::from(signature_str))
+    }
+
+    /// signs some data with the key that deriving a seed from `seed_id_str` at `index` of
+    /// `context` and a signing key from that seed would give, like [add_seed_from_seed] and
+    /// [add_signing_key_from_seed] do, but without adding either to the keystore.
+    /// The derived seed and key get zeroed before returning.
+    pub fn sign_derived(
+        &self,
+        seed_id_str: &str,
+        context: &SeedContext,
+        index: u64,
+        data: String,
+    ) -> HcResult<Signature> {
+        if data.is_empty() {
+            return Err(HolochainError::ErrorGeneric(
+                "Cannot sign empty data".to_string(),
+            ));
+        }
+        check_derivation_index(index)?;
+        let seed = self.get(seed_id_str)?;
+        let mut derived_seed = match *seed.lock()? {
+            Secret::Seed(ref mut seed_buf) => {
+                generate_derived_seed_buf(seed_buf, context, index, SEED_SIZE)?
             }
             _ => {
                 return Err(HolochainError::ErrorGeneric(
-                    "source secret is not a signing key".to_string(),
+                    "source secret is not a root seed".to_string(),
                 ));
             }
-        }
+        };
+        let derived_key = derive_key_pair(
+            &mut derived_seed,
+            &KeyType::Signing,
+            self.kdf_params.as_ref(),
+        );
+        zero_buf(&mut derived_seed);
+        let (mut derived_key, _) = derived_key?;
+        let mut data_buf = SecBuf::with_insecure_from_string(data);
+        let signature_buf = match derived_key {
+            Secret::SigningKey(ref mut key_pair) => key_pair.sign(&mut data_buf),
+            _ => unreachable!(),
+        };
+        zero_secret(&mut derived_key);
+        let mut signature_buf = signature_buf?;
+        let buf = signature_buf.read_lock();
+        Ok(Signature::from(base64::encode(&**buf)))
     }
-}
 
-pub fn test_hash_config() -> Option<PwHashConfig> {
-    Some(PwHashConfig(
-        OPSLIMIT_INTERACTIVE,
-        MEMLIMIT_INTERACTIVE,
+    /// Like [sign], but binds the signature to `domain`, so that it can't be passed off
+    /// as a signature made for another purpose. Check it with [verify_with_context].
+    pub fn sign_with_context(
+        &self,
+        src_id_str: &str,
+        data: String,
+        domain: &str,
+    ) -> HcResult<Signature> {
+        self.sign(src_id_str, domain_separated(domain, data))
+    }
+
+    /// signs the address of `content`, which is what entry signatures are made over.
+    /// Check it by verifying against `content.address().to_string()`.
+    pub fn sign_entry(
+        &self,
+        src_id_str: &str,
+        content: &dyn AddressableContent,
+    ) -> HcResult<Signature> {
+        self.sign(src_id_str, content.address().to_string())
+    }
+}
+
+pub fn test_hash_config() -> Option<PwHashConfig> {
+    Some(PwHashConfig(
+        OPSLIMIT_INTERACTIVE,
+        MEMLIMIT_INTERACTIVE,
         ALG_ARGON2ID13,
     ))
 }
 
 #[cfg(test)]
 pub mod tests {
+    extern crate tempfile;
+    use self::tempfile::tempdir;
     use super::*;
     use base64;
     use conductor::passphrase_manager::PassphraseServiceMock;
-    use holochain_dpki::utils;
-    use holochain_persistence_api::cas::content::Address;
+    use holochain_core_types::entry::test_entry;
+    use std::{collections::HashSet, sync::Condvar, thread, time::Duration};
 
     fn mock_passphrase_manager(passphrase: String) -> Arc<PassphraseManager> {
         Arc::new(PassphraseManager::new(Arc::new(Mutex::new(
@@ -580,7 +1434,7 @@ pub mod tests {
     #[test]
     fn test_save_load_roundtrip() {
         let random_passphrase = random_test_passphrase();
-        let mut keystore = new_test_keystore(random_passphrase.clone());
+        let keystore = new_test_keystore(random_passphrase.clone());
         assert_eq!(keystore.add_random_seed("my_root_seed", SEED_SIZE), Ok(()));
         assert_eq!(keystore.list(), vec!["my_root_seed".to_string()]);
 
@@ -588,7 +1442,7 @@ pub mod tests {
         path.push("tmp-test/test-keystore");
         keystore.save(path.clone()).unwrap();
 
-        let mut loaded_keystore = Keystore::new_from_file(
+        let loaded_keystore = Keystore::new_from_file(
             path.clone(),
             mock_passphrase_manager(random_passphrase),
             test_hash_config(),
@@ -617,6 +1471,119 @@ pub mod tests {
         assert_eq!(expected_seed, loaded_seed);
     }
 
+    #[test]
+    fn test_seeds_of_other_sizes_are_refused_on_load() {
+        let random_passphrase = random_test_passphrase();
+        let keystore = new_test_keystore(random_passphrase.clone());
+        // can't be stored anymore
+        assert!(keystore
+            .add(
+                "short_seed",
+                Arc::new(Mutex::new(Secret::Seed(utils::generate_random_buf(16))))
+            )
+            .is_err());
+        // so write the blob by hand
+        let mut passphrase = SecBuf::with_insecure_from_string(random_passphrase.clone());
+        let blob = KeyBlob {
+            blob_type: BlobType::Seed,
+            seed_type: SeedType::OneShot,
+            hint: "".to_string(),
+            data: encrypt_with_passphrase_buf(
+                &mut utils::generate_random_buf(16),
+                &mut passphrase,
+                test_hash_config(),
+            )
+            .unwrap(),
+        };
+        keystore
+            .secrets
+            .write()
+            .unwrap()
+            .insert("short_seed".to_string(), blob);
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test-keystore");
+        keystore.save(path.clone()).unwrap();
+        let loaded_keystore = Keystore::new_from_file(
+            path,
+            mock_passphrase_manager(random_passphrase),
+            test_hash_config(),
+        )
+        .unwrap();
+
+        let result = loaded_keystore.get("short_seed");
+        assert!(result.is_err());
+        assert!(format!("{:?}", result.err().unwrap()).contains("Could not decrypt 'short_seed'"));
+    }
+
+    #[test]
+    fn test_load_detects_corruption() {
+        let random_passphrase = random_test_passphrase();
+        let keystore = new_test_keystore(random_passphrase.clone());
+        keystore.add_random_seed("my_root_seed", SEED_SIZE).unwrap();
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test-keystore");
+        keystore.save(path.clone()).unwrap();
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert!(contents.contains("\"checksum\""));
+
+        // flip one character of the encrypted passphrase check
+        let start = contents.find("\"passphrase_check\":\"").unwrap() + 40;
+        let mut corrupted = contents.clone().into_bytes();
+        corrupted[start] = if corrupted[start] == b'A' { b'B' } else { b'A' };
+        std::fs::write(&path, &corrupted).unwrap();
+        let result = Keystore::new_from_file(
+            path.clone(),
+            mock_passphrase_manager(random_passphrase.clone()),
+            test_hash_config(),
+        );
+        assert_eq!(
+            result.err(),
+            Some(HolochainError::CorruptKeystore(
+                "checksum mismatch".to_string()
+            ))
+        );
+
+        // a truncated file is reported as corrupt as well
+        std::fs::write(&path, &contents.as_bytes()[..contents.len() / 2]).unwrap();
+        let result = Keystore::new_from_file(
+            path.clone(),
+            mock_passphrase_manager(random_passphrase.clone()),
+            test_hash_config(),
+        );
+        match result {
+            Err(HolochainError::CorruptKeystore(_)) => (),
+            _ => panic!("expected loading a truncated keystore to fail"),
+        }
+
+        // leaving out the checksum doesn't get around the check
+        let mut json: Value = serde_json::from_str(&contents).unwrap();
+        json.as_object_mut().unwrap().remove(CHECKSUM_KEY);
+        std::fs::write(&path, json.to_string()).unwrap();
+        let result = Keystore::new_from_file(
+            path.clone(),
+            mock_passphrase_manager(random_passphrase.clone()),
+            test_hash_config(),
+        );
+        assert_eq!(
+            result.err(),
+            Some(HolochainError::CorruptKeystore(
+                "checksum missing".to_string()
+            ))
+        );
+
+        // keystores saved before format versions were recorded may lack a checksum though
+        json.as_object_mut().unwrap().remove(VERSION_KEY);
+        std::fs::write(&path, json.to_string()).unwrap();
+        assert!(Keystore::new_from_file(
+            path,
+            mock_passphrase_manager(random_passphrase),
+            test_hash_config(),
+        )
+        .is_ok());
+    }
+
     #[test]
     fn test_keystore_change_passphrase() {
         let random_passphrase = random_test_passphrase();
@@ -643,7 +1610,7 @@ pub mod tests {
 
     #[test]
     fn test_keystore_add_random_seed() {
-        let mut keystore = new_test_keystore(random_test_passphrase());
+        let keystore = new_test_keystore(random_test_passphrase());
 
         assert_eq!(keystore.add_random_seed("my_root_seed", SEED_SIZE), Ok(()));
         assert_eq!(keystore.list(), vec!["my_root_seed".to_string()]);
@@ -653,11 +1620,92 @@ pub mod tests {
                 "identifier already exists".to_string()
             ))
         );
+
+        // seeds of any other size can't be derived from
+        let wrong_size = Err(HolochainError::ErrorGeneric(
+            "Invalid seed size: expected 32 bytes, got 16".to_string(),
+        ));
+        assert_eq!(keystore.add_random_seed("short_seed", 16), wrong_size);
+        assert_eq!(
+            keystore.add_seed_from_bytes("short_seed", &[7; 16]),
+            wrong_size
+        );
+        assert!(!keystore.contains("short_seed"));
     }
 
     #[test]
-    fn test_keystore_add_seed_from_seed() {
+    fn test_keystore_derivation_index_range() {
+        let keystore = new_test_keystore(random_test_passphrase());
+        keystore.add_random_seed("my_root_seed", SEED_SIZE).unwrap();
+        let context = SeedContext::new(*b"SOMECTXT");
+
+        assert_eq!(
+            keystore.add_seed_from_seed("my_root_seed", "first", &context, 1),
+            Ok(())
+        );
+        assert_eq!(
+            keystore.add_seed_from_seed("my_root_seed", "last", &context, MAX_DERIVATION_INDEX),
+            Ok(())
+        );
+
+        let out_of_range = |index: u64| {
+            Err(HolochainError::ErrorGeneric(format!(
+                "Derivation index {} is out of range, it has to be between 1 and {}",
+                index, MAX_DERIVATION_INDEX
+            )))
+        };
+        for index in &[0, MAX_DERIVATION_INDEX + 1, -1i64 as u64] {
+            assert_eq!(
+                keystore.add_seed_from_seed("my_root_seed", "too_far", &context, *index),
+                out_of_range(*index)
+            );
+            assert_eq!(
+                keystore.sign_derived("my_root_seed", &context, *index, "data".to_string()),
+                out_of_range(*index)
+            );
+        }
+        assert!(!keystore.contains("too_far"));
+    }
+
+    #[test]
+    fn test_keystore_sign_derived() {
         let mut keystore = new_test_keystore(random_test_passphrase());
+        let context = SeedContext::new(*b"SOMECTXT");
+        let data = String::from("some data");
+        assert!(keystore
+            .sign_derived("my_root_seed", &context, 3, data.clone())
+            .is_err());
+
+        keystore.add_random_seed("my_root_seed", SEED_SIZE).unwrap();
+        let signature = keystore
+            .sign_derived("my_root_seed", &context, 3, data.clone())
+            .unwrap();
+        // nothing derived is kept
+        assert_eq!(keystore.list(), vec!["my_root_seed".to_string()]);
+
+        keystore
+            .add_seed_from_seed("my_root_seed", "derived:seed", &context, 3)
+            .unwrap();
+        keystore
+            .add_signing_key_from_seed("derived:seed", "derived:key")
+            .unwrap();
+        assert_eq!(
+            keystore.sign("derived:key", data.clone()),
+            Ok(signature.clone())
+        );
+        keystore.remove_prefix("derived:").unwrap();
+        assert_eq!(keystore.list(), vec!["my_root_seed".to_string()]);
+
+        // another index derives another key
+        assert_ne!(
+            keystore.sign_derived("my_root_seed", &context, 4, data),
+            Ok(signature)
+        );
+    }
+
+    #[test]
+    fn test_keystore_add_seed_from_seed() {
+        let keystore = new_test_keystore(random_test_passphrase());
 
         let context = SeedContext::new(*b"SOMECTXT");
 
@@ -688,7 +1736,7 @@ pub mod tests {
 
     #[test]
     fn test_keystore_add_signing_key_from_seed() {
-        let mut keystore = new_test_keystore(random_test_passphrase());
+        let keystore = new_test_keystore(random_test_passphrase());
 
         assert_eq!(
             keystore.add_signing_key_from_seed("my_root_seed", "my_keypair"),
@@ -712,9 +1760,268 @@ pub mod tests {
         );
     }
 
+    #[test]
+    fn test_keystore_export_public_bundle() {
+        let passphrase = random_test_passphrase();
+        let keystore = new_test_keystore(passphrase.clone());
+        let seed = [7; SEED_SIZE];
+        keystore.add_seed_from_bytes("my_root_seed", &seed).unwrap();
+        let (sign_key, enc_key) = keystore
+            .add_keybundle_from_seed("my_root_seed", "my_keybundle")
+            .unwrap();
+
+        let bundle = keystore.export_public_bundle("my_keybundle").unwrap();
+        assert_eq!(bundle, PublicKeyBundle { sign_key, enc_key });
+
+        // the serialized bundle holds the two public keys and nothing else
+        let json = serde_json::to_value(&bundle).unwrap();
+        let fields = json.as_object().unwrap();
+        assert_eq!(fields.len(), 2);
+        assert_eq!(fields["sign_key"], Value::String(bundle.sign_key.clone()));
+        assert_eq!(fields["enc_key"], Value::String(bundle.enc_key.clone()));
+        assert!(!json.to_string().contains(&base64::encode(&seed[..])));
+
+        // works on a freshly loaded keystore that hasn't decrypted anything yet
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("keystore");
+        keystore.save(path.clone()).unwrap();
+        let loaded = Keystore::new_from_file(
+            path,
+            mock_passphrase_manager(passphrase),
+            test_hash_config(),
+        )
+        .unwrap();
+        assert_eq!(loaded.export_public_bundle("my_keybundle"), Ok(bundle));
+
+        assert_eq!(
+            loaded.export_public_bundle("my_root_seed").err(),
+            Some(HolochainError::ErrorGeneric(
+                "unknown source identifier".to_string()
+            ))
+        );
+    }
+
+    fn test_kdf_params(iterations: u64) -> KdfParams {
+        KdfParams {
+            iterations,
+            memory: 64 * 1024,
+        }
+    }
+
+    #[test]
+    fn test_kdf_params_affect_derivation() {
+        let derive_with = |kdf_params: Option<KdfParams>| {
+            let mut keystore = new_test_keystore(random_test_passphrase());
+            keystore.set_kdf_params(kdf_params);
+            keystore
+                .add_seed_from_bytes("my_root_seed", &[7; SEED_SIZE])
+                .unwrap();
+            keystore
+                .add_signing_key_from_seed("my_root_seed", "my_keypair")
+                .unwrap()
+        };
+
+        let plain = derive_with(None);
+        let stretched = derive_with(Some(test_kdf_params(2)));
+        assert_ne!(plain, stretched);
+        assert_ne!(stretched, derive_with(Some(test_kdf_params(3))));
+        assert_ne!(
+            stretched,
+            derive_with(Some(KdfParams {
+                memory: 128 * 1024,
+                ..test_kdf_params(2)
+            }))
+        );
+
+        // derivation stays deterministic for the same parameters
+        assert_eq!(stretched, derive_with(Some(test_kdf_params(2))));
+        assert_eq!(plain, derive_with(None));
+    }
+
+    #[test]
+    fn test_kdf_params_roundtrip() {
+        let passphrase = random_test_passphrase();
+        let dir = tempdir().unwrap();
+        let load = |path: PathBuf| {
+            Keystore::new_from_file(
+                path,
+                mock_passphrase_manager(passphrase.clone()),
+                test_hash_config(),
+            )
+            .unwrap()
+        };
+
+        let mut keystore = new_test_keystore(passphrase.clone());
+        keystore.set_kdf_params(Some(test_kdf_params(2)));
+        keystore
+            .add_seed_from_bytes("my_root_seed", &[7; SEED_SIZE])
+            .unwrap();
+        let pub_key = keystore
+            .add_signing_key_from_seed("my_root_seed", "my_keypair")
+            .unwrap();
+        let path = dir.path().join("stretched");
+        keystore.save(path.clone()).unwrap();
+
+        // the loaded keystore derives the same key from the same seed
+        let loaded = load(path);
+        assert_eq!(loaded.kdf_params(), Some(test_kdf_params(2)));
+        assert_eq!(
+            loaded.add_signing_key_from_seed("my_root_seed", "my_other_keypair"),
+            Ok(pub_key)
+        );
+
+        // keystores without parameters don't mention them and load without any
+        let keystore = new_test_keystore(passphrase.clone());
+        assert!(!serde_json::to_string(&keystore)
+            .unwrap()
+            .contains("kdf_params"));
+        let path = dir.path().join("plain");
+        keystore.save(path.clone()).unwrap();
+        assert_eq!(load(path).kdf_params(), None);
+    }
+
+    /// signs by returning the data reversed and counts how often it was asked to
+    struct MockSigner(Arc<Mutex<usize>>);
+
+    impl Signer for MockSigner {
+        fn sign(&self, data: &mut SecBuf) -> HcResult<SecBuf> {
+            *self.0.lock()? += 1;
+            let mut reversed: Vec<u8> = data.read_lock().to_vec();
+            reversed.reverse();
+            let mut signature = SecBuf::with_insecure(reversed.len());
+            signature.write(0, &reversed)?;
+            Ok(signature)
+        }
+    }
+
+    #[test]
+    fn test_keystore_contains() {
+        let keystore = new_test_keystore(random_test_passphrase());
+        assert!(!keystore.contains("my_root_seed"));
+
+        keystore.add_random_seed("my_root_seed", SEED_SIZE).unwrap();
+        keystore
+            .add_signing_key_from_seed("my_root_seed", "my_keypair")
+            .unwrap();
+        keystore
+            .add_signer("hsm", Box::new(MockSigner(Arc::new(Mutex::new(0)))))
+            .unwrap();
+
+        assert!(keystore.contains("my_root_seed"));
+        assert!(keystore.contains("my_keypair"));
+        assert!(keystore.contains("hsm"));
+        assert!(!keystore.contains("my_second_seed"));
+    }
+
+    #[test]
+    fn test_keystore_sign_with_signer() {
+        let keystore = new_test_keystore(random_test_passphrase());
+        let calls = Arc::new(Mutex::new(0));
+        keystore
+            .add_signer("hsm", Box::new(MockSigner(calls.clone())))
+            .unwrap();
+
+        let signature = keystore.sign("hsm", "abc".to_string()).unwrap();
+        assert_eq!(signature, Signature::from(base64::encode("cba")));
+        assert_eq!(*calls.lock().unwrap(), 1);
+
+        assert_eq!(
+            keystore.add_signer("hsm", Box::new(MockSigner(calls))),
+            Err(HolochainError::ErrorGeneric(
+                "identifier already exists".to_string()
+            ))
+        );
+
+        // software keys work as signers too
+        let mut seed = utils::generate_random_buf(SEED_SIZE);
+        let key_pair = SigningKeyPair::new_from_seed(&mut seed).unwrap();
+        let public_key = key_pair.public();
+        keystore
+            .add_signer("software", Box::new(Mutex::new(key_pair)))
+            .unwrap();
+        let data = base64::encode("the data to sign");
+        let signature = keystore.sign("software", data.clone()).unwrap();
+        assert_eq!(
+            utils::verify(Address::from(public_key), data, signature),
+            Ok(true)
+        );
+    }
+
+    /// signs like [MockSigner], but only once `expected` signers are signing at the same time
+    struct RendezvousSigner {
+        signing: Arc<(Mutex<usize>, Condvar)>,
+        expected: usize,
+    }
+
+    impl Signer for RendezvousSigner {
+        fn sign(&self, data: &mut SecBuf) -> HcResult<SecBuf> {
+            let (ref count, ref arrived) = *self.signing;
+            let mut count = count.lock()?;
+            *count += 1;
+            arrived.notify_all();
+            while *count < self.expected {
+                let (guard, wait) = arrived.wait_timeout(count, Duration::from_secs(10))?;
+                if wait.timed_out() {
+                    return Err(HolochainError::ErrorGeneric("signed on my own".to_string()));
+                }
+                count = guard;
+            }
+            MockSigner(Arc::new(Mutex::new(0))).sign(data)
+        }
+    }
+
+    #[test]
+    fn test_keystore_signs_concurrently() {
+        let keystore = Arc::new(new_test_keystore(random_test_passphrase()));
+        let sign_in_parallel = |ids: Vec<&'static str>, data: &str| {
+            let handles: Vec<_> = ids
+                .into_iter()
+                .map(|id| {
+                    let keystore = keystore.clone();
+                    let data = data.to_string();
+                    thread::spawn(move || keystore.sign(id, data))
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        // each signature can only be made while the other one is being made as well
+        let signing = Arc::new((Mutex::new(0), Condvar::new()));
+        for id in &["first_hsm", "second_hsm"] {
+            let signer = RendezvousSigner {
+                signing: signing.clone(),
+                expected: 2,
+            };
+            keystore.add_signer(id, Box::new(signer)).unwrap();
+        }
+        let expected = Ok(Signature::from(base64::encode("cba")));
+        assert_eq!(
+            sign_in_parallel(vec!["first_hsm", "second_hsm"], "abc"),
+            vec![expected.clone(), expected]
+        );
+
+        // keys from the keystore itself get decrypted by whichever thread uses them first
+        keystore.add_random_seed("my_root_seed", SEED_SIZE).unwrap();
+        let first_key = keystore
+            .add_signing_key_from_seed("my_root_seed", "first_key")
+            .unwrap();
+        let second_key = keystore
+            .add_signing_key_from_seed("my_root_seed", "second_key")
+            .unwrap();
+        let data = base64::encode("the data to sign");
+        let signatures = sign_in_parallel(vec!["first_key", "second_key"], &data);
+        for (key, signature) in vec![first_key, second_key].into_iter().zip(signatures) {
+            let result = utils::verify(Address::from(key), data.clone(), signature.unwrap());
+            assert_eq!(result, Ok(true));
+        }
+    }
+
     #[test]
     fn test_keystore_sign() {
-        let mut keystore = new_test_keystore(random_test_passphrase());
+        let keystore = new_test_keystore(random_test_passphrase());
         let _ = keystore.add_random_seed("my_root_seed", SEED_SIZE);
 
         let data = base64::encode("the data to sign");
@@ -752,8 +2059,262 @@ pub mod tests {
     }
 
     #[test]
-    fn test_keystore_keybundle() {
+    fn test_keystore_verify_integrity() {
+        let keystore = new_test_keystore(random_test_passphrase());
+        keystore.add_random_seed("my_root_seed", SEED_SIZE).unwrap();
+        keystore
+            .add_signing_key_from_seed("my_root_seed", "my_keypair")
+            .unwrap();
+        keystore
+            .add_encrypting_key_from_seed("my_root_seed", "my_enc_keypair")
+            .unwrap();
+        assert_eq!(keystore.verify_integrity("my_keypair"), Ok(true));
+        assert_eq!(keystore.verify_integrity("my_enc_keypair"), Ok(true));
+
+        // keys that weren't derived here can't be verified
+        let mut seed = SecBuf::with_insecure(SEED_SIZE);
+        seed.randomize();
+        let mut keybundle = KeyBundle::new_from_seed_buf(&mut seed).unwrap();
+        keystore.add_keybundle("imported", &mut keybundle).unwrap();
+        assert!(keystore
+            .verify_integrity(&["imported", KEYBUNDLE_SIGNKEY_SUFFIX].join(""))
+            .is_err());
+
+        // corrupt the stored key by swapping in one derived from another seed
+        keystore.add_random_seed("other_seed", SEED_SIZE).unwrap();
+        keystore
+            .add_signing_key_from_seed("other_seed", "other_keypair")
+            .unwrap();
+        {
+            let mut secrets = keystore.secrets.write().unwrap();
+            let other_blob = secrets.remove("other_keypair").unwrap();
+            secrets.insert("my_keypair".to_string(), other_blob);
+        }
+        keystore.cache.write().unwrap().clear();
+        assert_eq!(keystore.verify_integrity("my_keypair"), Ok(false));
+    }
+
+    #[test]
+    fn test_keystore_remove_prefix() {
         let mut keystore = new_test_keystore(random_test_passphrase());
+        keystore.add_random_seed("app_key_1", SEED_SIZE).unwrap();
+        keystore.add_random_seed("app_key_2", SEED_SIZE).unwrap();
+        keystore.add_random_seed("root", SEED_SIZE).unwrap();
+        let secret = keystore.get("app_key_1").unwrap();
+
+        assert_eq!(
+            keystore.remove_prefix("app_key_"),
+            Ok(vec!["app_key_1".to_string(), "app_key_2".to_string()])
+        );
+        assert_eq!(keystore.list(), vec!["root".to_string()]);
+        assert!(!keystore.contains("app_key_1"));
+        assert!(keystore.get("app_key_1").is_err());
+
+        // whoever still holds a removed secret only holds zeros
+        match *secret.lock().unwrap() {
+            Secret::Seed(ref mut buf) => assert!(buf.read_lock().iter().all(|byte| *byte == 0)),
+            _ => unreachable!(),
+        }
+
+        assert_eq!(keystore.remove_prefix("app_key_"), Ok(Vec::new()));
+        assert_eq!(keystore.list(), vec!["root".to_string()]);
+    }
+
+    #[test]
+    fn test_keystore_sign_empty_data() {
+        let keystore = new_test_keystore(random_test_passphrase());
+        let _ = keystore.add_random_seed("my_root_seed", SEED_SIZE);
+        let public_key = keystore
+            .add_signing_key_from_seed("my_root_seed", "my_keypair")
+            .unwrap();
+
+        assert_eq!(
+            keystore.sign("my_keypair", String::new()),
+            Err(HolochainError::ErrorGeneric(
+                "Cannot sign empty data".to_string()
+            ))
+        );
+
+        // a signature made over other data doesn't hold for empty data either
+        let signature = keystore
+            .sign("my_keypair", base64::encode("the data to sign"))
+            .unwrap();
+        assert_eq!(
+            verify(
+                &SignatureAlgorithm::default(),
+                Address::from(public_key),
+                String::new(),
+                signature
+            ),
+            Ok(false)
+        );
+    }
+
+    #[test]
+    fn test_keystore_sign_entry() {
+        let keystore = new_test_keystore(random_test_passphrase());
+        let _ = keystore.add_random_seed("my_root_seed", SEED_SIZE);
+        let public_key = keystore
+            .add_signing_key_from_seed("my_root_seed", "my_keypair")
+            .unwrap();
+
+        let entry = test_entry();
+        let signature = keystore.sign_entry("my_keypair", &entry).unwrap();
+
+        assert_eq!(
+            utils::verify(
+                Address::from(public_key),
+                entry.address().to_string(),
+                signature.clone()
+            ),
+            Ok(true)
+        );
+        assert_eq!(
+            signature,
+            keystore
+                .sign("my_keypair", entry.address().to_string())
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_verify_with_algorithm() {
+        let keystore = new_test_keystore(random_test_passphrase());
+        let _ = keystore.add_random_seed("my_root_seed", SEED_SIZE);
+        let public_key = keystore
+            .add_signing_key_from_seed("my_root_seed", "my_keypair")
+            .unwrap();
+        let data = base64::encode("the data to sign");
+        let signature = keystore.sign("my_keypair", data.clone()).unwrap();
+
+        let algorithm = SignatureAlgorithm::from_str("ed25519").unwrap();
+        assert_eq!(algorithm, SignatureAlgorithm::default());
+        assert_eq!(
+            verify(
+                &algorithm,
+                Address::from(public_key.clone()),
+                data.clone(),
+                signature.clone()
+            ),
+            Ok(true)
+        );
+        assert_eq!(
+            verify(
+                &algorithm,
+                Address::from(public_key),
+                base64::encode("other data"),
+                signature
+            ),
+            Ok(false)
+        );
+
+        assert_eq!(
+            SignatureAlgorithm::from_str("rsa"),
+            Err(HolochainError::ErrorGeneric(
+                "unsupported signature algorithm: rsa".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_keystore_sign_with_context() {
+        let keystore = new_test_keystore(random_test_passphrase());
+        let _ = keystore.add_random_seed("my_root_seed", SEED_SIZE);
+        let public_key = Address::from(
+            keystore
+                .add_signing_key_from_seed("my_root_seed", "my_keypair")
+                .unwrap(),
+        );
+        let algorithm = SignatureAlgorithm::default();
+        let data = String::from("the data to sign");
+
+        let signature = keystore
+            .sign_with_context("my_keypair", data.clone(), "A")
+            .unwrap();
+        let verify_for = |domain: &str, data: &str| {
+            verify_with_context(
+                &algorithm,
+                public_key.clone(),
+                data.to_string(),
+                domain,
+                signature.clone(),
+            )
+        };
+        assert_eq!(verify_for("A", &data), Ok(true));
+        assert_eq!(verify_for("B", &data), Ok(false));
+        assert_eq!(verify_for("", &data), Ok(false));
+
+        // a plain signature is not valid in any domain, nor is a domain signature plain
+        let plain_signature = keystore.sign("my_keypair", data.clone()).unwrap();
+        assert_eq!(
+            verify_with_context(
+                &algorithm,
+                public_key.clone(),
+                data.clone(),
+                "A",
+                plain_signature
+            ),
+            Ok(false)
+        );
+        assert_eq!(
+            verify(
+                &algorithm,
+                public_key.clone(),
+                data.clone(),
+                signature.clone()
+            ),
+            Ok(false)
+        );
+
+        // moving the boundary between domain and data doesn't produce the same message
+        let signature = keystore
+            .sign_with_context("my_keypair", String::from("B:data"), "A")
+            .unwrap();
+        assert_eq!(
+            verify_with_context(
+                &algorithm,
+                public_key,
+                String::from("data"),
+                "A:B",
+                signature
+            ),
+            Ok(false)
+        );
+    }
+
+    #[test]
+    fn test_keystore_sign_with_poisoned_secret() {
+        let keystore = new_test_keystore(random_test_passphrase());
+        let _ = keystore.add_random_seed("my_root_seed", SEED_SIZE);
+        keystore
+            .add_signing_key_from_seed("my_root_seed", "my_keypair")
+            .unwrap();
+        keystore
+            .add_signing_key_from_seed("my_root_seed", "my_other_keypair")
+            .unwrap();
+
+        // a thread panicking while holding the secret leaves its mutex poisoned
+        let secret = keystore.get("my_keypair").unwrap();
+        let _ = std::thread::spawn(move || {
+            let _guard = secret.lock().unwrap();
+            panic!("poisoning the secret");
+        })
+        .join();
+
+        let data = base64::encode("the data to sign");
+        let result = keystore.sign("my_keypair", data.clone());
+        match result {
+            Err(HolochainError::ErrorGeneric(msg)) => assert!(msg.contains("poison")),
+            _ => panic!("expected an error for the poisoned secret"),
+        }
+
+        // other secrets are unaffected
+        assert!(keystore.sign("my_other_keypair", data).is_ok());
+    }
+
+    #[test]
+    fn test_keystore_keybundle() {
+        let keystore = new_test_keystore(random_test_passphrase());
 
         assert_eq!(
             keystore.add_keybundle_from_seed("my_root_seed", "my_keybundle"),
@@ -795,13 +2356,50 @@ pub mod tests {
         assert!(key_bundle.enc_keys.is_same(&mut key_bundle_copy.enc_keys));
     }
 
+    #[test]
+    fn test_keystore_derive_agent_keys() {
+        let seed = [7; SEED_SIZE];
+        let keystore = new_test_keystore(random_test_passphrase());
+        keystore.add_seed_from_bytes("root_seed", &seed).unwrap();
+        let agent_keys = keystore.derive_agent_keys("root_seed").unwrap();
+
+        assert_eq!(agent_keys.keybundle_id, "root_seed:agent_keybundle");
+        assert_eq!(
+            keystore.list(),
+            vec![
+                "root_seed",
+                "root_seed:agent_keybundle:enc_key",
+                "root_seed:agent_keybundle:sign_key",
+                "root_seed:agent_seed",
+                "root_seed:device_seed",
+            ]
+        );
+
+        // the same derivation done step by step
+        let manual = new_test_keystore(random_test_passphrase());
+        manual.add_seed_from_bytes("root", &seed).unwrap();
+        manual
+            .add_seed_from_seed("root", "device", &SeedContext::new(*b"HCDEVICE"), 1)
+            .unwrap();
+        manual
+            .add_seed_from_seed("device", "agent", &SeedContext::new(*b"HCAGNTID"), 1)
+            .unwrap();
+        let (sign_public_key, enc_public_key) =
+            manual.add_keybundle_from_seed("agent", "keys").unwrap();
+
+        assert_eq!(agent_keys.sign_public_key, sign_public_key);
+        assert_eq!(agent_keys.enc_public_key, enc_public_key);
+
+        assert!(keystore.derive_agent_keys("unknown_seed").is_err());
+    }
+
     #[test]
     /// Tests if the keystore encrypted with holochain_common::DEFAULT_PASSPHRASE can be decrypted,
     /// no matter what passphrase we get from the passphrase manager
     /// ("definitely wrong passphrase" should not be used at all since the default passphrase should
     /// be tried before even asking the passphrase manager)
     fn test_keystore_default_passphrase() {
-        let mut loaded_keystore = Keystore::new_from_file(
+        let loaded_keystore = Keystore::new_from_file(
             PathBuf::from("test_keystore"),
             mock_passphrase_manager("definitely wrong passphrase".to_string()),
             None,
@@ -840,4 +2438,271 @@ pub mod tests {
         );
     }
 
+    #[test]
+    fn test_derivation_vectors() {
+        // (root seed, context, index) -> derived signing public key (raw, hex encoded)
+        let vectors: Vec<([u8; SEED_SIZE], [u8; 8], u64, &str)> = vec![
+            (
+                [0; SEED_SIZE],
+                *b"HCAGNTID",
+                1,
+                "b8cabf4820a88e6445074c6f6990d1026a067ac92759e4b0e7696c5bddbf026e",
+            ),
+            (
+                [0x2a; SEED_SIZE],
+                *b"HCAGNTID",
+                7,
+                "c0c65a88e4b4c6c9729f7cfb9f2977f4679ece6f4fd1a94f231af8f4b12a3f4f",
+            ),
+            (
+                [
+                    0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21,
+                    22, 23, 24, 25, 26, 27, 28, 29, 30, 31,
+                ],
+                *b"SOMECTXT",
+                42,
+                "2303349b2013fe03836296f840c9a932ccb1e7454f407f2b45cc41e19094abbb",
+            ),
+        ];
+
+        for (seed, context, index, expected) in vectors {
+            let keystore = new_test_keystore(random_test_passphrase());
+            keystore.add_seed_from_bytes("root_seed", &seed).unwrap();
+            keystore
+                .add_seed_from_seed(
+                    "root_seed",
+                    "derived_seed",
+                    &SeedContext::new(context),
+                    index,
+                )
+                .unwrap();
+            let pub_key = keystore
+                .add_signing_key_from_seed("derived_seed", "sign_key")
+                .unwrap();
+
+            let raw_key = holochain_dpki::CODEC_HCS0.decode(&pub_key).unwrap();
+            let hex_key: String = raw_key.iter().map(|b| format!("{:02x}", b)).collect();
+            assert_eq!(hex_key, expected, "vector for index {} drifted", index);
+        }
+
+        // pins the Base32 encoding as well as the derivation
+        let keystore = new_test_keystore(random_test_passphrase());
+        let mut name = SecBuf::with_insecure_from_string("testAgent".to_string());
+        let mut seed = SecBuf::with_insecure(SEED_SIZE);
+        lib3h_sodium::hash::sha256(&mut name, &mut seed).unwrap();
+        keystore
+            .add_seed_from_bytes("root_seed", &**seed.read_lock())
+            .unwrap();
+        assert_eq!(
+            keystore
+                .add_signing_key_from_seed("root_seed", "sign_key")
+                .unwrap(),
+            "HcScjN8wBwrn3tuyg89aab3a69xsIgdzmX5P9537BqQZ5A7TEZu7qCY4Xzzjhma".to_string()
+        );
+    }
+
+    #[test]
+    fn test_import_legacy() {
+        let keystore = Keystore::import_legacy(
+            PathBuf::from("test_legacy_agent.key"),
+            mock_passphrase_manager("legacy passphrase".to_string()),
+            test_hash_config(),
+        )
+        .unwrap();
+        assert_eq!(
+            keystore.list(),
+            vec!["primary_keybundle:enc_key", "primary_keybundle:sign_key"]
+        );
+
+        // the legacy file holds the keys derived straight from this seed
+        let mut seed = SecBuf::with_insecure(SEED_SIZE);
+        seed.write(0, &[7; SEED_SIZE]).unwrap();
+        let expected = KeyBundle::new_from_seed_buf(&mut seed).unwrap();
+        let mut imported = keystore.get_keybundle(PRIMARY_KEYBUNDLE_ID).unwrap();
+        assert_eq!(imported.get_id(), expected.get_id());
+        let raw_key = holochain_dpki::CODEC_HCS0
+            .decode(&imported.get_id())
+            .unwrap();
+        let hex_key: String = raw_key.iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(
+            hex_key,
+            "ea4a6c63e29c520abef5507b132ec5f9954776aebebe7b92421eea691446d22c"
+        );
+
+        // the imported keys work and get stored with the legacy passphrase
+        let mut data = SecBuf::with_insecure_from_string("some data".to_string());
+        let mut signature = imported.sign(&mut data).unwrap();
+        assert!(imported.verify(&mut data, &mut signature));
+        let mut passphrase = SecBuf::with_insecure_from_string("legacy passphrase".to_string());
+        assert_eq!(keystore.check_passphrase(&mut passphrase), Ok(true));
+    }
+
+    #[test]
+    fn test_import_legacy_errors() {
+        let import = |path: PathBuf, passphrase: &str| {
+            Keystore::import_legacy(
+                path,
+                mock_passphrase_manager(passphrase.to_string()),
+                test_hash_config(),
+            )
+        };
+        let message = |result: HcResult<Keystore>| match result {
+            Err(HolochainError::ErrorGeneric(message)) => message,
+            _ => panic!("expected a generic error"),
+        };
+
+        let wrong_passphrase = import(PathBuf::from("test_legacy_agent.key"), "wrong");
+        assert!(message(wrong_passphrase).contains("could not decrypt keys"));
+
+        let dir = tempdir().unwrap();
+        let malformed = dir.path().join("malformed.key");
+        std::fs::write(&malformed, "{\"not\": \"a key\"}").unwrap();
+        assert!(message(import(malformed, "legacy passphrase"))
+            .starts_with("Can't import legacy key file"));
+
+        let mut seed = Seed::new(utils::generate_random_seed_buf(), SeedType::Root).unwrap();
+        let mut passphrase = SecBuf::with_insecure_from_string("legacy passphrase".to_string());
+        let seed_blob = seed
+            .as_blob(&mut passphrase, "".to_string(), test_hash_config())
+            .unwrap();
+        let unsupported = dir.path().join("seed.key");
+        std::fs::write(&unsupported, serde_json::to_string(&seed_blob).unwrap()).unwrap();
+        assert!(
+            message(import(unsupported, "legacy passphrase")).contains("unsupported key type Seed")
+        );
+    }
+
+    #[test]
+    fn test_legacy_key_files_are_format_version_0() {
+        let legacy = PathBuf::from("test_legacy_agent.key");
+        assert_eq!(Keystore::file_format_version(&legacy), Ok(0));
+        assert_eq!(
+            Keystore::file_format_version(&PathBuf::from("test_keystore")),
+            Ok(1)
+        );
+
+        match Keystore::new_from_file(
+            legacy,
+            mock_passphrase_manager("legacy passphrase".to_string()),
+            test_hash_config(),
+        ) {
+            Err(HolochainError::ErrorGeneric(message)) => assert_eq!(
+                message,
+                "test_legacy_agent.key is a legacy agent key file, it needs to be migrated to a keystore first"
+            ),
+            _ => panic!("expected a generic error"),
+        }
+    }
+
+    #[test]
+    fn test_keystore_diff() {
+        let passphrase = random_test_passphrase();
+        let mut keystore = new_test_keystore(passphrase.clone());
+        keystore.add_random_seed("my_root_seed", SEED_SIZE).unwrap();
+        keystore
+            .add_signing_key_from_seed("my_root_seed", "my_keypair")
+            .unwrap();
+        keystore
+            .add_signing_key_from_seed("my_root_seed", "old:keypair")
+            .unwrap();
+        assert!(keystore.diff(&keystore).is_empty());
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("snapshot.keystore");
+        keystore.save(path.clone()).unwrap();
+        let snapshot = Keystore::new_from_file(
+            path,
+            mock_passphrase_manager(passphrase),
+            test_hash_config(),
+        )
+        .unwrap();
+        assert!(snapshot.diff(&keystore).is_empty());
+
+        keystore.remove_prefix("old:").unwrap();
+        keystore.remove_prefix("my_keypair").unwrap();
+        keystore
+            .add_encrypting_key_from_seed("my_root_seed", "my_keypair")
+            .unwrap();
+        keystore
+            .add_signing_key_from_seed("my_root_seed", "new:keypair")
+            .unwrap();
+        keystore
+            .add_signer("new:signer", Box::new(MockSigner(Arc::new(Mutex::new(0)))))
+            .unwrap();
+
+        let diff = snapshot.diff(&keystore);
+        assert_eq!(
+            diff,
+            KeystoreDiff {
+                added: vec!["new:keypair".to_string(), "new:signer".to_string()],
+                removed: vec!["old:keypair".to_string()],
+                type_changed: vec!["my_keypair".to_string()],
+            }
+        );
+        // the other way around, additions are removals
+        let reverse = keystore.diff(&snapshot);
+        assert_eq!(reverse.added, diff.removed);
+        assert_eq!(reverse.removed, diff.added);
+        assert_eq!(reverse.type_changed, diff.type_changed);
+    }
+
+    #[test]
+    fn test_keystore_sign_buffer_pool() {
+        let keystore = Arc::new(new_test_keystore(random_test_passphrase()));
+        keystore.set_sign_buffer_pool_size(2).unwrap();
+        keystore.add_random_seed("my_root_seed", SEED_SIZE).unwrap();
+        let keys: Vec<_> = vec!["first_key", "second_key"]
+            .into_iter()
+            .map(|id| {
+                let key = keystore
+                    .add_signing_key_from_seed("my_root_seed", id)
+                    .unwrap();
+                (id, key)
+            })
+            .collect();
+
+        // messages of the same length all go through the same few pooled buffers
+        let handles: Vec<_> = (0..4)
+            .map(|thread_index| {
+                let keystore = keystore.clone();
+                let keys = keys.clone();
+                thread::spawn(move || {
+                    (0..25)
+                        .map(|i| {
+                            let (id, key) = keys[i % keys.len()].clone();
+                            let data = format!("message {:02} from thread {}", i, thread_index);
+                            let signature = keystore.sign(id, data.clone()).unwrap();
+                            (key, data, signature)
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        let signed: Vec<_> = handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect();
+
+        for (key, data, signature) in signed.iter().cloned() {
+            let result = utils::verify(Address::from(key), data.clone(), signature.clone());
+            assert_eq!(result, Ok(true), "bad signature of {}", data);
+            // signing again from a reused buffer gives the same signature
+            let id = keys.iter().find(|(_, k)| *k == key).unwrap().0;
+            assert_eq!(keystore.sign(id, data), Ok(signature));
+        }
+        let signatures: HashSet<_> = signed.iter().map(|(_, _, signature)| signature).collect();
+        assert_eq!(signatures.len(), signed.len());
+
+        // the pool stays within its size and holds no data
+        let mut pool = keystore.sign_buffers.lock().unwrap();
+        assert!(pool.pooled() > 0 && pool.pooled() <= 2);
+        for buf in pool.buffers.values_mut().flat_map(|bufs| bufs.iter_mut()) {
+            assert!(buf.read_lock().iter().all(|byte| *byte == 0));
+        }
+        drop(pool);
+
+        keystore.set_sign_buffer_pool_size(0).unwrap();
+        keystore.sign("first_key", "more data".to_string()).unwrap();
+        assert_eq!(keystore.sign_buffers.lock().unwrap().pooled(), 0);
+    }
 }
@@ -18,8 +18,13 @@ use crate::{
 };
 
 use holochain_core_types::{
-    chain_header::ChainHeader, crud_status::CrudStatus, dna::Dna, entry::Entry,
-    error::HolochainError, link::link_data::LinkData, signature::Provenance,
+    chain_header::ChainHeader,
+    crud_status::CrudStatus,
+    dna::Dna,
+    entry::{cap_entries::RateLimit, Entry},
+    error::HolochainError,
+    link::link_data::LinkData,
+    signature::Provenance,
     validation::ValidationPackage,
 };
 use holochain_net::{connection::net_connection::NetHandler, p2p_config::P2pConfig};
@@ -28,6 +33,7 @@ use lib3h_protocol::data_types::{EntryListData, FetchEntryData, QueryEntryData};
 use snowflake;
 use std::{
     hash::{Hash, Hasher},
+    time::SystemTime,
     vec::Vec,
 };
 
@@ -145,6 +151,10 @@ pub enum Action {
     ///Performs a Query Timeout Action which times out based the values given
     QueryTimeout(QueryKey),
 
+    /// Gives up on the pending query with the given key, see `NetworkState::abort_query`.
+    /// Dispatched when whoever waited for the query stopped doing so before it resolved.
+    AbortQuery(QueryKey),
+
     /// Lets the network module respond to a Query request.
     /// Triggered from the corresponding workflow after retrieving the
     /// requested object from the DHT
@@ -154,6 +164,11 @@ pub enum Action {
     /// Triggered from the network handler.
     HandleQuery((NetworkQueryResult, QueryKey)),
 
+    /// We got one of the chunks a response for our get request was split into:
+    /// the chunk's index, the number of chunks and the chunk itself.
+    /// See `NetworkState::add_query_chunk`.
+    HandleQueryChunk((QueryKey, usize, usize, String)),
+
     RespondFetch((FetchEntryData, Vec<EntryAspect>)),
 
     UpdateEntry((Address, Address)),
@@ -213,6 +228,13 @@ pub enum Action {
     /// return the result of a zome WASM function call
     ReturnZomeFunctionResult(ExecuteZomeFnResponse),
 
+    /// Records a call made under a rate limited capability token at the given time.
+    /// Calls that fell out of the limit's window get dropped from the state.
+    RecordCapTokenCall((Address, RateLimit, SystemTime)),
+
+    /// Counts a use of a capability token whose grant can only be used so many times.
+    RecordCapTokenUse(Address),
+
     /// A validation result is returned from a local callback execution
     /// Key is an unique id of the calling context
     /// and the hash of the entry that was validated
@@ -234,10 +256,59 @@ pub enum Action {
     /// Clear an entry from the pending validation list
     RemovePendingValidation((Address, ValidatingWorkflow)),
 
+    /// Clear an entry from the pending validation list because validating it failed,
+    /// giving the reason
+    FailPendingValidation((Address, ValidatingWorkflow, String)),
+
     /// No-op, used to check if an action channel is still open
     Ping,
 }
 
+impl Action {
+    /// Name of the variant, like "Ping"
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Commit(_) => "Commit",
+            Action::Hold(_) => "Hold",
+            Action::AddLink(_) => "AddLink",
+            Action::CrudStatus(_) => "CrudStatus",
+            Action::RemoveLink(_) => "RemoveLink",
+            Action::InitNetwork(_) => "InitNetwork",
+            Action::ShutdownNetwork => "ShutdownNetwork",
+            Action::Publish(_) => "Publish",
+            Action::Query(_) => "Query",
+            Action::QueryTimeout(_) => "QueryTimeout",
+            Action::AbortQuery(_) => "AbortQuery",
+            Action::RespondQuery(_) => "RespondQuery",
+            Action::HandleQuery(_) => "HandleQuery",
+            Action::HandleQueryChunk(_) => "HandleQueryChunk",
+            Action::RespondFetch(_) => "RespondFetch",
+            Action::UpdateEntry(_) => "UpdateEntry",
+            Action::RemoveEntry(_) => "RemoveEntry",
+            Action::SendDirectMessage(_) => "SendDirectMessage",
+            Action::SendDirectMessageTimeout(_) => "SendDirectMessageTimeout",
+            Action::ResolveDirectConnection(_) => "ResolveDirectConnection",
+            Action::GetValidationPackage(_) => "GetValidationPackage",
+            Action::HandleGetValidationPackage(_) => "HandleGetValidationPackage",
+            Action::HandleCustomSendResponse(_) => "HandleCustomSendResponse",
+            Action::RespondAuthoringList(_) => "RespondAuthoringList",
+            Action::RespondGossipList(_) => "RespondGossipList",
+            Action::InitializeChain(_) => "InitializeChain",
+            Action::ReturnInitializationResult(_) => "ReturnInitializationResult",
+            Action::SignalZomeFunctionCall(_) => "SignalZomeFunctionCall",
+            Action::ReturnZomeFunctionResult(_) => "ReturnZomeFunctionResult",
+            Action::RecordCapTokenCall(_) => "RecordCapTokenCall",
+            Action::RecordCapTokenUse(_) => "RecordCapTokenUse",
+            Action::ReturnValidationResult(_) => "ReturnValidationResult",
+            Action::ReturnValidationPackage(_) => "ReturnValidationPackage",
+            Action::AddPendingValidation(_) => "AddPendingValidation",
+            Action::RemovePendingValidation(_) => "RemovePendingValidation",
+            Action::FailPendingValidation(_) => "FailPendingValidation",
+            Action::Ping => "Ping",
+        }
+    }
+}
+
 /// function signature for action handler functions
 // @TODO merge these into a single signature
 // @see https://github.com/holochain/holochain-rust/issues/194
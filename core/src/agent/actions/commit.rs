@@ -19,16 +19,25 @@ pub async fn commit_entry(
     maybe_link_update_delete: Option<Address>,
     context: &Arc<Context>,
 ) -> Result<Address, HolochainError> {
+    // deletions are how grants get revoked
+    let affects_grants = match entry {
+        Entry::CapTokenGrant(_) | Entry::Deletion(_) => true,
+        _ => false,
+    };
     let action_wrapper = ActionWrapper::new(Action::Commit((
         entry.clone(),
         maybe_link_update_delete,
         vec![],
     )));
     dispatch_action(context.action_channel(), action_wrapper.clone());
-    await!(CommitFuture {
+    let result = await!(CommitFuture {
         context: context.clone(),
         action: action_wrapper,
-    })
+    });
+    if affects_grants {
+        context.invalidate_grant_cache();
+    }
+    result
 }
 
 /// CommitFuture resolves to ActionResponse
@@ -0,0 +1,70 @@
+//! Common sink for the structured events an instance reports about what it does,
+//! so that auditing, metrics and the like only need to be wired up once, on the `Context`.
+
+use crate::nucleus::actions::call_zome_function::CapabilityAuditRecord;
+use crossbeam_channel::Sender;
+use std::sync::Mutex;
+
+/// Something that happened in an instance and is worth recording
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub enum AuditEvent {
+    /// A capability check was made for a zome call
+    CapabilityDecision(CapabilityAuditRecord),
+}
+
+/// Receives the audit events of the instances whose context it is installed on with
+/// `Context::set_audit_sink`.
+/// Events are reported from whatever thread produced them, so implementations must not block.
+pub trait AuditSink: Send + Sync {
+    fn record(&self, event: AuditEvent);
+}
+
+/// Drops all events. This is what contexts use unless another sink gets installed.
+#[derive(Clone, Debug, Default)]
+pub struct NullAuditSink;
+
+impl AuditSink for NullAuditSink {
+    fn record(&self, _event: AuditEvent) {}
+}
+
+/// Sends all events down a channel, e.g. to have them processed on a thread of their own.
+/// Sending is best effort, events get dropped once the receiver is gone.
+#[derive(Clone, Debug)]
+pub struct ChannelAuditSink {
+    sender: Sender<AuditEvent>,
+}
+
+impl ChannelAuditSink {
+    pub fn new(sender: Sender<AuditEvent>) -> Self {
+        ChannelAuditSink { sender }
+    }
+}
+
+impl AuditSink for ChannelAuditSink {
+    fn record(&self, event: AuditEvent) {
+        let _ = self.sender.send(event);
+    }
+}
+
+/// Keeps all events in memory, in the order they were recorded. Meant for tests.
+#[derive(Debug, Default)]
+pub struct MemoryAuditSink {
+    events: Mutex<Vec<AuditEvent>>,
+}
+
+impl MemoryAuditSink {
+    pub fn new() -> Self {
+        MemoryAuditSink::default()
+    }
+
+    /// Returns the events recorded so far
+    pub fn events(&self) -> Vec<AuditEvent> {
+        self.events.lock().unwrap().clone()
+    }
+}
+
+impl AuditSink for MemoryAuditSink {
+    fn record(&self, event: AuditEvent) {
+        self.events.lock().unwrap().push(event);
+    }
+}
@@ -1,16 +1,34 @@
 use crate::{
-    action::Action, context::Context, entry::CanPublish,
-    network::entry_with_header::EntryWithHeader,
+    action::Action,
+    context::Context,
+    entry::CanPublish,
+    logger::Logger,
+    network::{entry_with_header::EntryWithHeader, fifo_map::FifoMap},
 };
-use holochain_core_types::{agent::AgentId, entry::Entry, link::link_data::LinkData};
+use holochain_core_types::{
+    agent::AgentId, entry::Entry, error::HolochainError, link::link_data::LinkData, time::Iso8601,
+};
+use holochain_json_api::json::JsonString;
 use holochain_persistence_api::cas::content::{Address, AddressableContent};
 use serde::Serialize;
-use std::{collections::HashMap, sync::Arc};
+use snowflake::ProcessUniqueId;
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    convert::TryFrom,
+    fmt,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
 
 #[derive(Clone, Debug, Serialize)]
 pub struct ConsistencySignal<E: Serialize> {
     event: E,
     pending: Vec<PendingConsistency<E>>,
+    // when the model saw the action causing the event, so that consumers can measure how long
+    // it took from a cause (like a Publish) to its effects (like the Hold)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    observed_at: Option<Iso8601>,
 }
 
 impl<E: Serialize> ConsistencySignal<E> {
@@ -18,6 +36,7 @@ impl<E: Serialize> ConsistencySignal<E> {
         Self {
             event,
             pending: Vec::new(),
+            observed_at: None,
         }
     }
 
@@ -29,13 +48,21 @@ impl<E: Serialize> ConsistencySignal<E> {
                 group: group.clone(),
             })
             .collect();
-        Self { event, pending }
+        Self {
+            event,
+            pending,
+            observed_at: None,
+        }
     }
 }
 
 impl From<ConsistencySignalE> for ConsistencySignal<String> {
     fn from(signal: ConsistencySignalE) -> ConsistencySignal<String> {
-        let ConsistencySignalE { event, pending } = signal;
+        let ConsistencySignalE {
+            event,
+            pending,
+            observed_at,
+        } = signal;
         ConsistencySignal {
             event: serde_json::to_string(&event)
                 .expect("ConsistencySignal serialization cannot fail"),
@@ -47,13 +74,23 @@ impl From<ConsistencySignalE> for ConsistencySignal<String> {
                     group: p.group,
                 })
                 .collect(),
+            observed_at,
         }
     }
 }
 
+impl ConsistencySignal<String> {
+    /// Whether this signals that the instance finished its initialization
+    pub fn is_initialization_complete(&self) -> bool {
+        self.event
+            == serde_json::to_string(&ConsistencyEvent::InitializationComplete)
+                .expect("ConsistencySignal serialization cannot fail")
+    }
+}
+
 type ConsistencySignalE = ConsistencySignal<ConsistencyEvent>;
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub enum ConsistencyEvent {
     // CAUSES
     Publish(Address),                                   // -> Hold
@@ -65,9 +102,194 @@ pub enum ConsistencyEvent {
     UpdateEntry(Address, Address),                        // <- Publish, entry_type=Update
     RemoveEntry(Address, Address),                        // <- Publish, entry_type=Deletion
     AddLink(LinkData),                                    // <- Publish, entry_type=LinkAdd
-    RemoveLink(Entry),                                    // <- Publish, entry_type=LinkRemove
+    RemoveLink(Entry, LinkRemoval),                       // <- Publish, entry_type=LinkRemove
     RemovePendingValidation(Address),                     // <- AddPendingValidation
+    ValidationFailed(Address, String),                    // <- AddPendingValidation, if it fails
     ReturnZomeFunctionResult(snowflake::ProcessUniqueId), // <- SignalZomeFunctionCall
+
+    // STANDALONE
+    UpdateAgentId(Address, Address), // old agent address, new agent address
+    InitializationComplete,          // genesis/init finished successfully
+}
+
+/// Which link additions a `RemoveLink` event negates
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub enum LinkRemoval {
+    /// Addresses of the `LinkAdd` entries being removed that were seen getting added
+    Negates(Vec<Address>),
+    /// None of the links being removed were seen getting added
+    Orphan,
+}
+
+/// `orphan`, or the negated addresses separated by spaces
+impl fmt::Display for LinkRemoval {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LinkRemoval::Negates(addresses) => {
+                let addresses: Vec<String> = addresses.iter().map(Address::to_string).collect();
+                write!(f, "{}", addresses.join(" "))
+            }
+            LinkRemoval::Orphan => write!(f, "orphan"),
+        }
+    }
+}
+
+impl FromStr for LinkRemoval {
+    type Err = HolochainError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "orphan" => Ok(LinkRemoval::Orphan),
+            "" => Err(HolochainError::ErrorGeneric(
+                "Link removal negates no addresses".to_string(),
+            )),
+            _ => Ok(LinkRemoval::Negates(
+                s.split(' ').map(Address::from).collect(),
+            )),
+        }
+    }
+}
+
+/// Compact form for logs: `Name(arg, arg)` with addresses and ids as is
+/// and link data and entries as JSON. `FromStr` parses it back.
+impl fmt::Display for ConsistencyEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use ConsistencyEvent::*;
+        match self {
+            Publish(address) => write!(f, "Publish({})", address),
+            AddPendingValidation(address) => write!(f, "AddPendingValidation({})", address),
+            SignalZomeFunctionCall(id) => write!(f, "SignalZomeFunctionCall({})", id),
+            Hold(address) => write!(f, "Hold({})", address),
+            UpdateEntry(old, new) => write!(f, "UpdateEntry({}, {})", old, new),
+            RemoveEntry(old, new) => write!(f, "RemoveEntry({}, {})", old, new),
+            AddLink(link_data) => write!(f, "AddLink({})", JsonString::from(link_data.clone())),
+            RemoveLink(entry, removal) => {
+                write!(
+                    f,
+                    "RemoveLink({}, {})",
+                    removal,
+                    JsonString::from(entry.clone())
+                )
+            }
+            RemovePendingValidation(address) => {
+                write!(f, "RemovePendingValidation({})", address)
+            }
+            ValidationFailed(address, reason) => {
+                write!(f, "ValidationFailed({}, {})", address, reason)
+            }
+            ReturnZomeFunctionResult(id) => write!(f, "ReturnZomeFunctionResult({})", id),
+            UpdateAgentId(old, new) => write!(f, "UpdateAgentId({}, {})", old, new),
+            InitializationComplete => write!(f, "InitializationComplete"),
+        }
+    }
+}
+
+impl FromStr for ConsistencyEvent {
+    type Err = HolochainError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use ConsistencyEvent::*;
+        let invalid = || HolochainError::ErrorGeneric(format!("Invalid consistency event: {}", s));
+        if s == "InitializationComplete" {
+            return Ok(InitializationComplete);
+        }
+        // JSON arguments can contain parentheses themselves, so only strip the outer ones
+        let open = s.find('(').ok_or_else(invalid)?;
+        if !s.ends_with(')') {
+            return Err(invalid());
+        }
+        let (name, args) = (&s[..open], &s[open + 1..s.len() - 1]);
+        let address = || Address::from(args);
+        let address_pair = || match args.split(", ").collect::<Vec<_>>().as_slice() {
+            [old, new] => Ok((Address::from(*old), Address::from(*new))),
+            _ => Err(invalid()),
+        };
+        Ok(match name {
+            "Publish" => Publish(address()),
+            "AddPendingValidation" => AddPendingValidation(address()),
+            "SignalZomeFunctionCall" => SignalZomeFunctionCall(parse_process_unique_id(args)?),
+            "Hold" => Hold(address()),
+            "UpdateEntry" => address_pair().map(|(old, new)| UpdateEntry(old, new))?,
+            "RemoveEntry" => address_pair().map(|(old, new)| RemoveEntry(old, new))?,
+            "AddLink" => AddLink(LinkData::try_from(JsonString::from_json(args))?),
+            "RemoveLink" => {
+                // the removal never contains ", ", unlike the entry's JSON
+                let separator = args.find(", ").ok_or_else(invalid)?;
+                let removal = args[..separator].parse()?;
+                let entry = Entry::try_from(JsonString::from_json(&args[separator + 2..]))?;
+                RemoveLink(entry, removal)
+            }
+            "RemovePendingValidation" => RemovePendingValidation(address()),
+            "ValidationFailed" => {
+                // the reason can contain ", " itself, unlike the address
+                let separator = args.find(", ").ok_or_else(invalid)?;
+                let reason = args[separator + 2..].to_string();
+                ValidationFailed(Address::from(&args[..separator]), reason)
+            }
+            "ReturnZomeFunctionResult" => ReturnZomeFunctionResult(parse_process_unique_id(args)?),
+            "UpdateAgentId" => address_pair().map(|(old, new)| UpdateAgentId(old, new))?,
+            _ => return Err(invalid()),
+        })
+    }
+}
+
+/// ProcessUniqueId displays as `puid-<prefix>-<offset>` in hex but doesn't parse that back
+fn parse_process_unique_id(s: &str) -> Result<ProcessUniqueId, HolochainError> {
+    let invalid = || HolochainError::ErrorGeneric(format!("Invalid process unique id: {}", s));
+    match s.split('-').collect::<Vec<_>>().as_slice() {
+        ["puid", prefix, offset] => {
+            let prefix = usize::from_str_radix(prefix, 16).map_err(|_| invalid())?;
+            let offset = u64::from_str_radix(offset, 16).map_err(|_| invalid())?;
+            serde_json::from_value(serde_json::json!({ "prefix": prefix, "offset": offset }))
+                .map_err(|_| invalid())
+        }
+        _ => Err(invalid()),
+    }
+}
+
+/// Time since the UNIX epoch
+fn now() -> Duration {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .expect("System time must not be before UNIX EPOCH")
+}
+
+/// How many of the latest Publish to Hold latencies [ConsistencyModel::latency_stats] covers
+pub const LATENCY_WINDOW: usize = 1000;
+
+/// How many published entries the model waits for the Hold of at once.
+/// Beyond that the oldest Publish gets forgotten, so entries that are never held don't pile up.
+pub const MAX_AWAITED_HOLDS: usize = 1000;
+
+/// How many of the latest LinkAdd entries RemoveLink signals get matched against.
+/// Removals of older additions are reported as orphans.
+pub const MAX_OBSERVED_LINK_ADDS: usize = 5000;
+
+/// Statistics over the time it took published entries to be held, see
+/// [ConsistencyModel::latency_stats]
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct LatencyStats {
+    /// Number of latencies the statistics are computed from
+    pub count: usize,
+    pub average: Duration,
+    /// 95th percentile, using the nearest rank
+    pub p95: Duration,
+}
+
+impl LatencyStats {
+    fn from_latencies(latencies: &VecDeque<Duration>) -> Self {
+        if latencies.is_empty() {
+            return LatencyStats::default();
+        }
+        let count = latencies.len();
+        let mut sorted: Vec<Duration> = latencies.iter().cloned().collect();
+        sorted.sort();
+        let total: Duration = sorted.iter().sum();
+        let p95_rank = (count * 95 + 99) / 100;
+        LatencyStats {
+            count,
+            average: total / count as u32,
+            p95: sorted[p95_rank - 1],
+        }
+    }
 }
 
 #[derive(Clone, Debug, Serialize)]
@@ -82,6 +304,14 @@ pub enum ConsistencyGroup {
     Validators,
 }
 
+/// What a Publish means given the commits and publishes observed before it
+#[derive(Debug)]
+enum PublishOutcome {
+    Committed(ConsistencySignalE),
+    AlreadyPublished,
+    NeverCommitted,
+}
+
 #[derive(Clone)]
 pub struct ConsistencyModel {
     // upon Commit, caches the corresponding ConsistencySignal which will only be emitted
@@ -91,6 +321,31 @@ pub struct ConsistencyModel {
     // Stores the AgentId, once it has been committed
     agent_id: Option<AgentId>,
 
+    // Addresses of the latest MAX_OBSERVED_LINK_ADDS LinkAdd entries seen, so that
+    // RemoveLink signals can name the additions they negate
+    link_adds: FifoMap<Address, ()>,
+
+    // Addresses whose Publish already emitted the cached signal, until they are held,
+    // so that publishing them again isn't mistaken for publishing something that was never
+    // committed. Only the latest MAX_AWAITED_HOLDS of them.
+    published: FifoMap<Address, ()>,
+
+    // When the Publish signals of entries not held yet were emitted, by address,
+    // for the latest MAX_AWAITED_HOLDS of them
+    published_at: FifoMap<Address, Duration>,
+
+    // How long the latest held entries took from their Publish to their Hold, oldest first
+    latencies: VecDeque<Duration>,
+
+    // Whether to trace the actions the model has no signal for, see set_debug
+    debug: bool,
+
+    // Where debug mode traces to instead of the instance's log, see set_debug_logger
+    debug_logger: Option<Arc<Mutex<dyn Logger>>>,
+
+    // In debug mode, how often each variant of Action fell through without being modeled
+    unmodeled_actions: BTreeMap<String, usize>,
+
     // Context needed to examine state and do logging
     context: Arc<Context>,
 }
@@ -100,17 +355,181 @@ impl ConsistencyModel {
         Self {
             commit_cache: HashMap::new(),
             agent_id: None,
+            link_adds: FifoMap::new(MAX_OBSERVED_LINK_ADDS),
+            published: FifoMap::new(MAX_AWAITED_HOLDS),
+            published_at: FifoMap::new(MAX_AWAITED_HOLDS),
+            latencies: VecDeque::new(),
+            debug: false,
+            debug_logger: None,
+            unmodeled_actions: BTreeMap::new(),
             context,
         }
     }
 
+    /// Number of commits whose signal is cached, waiting for the corresponding Publish
+    pub fn pending_count(&self) -> usize {
+        self.commit_cache.len()
+    }
+
+    /// Turns the debug mode on or off, which is off by default.
+    /// In debug mode, every action the model doesn't know about gets logged at trace level
+    /// and counted in [unmodeled_actions], to find actions that should cause signals
+    /// but don't.
+    pub fn set_debug(&mut self, debug: bool) {
+        self.debug = debug;
+    }
+
+    /// Makes debug mode write its traces to `logger` instead of the instance's log
+    pub fn set_debug_logger(&mut self, logger: Arc<Mutex<dyn Logger>>) {
+        self.debug_logger = Some(logger);
+    }
+
+    /// How often each variant of Action was processed without being modeled, since debug
+    /// mode was turned on. Empty outside of debug mode.
+    pub fn unmodeled_actions(&self) -> BTreeMap<String, usize> {
+        self.unmodeled_actions.clone()
+    }
+
+    /// Count, average and 95th percentile of the time from the Publish of an entry to its Hold,
+    /// over the last [LATENCY_WINDOW] entries that were published and then held
+    pub fn latency_stats(&self) -> LatencyStats {
+        LatencyStats::from_latencies(&self.latencies)
+    }
+
+    /// Forgets all cached commits, observed links and publishes, latencies and the committed
+    /// AgentId, keeping the Context.
+    /// Gives tests that reuse a model a clean slate between phases.
+    pub fn reset(&mut self) {
+        self.commit_cache.clear();
+        self.agent_id = None;
+        self.link_adds.clear();
+        self.published.clear();
+        self.published_at.clear();
+        self.latencies.clear();
+        self.unmodeled_actions.clear();
+    }
+
+    fn observe_unmodeled(&mut self, action: &Action) {
+        if !self.debug {
+            return;
+        }
+        let variant = action.name();
+        let message = format!("consistency: no signal modeled for action {}", variant);
+        match self.debug_logger {
+            Some(ref logger) => logger
+                .lock()
+                .expect("owners of the debug logger Mutex shouldn't panic")
+                .log(message),
+            None => log_trace!(self.context, "{}", message),
+        }
+        *self
+            .unmodeled_actions
+            .entry(variant.to_string())
+            .or_insert(0) += 1;
+    }
+
+    /// Takes the signal cached for the committed entry at `address` out of the cache
+    fn observe_publish(&mut self, address: &Address) -> PublishOutcome {
+        match self.commit_cache.remove(address) {
+            Some(signal) => {
+                self.published.insert(address.clone(), ());
+                PublishOutcome::Committed(signal)
+            }
+            None if self.published.contains_key(address) => PublishOutcome::AlreadyPublished,
+            None => PublishOutcome::NeverCommitted,
+        }
+    }
+
+    fn observe_link_add(&mut self, link_data: &LinkData) {
+        self.link_adds
+            .insert(Entry::LinkAdd(link_data.clone()).address(), ());
+    }
+
+    /// Matches the additions a LinkRemove entry removes against the ones observed
+    fn link_removal(&self, entry: &Entry) -> LinkRemoval {
+        let negated: Vec<Address> = match entry {
+            Entry::LinkRemove((_, removed_adds)) => removed_adds
+                .iter()
+                .filter(|address| self.link_adds.contains_key(address))
+                .cloned()
+                .collect(),
+            _ => Vec::new(),
+        };
+        if negated.is_empty() {
+            LinkRemoval::Orphan
+        } else {
+            LinkRemoval::Negates(negated)
+        }
+    }
+
+    /// Processes a whole log of actions at once, returning the signals they produce in order.
+    /// The actions share the model's state, so a Commit and its Publish within the same batch
+    /// pair up into a single signal just like when they get processed one by one.
+    pub fn process_actions(&mut self, actions: &[Action]) -> Vec<ConsistencySignalE> {
+        actions
+            .iter()
+            .filter_map(|action| self.process_action(action))
+            .collect()
+    }
+
+    /// Returns the signal the action leads to, if any, stamped with the current time
     pub fn process_action(&mut self, action: &Action) -> Option<ConsistencySignalE> {
+        self.process_action_at(action, now())
+    }
+
+    /// Same as `process_action`, with the time since the UNIX epoch the action was observed at
+    fn process_action_at(
+        &mut self,
+        action: &Action,
+        observed_at: Duration,
+    ) -> Option<ConsistencySignalE> {
+        let signal = self.signal_for(action)?;
+        self.observe_latency(&signal.event, observed_at);
+        Some(ConsistencySignal {
+            observed_at: Some(Iso8601::new(
+                observed_at.as_secs() as i64,
+                observed_at.subsec_nanos(),
+            )),
+            ..signal
+        })
+    }
+
+    /// Correlates the Publish and Hold events of an entry by its address
+    fn observe_latency(&mut self, event: &ConsistencyEvent, observed_at: Duration) {
+        match event {
+            ConsistencyEvent::Publish(address) => {
+                self.published_at.insert(address.clone(), observed_at);
+            }
+            ConsistencyEvent::Hold(address) => {
+                if let Some(published_at) = self.published_at.remove(address) {
+                    if self.latencies.len() == LATENCY_WINDOW {
+                        self.latencies.pop_front();
+                    }
+                    // the clock may have been set back in between
+                    let latency = observed_at
+                        .checked_sub(published_at)
+                        .unwrap_or_else(|| Duration::from_secs(0));
+                    self.latencies.push_back(latency);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn signal_for(&mut self, action: &Action) -> Option<ConsistencySignalE> {
         use ConsistencyEvent::*;
         use ConsistencyGroup::*;
         match action {
             Action::Commit((Entry::AgentId(agent_id), _, _)) => {
-                self.agent_id = Some(agent_id.clone());
-                None
+                // The first AgentId commit is part of chain initialization and only gets recorded.
+                // Any later one that differs is an identity change consumers need to hear about.
+                let previous = self.agent_id.replace(agent_id.clone());
+                match previous {
+                    Some(ref old) if old != agent_id => Some(ConsistencySignal::new_terminal(
+                        UpdateAgentId(old.address(), agent_id.address()),
+                    )),
+                    _ => None,
+                }
             }
 
             Action::Commit((entry, crud_link, _)) => {
@@ -125,13 +544,18 @@ impl ConsistencyModel {
                 // If entry is publishable, construct the ConsistencySignal that should be emitted
                 // when the entry is finally published, and save it for later
                 if do_cache {
+                    if let Entry::LinkAdd(link_data) = entry {
+                        self.observe_link_add(link_data);
+                    }
                     let address = entry.address();
                     let hold = Hold(address.clone());
                     let meta = crud_link.clone().and_then(|crud| match entry {
                         Entry::App(_, _) => Some(UpdateEntry(crud, address.clone())),
                         Entry::Deletion(_) => Some(RemoveEntry(crud, address.clone())),
                         Entry::LinkAdd(link_data) => Some(AddLink(link_data.clone())),
-                        Entry::LinkRemove(_) => Some(RemoveLink(entry.clone())),
+                        Entry::LinkRemove(_) => {
+                            Some(RemoveLink(entry.clone(), self.link_removal(entry)))
+                        }
                         // Question: Why does Entry::LinkAdd take LinkData instead of Link?
                         // as of now, link data contains more information than just the link
                         _ => None,
@@ -147,19 +571,23 @@ impl ConsistencyModel {
                 }
                 None
             }
-            Action::Publish(address) => {
+            Action::Publish(address) => match self.observe_publish(address) {
                 // Emit the signal that was created when observing the corresponding Commit
-                let maybe_signal = self.commit_cache.remove(address);
-                maybe_signal.or_else(|| {
+                PublishOutcome::Committed(signal) => Some(signal),
+                // publishing twice is harmless, the signal went out the first time
+                PublishOutcome::AlreadyPublished => None,
+                PublishOutcome::NeverCommitted => {
                     log_warn!(
                         self.context,
                         "consistency: Publishing address that was not previously committed"
                     );
                     None
-                })
-            }
+                }
+            },
             Action::Hold(EntryWithHeader { entry, header: _ }) => {
-                Some(ConsistencySignal::new_terminal(Hold(entry.address())))
+                let address = entry.address();
+                self.published.remove(&address);
+                Some(ConsistencySignal::new_terminal(Hold(address)))
             }
             Action::UpdateEntry((old, new)) => Some(ConsistencySignal::new_terminal(
                 ConsistencyEvent::UpdateEntry(old.clone(), new.clone()),
@@ -167,11 +595,14 @@ impl ConsistencyModel {
             Action::RemoveEntry((old, new)) => Some(ConsistencySignal::new_terminal(
                 ConsistencyEvent::RemoveEntry(old.clone(), new.clone()),
             )),
-            Action::AddLink(link) => Some(ConsistencySignal::new_terminal(
-                ConsistencyEvent::AddLink(link.clone()),
-            )),
+            Action::AddLink(link) => {
+                self.observe_link_add(link);
+                Some(ConsistencySignal::new_terminal(ConsistencyEvent::AddLink(
+                    link.clone(),
+                )))
+            }
             Action::RemoveLink(entry) => Some(ConsistencySignal::new_terminal(
-                ConsistencyEvent::RemoveLink(entry.clone()),
+                ConsistencyEvent::RemoveLink(entry.clone(), self.link_removal(entry)),
             )),
 
             Action::AddPendingValidation(validation) => {
@@ -185,6 +616,9 @@ impl ConsistencyModel {
             Action::RemovePendingValidation((address, _)) => Some(ConsistencySignal::new_terminal(
                 RemovePendingValidation(address.clone()),
             )),
+            Action::FailPendingValidation((address, _, reason)) => Some(
+                ConsistencySignal::new_terminal(ValidationFailed(address.clone(), reason.clone())),
+            ),
 
             Action::SignalZomeFunctionCall(call) => Some(ConsistencySignal::new_pending(
                 SignalZomeFunctionCall(call.id()),
@@ -194,7 +628,489 @@ impl ConsistencyModel {
             Action::ReturnZomeFunctionResult(result) => Some(ConsistencySignal::new_terminal(
                 ReturnZomeFunctionResult(result.call().id()),
             )),
-            _ => None,
+            // Only a successful initialization leaves the instance ready for calls
+            Action::ReturnInitializationResult(Ok(_)) => {
+                Some(ConsistencySignal::new_terminal(InitializationComplete))
+            }
+            _ => {
+                self.observe_unmodeled(action);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::{
+        instance::tests::test_context, logger::test_logger,
+        nucleus::actions::initialize::Initialization,
+        scheduled_jobs::pending_validations::ValidatingWorkflow,
+    };
+    use holochain_core_types::{
+        agent::{test_agent_id, test_agent_id_with_name},
+        chain_header::test_chain_header,
+        entry::{test_entry, test_entry_b, test_entry_with_value},
+    };
+
+    #[test]
+    fn test_reset_clears_pending_commits() {
+        let mut model = ConsistencyModel::new(test_context("alice", None));
+        model.process_action(&Action::Commit((
+            Entry::AgentId(test_agent_id()),
+            None,
+            vec![],
+        )));
+        model.process_action(&Action::Commit((test_entry(), None, vec![])));
+        model.process_action(&Action::Commit((test_entry_b(), None, vec![])));
+        assert_eq!(model.pending_count(), 2);
+        assert!(model.agent_id.is_some());
+
+        model.reset();
+        assert_eq!(model.pending_count(), 0);
+        assert!(model.agent_id.is_none());
+
+        // a Publish left over from before the reset no longer produces a signal
+        assert!(model
+            .process_action(&Action::Publish(test_entry().address()))
+            .is_none());
+    }
+
+    #[test]
+    fn test_agent_id_update_is_recorded_and_signalled() {
+        let mut model = ConsistencyModel::new(test_context("alice", None));
+        let first = test_agent_id();
+        let second = test_agent_id_with_name("alice-migrated");
+
+        let signal = model.process_action(&Action::Commit((
+            Entry::AgentId(first.clone()),
+            None,
+            vec![],
+        )));
+        assert!(signal.is_none());
+        assert_eq!(model.agent_id, Some(first.clone()));
+
+        // committing the same id again is not a change
+        let signal = model.process_action(&Action::Commit((
+            Entry::AgentId(first.clone()),
+            None,
+            vec![],
+        )));
+        assert!(signal.is_none());
+
+        let signal = model
+            .process_action(&Action::Commit((
+                Entry::AgentId(second.clone()),
+                None,
+                vec![],
+            )))
+            .expect("changing the agent id should emit a signal");
+        assert_eq!(model.agent_id, Some(second.clone()));
+        match signal.event {
+            ConsistencyEvent::UpdateAgentId(old, new) => {
+                assert_eq!(old, first.address());
+                assert_eq!(new, second.address());
+            }
+            other => panic!("unexpected event: {:?}", other),
         }
+        assert_eq!(model.pending_count(), 0);
+    }
+
+    #[test]
+    fn test_initialization_complete_is_signalled() {
+        let mut model = ConsistencyModel::new(test_context("alice", None));
+
+        let signal = model
+            .process_action(&Action::ReturnInitializationResult(Ok(
+                Initialization::new(),
+            )))
+            .expect("successful initialization should emit a signal");
+        match signal.event {
+            ConsistencyEvent::InitializationComplete => (),
+            other => panic!("unexpected event: {:?}", other),
+        }
+        assert!(signal.pending.is_empty());
+
+        // a failed initialization doesn't make the instance ready
+        assert!(model
+            .process_action(&Action::ReturnInitializationResult(Err("failed".into())))
+            .is_none());
+    }
+
+    #[test]
+    fn test_validation_failure_is_signalled() {
+        let mut model = ConsistencyModel::new(test_context("alice", None));
+        let address = test_entry().address();
+
+        let signal = model
+            .process_action(&Action::FailPendingValidation((
+                address.clone(),
+                ValidatingWorkflow::HoldEntry,
+                "invalid entry, too long".to_string(),
+            )))
+            .expect("failing a validation should emit a signal");
+        assert_eq!(
+            signal.event,
+            ConsistencyEvent::ValidationFailed(address.clone(), "invalid entry, too long".into())
+        );
+        assert!(signal.pending.is_empty());
+        assert_eq!(
+            signal.event.to_string().parse::<ConsistencyEvent>(),
+            Ok(signal.event.clone())
+        );
+
+        // a successful validation still ends in the removal
+        let signal = model
+            .process_action(&Action::RemovePendingValidation((
+                address.clone(),
+                ValidatingWorkflow::HoldEntry,
+            )))
+            .expect("removing a pending validation should emit a signal");
+        assert_eq!(
+            signal.event,
+            ConsistencyEvent::RemovePendingValidation(address)
+        );
+    }
+
+    #[test]
+    fn test_double_publish_is_recognized() {
+        let mut model = ConsistencyModel::new(test_context("alice", None));
+        let entry = test_entry();
+        let address = entry.address();
+        assert!(model
+            .process_action(&Action::Commit((entry, None, vec![])))
+            .is_none());
+
+        match model.observe_publish(&address) {
+            PublishOutcome::Committed(signal) => {
+                assert_eq!(signal.event, ConsistencyEvent::Publish(address.clone()))
+            }
+            other => panic!("unexpected outcome: {:?}", other),
+        }
+        // the second publish is benign rather than a publish of something never committed
+        match model.observe_publish(&address) {
+            PublishOutcome::AlreadyPublished => (),
+            other => panic!("unexpected outcome: {:?}", other),
+        }
+        assert!(model.process_action(&Action::Publish(address)).is_none());
+    }
+
+    #[test]
+    fn test_held_entries_are_no_longer_tracked_as_published() {
+        let mut model = ConsistencyModel::new(test_context("alice", None));
+        let entry = test_entry();
+        let address = entry.address();
+        model.process_action(&Action::Commit((entry.clone(), None, vec![])));
+        model.process_action(&Action::Publish(address.clone()));
+        assert!(model.published.contains_key(&address));
+
+        model.process_action(&Action::Hold(EntryWithHeader {
+            entry,
+            header: test_chain_header(),
+        }));
+        assert!(model.published.is_empty());
+    }
+
+    #[test]
+    fn test_publish_without_commit_is_unexpected() {
+        let mut model = ConsistencyModel::new(test_context("alice", None));
+        let entry = test_entry();
+        let address = entry.address();
+
+        match model.observe_publish(&address) {
+            PublishOutcome::NeverCommitted => (),
+            other => panic!("unexpected outcome: {:?}", other),
+        }
+
+        // resetting forgets about earlier publishes too
+        model.process_action(&Action::Commit((entry, None, vec![])));
+        model.process_action(&Action::Publish(address.clone()));
+        model.reset();
+        match model.observe_publish(&address) {
+            PublishOutcome::NeverCommitted => (),
+            other => panic!("unexpected outcome: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_process_actions_pairs_commit_and_publish() {
+        let mut model = ConsistencyModel::new(test_context("alice", None));
+        let entry = test_entry();
+        let address = entry.address();
+
+        let signals = model.process_actions(&[
+            Action::Commit((entry.clone(), None, vec![])),
+            Action::Publish(address.clone()),
+            Action::Hold(EntryWithHeader {
+                entry,
+                header: test_chain_header(),
+            }),
+        ]);
+
+        // the commit only gets cached, its publish emits the pending signal the hold resolves
+        assert_eq!(signals.len(), 2);
+        assert_eq!(signals[0].event, ConsistencyEvent::Publish(address.clone()));
+        let pending: Vec<_> = signals[0].pending.iter().map(|p| p.event.clone()).collect();
+        assert_eq!(pending, vec![ConsistencyEvent::Hold(address.clone())]);
+        assert_eq!(signals[1].event, ConsistencyEvent::Hold(address));
+        assert!(signals[1].pending.is_empty());
+        assert_eq!(model.pending_count(), 0);
+
+        assert!(model.process_actions(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_signals_are_timestamped() {
+        let mut model = ConsistencyModel::new(test_context("alice", None));
+        let entry = test_entry();
+
+        model.process_action(&Action::Commit((entry.clone(), None, vec![])));
+        let publish = model
+            .process_action(&Action::Publish(entry.address()))
+            .expect("publishing a commit should emit a signal");
+        let hold = model
+            .process_action(&Action::Hold(EntryWithHeader {
+                entry,
+                header: test_chain_header(),
+            }))
+            .expect("holding should emit a signal");
+
+        let published_at = publish
+            .observed_at
+            .clone()
+            .expect("publish has no timestamp");
+        let held_at = hold.observed_at.clone().expect("hold has no timestamp");
+        assert!(held_at >= published_at);
+
+        // the timestamp survives the conversion consumers receive the signal in
+        let json = serde_json::to_value(ConsistencySignal::<String>::from(hold)).unwrap();
+        assert_eq!(json["observed_at"], serde_json::json!(held_at.to_string()));
+
+        // signals only get stamped when they get emitted
+        assert!(
+            ConsistencySignal::new_terminal(ConsistencyEvent::InitializationComplete)
+                .observed_at
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_latency_stats() {
+        let mut model = ConsistencyModel::new(test_context("alice", None));
+        assert_eq!(model.latency_stats(), LatencyStats::default());
+
+        let held = |entry: &Entry| {
+            Action::Hold(EntryWithHeader {
+                entry: entry.clone(),
+                header: test_chain_header(),
+            })
+        };
+        let at = |millis: u64| Duration::from_millis(millis);
+        let entries = [test_entry(), test_entry_b(), test_entry_with_value("c")];
+        for entry in entries.iter() {
+            model.process_action_at(&Action::Commit((entry.clone(), None, vec![])), at(0));
+        }
+        // publish to hold: 100ms, 300ms and 200ms
+        model.process_action_at(&Action::Publish(entries[0].address()), at(1000));
+        model.process_action_at(&Action::Publish(entries[1].address()), at(1000));
+        model.process_action_at(&held(&entries[0]), at(1100));
+        model.process_action_at(&Action::Publish(entries[2].address()), at(1200));
+        model.process_action_at(&held(&entries[2]), at(1400));
+        model.process_action_at(&held(&entries[1]), at(1300));
+
+        // holding again or holding what wasn't published here doesn't count
+        model.process_action_at(&held(&entries[0]), at(5000));
+        model.process_action_at(&held(&test_entry_with_value("d")), at(5000));
+
+        assert_eq!(
+            model.latency_stats(),
+            LatencyStats {
+                count: 3,
+                average: at(200),
+                p95: at(300),
+            }
+        );
+
+        model.reset();
+        assert_eq!(model.latency_stats(), LatencyStats::default());
+    }
+
+    #[test]
+    fn test_latency_of_entries_never_held_is_forgotten() {
+        let mut model = ConsistencyModel::new(test_context("alice", None));
+        let entries: Vec<Entry> = (0..=MAX_AWAITED_HOLDS)
+            .map(|i| test_entry_with_value(&i.to_string()))
+            .collect();
+        for entry in entries.iter() {
+            model.process_action(&Action::Commit((entry.clone(), None, vec![])));
+            model.process_action(&Action::Publish(entry.address()));
+        }
+        assert_eq!(model.published_at.len(), MAX_AWAITED_HOLDS);
+        assert_eq!(model.published.len(), MAX_AWAITED_HOLDS);
+
+        // the first publish made room for the last one
+        for entry in entries.iter().take(2) {
+            model.process_action(&Action::Hold(EntryWithHeader {
+                entry: entry.clone(),
+                header: test_chain_header(),
+            }));
+        }
+        assert_eq!(model.latency_stats().count, 1);
+        assert_eq!(model.published_at.len(), MAX_AWAITED_HOLDS - 1);
+        assert_eq!(model.published.len(), MAX_AWAITED_HOLDS - 1);
+    }
+
+    #[test]
+    fn test_unmodeled_actions_are_traced_in_debug_mode() {
+        let mut model = ConsistencyModel::new(test_context("alice", None));
+        let logger = test_logger();
+        model.set_debug_logger(logger.clone());
+        assert!(model.process_action(&Action::Ping).is_none());
+        assert!(model.unmodeled_actions().is_empty());
+
+        model.set_debug(true);
+        assert!(model.process_action(&Action::Ping).is_none());
+        assert!(model.process_action(&Action::Ping).is_none());
+        model.process_action(&Action::RecordCapTokenUse(Address::from("token")));
+        // modeled actions aren't reported, even if they don't lead to a signal
+        model.process_action(&Action::Publish(test_entry().address()));
+
+        let mut expected = BTreeMap::new();
+        expected.insert(String::from("Ping"), 2);
+        expected.insert(String::from("RecordCapTokenUse"), 1);
+        assert_eq!(model.unmodeled_actions(), expected);
+        assert_eq!(
+            logger.lock().unwrap().log,
+            vec![
+                String::from("consistency: no signal modeled for action Ping"),
+                String::from("consistency: no signal modeled for action Ping"),
+                String::from("consistency: no signal modeled for action RecordCapTokenUse"),
+            ]
+        );
+
+        model.set_debug(false);
+        model.process_action(&Action::Ping);
+        assert_eq!(model.unmodeled_actions(), expected);
+        assert_eq!(logger.lock().unwrap().log.len(), 3);
+    }
+
+    fn test_link_data(tag: &str) -> LinkData {
+        LinkData::new_add(
+            &test_entry().address(),
+            &test_entry_b().address(),
+            tag,
+            "link-type",
+            test_chain_header(),
+            test_agent_id(),
+        )
+    }
+
+    #[test]
+    fn test_remove_link_references_its_add() {
+        let mut model = ConsistencyModel::new(test_context("alice", None));
+        let link_data = test_link_data("tag");
+        let add_address = Entry::LinkAdd(link_data.clone()).address();
+        model.process_action(&Action::AddLink(link_data.clone()));
+
+        let remove = Entry::LinkRemove((link_data, vec![add_address.clone()]));
+        let signal = model
+            .process_action(&Action::RemoveLink(remove.clone()))
+            .expect("removing a link should emit a signal");
+        assert_eq!(
+            signal.event,
+            ConsistencyEvent::RemoveLink(remove, LinkRemoval::Negates(vec![add_address]))
+        );
+    }
+
+    #[test]
+    fn test_orphan_remove_link() {
+        let mut model = ConsistencyModel::new(test_context("alice", None));
+        model.process_action(&Action::AddLink(test_link_data("other tag")));
+
+        let link_data = test_link_data("tag");
+        let add_address = Entry::LinkAdd(link_data.clone()).address();
+        let remove = Entry::LinkRemove((link_data, vec![add_address]));
+        let signal = model
+            .process_action(&Action::RemoveLink(remove.clone()))
+            .expect("removing an unseen link should still emit a signal");
+        assert_eq!(
+            signal.event,
+            ConsistencyEvent::RemoveLink(remove, LinkRemoval::Orphan)
+        );
+    }
+
+    #[test]
+    fn test_only_the_latest_link_adds_are_matched() {
+        let mut model = ConsistencyModel::new(test_context("alice", None));
+        let links: Vec<LinkData> = (0..=MAX_OBSERVED_LINK_ADDS)
+            .map(|i| test_link_data(&i.to_string()))
+            .collect();
+        for link_data in links.iter() {
+            model.process_action(&Action::AddLink(link_data.clone()));
+        }
+        assert_eq!(model.link_adds.len(), MAX_OBSERVED_LINK_ADDS);
+
+        let removal = |link_data: &LinkData| {
+            let add_address = Entry::LinkAdd(link_data.clone()).address();
+            model.link_removal(&Entry::LinkRemove((link_data.clone(), vec![add_address])))
+        };
+        assert_eq!(removal(&links[0]), LinkRemoval::Orphan);
+        assert_eq!(
+            removal(&links[1]),
+            LinkRemoval::Negates(vec![Entry::LinkAdd(links[1].clone()).address()])
+        );
+    }
+
+    #[test]
+    fn test_consistency_event_display_roundtrip() {
+        let address = test_entry().address();
+        let other_address = test_entry_b().address();
+        let link_data = LinkData::new_add(
+            &address,
+            &other_address,
+            "tag (with parentheses)",
+            "link-type",
+            test_chain_header(),
+            test_agent_id(),
+        );
+        let id = snowflake::ProcessUniqueId::new();
+        let events = vec![
+            ConsistencyEvent::Publish(address.clone()),
+            ConsistencyEvent::AddPendingValidation(address.clone()),
+            ConsistencyEvent::SignalZomeFunctionCall(id),
+            ConsistencyEvent::Hold(address.clone()),
+            ConsistencyEvent::UpdateEntry(address.clone(), other_address.clone()),
+            ConsistencyEvent::RemoveEntry(address.clone(), other_address.clone()),
+            ConsistencyEvent::AddLink(link_data.clone()),
+            ConsistencyEvent::RemoveLink(
+                Entry::LinkRemove((link_data.clone(), vec![address.clone()])),
+                LinkRemoval::Negates(vec![address.clone(), other_address.clone()]),
+            ),
+            ConsistencyEvent::RemoveLink(
+                Entry::LinkRemove((link_data, vec![address.clone()])),
+                LinkRemoval::Orphan,
+            ),
+            ConsistencyEvent::RemovePendingValidation(address.clone()),
+            ConsistencyEvent::ReturnZomeFunctionResult(id),
+            ConsistencyEvent::UpdateAgentId(address.clone(), other_address.clone()),
+            ConsistencyEvent::InitializationComplete,
+        ];
+        for event in events {
+            let display = event.to_string();
+            assert_eq!(display.parse::<ConsistencyEvent>(), Ok(event));
+        }
+
+        assert_eq!(
+            ConsistencyEvent::Hold(address.clone()).to_string(),
+            format!("Hold({})", address)
+        );
+        assert_eq!(
+            ConsistencyEvent::SignalZomeFunctionCall(id).to_string(),
+            format!("SignalZomeFunctionCall({})", id)
+        );
+        assert!("Hold".parse::<ConsistencyEvent>().is_err());
+        assert!("Unknown(QmFoo)".parse::<ConsistencyEvent>().is_err());
+        assert!("UpdateEntry(QmFoo)".parse::<ConsistencyEvent>().is_err());
     }
 }
@@ -1,8 +1,10 @@
 use crate::{
     action::{Action, ActionWrapper},
+    audit::{AuditEvent, AuditSink, NullAuditSink},
     conductor_api::ConductorApi,
     instance::Observer,
-    nucleus::actions::get_entry::get_entry_from_cas,
+    network::fifo_map::FifoMap,
+    nucleus::actions::{call_zome_function::CapabilityPolicy, get_entry::get_entry_from_cas},
     persister::Persister,
     signal::{Signal, SignalSender},
 };
@@ -16,7 +18,7 @@ use holochain_core_types::{
     dna::{wasm::DnaWasm, Dna},
     eav::Attribute,
     entry::{
-        cap_entries::{CapabilityType, ReservedCapabilityId},
+        cap_entries::{CapTokenGrant, CapabilityType, ReservedCapabilityId},
         entry_type::EntryType,
         Entry,
     },
@@ -32,9 +34,13 @@ use holochain_persistence_api::{
 };
 use jsonrpc_core::{self, IoHandler};
 use std::{
-    sync::{Arc, Mutex, RwLock, RwLockReadGuard},
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex, MutexGuard, RwLock, RwLockReadGuard,
+    },
     thread::sleep,
-    time::Duration,
+    time::{Duration, Instant},
 };
 #[cfg(test)]
 use test_utils::mock_signing::mock_conductor_api;
@@ -59,6 +65,38 @@ pub struct Context {
     pub(crate) signal_tx: Option<Sender<Signal>>,
     pub(crate) instance_is_alive: Arc<Mutex<bool>>,
     pub state_dump_logging: bool,
+    audit_sink: Arc<dyn AuditSink>,
+    capability_policies: HashMap<String, Arc<dyn CapabilityPolicy>>,
+    max_parameter_bytes: usize,
+    grant_cache: Arc<Mutex<FifoMap<Address, Option<CapTokenGrant>>>>,
+    grant_cache_generation: Arc<AtomicUsize>,
+    cap_token_use_lock: Arc<Mutex<()>>,
+}
+
+/// Zome calls with parameters bigger than this are rejected unless the context is configured
+/// otherwise with `Context::set_max_parameter_bytes`.
+pub const DEFAULT_MAX_PARAMETER_BYTES: usize = 16 * 1024 * 1024;
+
+/// How many capability grant lookups are cached, per instance
+pub const GRANT_CACHE_CAPACITY: usize = 1000;
+
+/// Flag that lets any thread abort a `Context::block_on_cancellable` it was handed to.
+/// Clones share the same flag, so a fresh token is needed for every operation.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
 }
 
 impl Context {
@@ -118,6 +156,12 @@ impl Context {
             )),
             instance_is_alive: Arc::new(Mutex::new(true)),
             state_dump_logging,
+            audit_sink: Arc::new(NullAuditSink),
+            capability_policies: HashMap::new(),
+            max_parameter_bytes: DEFAULT_MAX_PARAMETER_BYTES,
+            grant_cache: Arc::new(Mutex::new(FifoMap::new(GRANT_CACHE_CAPACITY))),
+            grant_cache_generation: Arc::new(AtomicUsize::new(0)),
+            cap_token_use_lock: Arc::new(Mutex::new(())),
         }
     }
 
@@ -148,6 +192,12 @@ impl Context {
             conductor_api: ConductorApi::new(Self::test_check_conductor_api(None, agent_id)),
             instance_is_alive: Arc::new(Mutex::new(true)),
             state_dump_logging,
+            audit_sink: Arc::new(NullAuditSink),
+            capability_policies: HashMap::new(),
+            max_parameter_bytes: DEFAULT_MAX_PARAMETER_BYTES,
+            grant_cache: Arc::new(Mutex::new(FifoMap::new(GRANT_CACHE_CAPACITY))),
+            grant_cache_generation: Arc::new(AtomicUsize::new(0)),
+            cap_token_use_lock: Arc::new(Mutex::new(())),
         })
     }
 
@@ -160,6 +210,73 @@ impl Context {
         self.state = Some(state);
     }
 
+    /// Makes this instance report its audit events to `sink` instead of dropping them.
+    pub fn set_audit_sink(&mut self, sink: Arc<dyn AuditSink>) {
+        self.audit_sink = sink;
+    }
+
+    /// Passes the event on to the audit sink
+    pub fn audit(&self, event: AuditEvent) {
+        self.audit_sink.record(event);
+    }
+
+    /// Registers the policy that grants naming `name` (see `CapTokenGrant::with_policy`)
+    /// get checked against, replacing any policy registered under that name before.
+    pub fn register_capability_policy(&mut self, name: &str, policy: Arc<dyn CapabilityPolicy>) {
+        self.capability_policies.insert(name.to_string(), policy);
+    }
+
+    pub fn capability_policy(&self, name: &str) -> Option<Arc<dyn CapabilityPolicy>> {
+        self.capability_policies.get(name).cloned()
+    }
+
+    /// Sets the maximum size in bytes of the parameters a zome call may be made with.
+    pub fn set_max_parameter_bytes(&mut self, max_parameter_bytes: usize) {
+        self.max_parameter_bytes = max_parameter_bytes;
+    }
+
+    pub fn max_parameter_bytes(&self) -> usize {
+        self.max_parameter_bytes
+    }
+
+    /// Returns the cached outcome of looking up the grant for `token` on the agent's chain,
+    /// if there is one. `Some(None)` means the chain was found to have no such grant.
+    pub fn cached_grant(&self, token: &Address) -> Option<Option<CapTokenGrant>> {
+        self.grant_cache.lock().unwrap().get(token).cloned()
+    }
+
+    /// Returns how often the grant cache has been invalidated. Has to be taken before
+    /// looking up a grant whose outcome is to be passed to `cache_grant`.
+    pub fn grant_cache_generation(&self) -> usize {
+        self.grant_cache_generation.load(Ordering::SeqCst)
+    }
+
+    /// Remembers the outcome of looking up the grant for `token`, unless the cache has been
+    /// invalidated since `generation` was taken, as the lookup may then predate a revocation.
+    /// Clones of this context share the cache.
+    pub fn cache_grant(&self, token: Address, grant: Option<CapTokenGrant>, generation: usize) {
+        let mut cache = self.grant_cache.lock().unwrap();
+        if self.grant_cache_generation() == generation {
+            cache.insert(token, grant);
+        }
+    }
+
+    /// Drops all cached grant lookups. Has to be called whenever a grant gets authored or
+    /// revoked, which `commit_entry` takes care of.
+    pub fn invalidate_grant_cache(&self) {
+        let mut cache = self.grant_cache.lock().unwrap();
+        self.grant_cache_generation.fetch_add(1, Ordering::SeqCst);
+        *cache = FifoMap::new(GRANT_CACHE_CAPACITY);
+    }
+
+    /// Has to be held from checking how often a capability token has been used, or called
+    /// within its rate limit, until the call is recorded, so that concurrent calls can't use
+    /// a grant more often than it allows.
+    /// Clones of this context share the lock.
+    pub(crate) fn lock_cap_token_uses(&self) -> MutexGuard<()> {
+        self.cap_token_use_lock.lock().unwrap()
+    }
+
     pub fn state(&self) -> Option<RwLockReadGuard<StateWrapper>> {
         self.state.as_ref().map(|s| s.read().unwrap())
     }
@@ -279,6 +396,72 @@ impl Context {
         }
     }
 
+    /// Like `block_on`, but gives up after `timeout` instead of waiting forever.
+    /// Returns `HolochainError::Timeout` if the future did not resolve in time, and an error
+    /// (instead of panicking) if the instance or its redux loop went away while waiting.
+    pub fn block_on_timeout<F: Future>(
+        &self,
+        future: F,
+        timeout: Duration,
+    ) -> Result<<F as Future>::Output, HolochainError> {
+        self.block_on_until(
+            future,
+            Some(Instant::now() + timeout),
+            None,
+            "block_on_timeout",
+        )
+    }
+
+    /// Like `block_on`, but returns `HolochainError::Cancelled` as soon as `token` gets
+    /// cancelled, e.g. from another thread holding a clone of it.
+    pub fn block_on_cancellable<F: Future>(
+        &self,
+        future: F,
+        token: &CancellationToken,
+    ) -> Result<<F as Future>::Output, HolochainError> {
+        self.block_on_until(future, None, Some(token), "block_on_cancellable")
+    }
+
+    fn block_on_until<F: Future>(
+        &self,
+        future: F,
+        deadline: Option<Instant>,
+        token: Option<&CancellationToken>,
+        caller: &str,
+    ) -> Result<<F as Future>::Output, HolochainError> {
+        let tick_rx = self.create_observer();
+        pin_utils::pin_mut!(future);
+
+        let mut cx = std::task::Context::from_waker(noop_waker_ref());
+
+        loop {
+            if token.map(CancellationToken::is_cancelled).unwrap_or(false) {
+                return Err(HolochainError::Cancelled);
+            }
+            if let Poll::Ready(result) = future.as_mut().poll(&mut cx) {
+                return Ok(result);
+            }
+            let mut wait = Duration::from_millis(10);
+            if let Some(deadline) = deadline {
+                let now = Instant::now();
+                if now >= deadline {
+                    return Err(HolochainError::Timeout);
+                }
+                wait = std::cmp::min(wait, deadline - now);
+            }
+            let _ = tick_rx.recv_timeout(wait);
+            if !self.instance_still_alive() {
+                return Err(HolochainError::LifecycleError(format!(
+                    "Context::{}() waiting for future but instance is not alive anymore",
+                    caller
+                )));
+            }
+            if let Some(err) = self.action_channel_error(&format!("Context::{}", caller)) {
+                return Err(err);
+            }
+        }
+    }
+
     /// returns the public capability token (if any)
     pub fn get_public_token(&self) -> Result<Address, HolochainError> {
         let state = self.state().ok_or("State uninitialized!")?;
@@ -331,6 +514,30 @@ pub async fn get_dna_and_agent(context: &Arc<Context>) -> HcResult<(Address, Str
     Ok((dna.address(), agent_id))
 }
 
+/// Deterministic executor for testing futures in isolation, without an instance and its
+/// redux loop. Polls `future`, and each time it is pending calls `step` with the number of
+/// the step before polling again. `step` stands in for the redux loop, e.g. by reducing an
+/// action into a mock state.
+/// Panics if the future is still pending after `max_steps` steps.
+#[cfg(test)]
+pub fn block_on_stepwise<F, S>(future: F, max_steps: usize, mut step: S) -> <F as Future>::Output
+where
+    F: Future,
+    S: FnMut(usize),
+{
+    pin_utils::pin_mut!(future);
+    let mut cx = std::task::Context::from_waker(noop_waker_ref());
+    for step_number in 0..=max_steps {
+        if let Poll::Ready(result) = future.as_mut().poll(&mut cx) {
+            return result;
+        }
+        if step_number < max_steps {
+            step(step_number);
+        }
+    }
+    panic!("Future still pending after {} steps", max_steps)
+}
+
 /// Create an in-memory network config with the provided name,
 /// otherwise create a unique name and thus network using snowflake.
 /// This is the base function that many other `text_context*` functions use, and hence they also
@@ -348,7 +555,7 @@ pub fn test_memory_network_config(network_name: Option<&str>) -> P2pConfig {
 pub mod tests {
     use self::tempfile::tempdir;
     use super::*;
-    use crate::persister::SimplePersister;
+    use crate::{instance::tests::test_instance_and_context, persister::SimplePersister};
     use holochain_core_types::agent::AgentId;
     use holochain_persistence_file::{cas::file::FilesystemStorage, eav::file::EavFileStorage};
     use std::sync::{Arc, Mutex, RwLock};
@@ -397,6 +604,50 @@ pub mod tests {
         guard.flush();
     }
 
+    #[test]
+    fn test_block_on_timeout() {
+        let dna = test_utils::create_test_dna_with_wat("test_zome", None);
+        let (_instance, context) = test_instance_and_context(dna, None).unwrap();
+
+        let start = Instant::now();
+        let result =
+            context.block_on_timeout(futures::future::pending::<()>(), Duration::from_millis(100));
+        assert_eq!(result, Err(HolochainError::Timeout));
+        assert!(start.elapsed() >= Duration::from_millis(100));
+
+        let result =
+            context.block_on_timeout(futures::future::ready(42), Duration::from_millis(100));
+        assert_eq!(result, Ok(42));
+    }
+
+    #[test]
+    fn test_block_on_cancellable() {
+        let dna = test_utils::create_test_dna_with_wat("test_zome", None);
+        let (_instance, context) = test_instance_and_context(dna, None).unwrap();
+
+        let token = CancellationToken::new();
+        let canceller_token = token.clone();
+        let canceller = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            canceller_token.cancel();
+        });
+
+        let result = context.block_on_cancellable(futures::future::pending::<()>(), &token);
+        assert_eq!(result, Err(HolochainError::Cancelled));
+        canceller.join().unwrap();
+        assert!(token.is_cancelled());
+
+        // the cancellation only concerns the operation the token was handed to
+        assert_eq!(
+            context.block_on_cancellable(futures::future::ready(42), &CancellationToken::new()),
+            Ok(42)
+        );
+        assert_eq!(
+            context.block_on_timeout(futures::future::ready(42), Duration::from_secs(1)),
+            Ok(42)
+        );
+    }
+
     #[test]
     #[should_panic]
     #[cfg(not(windows))] // RwLock does not panic on windows since mutexes are recursive
@@ -1,6 +1,6 @@
 use crate::{
     action::{Action, ActionWrapper},
-    consistency::ConsistencyModel,
+    consistency::{ConsistencyModel, LatencyStats},
     context::Context,
     network,
     persister::Persister,
@@ -25,6 +25,7 @@ use holochain_core_types::{
 use holochain_persistence_api::cas::content::Address;
 use snowflake::ProcessUniqueId;
 use std::{
+    collections::BTreeMap,
     sync::{Arc, Mutex, RwLock, RwLockReadGuard},
     thread,
     time::Duration,
@@ -42,7 +43,8 @@ pub struct Instance {
     observer_channel: Option<Sender<Observer>>,
     scheduler_handle: Option<Arc<ScheduleHandle>>,
     persister: Option<Arc<Mutex<dyn Persister>>>,
-    consistency_model: ConsistencyModel,
+    /// Shared with the clone of the instance that runs the action loop
+    consistency_model: Arc<Mutex<ConsistencyModel>>,
     kill_switch: Option<Sender<()>>,
 }
 
@@ -279,6 +281,8 @@ impl Instance {
             });
 
             self.consistency_model
+                .lock()
+                .expect("owners of the consistency model Mutex shouldn't panic")
                 .process_action(action_wrapper.action())
                 .map(|signal| {
                     tx.send(Signal::Consistency(signal.into()))
@@ -301,7 +305,7 @@ impl Instance {
             observer_channel: None,
             scheduler_handle: None,
             persister: None,
-            consistency_model: ConsistencyModel::new(context.clone()),
+            consistency_model: Arc::new(Mutex::new(ConsistencyModel::new(context.clone()))),
             kill_switch: None,
         }
     }
@@ -313,11 +317,37 @@ impl Instance {
             observer_channel: None,
             scheduler_handle: None,
             persister: None,
-            consistency_model: ConsistencyModel::new(context.clone()),
+            consistency_model: Arc::new(Mutex::new(ConsistencyModel::new(context.clone()))),
             kill_switch: None,
         }
     }
 
+    /// Turns the debug mode of the instance's consistency model on or off,
+    /// see [ConsistencyModel::set_debug]
+    pub fn set_consistency_debug(&self, debug: bool) {
+        self.consistency_model
+            .lock()
+            .expect("owners of the consistency model Mutex shouldn't panic")
+            .set_debug(debug);
+    }
+
+    /// Actions the consistency model processed without modeling them while in debug mode,
+    /// see [ConsistencyModel::unmodeled_actions]
+    pub fn consistency_unmodeled_actions(&self) -> BTreeMap<String, usize> {
+        self.consistency_model
+            .lock()
+            .expect("owners of the consistency model Mutex shouldn't panic")
+            .unmodeled_actions()
+    }
+
+    /// Publish to Hold latencies of this instance's entries, see [ConsistencyModel::latency_stats]
+    pub fn consistency_latency_stats(&self) -> LatencyStats {
+        self.consistency_model
+            .lock()
+            .expect("owners of the consistency model Mutex shouldn't panic")
+            .latency_stats()
+    }
+
     pub fn state(&self) -> RwLockReadGuard<StateWrapper> {
         self.state
             .read()
@@ -396,6 +426,7 @@ pub mod tests {
         },
         context::{test_memory_network_config, Context},
         logger::{test_logger, TestLogger},
+        network::entry_with_header::EntryWithHeader,
     };
     use holochain_core_types::{
         agent::AgentId,
@@ -864,4 +895,51 @@ pub mod tests {
                 _ => false,
             });
     }
+
+    #[test]
+    fn test_latency_stats_of_the_action_loop_are_visible() {
+        let (signal_tx, _signal_rx) = unbounded();
+        let mut context = (*test_context("alice", None)).clone();
+        context.signal_tx = Some(signal_tx);
+        let context = Arc::new(context);
+        let instance = Instance::new(context.clone());
+        assert_eq!(instance.consistency_latency_stats().count, 0);
+
+        // the action loop emits the signals from its own clone of the instance
+        let mut action_loop_instance = instance.clone();
+        let entry = test_entry();
+        let actions = vec![
+            Action::Commit((entry.clone(), None, vec![])),
+            Action::Publish(entry.address()),
+            Action::Hold(EntryWithHeader {
+                entry,
+                header: test_chain_header(),
+            }),
+        ];
+        for action in actions {
+            action_loop_instance.emit_signals(&context, &ActionWrapper::new(action));
+        }
+
+        assert_eq!(instance.consistency_latency_stats().count, 1);
+    }
+
+    #[test]
+    fn test_consistency_debug_switch() {
+        let (signal_tx, _signal_rx) = unbounded();
+        let mut context = (*test_context("alice", None)).clone();
+        context.signal_tx = Some(signal_tx);
+        let context = Arc::new(context);
+        let instance = Instance::new(context.clone());
+        let mut action_loop_instance = instance.clone();
+        let action = ActionWrapper::new(Action::RecordCapTokenUse(Address::from("token")));
+
+        action_loop_instance.emit_signals(&context, &action);
+        assert!(instance.consistency_unmodeled_actions().is_empty());
+
+        instance.set_consistency_debug(true);
+        action_loop_instance.emit_signals(&context, &action);
+        let mut expected = BTreeMap::new();
+        expected.insert(String::from("RecordCapTokenUse"), 1);
+        assert_eq!(instance.consistency_unmodeled_actions(), expected);
+    }
 }
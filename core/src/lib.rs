@@ -28,6 +28,7 @@ extern crate logging;
 pub mod macros;
 pub mod action;
 pub mod agent;
+pub mod audit;
 pub mod consistency;
 pub mod context;
 pub mod dht;
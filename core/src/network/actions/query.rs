@@ -91,6 +91,27 @@ pub struct QueryFuture {
     key: QueryKey,
 }
 
+impl Drop for QueryFuture {
+    /// Aborts the query if it is still pending, e.g. because the zome call that waited for it
+    /// got cancelled, so that a response arriving later doesn't linger in the state.
+    fn drop(&mut self) {
+        let pending = self
+            .context
+            .state()
+            .map(
+                |state| match state.network().get_query_results.get(&self.key) {
+                    Some(None) => true,
+                    _ => false,
+                },
+            )
+            .unwrap_or(false);
+        if pending && self.context.is_action_channel_open() {
+            let action_wrapper = ActionWrapper::new(Action::AbortQuery(self.key.clone()));
+            dispatch_action(self.context.action_channel(), action_wrapper);
+        }
+    }
+}
+
 impl Future for QueryFuture {
     type Output = HcResult<NetworkQueryResult>;
 
@@ -0,0 +1,139 @@
+use std::{
+    borrow::Borrow,
+    collections::{HashMap, VecDeque},
+    hash::Hash,
+};
+
+/// A map that holds at most `capacity` entries.
+/// Inserting beyond that evicts the oldest entries first, so reads for them return `None`.
+/// Overwriting the value of a key doesn't make its entry any younger.
+#[derive(Clone, Debug)]
+pub struct FifoMap<K: Eq + Hash + Clone, V> {
+    entries: HashMap<K, V>,
+    insertion_order: VecDeque<K>,
+    capacity: usize,
+}
+
+impl<K: Eq + Hash + Clone, V> FifoMap<K, V> {
+    pub fn new(capacity: usize) -> Self {
+        FifoMap {
+            entries: HashMap::new(),
+            insertion_order: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Changes the capacity, evicting the oldest entries right away if it shrinks below the
+    /// number of entries held
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.evict();
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let previous = self.entries.insert(key.clone(), value);
+        if previous.is_none() {
+            self.insertion_order.push_back(key);
+            self.evict();
+        }
+        previous
+    }
+
+    pub fn get<Q: ?Sized>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash,
+    {
+        self.entries.get(key)
+    }
+
+    pub fn contains_key<Q: ?Sized>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash,
+    {
+        self.entries.contains_key(key)
+    }
+
+    pub fn remove<Q: ?Sized>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash,
+    {
+        let removed = self.entries.remove(key);
+        if removed.is_some() {
+            self.insertion_order
+                .retain(|inserted| <K as Borrow<Q>>::borrow(inserted) != key);
+        }
+        removed
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.insertion_order.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn evict(&mut self) {
+        while self.insertion_order.len() > self.capacity {
+            if let Some(oldest) = self.insertion_order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evicts_oldest_entries() {
+        let mut map = FifoMap::new(3);
+        for i in 0..5 {
+            map.insert(i.to_string(), i);
+        }
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.get("0"), None);
+        assert_eq!(map.get("1"), None);
+        assert_eq!(map.get("2"), Some(&2));
+        assert_eq!(map.get("4"), Some(&4));
+
+        // overwriting keeps the entry's place in line
+        assert_eq!(map.insert("2".to_string(), 20), Some(2));
+        map.insert("5".to_string(), 5);
+        assert_eq!(map.get("2"), None);
+        assert!(map.contains_key("3"));
+
+        map.set_capacity(1);
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.get("5"), Some(&5));
+    }
+
+    #[test]
+    fn test_removed_entries_free_their_place() {
+        let mut map = FifoMap::new(2);
+        map.insert("a".to_string(), 1);
+        map.insert("b".to_string(), 2);
+        assert_eq!(map.remove("a"), Some(1));
+        assert_eq!(map.remove("a"), None);
+
+        map.insert("c".to_string(), 3);
+        assert_eq!(map.get("b"), Some(&2));
+        assert_eq!(map.get("c"), Some(&3));
+
+        map.clear();
+        assert!(map.is_empty());
+    }
+}
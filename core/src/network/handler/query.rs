@@ -5,6 +5,7 @@ use crate::{
     instance::dispatch_action,
     network::query::{
         GetLinkData, GetLinksNetworkQuery, GetLinksNetworkResult, NetworkQuery, NetworkQueryResult,
+        NetworkQueryResultChunk,
     },
     nucleus,
     workflows::get_entry_result::get_entry_result_workflow,
@@ -21,7 +22,10 @@ use holochain_wasm_utils::api_serialization::get_entry::{
     GetEntryArgs, GetEntryOptions, GetEntryResultType,
 };
 use lib3h_protocol::data_types::{QueryEntryData, QueryEntryResultData};
-use std::{convert::TryInto, sync::Arc};
+use std::{
+    convert::{TryFrom, TryInto},
+    sync::Arc,
+};
 
 fn get_links(
     context: &Arc<Context>,
@@ -208,6 +212,10 @@ pub fn handle_query_entry_data(query_data: QueryEntryData, context: Arc<Context>
 pub fn handle_query_entry_result(query_result_data: QueryEntryResultData, context: Arc<Context>) {
     let query_result_json =
         JsonString::from_json(&String::from_utf8(query_result_data.query_result).unwrap());
+    if let Ok(chunk) = NetworkQueryResultChunk::try_from(query_result_json.clone()) {
+        handle_query_result_chunk(chunk, &query_result_data.request_id, context);
+        return;
+    }
     let action_wrapper = match query_result_json.clone().try_into() {
         Ok(NetworkQueryResult::Entry(maybe_entry)) => {
             let payload = NetworkQueryResult::Entry(maybe_entry);
@@ -242,3 +250,30 @@ pub fn handle_query_entry_result(query_result_data: QueryEntryResultData, contex
     };
     dispatch_action(context.action_channel(), action_wrapper.clone());
 }
+
+/// Chunks only carry the id of the request they belong to, so the key of the query
+/// is looked up among the queries waiting for a result
+fn handle_query_result_chunk(
+    chunk: NetworkQueryResultChunk,
+    request_id: &str,
+    context: Arc<Context>,
+) {
+    let maybe_key = context
+        .state()
+        .and_then(|state| state.network().pending_query_key(request_id));
+    match maybe_key {
+        Some(key) => dispatch_action(
+            context.action_channel(),
+            ActionWrapper::new(Action::HandleQueryChunk((
+                key,
+                chunk.index,
+                chunk.count,
+                chunk.chunk,
+            ))),
+        ),
+        None => log_warn!(context,
+            "net: Got a query result chunk for request {} which isn't pending",
+            request_id
+        ),
+    }
+}
@@ -8,6 +8,7 @@ use crate::{
         respond_validation_package_request::respond_validation_package_request,
     },
 };
+use holochain_core_types::json::JsonStringExt;
 use holochain_persistence_api::cas::content::Address;
 use std::{sync::Arc, thread};
 
@@ -17,10 +18,10 @@ use snowflake::ProcessUniqueId;
 use std::convert::TryFrom;
 
 fn parse_direct_message(content: Vec<u8>) -> Result<DirectMessage, JsonError> {
-    DirectMessage::try_from(JsonString::from_json(
-        &String::from_utf8(content)
+    DirectMessage::try_from(
+        JsonString::from_bytes(content)
             .map_err(|error| JsonError::SerializationError(error.to_string()))?,
-    ))
+    )
 }
 
 /// We got a ProtocolWrapper::SendMessage, this means somebody initiates message roundtrip
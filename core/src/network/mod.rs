@@ -2,6 +2,7 @@ pub mod actions;
 pub mod direct_message;
 pub mod entry_aspect;
 pub mod entry_with_header;
+pub mod fifo_map;
 pub mod handler;
 pub mod query;
 pub mod reducers;
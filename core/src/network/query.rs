@@ -58,3 +58,12 @@ pub enum NetworkQueryResult {
     Entry(Option<EntryWithMetaAndHeader>),
     Links(GetLinksNetworkResult, String, String),
 }
+
+/// Piece number `index` of the `count` consecutive pieces the JSON of a `NetworkQueryResult`
+/// was split into to fit the transport's limits, see `NetworkState::add_query_chunk`
+#[derive(Debug, Serialize, Deserialize, PartialEq, DefaultJson, Clone)]
+pub struct NetworkQueryResultChunk {
+    pub index: usize,
+    pub count: usize,
+    pub chunk: String,
+}
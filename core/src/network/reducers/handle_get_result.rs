@@ -8,7 +8,120 @@ pub fn reduce_handle_get_result(
     let action = action_wrapper.action();
     let (payload, key) = unwrap_to!(action => crate::action::Action::HandleQuery);
 
+    // nobody is waiting for this response anymore
+    if network_state.is_query_aborted(key) {
+        return;
+    }
+
     network_state
         .get_query_results
         .insert(key.clone(), Some(Ok(payload.clone())));
 }
+
+pub fn reduce_handle_query_chunk(
+    network_state: &mut NetworkState,
+    _root_state: &State,
+    action_wrapper: &ActionWrapper,
+) {
+    let action = action_wrapper.action();
+    let (key, index, count, chunk) = unwrap_to!(action => crate::action::Action::HandleQueryChunk);
+
+    if let Err(error) = network_state.add_query_chunk(key, *index, *count, chunk.clone()) {
+        // a chunk that doesn't fit fails the query it belongs to, if it is still pending
+        if let Some(result @ None) = network_state.get_query_results.get_mut(key) {
+            *result = Some(Err(error));
+            network_state.query_chunks.remove(key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        action::{Action, GetEntryKey, QueryKey},
+        instance::tests::test_context,
+        network::query::NetworkQueryResult,
+        state::test_store,
+    };
+    use holochain_core_types::error::HolochainError;
+    use holochain_persistence_api::cas::content::Address;
+
+    fn test_query_key() -> QueryKey {
+        QueryKey::Entry(GetEntryKey {
+            address: Address::from("QmTestEntry"),
+            id: snowflake::ProcessUniqueId::new().to_string(),
+        })
+    }
+
+    fn chunk_action(key: &QueryKey, index: usize, count: usize, chunk: &str) -> ActionWrapper {
+        ActionWrapper::new(Action::HandleQueryChunk((
+            key.clone(),
+            index,
+            count,
+            chunk.to_string(),
+        )))
+    }
+
+    #[test]
+    fn test_reduce_handle_query_chunk() {
+        let root_state = test_store(test_context("alice", None));
+        let mut network_state = NetworkState::new();
+        let key = test_query_key();
+        network_state.get_query_results.insert(key.clone(), None);
+
+        let json = serde_json::to_string(&NetworkQueryResult::Entry(None)).unwrap();
+        let (first, second) = json.split_at(json.len() / 2);
+        reduce_handle_query_chunk(
+            &mut network_state,
+            &root_state,
+            &chunk_action(&key, 0, 2, first),
+        );
+        assert_eq!(network_state.get_query_results.get(&key), Some(&None));
+        reduce_handle_query_chunk(
+            &mut network_state,
+            &root_state,
+            &chunk_action(&key, 1, 2, second),
+        );
+        assert_eq!(
+            network_state.get_query_results.get(&key),
+            Some(&Some(Ok(NetworkQueryResult::Entry(None))))
+        );
+
+        // chunks of queries nobody started don't show up in the state
+        let unknown = test_query_key();
+        reduce_handle_query_chunk(
+            &mut network_state,
+            &root_state,
+            &chunk_action(&unknown, 0, 2, first),
+        );
+        assert_eq!(network_state.get_query_results.get(&unknown), None);
+        assert!(network_state.query_chunks.is_empty());
+    }
+
+    #[test]
+    fn test_reduce_handle_query_chunk_that_does_not_fit() {
+        let root_state = test_store(test_context("alice", None));
+        let mut network_state = NetworkState::new();
+        let key = test_query_key();
+        network_state.get_query_results.insert(key.clone(), None);
+
+        reduce_handle_query_chunk(
+            &mut network_state,
+            &root_state,
+            &chunk_action(&key, 0, 2, "{"),
+        );
+        reduce_handle_query_chunk(
+            &mut network_state,
+            &root_state,
+            &chunk_action(&key, 1, 3, "}"),
+        );
+        assert_eq!(
+            network_state.get_query_results.get(&key),
+            Some(&Some(Err(HolochainError::ErrorGeneric(String::from(
+                "Query result was split into 2 chunks, got a chunk out of 3"
+            )))))
+        );
+        assert!(network_state.query_chunks.is_empty());
+    }
+}
@@ -18,10 +18,10 @@ use crate::{
     network::{
         direct_message::DirectMessage,
         reducers::{
-            query::{reduce_query, reduce_query_timeout},
+            query::{reduce_abort_query, reduce_query, reduce_query_timeout},
             get_validation_package::reduce_get_validation_package,
             handle_custom_send_response::reduce_handle_custom_send_response,
-            handle_get_result::reduce_handle_get_result,
+            handle_get_result::{reduce_handle_get_result, reduce_handle_query_chunk},
             handle_get_validation_package::reduce_handle_get_validation_package,
             init::reduce_init,
             publish::reduce_publish,
@@ -52,9 +52,11 @@ fn resolve_reducer(action_wrapper: &ActionWrapper) -> Option<NetworkReduceFn> {
     match action_wrapper.action() {
         Action::Query(_) => Some(reduce_query),
         Action::QueryTimeout(_) => Some(reduce_query_timeout),
+        Action::AbortQuery(_) => Some(reduce_abort_query),
         Action::GetValidationPackage(_) => Some(reduce_get_validation_package),
         Action::HandleCustomSendResponse(_) => Some(reduce_handle_custom_send_response),
         Action::HandleQuery(_) => Some(reduce_handle_get_result),
+        Action::HandleQueryChunk(_) => Some(reduce_handle_query_chunk),
         Action::HandleGetValidationPackage(_) => Some(reduce_handle_get_validation_package),
         Action::InitNetwork(_) => Some(reduce_init),
         Action::Publish(_) => Some(reduce_publish),
@@ -74,18 +74,31 @@ pub fn reduce_query_timeout(
         network_state
             .get_query_results
             .insert(key.clone(), Some(Err(HolochainError::Timeout)));
+        network_state.query_chunks.remove(key);
     }
 }
 
-
+pub fn reduce_abort_query(
+    network_state: &mut NetworkState,
+    _root_state: &State,
+    action_wrapper: &ActionWrapper,
+) {
+    let action = action_wrapper.action();
+    let key = unwrap_to!(action => crate::action::Action::AbortQuery);
+    network_state.abort_query(key);
+}
 
 #[cfg(test)]
 mod tests {
 
+    use super::reduce_abort_query;
     use crate::{
         action::{Action, ActionWrapper, GetEntryKey, GetLinksKey, QueryKey, QueryPayload},
         instance::tests::test_context,
-        network::query::{GetLinksNetworkQuery, GetLinksQueryConfiguration},
+        network::{
+            query::{GetLinksNetworkQuery, GetLinksQueryConfiguration},
+            state::NetworkState,
+        },
         state::test_store,
     };
     use holochain_persistence_api::cas::content::AddressableContent;
@@ -310,6 +323,39 @@ mod tests {
         );
     }
 
+    #[test]
+    pub fn reduce_abort_query_test() {
+        let root_state = test_store(test_context("alice", None));
+        let mut network_state = NetworkState::new();
+        let key = QueryKey::Entry(GetEntryKey {
+            address: test_entry().address(),
+            id: snowflake::ProcessUniqueId::new().to_string(),
+        });
+        network_state.get_query_results.insert(key.clone(), None);
+
+        let action_wrapper = ActionWrapper::new(Action::AbortQuery(key.clone()));
+        reduce_abort_query(&mut network_state, &root_state, &action_wrapper);
+        assert_eq!(
+            network_state.get_query_results.get(&key),
+            Some(&Some(Err(HolochainError::Cancelled)))
+        );
+
+        // queries that already resolved keep their result
+        let resolved_key = QueryKey::Entry(GetEntryKey {
+            address: test_entry().address(),
+            id: snowflake::ProcessUniqueId::new().to_string(),
+        });
+        network_state
+            .get_query_results
+            .insert(resolved_key.clone(), Some(Err(HolochainError::Timeout)));
+        let action_wrapper = ActionWrapper::new(Action::AbortQuery(resolved_key.clone()));
+        reduce_abort_query(&mut network_state, &root_state, &action_wrapper);
+        assert_eq!(
+            network_state.get_query_results.get(&resolved_key),
+            Some(&Some(Err(HolochainError::Timeout)))
+        );
+    }
+
     #[test]
     // This test needs to be refactored.
     // It is non-deterministically failing with "sending on a closed channel" originating form
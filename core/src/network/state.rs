@@ -1,6 +1,9 @@
 use crate::{
     action::{ActionWrapper, QueryKey},
-    network::{actions::ActionResponse, direct_message::DirectMessage,query::NetworkQueryResult},
+    network::{
+        actions::ActionResponse, direct_message::DirectMessage, fifo_map::FifoMap,
+        query::NetworkQueryResult,
+    },
 };
 use boolinator::*;
 use holochain_core_types::{error::HolochainError, validation::ValidationPackage};
@@ -10,6 +13,7 @@ use snowflake;
 use std::{
     collections::HashMap,
     sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
 };
 
 type Actions = HashMap<ActionWrapper, ActionResponse>;
@@ -24,6 +28,68 @@ type GetValidationPackageResult = Option<Result<Option<ValidationPackage>, Holoc
 
 type GetResults = Option<Result<NetworkQueryResult, HolochainError>>;
 
+/// How long the chunks of a query result may take to arrive after the first one,
+/// see `timed_out_query_chunks`
+pub const QUERY_CHUNK_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How many replies to custom direct messages are kept by default before the oldest get dropped
+pub const DEFAULT_CUSTOM_DIRECT_MESSAGE_REPLY_CAPACITY: usize = 1000;
+
+/// Summary of whether the network is usable, as reported by `NetworkState::health`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NetworkHealth {
+    pub initialized: bool,
+    /// None as long as the network doesn't report its peers to the state
+    pub peer_count: Option<usize>,
+    /// Queries that still wait for a response from the network
+    pub pending_queries: usize,
+    /// Requests for validation packages that still wait for a response
+    pub pending_validation_packages: usize,
+    /// Direct messages that were sent but not answered yet
+    pub pending_direct_messages: usize,
+}
+
+/// What became of a query, as reported by `NetworkState::query_status`
+#[derive(Clone, Debug, PartialEq)]
+pub enum QueryStatus {
+    /// No query with that key was started
+    Unknown,
+    /// Still waiting for a response from the network
+    Pending,
+    Success(NetworkQueryResult),
+    Failed(HolochainError),
+    /// Not all chunks of the result arrived in time, see `timed_out_query_chunks`
+    TimedOut,
+    /// Aborted with `abort_query`
+    Cancelled,
+}
+
+/// The parts of a query result that was split up to fit the transport's limits.
+/// Chunks are consecutive pieces of the result's JSON, collected until all of them arrived.
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueryChunks {
+    chunks: Vec<Option<String>>,
+    first_received: SystemTime,
+}
+
+impl QueryChunks {
+    fn new(count: usize, first_received: SystemTime) -> Self {
+        QueryChunks {
+            chunks: vec![None; count],
+            first_received,
+        }
+    }
+
+    /// The assembled JSON once every chunk arrived
+    fn assemble(&self) -> Option<String> {
+        self.chunks
+            .iter()
+            .map(|chunk| chunk.as_ref().map(String::as_str))
+            .collect::<Option<Vec<&str>>>()
+            .map(|chunks| chunks.concat())
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct NetworkState {
     /// every action and the result of that action
@@ -45,7 +111,13 @@ pub struct NetworkState {
     /// Entries get removed when we receive an answer through Action::ResolveDirectConnection.
     pub direct_message_connections: HashMap<String, DirectMessage>,
 
-    pub custom_direct_message_replys: HashMap<String, Result<String, HolochainError>>,
+    /// Replies to custom direct messages, bounded so that replies nobody picks up don't
+    /// pile up forever. Use `set_capacity` on it to configure how many are kept.
+    pub custom_direct_message_replys: FifoMap<String, Result<String, HolochainError>>,
+
+    /// Query results that arrive in chunks and are still incomplete.
+    /// See `add_query_chunk`.
+    pub query_chunks: HashMap<QueryKey, QueryChunks>,
 
     id: snowflake::ProcessUniqueId,
 }
@@ -66,7 +138,10 @@ impl NetworkState {
             get_query_results: HashMap::new(),
             get_validation_package_results: HashMap::new(),
             direct_message_connections: HashMap::new(),
-            custom_direct_message_replys: HashMap::new(),
+            custom_direct_message_replys: FifoMap::new(
+                DEFAULT_CUSTOM_DIRECT_MESSAGE_REPLY_CAPACITY,
+            ),
+            query_chunks: HashMap::new(),
 
             id: snowflake::ProcessUniqueId::new(),
         }
@@ -84,4 +159,352 @@ impl NetworkState {
             "Network not initialized".to_string(),
         ))
     }
+
+    /// Aggregates the state into a summary of whether the network is usable
+    pub fn health(&self) -> NetworkHealth {
+        NetworkHealth {
+            initialized: self.initialized().is_ok(),
+            peer_count: None,
+            pending_queries: self
+                .get_query_results
+                .values()
+                .filter(|result| result.is_none())
+                .count(),
+            pending_validation_packages: self
+                .get_validation_package_results
+                .values()
+                .filter(|result| result.is_none())
+                .count(),
+            pending_direct_messages: self.direct_message_connections.len(),
+        }
+    }
+
+    /// Resolves a still pending query with `HolochainError::Cancelled` so that the future
+    /// waiting for it returns. Responses arriving for it afterwards get ignored.
+    /// Queries that already have a result are left as they are.
+    pub fn abort_query(&mut self, key: &QueryKey) {
+        if let Some(result @ None) = self.get_query_results.get_mut(key) {
+            *result = Some(Err(HolochainError::Cancelled));
+            self.query_chunks.remove(key);
+        }
+    }
+
+    /// Stores chunk number `index` of the `count` chunks a query's result was split into.
+    /// Once all of them arrived the result gets reassembled and stored in `get_query_results`
+    /// like a result that came in as a whole.
+    /// Fails for chunks that don't fit the ones received before and for chunks of queries
+    /// that aren't pending.
+    pub fn add_query_chunk(
+        &mut self,
+        key: &QueryKey,
+        index: usize,
+        count: usize,
+        chunk: String,
+    ) -> Result<(), HolochainError> {
+        if index >= count {
+            return Err(HolochainError::ErrorGeneric(format!(
+                "Query result chunk {} is out of range for {} chunks",
+                index, count
+            )));
+        }
+        // nobody is waiting for this result anymore
+        if self.is_query_aborted(key) {
+            self.query_chunks.remove(key);
+            return Ok(());
+        }
+        if self.get_query_results.get(key) != Some(&None) {
+            self.query_chunks.remove(key);
+            return Err(HolochainError::ErrorGeneric(format!(
+                "Got a result chunk for query {:?} which isn't pending",
+                key
+            )));
+        }
+
+        let assembled = {
+            let chunks = self
+                .query_chunks
+                .entry(key.clone())
+                .or_insert_with(|| QueryChunks::new(count, SystemTime::now()));
+            if chunks.chunks.len() != count {
+                return Err(HolochainError::ErrorGeneric(format!(
+                    "Query result was split into {} chunks, got a chunk out of {}",
+                    chunks.chunks.len(),
+                    count
+                )));
+            }
+            chunks.chunks[index] = Some(chunk);
+            chunks.assemble()
+        };
+
+        if let Some(json) = assembled {
+            self.query_chunks.remove(key);
+            let result = serde_json::from_str::<NetworkQueryResult>(&json).map_err(|e| {
+                HolochainError::ErrorGeneric(format!(
+                    "Could not reassemble chunked query result: {}",
+                    e
+                ))
+            });
+            self.get_query_results.insert(key.clone(), Some(result));
+        }
+        Ok(())
+    }
+
+    /// The queries whose chunked results are still missing chunks `timeout` after the first
+    /// one arrived. A scheduled job gives up on them with `Action::QueryTimeout`.
+    pub fn timed_out_query_chunks(&self, timeout: Duration, now: SystemTime) -> Vec<QueryKey> {
+        self.query_chunks
+            .iter()
+            .filter(|(_, chunks)| {
+                now.duration_since(chunks.first_received)
+                    .map(|waited| waited > timeout)
+                    .unwrap_or(false)
+            })
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    /// The key of the pending query with the given request id, if there is one
+    pub fn pending_query_key(&self, request_id: &str) -> Option<QueryKey> {
+        self.get_query_results
+            .iter()
+            .filter(|(_, result)| result.is_none())
+            .map(|(key, _)| key)
+            .find(|key| match key {
+                QueryKey::Entry(key) => key.id == request_id,
+                QueryKey::Links(key) => key.id == request_id,
+            })
+            .cloned()
+    }
+
+    /// Where the query with the given key stands, without having to pick apart
+    /// its entry in `get_query_results`
+    pub fn query_status(&self, key: &QueryKey) -> QueryStatus {
+        match self.get_query_results.get(key) {
+            None => QueryStatus::Unknown,
+            Some(None) => QueryStatus::Pending,
+            Some(Some(Ok(result))) => QueryStatus::Success(result.clone()),
+            Some(Some(Err(HolochainError::Timeout))) => QueryStatus::TimedOut,
+            Some(Some(Err(HolochainError::Cancelled))) => QueryStatus::Cancelled,
+            Some(Some(Err(error))) => QueryStatus::Failed(error.clone()),
+        }
+    }
+
+    /// Whether the given query was aborted with `abort_query`
+    pub fn is_query_aborted(&self, key: &QueryKey) -> bool {
+        match self.get_query_results.get(key) {
+            Some(Some(Err(HolochainError::Cancelled))) => true,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::{
+        action::{Action, GetEntryKey},
+        instance::tests::{test_context, test_instance_and_context},
+        network::{
+            query::NetworkQueryResult, reducers::handle_get_result::reduce_handle_get_result,
+        },
+        state::test_store,
+    };
+
+    fn test_query_key() -> QueryKey {
+        QueryKey::Entry(GetEntryKey {
+            address: Address::from("QmTestEntry"),
+            id: snowflake::ProcessUniqueId::new().to_string(),
+        })
+    }
+
+    #[test]
+    fn test_abort_query() {
+        let mut network_state = NetworkState::new();
+        let key = test_query_key();
+        network_state.get_query_results.insert(key.clone(), None);
+
+        network_state.abort_query(&key);
+        // this is what the waiting QueryFuture resolves with
+        assert_eq!(
+            network_state.get_query_results.get(&key),
+            Some(&Some(Err(HolochainError::Cancelled)))
+        );
+        assert!(network_state.is_query_aborted(&key));
+
+        // a genuine response arriving late doesn't replace the cancellation
+        let root_state = test_store(test_context("alice", None));
+        let late_response = ActionWrapper::new(Action::HandleQuery((
+            NetworkQueryResult::Entry(None),
+            key.clone(),
+        )));
+        reduce_handle_get_result(&mut network_state, &root_state, &late_response);
+        assert_eq!(
+            network_state.get_query_results.get(&key),
+            Some(&Some(Err(HolochainError::Cancelled)))
+        );
+    }
+
+    #[test]
+    fn test_query_status() {
+        let mut network_state = NetworkState::new();
+        let status_for = |network_state: &mut NetworkState, result: GetResults| {
+            let key = test_query_key();
+            network_state.get_query_results.insert(key.clone(), result);
+            network_state.query_status(&key)
+        };
+
+        assert_eq!(
+            network_state.query_status(&test_query_key()),
+            QueryStatus::Unknown
+        );
+        assert_eq!(status_for(&mut network_state, None), QueryStatus::Pending);
+        assert_eq!(
+            status_for(
+                &mut network_state,
+                Some(Ok(NetworkQueryResult::Entry(None)))
+            ),
+            QueryStatus::Success(NetworkQueryResult::Entry(None))
+        );
+        assert_eq!(
+            status_for(
+                &mut network_state,
+                Some(Err(HolochainError::ErrorGeneric("oops".to_string())))
+            ),
+            QueryStatus::Failed(HolochainError::ErrorGeneric("oops".to_string()))
+        );
+        assert_eq!(
+            status_for(&mut network_state, Some(Err(HolochainError::Timeout))),
+            QueryStatus::TimedOut
+        );
+
+        let key = test_query_key();
+        network_state.get_query_results.insert(key.clone(), None);
+        network_state.abort_query(&key);
+        assert_eq!(network_state.query_status(&key), QueryStatus::Cancelled);
+    }
+
+    #[test]
+    fn test_abort_query_keeps_existing_results() {
+        let mut network_state = NetworkState::new();
+        let key = test_query_key();
+        network_state
+            .get_query_results
+            .insert(key.clone(), Some(Ok(NetworkQueryResult::Entry(None))));
+
+        network_state.abort_query(&key);
+        assert!(!network_state.is_query_aborted(&key));
+
+        // unknown queries stay unknown
+        let unknown = test_query_key();
+        network_state.abort_query(&unknown);
+        assert_eq!(network_state.get_query_results.get(&unknown), None);
+    }
+
+    #[test]
+    fn test_add_query_chunk() {
+        let mut network_state = NetworkState::new();
+        let key = test_query_key();
+        network_state.get_query_results.insert(key.clone(), None);
+
+        let json = serde_json::to_string(&NetworkQueryResult::Entry(None)).unwrap();
+        let (first, second) = json.split_at(json.len() / 2);
+
+        // chunks may arrive in any order
+        network_state
+            .add_query_chunk(&key, 1, 2, second.to_string())
+            .unwrap();
+        assert_eq!(network_state.get_query_results.get(&key), Some(&None));
+        assert!(network_state
+            .add_query_chunk(&key, 0, 3, first.to_string())
+            .is_err());
+        network_state
+            .add_query_chunk(&key, 0, 2, first.to_string())
+            .unwrap();
+
+        assert_eq!(
+            network_state.get_query_results.get(&key),
+            Some(&Some(Ok(NetworkQueryResult::Entry(None))))
+        );
+        assert!(network_state.query_chunks.is_empty());
+
+        // the query is resolved now, as are queries nobody started
+        assert!(network_state
+            .add_query_chunk(&key, 0, 2, first.to_string())
+            .is_err());
+        assert!(network_state
+            .add_query_chunk(&test_query_key(), 0, 2, first.to_string())
+            .is_err());
+        assert!(network_state.query_chunks.is_empty());
+    }
+
+    #[test]
+    fn test_query_chunks_time_out() {
+        let mut network_state = NetworkState::new();
+        let key = test_query_key();
+        network_state.get_query_results.insert(key.clone(), None);
+        let timeout = Duration::from_secs(10);
+
+        let json = serde_json::to_string(&NetworkQueryResult::Entry(None)).unwrap();
+        let (first, _) = json.split_at(json.len() / 2);
+        network_state
+            .add_query_chunk(&key, 0, 2, first.to_string())
+            .unwrap();
+
+        // still within the timeout
+        let timed_out = network_state.timed_out_query_chunks(timeout, SystemTime::now());
+        assert!(timed_out.is_empty());
+
+        let timed_out =
+            network_state.timed_out_query_chunks(timeout, SystemTime::now() + timeout * 2);
+        assert_eq!(timed_out, vec![key]);
+    }
+
+    #[test]
+    fn test_pending_query_key() {
+        let mut network_state = NetworkState::new();
+        let key = test_query_key();
+        let request_id = match key {
+            QueryKey::Entry(ref key) => key.id.clone(),
+            QueryKey::Links(_) => unreachable!(),
+        };
+        assert_eq!(network_state.pending_query_key(&request_id), None);
+
+        network_state.get_query_results.insert(key.clone(), None);
+        assert_eq!(
+            network_state.pending_query_key(&request_id),
+            Some(key.clone())
+        );
+        assert_eq!(network_state.pending_query_key("some other request"), None);
+
+        network_state
+            .get_query_results
+            .insert(key, Some(Ok(NetworkQueryResult::Entry(None))));
+        assert_eq!(network_state.pending_query_key(&request_id), None);
+    }
+
+    #[test]
+    fn test_health() {
+        let mut network_state = NetworkState::new();
+        let health = network_state.health();
+        assert!(!health.initialized);
+        assert_eq!(health.peer_count, None);
+        assert_eq!(health.pending_queries, 0);
+
+        let pending = test_query_key();
+        network_state.get_query_results.insert(pending, None);
+        network_state
+            .get_query_results
+            .insert(test_query_key(), Some(Ok(NetworkQueryResult::Entry(None))));
+        network_state
+            .get_validation_package_results
+            .insert(Address::from("QmTestEntry"), None);
+        let health = network_state.health();
+        assert_eq!(health.pending_queries, 1);
+        assert_eq!(health.pending_validation_packages, 1);
+        assert_eq!(health.pending_direct_messages, 0);
+
+        let dna = test_utils::create_test_dna_with_wat("test_zome", None);
+        let (_instance, context) = test_instance_and_context(dna, None).unwrap();
+        assert!(context.state().unwrap().network().health().initialized);
+    }
 }
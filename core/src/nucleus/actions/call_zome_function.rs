@@ -1,19 +1,24 @@
 use crate::{
     action::{Action, ActionWrapper},
+    audit::AuditEvent,
     context::Context,
+    instance::dispatch_action_and_wait,
     nucleus::{
-        actions::get_entry::get_entry_from_agent_chain,
+        actions::get_entry::{get_entry_from_agent_chain, get_entry_from_cas},
         ribosome::{self, WasmCallData},
-        ZomeFnCall, ZomeFnResult,
+        state::NucleusStatus,
+        ZomeCallOutcome, ZomeFnCall, ZomeFnResult,
     },
 };
 use holochain_core_types::{
-    dna::{capabilities::CapabilityRequest, wasm::DnaWasm},
+    dna::{capabilities::CapabilityRequest, fn_declarations::FnDeclaration, wasm::DnaWasm},
     entry::{
-        cap_entries::{CapTokenGrant, CapabilityType},
+        cap_entries::{CapFunctions, CapTokenGrant, CapabilityType, RateLimit},
+        entry_type::EntryType,
         Entry,
     },
     error::HolochainError,
+    json::JsonStringExt,
     signature::{Provenance, Signature},
     ugly::lax_send_sync,
 };
@@ -28,7 +33,18 @@ use base64;
 use futures::{future::Future, task::Poll};
 use holochain_wasm_utils::api_serialization::crypto::CryptoMethod;
 use snowflake::ProcessUniqueId;
-use std::{pin::Pin, sync::Arc, thread};
+use std::{
+    collections::HashSet,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+/// How often sending a zome call's result to the action channel is tried before giving up
+const RESULT_SEND_ATTEMPTS: u32 = 5;
+/// Pause after the first failed send of a result, doubling with every further failure
+const RESULT_SEND_BACKOFF: Duration = Duration::from_millis(10);
 
 #[derive(Clone, Debug, PartialEq, Hash, Serialize)]
 pub struct ExecuteZomeFnResponse {
@@ -50,6 +66,16 @@ impl ExecuteZomeFnResponse {
     pub fn result(&self) -> Result<JsonString, HolochainError> {
         self.result.clone()
     }
+
+    /// the result with the zome's own errors told apart from failures to run the call
+    pub fn outcome(&self) -> ZomeCallOutcome {
+        ZomeCallOutcome::from(self.result())
+    }
+
+    /// the request id the caller tagged the call with, if any
+    pub fn client_request_id(&self) -> Option<String> {
+        self.call.client_request_id.clone()
+    }
 }
 
 /// Execution of zome calls
@@ -77,6 +103,10 @@ pub async fn call_zome_function(
     // 1. Validate the call (a number of things could go wrong)
     validate_call(context.clone(), &zome_call)?;
 
+    // 2. Fill in the defaults the DNA declares for parameters the caller left out.
+    // This has to happen after validation since the call's signature covers what was sent.
+    let zome_call = with_default_parameters(&context, zome_call)?;
+
     log_debug!(context,
         "actions/call_zome_fn: executing call: {:?}",
         zome_call
@@ -85,6 +115,9 @@ pub async fn call_zome_function(
     // Clone context and call data for the Ribosome thread
     let context_clone = context.clone();
     let zome_call_clone = zome_call.clone();
+    // Lets the Ribosome thread fail the future if the result can't be delivered
+    let send_failure = Arc::new(Mutex::new(None));
+    let send_failure_clone = send_failure.clone();
 
     // Signal (currently mainly to the nodejs_waiter) that we are about to start a zome function:
     context
@@ -110,12 +143,33 @@ pub async fn call_zome_function(
             let response = ExecuteZomeFnResponse::new(zome_call_clone, call_result);
             // Send ReturnZomeFunctionResult Action
             log_debug!(context_clone, "actions/call_zome_fn: sending ReturnZomeFunctionResult action.");
-            lax_send_sync(
-                context_clone.action_channel().clone(),
-                ActionWrapper::new(Action::ReturnZomeFunctionResult(response)),
-                "call_zome_function",
+            let sent = retry_send(
+                || {
+                    lax_send_sync(
+                        context_clone.action_channel().clone(),
+                        ActionWrapper::new(Action::ReturnZomeFunctionResult(response.clone())),
+                        "call_zome_function",
+                    )
+                },
+                RESULT_SEND_ATTEMPTS,
+                RESULT_SEND_BACKOFF,
             );
-            log_debug!(context_clone, "actions/call_zome_fn: sent ReturnZomeFunctionResult action.");
+            if sent {
+                log_debug!(
+                    context_clone,
+                    "actions/call_zome_fn: sent ReturnZomeFunctionResult action."
+                );
+            } else {
+                log_error!(
+                    context_clone,
+                    "actions/call_zome_fn: could not send ReturnZomeFunctionResult action."
+                );
+                *send_failure_clone.lock().unwrap() = Some(HolochainError::ErrorGeneric(format!(
+                    "Could not deliver the result of zome call {:?} after {} attempts",
+                    response.call().id(),
+                    RESULT_SEND_ATTEMPTS
+                )));
+            }
         })
         .expect("Could not spawn thread for call_zome_function");
 
@@ -128,126 +182,576 @@ pub async fn call_zome_function(
     await!(CallResultFuture {
         context: context.clone(),
         zome_call,
+        send_failure,
     })
 }
 
+/// Calls `send` until it succeeds, at most `attempts` times, sleeping `backoff` after the
+/// first failure and twice as long after each one that follows.
+/// Returns whether sending succeeded eventually.
+fn retry_send<F: FnMut() -> bool>(mut send: F, attempts: u32, backoff: Duration) -> bool {
+    let mut pause = backoff;
+    for attempt in 1..=attempts {
+        if send() {
+            return true;
+        }
+        if attempt < attempts {
+            thread::sleep(pause);
+            pause *= 2;
+        }
+    }
+    false
+}
+
 /// validates that a given zome function call specifies a correct zome function and capability grant
 pub fn validate_call(
     context: Arc<Context>,
     fn_call: &ZomeFnCall,
 ) -> Result<(String, DnaWasm), HolochainError> {
-    // make sure the dna, zome and function exists and return pretty errors if they don't
-    let (dna_name, code) = {
-        let state = context.state().ok_or(HolochainError::ErrorGeneric(
-            "Context not initialized".to_string(),
-        ))?;
-
-        let nucleus_state = state.nucleus();
-        let dna = nucleus_state
-            .dna()
-            .ok_or_else(|| HolochainError::DnaMissing)?;
-        let zome = dna
-            .get_zome(&fn_call.zome_name)
-            .map_err(|e| HolochainError::Dna(e))?;
-        let _ = dna
-            .get_function_with_zome_name(&fn_call.zome_name, &fn_call.fn_name)
-            .map_err(|e| HolochainError::Dna(e))?;
-        (dna.name.clone(), zome.code.clone())
+    let (dna_name, code, read_only) = look_up_call(&context, fn_call)?;
+
+    let (allowed, granting_call) = authorize_call(&context, fn_call, read_only);
+    let outcome = if !allowed {
+        Err(HolochainError::CapabilityCheckFailed)
+    } else if let Some(ref granting_call) = granting_call {
+        check_limits(context.clone(), granting_call, SystemTime::now())
+    } else {
+        Ok(())
     };
+    // audited once the limits are checked too, calls over them aren't allowed either
+    audit_capability_decision(
+        &context,
+        granting_call.as_ref().unwrap_or(fn_call),
+        outcome.is_ok(),
+    );
+    outcome.map(|_| (dna_name, code))
+}
 
-    if check_capability(context.clone(), fn_call)
-        || (is_token_the_agent(context.clone(), &fn_call.cap)
-            && verify_call_sig(
-                &fn_call.cap.provenance,
-                &fn_call.fn_name,
-                fn_call.parameters.clone(),
-            ))
+/// Tells whether `validate_call` would accept the call right now, without executing anything.
+/// Unlike `validate_call` it has no side effects: the decision isn't audited and nothing gets
+/// counted against rate limits. This lets clients disable actions their caller isn't allowed.
+pub fn can_call(context: Arc<Context>, fn_call: &ZomeFnCall) -> bool {
+    let read_only = match look_up_call(&context, fn_call) {
+        Ok((_, _, read_only)) => read_only,
+        Err(_) => return false,
+    };
+    match authorize_call(&context, fn_call, read_only) {
+        (false, _) => false,
+        (true, None) => true,
+        (true, Some(granting_call)) => {
+            let token = granting_call.cap_token();
+            let within_rate_limit = match rate_limit_at(&context, &token, SystemTime::now()) {
+                Some((_, allows)) => allows,
+                None => true,
+            };
+            within_rate_limit && uses_left(&context, &token) != Some(0)
+        }
+    }
+}
+
+/// checks the size of the parameters, that the dna is the one the call expects, if any,
+/// and that the dna, zome and function exist, returning pretty errors if they don't.
+/// Returns the name of the DNA, the code of the zome and whether the function is read-only.
+fn look_up_call(
+    context: &Arc<Context>,
+    fn_call: &ZomeFnCall,
+) -> Result<(String, DnaWasm, bool), HolochainError> {
+    // reject oversized payloads before doing any work on them
+    let parameter_bytes = fn_call.parameters.byte_len();
+    if parameter_bytes > context.max_parameter_bytes() {
+        return Err(HolochainError::ErrorGeneric(format!(
+            "Zome call parameters of {} bytes exceed the limit of {} bytes",
+            parameter_bytes,
+            context.max_parameter_bytes()
+        )));
+    }
+
+    let state = context.state().ok_or(HolochainError::ErrorGeneric(
+        "Context not initialized".to_string(),
+    ))?;
+
+    let nucleus_state = state.nucleus();
+    let dna = nucleus_state
+        .dna()
+        .ok_or_else(|| dna_missing_error(context, &nucleus_state.status()))?;
+    if let Some(ref expected_dna_hash) = fn_call.expected_dna_hash {
+        let dna_hash = dna.address();
+        if dna_hash != *expected_dna_hash {
+            return Err(HolochainError::DnaHashMismatch(
+                expected_dna_hash.clone(),
+                dna_hash,
+            ));
+        }
+    }
+    let zome = dna
+        .get_zome(&fn_call.zome_name)
+        .map_err(|e| HolochainError::Dna(e))?;
+    let _ = dna
+        .get_function_with_zome_name(&fn_call.zome_name, &fn_call.fn_name)
+        .map_err(|e| HolochainError::Dna(e))?;
+    Ok((
+        dna.name.clone(),
+        zome.code.clone(),
+        zome.is_fn_read_only(&fn_call.fn_name),
+    ))
+}
+
+/// returns the call with the defaults its function declares filled in for missing parameters
+fn with_default_parameters(
+    context: &Arc<Context>,
+    mut fn_call: ZomeFnCall,
+) -> Result<ZomeFnCall, HolochainError> {
+    let state = context.state().ok_or(HolochainError::ErrorGeneric(
+        "Context not initialized".to_string(),
+    ))?;
+    let nucleus_state = state.nucleus();
+    let dna = nucleus_state
+        .dna()
+        .ok_or_else(|| dna_missing_error(context, &nucleus_state.status()))?;
+    let fn_declaration = dna
+        .get_function_with_zome_name(&fn_call.zome_name, &fn_call.fn_name)
+        .map_err(|e| HolochainError::Dna(e))?;
+    fn_call.parameters = apply_default_parameters(fn_declaration, &fn_call.parameters)?;
+    Ok(fn_call)
+}
+
+/// merges the declared defaults into the object of parameters given, failing if an input
+/// without a default is still missing afterwards.
+/// Parameters of functions that declare no defaults are passed on untouched, as are
+/// parameters that aren't a JSON object.
+fn apply_default_parameters(
+    fn_declaration: &FnDeclaration,
+    parameters: &JsonString,
+) -> Result<JsonString, HolochainError> {
+    if fn_declaration
+        .inputs
+        .iter()
+        .all(|input| input.default.is_none())
     {
-        Ok((dna_name, code))
-    } else {
-        Err(HolochainError::CapabilityCheckFailed)
+        return Ok(parameters.clone());
+    }
+    let mut fields = match serde_json::from_str::<serde_json::Value>(&parameters.to_string()) {
+        Ok(serde_json::Value::Object(fields)) => fields,
+        _ => return Ok(parameters.clone()),
+    };
+    for input in fn_declaration.inputs.iter() {
+        if fields.contains_key(&input.name) {
+            continue;
+        }
+        match input.default {
+            Some(ref default) => {
+                fields.insert(input.name.clone(), default.clone());
+            }
+            None => {
+                return Err(HolochainError::ErrorGeneric(format!(
+                    "Call to {} is missing parameter {}, which has no default",
+                    fn_declaration.name, input.name
+                )));
+            }
+        }
+    }
+    Ok(JsonString::from_json(
+        &serde_json::Value::Object(fields).to_string(),
+    ))
+}
+
+/// decides whether the call is authorized, leaving rate limits aside.
+/// Also returns the call as made with the capability request whose grant authorized it, if any.
+fn authorize_call(
+    context: &Arc<Context>,
+    fn_call: &ZomeFnCall,
+    read_only: bool,
+) -> (bool, Option<ZomeFnCall>) {
+    // functions the DNA declares read-only are callable by anyone, no grant needed
+    if read_only {
+        return (true, None);
+    }
+    // the agent's own token only counts for direct calls, bridge callers need a bridge grant
+    let is_agent_call = fn_call.bridge_caller.is_none()
+        && is_token_the_agent(context.clone(), &fn_call.cap)
+        && verify_call_sig(
+            &fn_call.cap.provenance,
+            &fn_call.zome_name,
+            &fn_call.fn_name,
+            fn_call.parameters.clone(),
+        );
+    // self-calls are the most common ones, don't scan the chain for grants they can't have.
+    // Further tokens may still have a grant, which would then be rate limited.
+    if is_agent_call && fn_call.additional_caps.is_empty() {
+        return (true, None);
+    }
+    let granting_call = find_granting_call(context.clone(), fn_call)
+        .or_else(|| find_bridge_granting_call(context, fn_call));
+    (is_agent_call || granting_call.is_some(), granting_call)
+}
+
+/// Extension point for grants with conditions the capability types can't express,
+/// such as time windows. Implementations get registered with
+/// `Context::register_capability_policy` and grants refer to them by name
+/// with `CapTokenGrant::with_policy`.
+pub trait CapabilityPolicy: Send + Sync {
+    /// Decides whether `fn_call` may use `grant`, which already passed the checks of its
+    /// capability type
+    fn allows(&self, grant: &CapTokenGrant, fn_call: &ZomeFnCall) -> bool;
+}
+
+/// A record of a single capability check, for auditing who accessed what
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct CapabilityAuditRecord {
+    pub source: Address,
+    pub zome_name: String,
+    pub fn_name: String,
+    pub cap_token: Address,
+    pub allowed: bool,
+}
+
+/// explains why the instance has no DNA to call into and what to do about it
+fn dna_missing_error(context: &Arc<Context>, status: &NucleusStatus) -> HolochainError {
+    let reason = match status {
+        NucleusStatus::New => String::from(
+            "no DNA is installed. Install a DNA by referencing it from the instance's \
+             configuration and initialize the instance before calling zome functions",
+        ),
+        NucleusStatus::Initializing => String::from(
+            "its DNA is still being installed. Wait for the instance's initialization to finish",
+        ),
+        NucleusStatus::InitializationFailed(error) => {
+            format!("installing its DNA failed during initialization: {}", error)
+        }
+        NucleusStatus::Initialized(_) => String::from(
+            "it was initialized without a DNA. Re-initialize it with the DNA it should run",
+        ),
+    };
+    HolochainError::DnaMissing(format!(
+        "can't call zome functions of instance '{}': {}",
+        context.instance_name, reason
+    ))
+}
+
+/// logs the outcome of a capability check and passes it on to the context's audit sinks
+fn audit_capability_decision(context: &Arc<Context>, fn_call: &ZomeFnCall, allowed: bool) {
+    let record = CapabilityAuditRecord {
+        source: fn_call.cap.provenance.source(),
+        zome_name: fn_call.zome_name.clone(),
+        fn_name: fn_call.fn_name.clone(),
+        cap_token: fn_call.cap_token(),
+        allowed,
+    };
+    log_debug!(
+        context,
+        "actions/validate_call: capability audit: {:?}",
+        record
+    );
+    context.audit(AuditEvent::CapabilityDecision(record));
+}
+
+/// enforces the rate limit and the maximum number of uses of the grant behind the call's
+/// token, if it has them, and records the call at `now` against them.
+/// Nothing is recorded unless both limits allow the call.
+fn check_limits(
+    context: Arc<Context>,
+    fn_call: &ZomeFnCall,
+    now: SystemTime,
+) -> Result<(), HolochainError> {
+    let token = fn_call.cap_token();
+    // held until the call is recorded, so that racing calls can't all fit into the last slot
+    // or take the last use
+    let _limits_lock = context.lock_cap_token_uses();
+    let rate_limit = check_rate_limit(&context, &token, now)?;
+    let use_limited = check_use_limit(&context, &token)?;
+    if let Some(rate_limit) = rate_limit {
+        dispatch_action_and_wait(
+            context.clone(),
+            ActionWrapper::new(Action::RecordCapTokenCall((token.clone(), rate_limit, now))),
+        );
+    }
+    if use_limited {
+        dispatch_action_and_wait(
+            context,
+            ActionWrapper::new(Action::RecordCapTokenUse(token)),
+        );
+    }
+    Ok(())
+}
+
+/// rejects the call if the grant behind `token` has a rate limit that doesn't allow another
+/// call at `now`, and otherwise returns the rate limit to record the call against
+fn check_rate_limit(
+    context: &Arc<Context>,
+    token: &Address,
+    now: SystemTime,
+) -> Result<Option<RateLimit>, HolochainError> {
+    match rate_limit_at(context, token, now) {
+        None => Ok(None),
+        Some((rate_limit, false)) => {
+            log_debug!(
+                context,
+                "actions/check_rate_limit: token {:?} exceeded {:?}",
+                token,
+                rate_limit
+            );
+            Err(HolochainError::RateLimitExceeded)
+        }
+        Some((rate_limit, true)) => Ok(Some(rate_limit)),
+    }
+}
+
+/// rejects the call if the grant behind `token` can only be used so many times and has
+/// been used up, and otherwise returns whether the call counts as a use of it
+fn check_use_limit(context: &Arc<Context>, token: &Address) -> Result<bool, HolochainError> {
+    match uses_left(context, token) {
+        None => Ok(false),
+        Some(0) => {
+            log_debug!(
+                context,
+                "actions/check_use_limit: token {:?} is used up",
+                token
+            );
+            Err(HolochainError::CapabilityExhausted)
+        }
+        Some(_) => Ok(true),
     }
 }
 
+/// returns how many more times the grant behind `token` can be used,
+/// if it can only be used so many times
+fn uses_left(context: &Arc<Context>, token: &Address) -> Option<u32> {
+    let max_uses = get_grant(context, token).and_then(|grant| grant.max_uses())?;
+    let uses = context
+        .state()
+        .and_then(|state| state.nucleus().cap_token_uses.get(token).cloned())
+        .unwrap_or(0);
+    Some(max_uses.saturating_sub(uses))
+}
+
+/// returns the rate limit of the grant behind `token`, if it has one,
+/// and whether it allows another call at `now`
+fn rate_limit_at(
+    context: &Arc<Context>,
+    token: &Address,
+    now: SystemTime,
+) -> Option<(RateLimit, bool)> {
+    let rate_limit = get_grant(context, token).and_then(|grant| grant.rate_limit())?;
+    let previous_calls = context
+        .state()
+        .and_then(|state| state.nucleus().cap_token_calls.get(token).cloned())
+        .unwrap_or_default();
+    let allows = rate_limit.allows(&previous_calls, now);
+    Some((rate_limit, allows))
+}
+
 fn is_token_the_agent(context: Arc<Context>, request: &CapabilityRequest) -> bool {
     context.agent_id.pub_sign_key == request.cap_token.to_string()
 }
 
 fn get_grant(context: &Arc<Context>, address: &Address) -> Option<CapTokenGrant> {
-    match get_entry_from_agent_chain(context, address).ok()?? {
-        Entry::CapTokenGrant(grant) => Some(grant),
-        _ => None,
+    if let Some(cached) = context.cached_grant(address) {
+        return cached;
     }
+    let generation = context.grant_cache_generation();
+    let grant = match get_entry_from_agent_chain(context, address) {
+        Ok(Some(Entry::CapTokenGrant(grant))) if !replaced_on_chain(context).contains(address) => {
+            Some(grant)
+        }
+        Ok(_) => None,
+        // failed lookups aren't cached so that the next call tries again
+        Err(_) => return None,
+    };
+    context.cache_grant(address.clone(), grant.clone(), generation);
+    grant
+}
+
+/// returns the addresses of the entries that a later entry on the agent's chain
+/// updates or deletes, i.e. for grants the ones that got replaced or revoked
+fn replaced_on_chain(context: &Arc<Context>) -> HashSet<Address> {
+    let state = match context.state() {
+        Some(state) => state,
+        None => return HashSet::new(),
+    };
+    let agent = state.agent();
+    let top_header = agent.top_chain_header();
+    agent
+        .chain_store()
+        .iter(&top_header)
+        .filter_map(|header| header.link_update_delete())
+        .collect()
+}
+
+/// returns the capability grants on this agent's chain that are still in effect, newest first
+pub fn list_grants(context: &Arc<Context>) -> Vec<CapTokenGrant> {
+    let state = match context.state() {
+        Some(state) => state,
+        None => return Vec::new(),
+    };
+    let agent = state.agent();
+    let top_header = agent.top_chain_header();
+    let cas = agent.chain_store().content_storage();
+    let replaced = replaced_on_chain(context);
+    agent
+        .chain_store()
+        .iter_type(&top_header, &EntryType::CapTokenGrant)
+        .filter(|header| !replaced.contains(header.entry_address()))
+        .filter_map(|header| get_entry_from_cas(&cas, header.entry_address()).ok()?)
+        .filter_map(|entry| match entry {
+            Entry::CapTokenGrant(grant) => Some(grant),
+            _ => None,
+        })
+        .collect()
 }
 
 /// checks to see if a given function call is allowable according to the capabilities
 /// that have been registered to callers by looking for grants in the chain.
+/// Succeeds if any one of the call's capability requests is granted the function.
 pub fn check_capability(context: Arc<Context>, fn_call: &ZomeFnCall) -> bool {
-    let maybe_grant = get_grant(&context.clone(), &fn_call.cap_token());
-    match maybe_grant {
-        None => false,
-        Some(grant) => verify_grant(context.clone(), &grant, fn_call),
-    }
+    find_granting_call(context, fn_call).is_some()
+}
+
+/// returns the call as made with the first of its capability requests whose grant
+/// authorizes it, so that the grant's token is the one checked for rate limits
+fn find_granting_call(context: Arc<Context>, fn_call: &ZomeFnCall) -> Option<ZomeFnCall> {
+    fn_call
+        .caps()
+        .map(|cap| ZomeFnCall {
+            cap: cap.clone(),
+            additional_caps: Vec::new(),
+            ..fn_call.clone()
+        })
+        // no grant is stored under the agent's own address, so there is nothing to look up
+        .filter(|call| !is_token_the_agent(context.clone(), &call.cap))
+        .find(|call| match get_grant(&context, &call.cap_token()) {
+            None => false,
+            Some(grant) => verify_grant(context.clone(), &grant, call),
+        })
+}
+
+/// Instances calling over a bridge don't know the tokens of the instance they call into,
+/// so their calls can also be authorized by any bridge grant assigned to the calling instance.
+/// Returns the call as made with the token of the first such grant that authorizes it.
+fn find_bridge_granting_call(context: &Arc<Context>, fn_call: &ZomeFnCall) -> Option<ZomeFnCall> {
+    let caller = fn_call.bridge_caller.as_ref()?;
+    list_grants(context)
+        .into_iter()
+        .filter(|grant| {
+            grant.cap_type() == CapabilityType::Bridge
+                && grant
+                    .assignees()
+                    .map(|assignees| assignees.contains(caller))
+                    .unwrap_or(false)
+        })
+        .map(|grant| {
+            let provenance = &fn_call.cap.provenance;
+            let cap =
+                CapabilityRequest::new(grant.token(), provenance.source(), provenance.signature());
+            let call = ZomeFnCall {
+                cap,
+                additional_caps: Vec::new(),
+                ..fn_call.clone()
+            };
+            (grant, call)
+        })
+        .find(|(grant, call)| verify_grant(context.clone(), grant, call))
+        .map(|(_, call)| call)
+}
+
+/// encodes what gets signed for a zome call. The zome is part of it so that a signature for
+/// a function can't be replayed against the function of the same name in another zome.
+pub fn encode_call_data_for_signing<J: Into<JsonString>>(
+    zome: &str,
+    function: &str,
+    parameters: J,
+) -> String {
+    base64::encode(&format!("{}:{}:{}", zome, function, parameters.into()))
 }
 
-pub fn encode_call_data_for_signing<J: Into<JsonString>>(function: &str, parameters: J) -> String {
+/// encodes what got signed for a zome call before the zome was part of it.
+/// Deprecated: still accepted by `verify_call_sig` so that clients can move to
+/// `encode_call_data_for_signing`, to be dropped in a later release.
+pub fn encode_legacy_call_data_for_signing<J: Into<JsonString>>(
+    function: &str,
+    parameters: J,
+) -> String {
     base64::encode(&format!("{}:{}", function, parameters.into()))
 }
 
 // temporary function to create a mock signature of for a zome call cap request
 fn make_call_sig<J: Into<JsonString>>(
     context: Arc<Context>,
+    zome: &str,
     function: &str,
     parameters: J,
-) -> Signature {
-    let encode_call_data = encode_call_data_for_signing(function, parameters);
-    Signature::from(
-        context
-            .conductor_api
-            .execute(encode_call_data, CryptoMethod::Sign)
-            .expect("signing should work"),
-    )
+) -> Result<Signature, HolochainError> {
+    let encode_call_data = encode_call_data_for_signing(zome, function, parameters);
+    context
+        .conductor_api
+        .execute(encode_call_data, CryptoMethod::Sign)
+        .map(Signature::from)
+        .map_err(|error| {
+            HolochainError::ErrorGeneric(format!(
+                "Could not sign call to {} of zome {}, conductor signing failed: {}",
+                function, zome, error
+            ))
+        })
 }
 
 // temporary function to verify a mock signature of for a zome call cap request
 pub fn verify_call_sig<J: Into<JsonString>>(
     provenance: &Provenance,
+    zome: &str,
     function: &str,
     parameters: J,
 ) -> bool {
-    let what_was_signed = encode_call_data_for_signing(function, parameters);
+    let parameters = parameters.into();
+    let what_was_signed = encode_call_data_for_signing(zome, function, parameters.clone());
     provenance.verify(what_was_signed).unwrap()
+        || provenance
+            .verify(encode_legacy_call_data_for_signing(function, parameters))
+            .unwrap()
 }
 
-/// creates a capability request for a zome call by signing the function name and parameters
+/// creates a capability request for a zome call by signing the zome and function names
+/// and the parameters.
+/// Fails if the conductor can't sign the call.
 pub fn make_cap_request_for_call<J: Into<JsonString>>(
     callers_context: Arc<Context>,
     cap_token: Address,
+    zome: &str,
     function: &str,
     parameters: J,
-) -> CapabilityRequest {
-    CapabilityRequest::new(
+) -> Result<CapabilityRequest, HolochainError> {
+    Ok(CapabilityRequest::new(
         cap_token,
         callers_context.agent_id.address(),
-        make_call_sig(callers_context, function, parameters),
-    )
+        make_call_sig(callers_context, zome, function, parameters)?,
+    ))
+}
+
+/// checks that `functions` grant `function` of exactly the zome `zome`.
+/// Zomes can declare functions of the same name, granting one of them never grants the others.
+fn grants_zome_function(functions: &CapFunctions, zome: &str, function: &str) -> bool {
+    functions
+        .get(zome)
+        .map(|granted| granted.iter().any(|granted_fn| granted_fn == function))
+        .unwrap_or(false)
 }
 
 /// verifies that this grant is valid for a given requester and token value
 pub fn verify_grant(context: Arc<Context>, grant: &CapTokenGrant, fn_call: &ZomeFnCall) -> bool {
     let cap_functions = grant.functions();
-    let maybe_zome_grants = cap_functions.get(&fn_call.zome_name);
-    if maybe_zome_grants.is_none() {
+    if !grants_zome_function(&cap_functions, &fn_call.zome_name, &fn_call.fn_name) {
         log_debug!(context,
-            "actions/verify_grant: no grant for zome {:?} in grant {:?}",
-            fn_call.zome_name, cap_functions
+            "actions/verify_grant: no grant for function {:?} of zome {:?} in grant {:?}",
+            fn_call.fn_name,
+            fn_call.zome_name,
+            cap_functions
         );
         return false;
     }
-    if !maybe_zome_grants.unwrap().contains(&fn_call.fn_name) {
-        log_debug!(context,
-            "actions/verify_grant: no grant for function {:?} in grant {:?}",
-            fn_call.fn_name, maybe_zome_grants
+
+    // bridge calls can use grants of the other types as well, but not the other way around
+    if grant.cap_type() == CapabilityType::Bridge && fn_call.bridge_caller.is_none() {
+        log_debug!(
+            context,
+            "actions/verify_grant: bridge grants are for bridge calls only"
         );
         return false;
     }
@@ -263,6 +767,7 @@ pub fn verify_grant(context: Arc<Context>, grant: &CapTokenGrant, fn_call: &Zome
 
     if !verify_call_sig(
         &fn_call.cap.provenance,
+        &fn_call.zome_name,
         &fn_call.fn_name,
         fn_call.parameters.clone(),
     ) {
@@ -270,7 +775,7 @@ pub fn verify_grant(context: Arc<Context>, grant: &CapTokenGrant, fn_call: &Zome
         return false;
     }
 
-    match grant.cap_type() {
+    let allowed_by_type = match grant.cap_type() {
         CapabilityType::Public => true,
         CapabilityType::Transferable => true,
         CapabilityType::Assigned => {
@@ -286,6 +791,42 @@ pub fn verify_grant(context: Arc<Context>, grant: &CapTokenGrant, fn_call: &Zome
             }
             true
         }
+        CapabilityType::Bridge => {
+            // unwraps are safe because bridge grants always have assignees
+            // and the bridge caller was checked above
+            if !grant
+                .assignees()
+                .unwrap()
+                .contains(fn_call.bridge_caller.as_ref().unwrap())
+            {
+                log_debug!(
+                    context,
+                    "actions/verify_grant: bridge caller not one of the assignees"
+                );
+                return false;
+            }
+            true
+        }
+    };
+    allowed_by_type && satisfies_policy(&context, grant, fn_call)
+}
+
+/// grants naming a custom policy also need the approval of that policy
+fn satisfies_policy(context: &Arc<Context>, grant: &CapTokenGrant, fn_call: &ZomeFnCall) -> bool {
+    let name = match grant.policy() {
+        Some(name) => name,
+        None => return true,
+    };
+    match context.capability_policy(&name) {
+        Some(policy) => policy.allows(grant, fn_call),
+        None => {
+            log_debug!(
+                context,
+                "actions/verify_grant: no capability policy {:?} registered",
+                name
+            );
+            false
+        }
     }
 }
 
@@ -294,6 +835,7 @@ pub fn verify_grant(context: Arc<Context>, grant: &CapTokenGrant, fn_call: &Zome
 pub struct CallResultFuture {
     context: Arc<Context>,
     zome_call: ZomeFnCall,
+    send_failure: Arc<Mutex<Option<HolochainError>>>,
 }
 
 impl Future for CallResultFuture {
@@ -303,6 +845,10 @@ impl Future for CallResultFuture {
         if let Some(err) = self.context.action_channel_error("CallResultFuture") {
             return Poll::Ready(Err(err));
         }
+        // The result is never going to show up in the state
+        if let Some(err) = self.send_failure.lock().unwrap().clone() {
+            return Poll::Ready(Err(err));
+        }
         // With our own executor implementation in Context::block_on we actually
         // wouldn't need the waker since this executor is attached to the redux loop
         // and re-polls after every State mutation.
@@ -326,27 +872,45 @@ impl Future for CallResultFuture {
 pub mod tests {
     use super::*;
     use crate::{
-        context::Context,
+        audit::{ChannelAuditSink, MemoryAuditSink},
+        context::{block_on_stepwise, Context},
         instance::tests::*,
         nucleus::{actions::tests::test_dna, tests::*},
+        state::StateWrapper,
         workflows::author_entry::author_entry,
     };
+    use crossbeam_channel::unbounded;
     use holochain_core_types::{
-        dna::capabilities::CapabilityRequest,
+        dna::{
+            capabilities::CapabilityRequest,
+            fn_declarations::{FnParameter, TraitFns},
+            traits::ReservedTraitNames,
+        },
         entry::{
-            cap_entries::{CapFunctions, CapTokenGrant, CapabilityType},
+            cap_entries::{CapFunctions, CapTokenGrant, CapabilityType, RateLimit},
+            deletion_entry::DeletionEntry,
             Entry,
         },
         signature::Signature,
     };
     use holochain_persistence_api::cas::content::{Address, AddressableContent};
+    use std::sync::{
+        atomic::{AtomicU64, Ordering},
+        RwLock,
+    };
 
     #[test]
     fn test_agent_as_token() {
         let context = test_context("alice", None);
         let agent_token = context.agent_id.address();
-        let cap_request =
-            make_cap_request_for_call(context.clone(), agent_token.clone(), "test", "{}");
+        let cap_request = make_cap_request_for_call(
+            context.clone(),
+            agent_token.clone(),
+            "test_zome",
+            "test",
+            "{}",
+        )
+        .unwrap();
         assert!(is_token_the_agent(context.clone(), &cap_request));
 
         // bogus token should fail
@@ -364,22 +928,42 @@ pub mod tests {
         let context2 = test_context("bob", None);
 
         // only exact same call signed by the same person should verify
-        let call_sig1 = make_call_sig(context1.clone(), "func", "{}");
+        let call_sig1 = make_call_sig(context1.clone(), "zome", "func", "{}").unwrap();
         let provenance1 = Provenance::new(context1.agent_id.address(), call_sig1.clone());
-        assert!(verify_call_sig(&provenance1, "func", "{}"));
-        assert!(!verify_call_sig(&provenance1, "func1", "{}"));
-        assert!(!verify_call_sig(&provenance1, "func", "{\"x\":1}"));
+        assert!(verify_call_sig(&provenance1, "zome", "func", "{}"));
+        assert!(!verify_call_sig(&provenance1, "zome", "func1", "{}"));
+        assert!(!verify_call_sig(&provenance1, "zome", "func", "{\"x\":1}"));
+        assert!(!verify_call_sig(&provenance1, "zome1", "func", "{}"));
 
         let bad_provenance = Provenance::new(context2.agent_id.address(), call_sig1);
 
-        assert!(!verify_call_sig(&bad_provenance, "func", "{}"));
+        assert!(!verify_call_sig(&bad_provenance, "zome", "func", "{}"));
+
+        // signatures in the format without the zome are still accepted for now
+        let legacy_sig = context1
+            .conductor_api
+            .execute(
+                encode_legacy_call_data_for_signing("func", "{}"),
+                CryptoMethod::Sign,
+            )
+            .map(Signature::from)
+            .unwrap();
+        let legacy_provenance = Provenance::new(context1.agent_id.address(), legacy_sig);
+        assert!(verify_call_sig(&legacy_provenance, "zome", "func", "{}"));
+        assert!(!verify_call_sig(&legacy_provenance, "zome", "func1", "{}"));
     }
 
     #[test]
     fn test_make_cap_request_for_call() {
         let context = test_context("alice", None);
-        let cap_request =
-            make_cap_request_for_call(context.clone(), dummy_capability_token(), "some_fn", "{}");
+        let cap_request = make_cap_request_for_call(
+            context.clone(),
+            dummy_capability_token(),
+            "some_zome",
+            "some_fn",
+            "{}",
+        )
+        .unwrap();
         assert_eq!(cap_request.cap_token, dummy_capability_token());
         assert_eq!(
             cap_request.provenance.source().to_string(),
@@ -387,7 +971,7 @@ pub mod tests {
         );
         assert_eq!(
             cap_request.provenance.signature(),
-            make_call_sig(context, "some_fn", "{}")
+            make_call_sig(context, "some_zome", "some_fn", "{}").unwrap()
         );
     }
 
@@ -410,6 +994,177 @@ pub mod tests {
         assert_eq!(maybe_grant, Some(grant));
     }
 
+    #[test]
+    fn test_grant_lookups_are_cached() {
+        use crate::nucleus::actions::get_entry::AGENT_CHAIN_LOOKUPS;
+        let lookups = || AGENT_CHAIN_LOOKUPS.with(|lookups| lookups.get());
+
+        let dna = test_dna();
+        let (_instance, context) =
+            test_instance_and_context(dna, None).expect("Could not initialize test instance");
+
+        let mut cap_functions = CapFunctions::new();
+        cap_functions.insert("test_zome".to_string(), vec![String::from("test")]);
+        let grant = CapTokenGrant::create("foo", CapabilityType::Transferable, None, cap_functions)
+            .unwrap();
+        let grant_addr = context
+            .block_on(author_entry(
+                &Entry::CapTokenGrant(grant.clone()),
+                None,
+                &context,
+                &vec![],
+            ))
+            .unwrap()
+            .address();
+
+        let before = lookups();
+        assert_eq!(get_grant(&context, &grant_addr), Some(grant.clone()));
+        assert_eq!(get_grant(&context, &grant_addr), Some(grant.clone()));
+        assert_eq!(lookups(), before + 1);
+        assert_eq!(context.cached_grant(&grant_addr), Some(Some(grant)));
+
+        // tokens without a grant are remembered as well
+        let unknown = Address::from("some token");
+        assert_eq!(get_grant(&context, &unknown), None);
+        assert_eq!(get_grant(&context, &unknown), None);
+        assert_eq!(lookups(), before + 2);
+    }
+
+    #[test]
+    fn test_revoking_a_grant_invalidates_the_grant_cache() {
+        let dna = test_dna();
+        let (_instance, context) =
+            test_instance_and_context(dna, None).expect("Could not initialize test instance");
+
+        let mut cap_functions = CapFunctions::new();
+        cap_functions.insert("test_zome".to_string(), vec![String::from("test")]);
+        let grant = CapTokenGrant::create("foo", CapabilityType::Transferable, None, cap_functions)
+            .unwrap();
+        let grant_addr = context
+            .block_on(author_entry(
+                &Entry::CapTokenGrant(grant.clone()),
+                None,
+                &context,
+                &vec![],
+            ))
+            .unwrap()
+            .address();
+        assert_eq!(get_grant(&context, &grant_addr), Some(grant));
+        assert!(context.cached_grant(&grant_addr).is_some());
+
+        let revocation = Entry::Deletion(DeletionEntry::new(grant_addr.clone()));
+        context
+            .block_on(author_entry(
+                &revocation,
+                Some(grant_addr.clone()),
+                &context,
+                &vec![],
+            ))
+            .unwrap();
+        assert_eq!(context.cached_grant(&grant_addr), None);
+        assert_eq!(get_grant(&context, &grant_addr), None);
+
+        // authoring a new grant invalidates lookups of tokens that had none
+        let unknown = Address::from("some token");
+        assert_eq!(get_grant(&context, &unknown), None);
+        assert_eq!(context.cached_grant(&unknown), Some(None));
+        let mut cap_functions = CapFunctions::new();
+        cap_functions.insert("test_zome".to_string(), vec![String::from("test")]);
+        let other_grant =
+            CapTokenGrant::create("bar", CapabilityType::Transferable, None, cap_functions)
+                .unwrap();
+        context
+            .block_on(author_entry(
+                &Entry::CapTokenGrant(other_grant),
+                None,
+                &context,
+                &vec![],
+            ))
+            .unwrap();
+        assert_eq!(context.cached_grant(&unknown), None);
+    }
+
+    #[test]
+    fn test_lookups_racing_an_invalidation_are_not_cached() {
+        let context = test_context("alice", None);
+        let token = Address::from("some token");
+
+        // a lookup that started before a revocation got committed
+        let generation = context.grant_cache_generation();
+        context.invalidate_grant_cache();
+        context.cache_grant(token.clone(), None, generation);
+        assert_eq!(context.cached_grant(&token), None);
+
+        let generation = context.grant_cache_generation();
+        context.cache_grant(token.clone(), None, generation);
+        assert_eq!(context.cached_grant(&token), Some(None));
+    }
+
+    #[test]
+    fn test_list_grants() {
+        let dna = test_dna();
+        let (_instance, context) =
+            test_instance_and_context(dna, None).expect("Could not initialize test instance");
+
+        let mut cap_functions = CapFunctions::new();
+        cap_functions.insert("test_zome".to_string(), vec![String::from("test")]);
+        let grant_foo = CapTokenGrant::create(
+            "foo",
+            CapabilityType::Transferable,
+            None,
+            cap_functions.clone(),
+        )
+        .unwrap();
+        let grant_bar =
+            CapTokenGrant::create("bar", CapabilityType::Public, None, cap_functions).unwrap();
+        for grant in vec![grant_foo.clone(), grant_bar.clone()] {
+            context
+                .block_on(author_entry(
+                    &Entry::CapTokenGrant(grant),
+                    None,
+                    &context,
+                    &vec![],
+                ))
+                .unwrap();
+        }
+
+        let grants = list_grants(&context);
+        assert!(grants.contains(&grant_foo));
+        assert!(grants.contains(&grant_bar));
+        // newest first
+        let position = |grant: &CapTokenGrant| grants.iter().position(|g| g == grant).unwrap();
+        assert!(position(&grant_bar) < position(&grant_foo));
+
+        // revoked and replaced grants are no longer listed
+        let revocation = Entry::Deletion(DeletionEntry::new(grant_foo.token()));
+        context
+            .block_on(author_entry(
+                &revocation,
+                Some(grant_foo.token()),
+                &context,
+                &vec![],
+            ))
+            .unwrap();
+        let mut cap_functions = CapFunctions::new();
+        cap_functions.insert("test_zome".to_string(), vec![String::from("test")]);
+        let grant_baz =
+            CapTokenGrant::create("baz", CapabilityType::Public, None, cap_functions).unwrap();
+        context
+            .block_on(author_entry(
+                &Entry::CapTokenGrant(grant_baz.clone()),
+                Some(grant_bar.token()),
+                &context,
+                &vec![],
+            ))
+            .unwrap();
+        let grants = list_grants(&context);
+        assert!(!grants.contains(&grant_foo));
+        assert!(!grants.contains(&grant_bar));
+        assert!(grants.contains(&grant_baz));
+        assert_eq!(get_grant(&context, &grant_foo.token()), None);
+        assert_eq!(get_grant(&context, &grant_bar.token()), None);
+    }
+
     #[test]
     fn test_verify_grant() {
         let context = test_context("alice", None);
@@ -419,7 +1174,14 @@ pub mod tests {
         fn zome_call_valid(context: Arc<Context>, token: &Address) -> ZomeFnCall {
             ZomeFnCall::new(
                 "test_zome",
-                make_cap_request_for_call(context.clone(), token.clone(), "test", "{}"),
+                make_cap_request_for_call(
+                    context.clone(),
+                    token.clone(),
+                    "test_zome",
+                    "test",
+                    "{}",
+                )
+                .unwrap(),
                 "test",
                 "{}",
             )
@@ -427,7 +1189,14 @@ pub mod tests {
 
         let zome_call_from_addr1_bad_token = &ZomeFnCall::new(
             "test_zome",
-            make_cap_request_for_call(context.clone(), Address::from("bad token"), "test", "{}"),
+            make_cap_request_for_call(
+                context.clone(),
+                Address::from("bad token"),
+                "test_zome",
+                "test",
+                "{}",
+            )
+            .unwrap(),
             "test",
             "{}",
         );
@@ -475,7 +1244,8 @@ pub mod tests {
         // call with cap_request for a different function than the zome call
         let zome_call_from_addr1_bad_cap_request = &ZomeFnCall::new(
             "test_zome",
-            make_cap_request_for_call(context.clone(), token.clone(), "foo-fn", "{}"),
+            make_cap_request_for_call(context.clone(), token.clone(), "test_zome", "foo-fn", "{}")
+                .unwrap(),
             "test",
             "{}",
         );
@@ -516,7 +1286,8 @@ pub mod tests {
         // call with cap_request for a different function than the zome call
         let zome_call_from_addr1_bad_cap_request = &ZomeFnCall::new(
             "test_zome",
-            make_cap_request_for_call(context.clone(), token.clone(), "foo-fn", "{}"),
+            make_cap_request_for_call(context.clone(), token.clone(), "test_zome", "foo-fn", "{}")
+                .unwrap(),
             "test",
             "{}",
         );
@@ -538,4 +1309,1073 @@ pub mod tests {
             &zome_call_valid(context2.clone(), &token)
         ));
     }
+
+    #[test]
+    fn test_grant_templates() {
+        let context = test_context("alice", None);
+        let call_with = |grant: &CapTokenGrant, function: &str| {
+            let cap_request = make_cap_request_for_call(
+                context.clone(),
+                grant.token(),
+                "test_zome",
+                function,
+                "{}",
+            )
+            .unwrap();
+            ZomeFnCall::new("test_zome", cap_request, function, "{}")
+        };
+        let functions = ["get_post", "list_posts"];
+
+        let public = CapTokenGrant::public_for("read", "test_zome", &functions);
+        let transferable = CapTokenGrant::transferable_for("read", "test_zome", &functions);
+        let assigned = CapTokenGrant::assigned_for(
+            "read",
+            "test_zome",
+            &functions,
+            vec![context.agent_id.address()],
+        )
+        .unwrap();
+        assert_eq!(public.cap_type(), CapabilityType::Public);
+        assert_eq!(transferable.cap_type(), CapabilityType::Transferable);
+        assert_eq!(assigned.cap_type(), CapabilityType::Assigned);
+
+        for grant in &[public, transferable, assigned] {
+            for function in functions.iter() {
+                assert!(verify_grant(
+                    context.clone(),
+                    grant,
+                    &call_with(grant, function)
+                ));
+            }
+            assert!(!verify_grant(
+                context.clone(),
+                grant,
+                &call_with(grant, "create_post")
+            ));
+        }
+
+        assert!(CapTokenGrant::assigned_for("read", "test_zome", &functions, Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_grants_are_bound_to_their_zome() {
+        let context = test_context("alice", None);
+        let call_with = |grant: &CapTokenGrant, zome: &str| {
+            let cap_request =
+                make_cap_request_for_call(context.clone(), grant.token(), zome, "test", "{}")
+                    .unwrap();
+            ZomeFnCall::new(zome, cap_request, "test", "{}")
+        };
+
+        // both zomes have a `test` function, only the one of zome_a is granted
+        let grant = CapTokenGrant::transferable_for("only_a", "zome_a", &["test"]);
+        assert!(verify_grant(
+            context.clone(),
+            &grant,
+            &call_with(&grant, "zome_a")
+        ));
+        assert!(!verify_grant(
+            context.clone(),
+            &grant,
+            &call_with(&grant, "zome_b")
+        ));
+
+        // granting zome_b something else doesn't make its `test` callable either
+        let mut functions = grant.functions();
+        functions.insert(String::from("zome_b"), vec![String::from("other")]);
+        let grant = CapTokenGrant::create("a_and_b", CapabilityType::Transferable, None, functions)
+            .unwrap();
+        assert!(verify_grant(
+            context.clone(),
+            &grant,
+            &call_with(&grant, "zome_a")
+        ));
+        assert!(!verify_grant(
+            context.clone(),
+            &grant,
+            &call_with(&grant, "zome_b")
+        ));
+
+        // and when both are granted, a request signed for one can't be used for the other
+        let mut functions = grant.functions();
+        functions.insert(String::from("zome_b"), vec![String::from("test")]);
+        let grant =
+            CapTokenGrant::create("both", CapabilityType::Transferable, None, functions).unwrap();
+        assert!(verify_grant(
+            context.clone(),
+            &grant,
+            &call_with(&grant, "zome_b")
+        ));
+        let replayed = ZomeFnCall::new("zome_b", call_with(&grant, "zome_a").cap, "test", "{}");
+        assert!(!verify_grant(context.clone(), &grant, &replayed));
+    }
+
+    #[test]
+    fn test_rate_limited_grant() {
+        let dna = test_utils::create_test_dna_with_wat("test_zome", None);
+        let (_instance, context) =
+            test_instance_and_context(dna, None).expect("Could not initialize test instance");
+
+        let mut cap_functions = CapFunctions::new();
+        cap_functions.insert(
+            "test_zome".to_string(),
+            vec![String::from("public_test_fn")],
+        );
+        let grant = CapTokenGrant::create("foo", CapabilityType::Transferable, None, cap_functions)
+            .unwrap()
+            .with_rate_limit(RateLimit::new(2, 1));
+        let grant_entry = Entry::CapTokenGrant(grant);
+        let token = context
+            .block_on(author_entry(&grant_entry, None, &context, &vec![]))
+            .unwrap()
+            .address();
+
+        let zome_call = || {
+            ZomeFnCall::create(
+                context.clone(),
+                "test_zome",
+                token.clone(),
+                "public_test_fn",
+                "{}",
+            )
+            .unwrap()
+        };
+
+        let start = SystemTime::now();
+        let call_at = |millis| {
+            check_limits(
+                context.clone(),
+                &zome_call(),
+                start + Duration::from_millis(millis),
+            )
+        };
+
+        assert!(call_at(0).is_ok());
+        assert!(call_at(400).is_ok());
+        assert_eq!(call_at(900).err(), Some(HolochainError::RateLimitExceeded));
+
+        // once the first call has left the window the token can be used once more
+        assert!(call_at(1100).is_ok());
+        assert_eq!(call_at(1200).err(), Some(HolochainError::RateLimitExceeded));
+
+        // validate_call goes through the same limit
+        assert_eq!(
+            validate_call(context.clone(), &zome_call()).err(),
+            Some(HolochainError::RateLimitExceeded)
+        );
+    }
+
+    #[test]
+    fn test_rate_limit_under_concurrent_calls() {
+        let dna = test_utils::create_test_dna_with_wat("test_zome", None);
+        let (_instance, context) =
+            test_instance_and_context(dna, None).expect("Could not initialize test instance");
+        let mut cap_functions = CapFunctions::new();
+        cap_functions.insert(
+            "test_zome".to_string(),
+            vec![String::from("public_test_fn")],
+        );
+        let grant = CapTokenGrant::create("foo", CapabilityType::Transferable, None, cap_functions)
+            .unwrap()
+            .with_rate_limit(RateLimit::new(2, 60));
+        let token = context
+            .block_on(author_entry(
+                &Entry::CapTokenGrant(grant),
+                None,
+                &context,
+                &vec![],
+            ))
+            .unwrap()
+            .address();
+
+        let callers: Vec<_> = (0..10)
+            .map(|_| {
+                let context = context.clone();
+                let token = token.clone();
+                thread::spawn(move || {
+                    let zome_call = ZomeFnCall::create(
+                        context.clone(),
+                        "test_zome",
+                        token,
+                        "public_test_fn",
+                        "{}",
+                    )
+                    .unwrap();
+                    validate_call(context, &zome_call)
+                })
+            })
+            .collect();
+        let results: Vec<_> = callers
+            .into_iter()
+            .map(|caller| caller.join().unwrap())
+            .collect();
+
+        assert_eq!(results.iter().filter(|result| result.is_ok()).count(), 2);
+        assert!(results
+            .iter()
+            .filter_map(|result| result.as_ref().err())
+            .all(|err| *err == HolochainError::RateLimitExceeded));
+        let calls = context.state().unwrap().nucleus().cap_token_calls[&token].len();
+        assert_eq!(calls, 2);
+    }
+
+    /// commits a transferable grant for public_test_fn that can be used `max_uses` times
+    /// and returns its token
+    fn commit_limited_use_grant(context: &Arc<Context>, max_uses: u32) -> Address {
+        let mut cap_functions = CapFunctions::new();
+        cap_functions.insert(
+            "test_zome".to_string(),
+            vec![String::from("public_test_fn")],
+        );
+        let grant = CapTokenGrant::create("foo", CapabilityType::Transferable, None, cap_functions)
+            .unwrap()
+            .with_max_uses(max_uses);
+        let grant_entry = Entry::CapTokenGrant(grant);
+        context
+            .block_on(author_entry(&grant_entry, None, context, &vec![]))
+            .unwrap()
+            .address()
+    }
+
+    #[test]
+    fn test_limited_use_grant() {
+        let dna = test_utils::create_test_dna_with_wat("test_zome", None);
+        let (_instance, context) =
+            test_instance_and_context(dna, None).expect("Could not initialize test instance");
+        let token = commit_limited_use_grant(&context, 3);
+
+        let zome_call = || {
+            ZomeFnCall::create(
+                context.clone(),
+                "test_zome",
+                token.clone(),
+                "public_test_fn",
+                "{}",
+            )
+            .unwrap()
+        };
+
+        for _ in 0..3 {
+            assert!(can_call(context.clone(), &zome_call()));
+            assert!(validate_call(context.clone(), &zome_call()).is_ok());
+        }
+        assert!(!can_call(context.clone(), &zome_call()));
+        assert_eq!(
+            validate_call(context.clone(), &zome_call()).err(),
+            Some(HolochainError::CapabilityExhausted)
+        );
+
+        // calls that fail other checks don't use up a grant
+        let token = commit_limited_use_grant(&context, 1);
+        let unknown_fn_call =
+            ZomeFnCall::create(context.clone(), "test_zome", token.clone(), "xxx", "{}").unwrap();
+        assert!(validate_call(context.clone(), &unknown_fn_call).is_err());
+        let one_shot_call = || {
+            ZomeFnCall::create(
+                context.clone(),
+                "test_zome",
+                token.clone(),
+                "public_test_fn",
+                "{}",
+            )
+            .unwrap()
+        };
+        assert!(validate_call(context.clone(), &one_shot_call()).is_ok());
+        assert_eq!(
+            validate_call(context.clone(), &one_shot_call()).err(),
+            Some(HolochainError::CapabilityExhausted)
+        );
+    }
+
+    #[test]
+    fn test_limited_use_grant_under_concurrent_calls() {
+        let dna = test_utils::create_test_dna_with_wat("test_zome", None);
+        let (_instance, context) =
+            test_instance_and_context(dna, None).expect("Could not initialize test instance");
+        let token = commit_limited_use_grant(&context, 3);
+
+        let callers: Vec<_> = (0..10)
+            .map(|_| {
+                let context = context.clone();
+                let token = token.clone();
+                thread::spawn(move || {
+                    let zome_call = ZomeFnCall::create(
+                        context.clone(),
+                        "test_zome",
+                        token,
+                        "public_test_fn",
+                        "{}",
+                    )
+                    .unwrap();
+                    validate_call(context, &zome_call)
+                })
+            })
+            .collect();
+        let results: Vec<_> = callers
+            .into_iter()
+            .map(|caller| caller.join().unwrap())
+            .collect();
+
+        assert_eq!(results.iter().filter(|result| result.is_ok()).count(), 3);
+        assert!(results
+            .iter()
+            .filter_map(|result| result.as_ref().err())
+            .all(|err| *err == HolochainError::CapabilityExhausted));
+        let uses = context.state().unwrap().nucleus().cap_token_uses[&token];
+        assert_eq!(uses, 3);
+    }
+
+    #[test]
+    fn test_rejected_calls_count_against_no_limit() {
+        let dna = test_utils::create_test_dna_with_wat("test_zome", None);
+        let (_instance, context) =
+            test_instance_and_context(dna, None).expect("Could not initialize test instance");
+        let commit_grant = |max_uses, rate_limit| {
+            let mut cap_functions = CapFunctions::new();
+            cap_functions.insert(
+                "test_zome".to_string(),
+                vec![String::from("public_test_fn")],
+            );
+            let grant =
+                CapTokenGrant::create("foo", CapabilityType::Transferable, None, cap_functions)
+                    .unwrap()
+                    .with_max_uses(max_uses)
+                    .with_rate_limit(rate_limit);
+            context
+                .block_on(author_entry(
+                    &Entry::CapTokenGrant(grant),
+                    None,
+                    &context,
+                    &vec![],
+                ))
+                .unwrap()
+                .address()
+        };
+        let call = |token: &Address| {
+            let zome_call = ZomeFnCall::create(
+                context.clone(),
+                "test_zome",
+                token.clone(),
+                "public_test_fn",
+                "{}",
+            )
+            .unwrap();
+            validate_call(context.clone(), &zome_call)
+        };
+        let nucleus = || context.state().unwrap().nucleus();
+
+        // a used up grant doesn't take a slot of its rate limit
+        let token = commit_grant(1, RateLimit::new(5, 60));
+        assert!(call(&token).is_ok());
+        assert_eq!(
+            call(&token).err(),
+            Some(HolochainError::CapabilityExhausted)
+        );
+        assert_eq!(nucleus().cap_token_calls[&token].len(), 1);
+
+        // and a rate limited call doesn't use up the grant
+        let token = commit_grant(5, RateLimit::new(1, 60));
+        assert!(call(&token).is_ok());
+        assert_eq!(call(&token).err(), Some(HolochainError::RateLimitExceeded));
+        assert_eq!(nucleus().cap_token_uses[&token], 1);
+    }
+
+    #[test]
+    fn test_expected_dna_hash() {
+        let dna = test_utils::create_test_dna_with_wat("test_zome", None);
+        let dna_hash = dna.address();
+        let (_instance, context) =
+            test_instance_and_context(dna, None).expect("Could not initialize test instance");
+        let zome_call = || {
+            ZomeFnCall::create(
+                context.clone(),
+                "test_zome",
+                context.agent_id.address(),
+                "public_test_fn",
+                "{}",
+            )
+            .unwrap()
+        };
+
+        let matching_call = zome_call().with_expected_dna_hash(dna_hash.clone());
+        assert!(validate_call(context.clone(), &matching_call).is_ok());
+
+        let other_hash = Address::from("QmSomeOtherDna");
+        let mismatching_call = zome_call().with_expected_dna_hash(other_hash.clone());
+        assert_eq!(
+            validate_call(context.clone(), &mismatching_call).err(),
+            Some(HolochainError::DnaHashMismatch(other_hash, dna_hash))
+        );
+    }
+
+    #[test]
+    fn test_capability_decisions_are_audited() {
+        let dna = test_utils::create_test_dna_with_wat("test_zome", None);
+        let (_instance, context) =
+            test_instance_and_context(dna, None).expect("Could not initialize test instance");
+        let (audit_tx, audit_rx) = crossbeam_channel::unbounded();
+        let mut context = (*context).clone();
+        context.set_audit_sink(Arc::new(ChannelAuditSink::new(audit_tx)));
+        let context = Arc::new(context);
+
+        // the agent calling with its own address as token is allowed
+        let allowed_call = ZomeFnCall::create(
+            context.clone(),
+            "test_zome",
+            context.agent_id.address(),
+            "public_test_fn",
+            "{}",
+        )
+        .unwrap();
+        assert!(validate_call(context.clone(), &allowed_call).is_ok());
+        let AuditEvent::CapabilityDecision(record) =
+            audit_rx.try_recv().expect("allow should be audited");
+        assert_eq!(
+            record,
+            CapabilityAuditRecord {
+                source: context.agent_id.address(),
+                zome_name: String::from("test_zome"),
+                fn_name: String::from("public_test_fn"),
+                cap_token: context.agent_id.address(),
+                allowed: true,
+            }
+        );
+
+        let denied_call = ZomeFnCall::new(
+            "test_zome",
+            dummy_capability_request(),
+            "public_test_fn",
+            "{}",
+        );
+        assert!(validate_call(context.clone(), &denied_call).is_err());
+        let AuditEvent::CapabilityDecision(record) =
+            audit_rx.try_recv().expect("deny should be audited");
+        assert_eq!(record.source, Address::from("test caller"));
+        assert_eq!(record.cap_token, denied_call.cap_token());
+        assert!(!record.allowed);
+
+        // calls over a limit of the grant are denied as well
+        let mut cap_functions = CapFunctions::new();
+        cap_functions.insert(
+            "test_zome".to_string(),
+            vec![String::from("public_test_fn")],
+        );
+        let grant = CapTokenGrant::create("foo", CapabilityType::Transferable, None, cap_functions)
+            .unwrap()
+            .with_rate_limit(RateLimit::new(1, 60));
+        let token = context
+            .block_on(author_entry(
+                &Entry::CapTokenGrant(grant),
+                None,
+                &context,
+                &vec![],
+            ))
+            .unwrap()
+            .address();
+        let limited_call = ZomeFnCall::create(
+            context.clone(),
+            "test_zome",
+            token.clone(),
+            "public_test_fn",
+            "{}",
+        )
+        .unwrap();
+        assert!(validate_call(context.clone(), &limited_call).is_ok());
+        let AuditEvent::CapabilityDecision(record) =
+            audit_rx.try_recv().expect("allow should be audited");
+        assert!(record.allowed);
+        assert_eq!(
+            validate_call(context.clone(), &limited_call).err(),
+            Some(HolochainError::RateLimitExceeded)
+        );
+        let AuditEvent::CapabilityDecision(record) =
+            audit_rx.try_recv().expect("deny should be audited");
+        assert_eq!(record.cap_token, token);
+        assert!(!record.allowed);
+
+        assert!(audit_rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_capability_decisions_reach_the_audit_sink() {
+        let dna = test_utils::create_test_dna_with_wat("test_zome", None);
+        let (_instance, context) =
+            test_instance_and_context(dna, None).expect("Could not initialize test instance");
+        let sink = Arc::new(MemoryAuditSink::new());
+        let mut context = (*context).clone();
+        context.set_audit_sink(sink.clone());
+        let context = Arc::new(context);
+
+        let call = ZomeFnCall::create(
+            context.clone(),
+            "test_zome",
+            context.agent_id.address(),
+            "public_test_fn",
+            "{}",
+        )
+        .unwrap();
+        assert!(validate_call(context.clone(), &call).is_ok());
+        assert_eq!(
+            sink.events(),
+            vec![AuditEvent::CapabilityDecision(CapabilityAuditRecord {
+                source: context.agent_id.address(),
+                zome_name: String::from("test_zome"),
+                fn_name: String::from("public_test_fn"),
+                cap_token: context.agent_id.address(),
+                allowed: true,
+            })]
+        );
+    }
+
+    #[test]
+    fn test_bridge_calls() {
+        let dna = test_utils::create_test_dna_with_wat("test_zome", None);
+        let (_instance, context) =
+            test_instance_and_context(dna, None).expect("Could not initialize test instance");
+
+        let bridge_caller = Address::from("caller instance");
+        let mut cap_functions = CapFunctions::new();
+        cap_functions.insert(
+            "test_zome".to_string(),
+            vec![String::from("public_test_fn")],
+        );
+        let grant = CapTokenGrant::create(
+            "bridge",
+            CapabilityType::Bridge,
+            Some(vec![bridge_caller.clone()]),
+            cap_functions,
+        )
+        .unwrap();
+        let token = context
+            .block_on(author_entry(
+                &Entry::CapTokenGrant(grant),
+                None,
+                &context,
+                &vec![],
+            ))
+            .unwrap()
+            .address();
+        let call_with = |token: &Address| {
+            ZomeFnCall::create(
+                context.clone(),
+                "test_zome",
+                token.clone(),
+                "public_test_fn",
+                "{}",
+            )
+            .unwrap()
+        };
+
+        // valid bridge call
+        let call = call_with(&token).with_bridge_caller(bridge_caller.clone());
+        assert!(validate_call(context.clone(), &call).is_ok());
+
+        // bridge call from an instance the grant wasn't made for
+        let call = call_with(&token).with_bridge_caller(Address::from("other instance"));
+        assert_eq!(
+            validate_call(context.clone(), &call).err(),
+            Some(HolochainError::CapabilityCheckFailed)
+        );
+
+        // the agent's own token doesn't authorize bridge calls
+        let call = call_with(&context.agent_id.address())
+            .with_bridge_caller(Address::from("other instance"));
+        assert_eq!(
+            validate_call(context.clone(), &call).err(),
+            Some(HolochainError::CapabilityCheckFailed)
+        );
+
+        // bridge callers don't need to know the token of the grant made for them
+        let call = call_with(&Address::from("unknown token")).with_bridge_caller(bridge_caller);
+        assert!(validate_call(context.clone(), &call).is_ok());
+
+        // a bridge grant can't be used for direct calls, which otherwise work as before
+        assert_eq!(
+            validate_call(context.clone(), &call_with(&token)).err(),
+            Some(HolochainError::CapabilityCheckFailed)
+        );
+        assert!(validate_call(context.clone(), &call_with(&context.agent_id.address())).is_ok());
+    }
+
+    #[test]
+    fn test_call_with_several_tokens() {
+        let mut dna = test_utils::create_test_dna_with_wat("test_zome", None);
+        dna.zomes.get_mut("test_zome").unwrap().add_fn_declaration(
+            String::from("other_fn"),
+            vec![],
+            vec![],
+        );
+        let (_instance, context) =
+            test_instance_and_context(dna, None).expect("Could not initialize test instance");
+
+        let commit_grant = |id: &str, function: &str| {
+            let mut cap_functions = CapFunctions::new();
+            cap_functions.insert("test_zome".to_string(), vec![String::from(function)]);
+            let grant =
+                CapTokenGrant::create(id, CapabilityType::Transferable, None, cap_functions)
+                    .unwrap();
+            context
+                .block_on(author_entry(
+                    &Entry::CapTokenGrant(grant),
+                    None,
+                    &context,
+                    &vec![],
+                ))
+                .unwrap()
+                .address()
+        };
+        let other_fn_token = commit_grant("other role", "other_fn");
+        let test_fn_token = commit_grant("test role", "public_test_fn");
+        let call_with = |token: &Address, additional_token: &Address| {
+            ZomeFnCall::create(
+                context.clone(),
+                "test_zome",
+                token.clone(),
+                "public_test_fn",
+                "{}",
+            )
+            .unwrap()
+            .with_additional_cap(
+                make_cap_request_for_call(
+                    context.clone(),
+                    additional_token.clone(),
+                    "test_zome",
+                    "public_test_fn",
+                    "{}",
+                )
+                .unwrap(),
+            )
+        };
+
+        // the first token doesn't grant the function but the second one does
+        let call = call_with(&other_fn_token, &test_fn_token);
+        assert!(check_capability(context.clone(), &call));
+        assert!(validate_call(context.clone(), &call).is_ok());
+
+        // neither token grants it
+        let unknown_token = Address::from("unknown token");
+        let call = call_with(&other_fn_token, &unknown_token);
+        assert!(!check_capability(context.clone(), &call));
+        assert_eq!(
+            validate_call(context.clone(), &call).err(),
+            Some(HolochainError::CapabilityCheckFailed)
+        );
+    }
+
+    #[test]
+    fn test_retry_send() {
+        // fails twice, then goes through
+        let mut attempts = 0;
+        let sent = retry_send(
+            || {
+                attempts += 1;
+                attempts > 2
+            },
+            RESULT_SEND_ATTEMPTS,
+            Duration::from_millis(1),
+        );
+        assert!(sent);
+        assert_eq!(attempts, 3);
+
+        // never goes through
+        let mut attempts = 0;
+        let sent = retry_send(
+            || {
+                attempts += 1;
+                false
+            },
+            RESULT_SEND_ATTEMPTS,
+            Duration::from_millis(1),
+        );
+        assert!(!sent);
+        assert_eq!(attempts, RESULT_SEND_ATTEMPTS);
+    }
+
+    #[test]
+    fn test_undelivered_result_fails_the_call() {
+        let dna = test_utils::create_test_dna_with_wat("test_zome", None);
+        let (_instance, context) =
+            test_instance_and_context(dna, None).expect("Could not initialize test instance");
+        let zome_call = ZomeFnCall::new(
+            "test_zome",
+            dummy_capability_request(),
+            "public_test_fn",
+            "{}",
+        );
+        let failure = HolochainError::ErrorGeneric("result got lost".to_string());
+        let future = CallResultFuture {
+            context: context.clone(),
+            zome_call,
+            send_failure: Arc::new(Mutex::new(Some(failure.clone()))),
+        };
+        assert_eq!(context.block_on(future), Err(failure));
+    }
+
+    /// allows calls on even-numbered minutes of a clock given in seconds since the epoch
+    struct EvenMinutes(Arc<AtomicU64>);
+
+    impl CapabilityPolicy for EvenMinutes {
+        fn allows(&self, _grant: &CapTokenGrant, _fn_call: &ZomeFnCall) -> bool {
+            (self.0.load(Ordering::SeqCst) / 60) % 2 == 0
+        }
+    }
+
+    #[test]
+    fn test_custom_capability_policy() {
+        let dna = test_utils::create_test_dna_with_wat("test_zome", None);
+        let (_instance, context) =
+            test_instance_and_context(dna, None).expect("Could not initialize test instance");
+        let clock = Arc::new(AtomicU64::new(0));
+        let mut context = (*context).clone();
+        context.register_capability_policy("even_minutes", Arc::new(EvenMinutes(clock.clone())));
+        let context = Arc::new(context);
+
+        let commit_grant = |policy: &str| {
+            let mut cap_functions = CapFunctions::new();
+            cap_functions.insert(
+                "test_zome".to_string(),
+                vec![String::from("public_test_fn")],
+            );
+            let grant = CapTokenGrant::create("timed", CapabilityType::Public, None, cap_functions)
+                .unwrap()
+                .with_policy(policy);
+            context
+                .block_on(author_entry(
+                    &Entry::CapTokenGrant(grant),
+                    None,
+                    &context,
+                    &vec![],
+                ))
+                .unwrap()
+                .address()
+        };
+        let call_with = |token: &Address| {
+            ZomeFnCall::create(
+                context.clone(),
+                "test_zome",
+                token.clone(),
+                "public_test_fn",
+                "{}",
+            )
+            .unwrap()
+        };
+
+        let token = commit_grant("even_minutes");
+        clock.store(2 * 60 + 30, Ordering::SeqCst);
+        assert!(validate_call(context.clone(), &call_with(&token)).is_ok());
+        clock.store(3 * 60, Ordering::SeqCst);
+        assert_eq!(
+            validate_call(context.clone(), &call_with(&token)).err(),
+            Some(HolochainError::CapabilityCheckFailed)
+        );
+
+        // grants whose policy isn't registered allow nothing
+        let token = commit_grant("unknown");
+        clock.store(0, Ordering::SeqCst);
+        assert_eq!(
+            validate_call(context.clone(), &call_with(&token)).err(),
+            Some(HolochainError::CapabilityCheckFailed)
+        );
+    }
+
+    #[test]
+    fn test_parameter_size_limit() {
+        let dna = test_utils::create_test_dna_with_wat("test_zome", None);
+        let (_instance, context) =
+            test_instance_and_context(dna, None).expect("Could not initialize test instance");
+        let mut context = (*context).clone();
+        context.set_max_parameter_bytes(20);
+        let context = Arc::new(context);
+        let call_with = |parameters: &str| {
+            ZomeFnCall::create(
+                context.clone(),
+                "test_zome",
+                context.agent_id.address(),
+                "public_test_fn",
+                JsonString::from_json(parameters),
+            )
+            .unwrap()
+        };
+
+        // exactly at the limit
+        assert!(validate_call(context.clone(), &call_with(r#"{"data":"012345678"}"#)).is_ok());
+
+        assert_eq!(
+            validate_call(context.clone(), &call_with(r#"{"data":"0123456789"}"#)).err(),
+            Some(HolochainError::ErrorGeneric(
+                "Zome call parameters of 21 bytes exceed the limit of 20 bytes".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_default_parameters() {
+        let mut dna = test_utils::create_test_dna_with_wat("test_zome", None);
+        dna.zomes.get_mut("test_zome").unwrap().add_fn_declaration(
+            String::from("defaults_fn"),
+            vec![
+                FnParameter::new("title", "String"),
+                FnParameter::new("count", "u32").with_default(serde_json::json!(10)),
+            ],
+            vec![],
+        );
+        let (_instance, context) =
+            test_instance_and_context(dna, None).expect("Could not initialize test instance");
+        let call_with = |parameters: &str| {
+            ZomeFnCall::new(
+                "test_zome",
+                dummy_capability_request(),
+                "defaults_fn",
+                JsonString::from_json(parameters),
+            )
+        };
+
+        // omitted parameter with a default gets filled in
+        let call = with_default_parameters(&context, call_with(r#"{"title":"hello"}"#)).unwrap();
+        assert_eq!(
+            call.parameters,
+            JsonString::from_json(r#"{"title":"hello","count":10}"#)
+        );
+
+        // given parameters take precedence over defaults
+        let call =
+            with_default_parameters(&context, call_with(r#"{"title":"hello","count":2}"#)).unwrap();
+        assert_eq!(
+            call.parameters,
+            JsonString::from_json(r#"{"title":"hello","count":2}"#)
+        );
+
+        // omitted parameter without a default
+        assert_eq!(
+            with_default_parameters(&context, call_with(r#"{"count":2}"#)).err(),
+            Some(HolochainError::ErrorGeneric(
+                "Call to defaults_fn is missing parameter title, which has no default".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_read_only_fn_needs_no_grant() {
+        let mut dna = test_utils::create_test_dna_with_wat("test_zome", None);
+        {
+            let zome = dna.zomes.get_mut("test_zome").unwrap();
+            zome.add_fn_declaration(String::from("read_fn"), vec![], vec![]);
+            zome.add_fn_declaration(String::from("write_fn"), vec![], vec![]);
+            let mut read_only_fns = TraitFns::new();
+            read_only_fns.functions = vec![String::from("read_fn")];
+            zome.traits.insert(
+                ReservedTraitNames::ReadOnly.as_str().to_string(),
+                read_only_fns,
+            );
+        }
+        let (_instance, context) =
+            test_instance_and_context(dna, None).expect("Could not initialize test instance");
+
+        // a token nobody granted
+        let read_call = ZomeFnCall::new("test_zome", dummy_capability_request(), "read_fn", "{}");
+        assert!(validate_call(context.clone(), &read_call).is_ok());
+
+        let write_call = ZomeFnCall::new("test_zome", dummy_capability_request(), "write_fn", "{}");
+        assert_eq!(
+            validate_call(context.clone(), &write_call).err(),
+            Some(HolochainError::CapabilityCheckFailed)
+        );
+    }
+
+    #[test]
+    fn test_can_call() {
+        let dna = test_utils::create_test_dna_with_wat("test_zome", None);
+        let (_instance, context) =
+            test_instance_and_context(dna, None).expect("Could not initialize test instance");
+        let context2 = test_context("bob", None);
+
+        let commit_grant = |cap_type, assignees: Option<Vec<Address>>, function: &str| {
+            let mut cap_functions = CapFunctions::new();
+            cap_functions.insert("test_zome".to_string(), vec![String::from(function)]);
+            let grant = CapTokenGrant::create("foo", cap_type, assignees, cap_functions).unwrap();
+            context
+                .block_on(author_entry(
+                    &Entry::CapTokenGrant(grant),
+                    None,
+                    &context,
+                    &vec![],
+                ))
+                .unwrap()
+                .address()
+        };
+        let call_from = |caller: &Arc<Context>, token: &Address, cap_fn: &str| {
+            ZomeFnCall::new(
+                "test_zome",
+                make_cap_request_for_call(caller.clone(), token.clone(), "test_zome", cap_fn, "{}")
+                    .unwrap(),
+                "public_test_fn",
+                "{}",
+            )
+        };
+
+        let token = commit_grant(CapabilityType::Public, None, "public_test_fn");
+        assert!(can_call(
+            context.clone(),
+            &call_from(&context, &token, "public_test_fn")
+        ));
+        let bad_token = Address::from("bad token");
+        assert!(!can_call(
+            context.clone(),
+            &call_from(&context, &bad_token, "public_test_fn")
+        ));
+
+        let token = commit_grant(CapabilityType::Transferable, None, "other_fn");
+        assert!(!can_call(
+            context.clone(),
+            &call_from(&context, &token, "public_test_fn")
+        ));
+
+        let token = commit_grant(CapabilityType::Transferable, None, "public_test_fn");
+        assert!(can_call(
+            context.clone(),
+            &call_from(&context, &token, "public_test_fn")
+        ));
+        // should work with same token from a different adddress
+        assert!(can_call(
+            context.clone(),
+            &call_from(&context2, &token, "public_test_fn")
+        ));
+        // call with cap_request for a different function than the zome call
+        assert!(!can_call(
+            context.clone(),
+            &call_from(&context, &token, "foo-fn")
+        ));
+
+        let assignees = Some(vec![context.agent_id.address()]);
+        let token = commit_grant(CapabilityType::Assigned, assignees, "public_test_fn");
+        assert!(can_call(
+            context.clone(),
+            &call_from(&context, &token, "public_test_fn")
+        ));
+        // should NOT work with same token from a different adddress
+        assert!(!can_call(
+            context.clone(),
+            &call_from(&context2, &token, "public_test_fn")
+        ));
+
+        // calls that wouldn't get past the lookup aren't allowed either
+        let call = ZomeFnCall::create(
+            context.clone(),
+            "test_zome",
+            context.agent_id.address(),
+            "missing_fn",
+            "{}",
+        )
+        .unwrap();
+        assert!(!can_call(context.clone(), &call));
+    }
+
+    #[test]
+    fn test_can_call_has_no_side_effects() {
+        let dna = test_utils::create_test_dna_with_wat("test_zome", None);
+        let (_instance, context) =
+            test_instance_and_context(dna, None).expect("Could not initialize test instance");
+        let (audit_tx, audit_rx) = crossbeam_channel::unbounded();
+        let mut context = (*context).clone();
+        context.set_audit_sink(Arc::new(ChannelAuditSink::new(audit_tx)));
+        let context = Arc::new(context);
+
+        let mut cap_functions = CapFunctions::new();
+        cap_functions.insert(
+            "test_zome".to_string(),
+            vec![String::from("public_test_fn")],
+        );
+        let grant = CapTokenGrant::create("foo", CapabilityType::Transferable, None, cap_functions)
+            .unwrap()
+            .with_rate_limit(RateLimit::new(1, 60));
+        let token = context
+            .block_on(author_entry(
+                &Entry::CapTokenGrant(grant),
+                None,
+                &context,
+                &vec![],
+            ))
+            .unwrap()
+            .address();
+        let call = ZomeFnCall::create(context.clone(), "test_zome", token, "public_test_fn", "{}")
+            .unwrap();
+
+        // asking doesn't use up the single call the grant allows, nor gets audited
+        assert!(can_call(context.clone(), &call));
+        assert!(can_call(context.clone(), &call));
+        assert!(audit_rx.try_recv().is_err());
+
+        assert!(validate_call(context.clone(), &call).is_ok());
+        assert!(!can_call(context.clone(), &call));
+    }
+
+    #[test]
+    fn test_self_call_skips_chain_scan() {
+        use crate::nucleus::actions::get_entry::AGENT_CHAIN_LOOKUPS;
+        let lookups = || AGENT_CHAIN_LOOKUPS.with(|lookups| lookups.get());
+
+        let dna = test_utils::create_test_dna_with_wat("test_zome", None);
+        let (_instance, context) =
+            test_instance_and_context(dna, None).expect("Could not initialize test instance");
+
+        let token = context.agent_id.address();
+        let call = ZomeFnCall::create(context.clone(), "test_zome", token, "public_test_fn", "{}")
+            .unwrap();
+        let before = lookups();
+        assert!(validate_call(context.clone(), &call).is_ok());
+        assert!(can_call(context.clone(), &call));
+        assert!(!check_capability(context.clone(), &call));
+        assert_eq!(lookups(), before);
+
+        // calls with a badly signed agent token are still refused
+        let mut bad_call = call.clone();
+        bad_call.parameters = JsonString::from_json("{\"x\":1}");
+        assert!(validate_call(context.clone(), &bad_call).is_err());
+
+        // calls with any other token still look for their grant
+        let call = ZomeFnCall::create(
+            context.clone(),
+            "test_zome",
+            Address::from("some token"),
+            "public_test_fn",
+            "{}",
+        )
+        .unwrap();
+        let before = lookups();
+        assert!(validate_call(context.clone(), &call).is_err());
+        assert_eq!(lookups(), before + 1);
+    }
+
+    #[test]
+    fn test_call_result_future_resolves_from_mock_state() {
+        let (action_tx, _action_rx) = unbounded();
+        let (observer_tx, _observer_rx) = unbounded();
+        let mut context =
+            (*test_context_with_channels("alice", &action_tx, &observer_tx, None)).clone();
+        let state = Arc::new(RwLock::new(StateWrapper::new(Arc::new(context.clone()))));
+        context.set_state(state.clone());
+        let zome_call = test_zome_call();
+
+        let future = CallResultFuture {
+            context: Arc::new(context),
+            zome_call: zome_call.clone(),
+            send_failure: Arc::new(Mutex::new(None)),
+        };
+        let mut steps = 0;
+        let result = block_on_stepwise(future, 5, |step| {
+            steps += 1;
+            // the call only gets its result after it was signalled
+            let action = match step {
+                0 => Action::SignalZomeFunctionCall(zome_call.clone()),
+                _ => Action::ReturnZomeFunctionResult(ExecuteZomeFnResponse::new(
+                    zome_call.clone(),
+                    Ok("foo".into()),
+                )),
+            };
+            let new_state = state.read().unwrap().reduce(ActionWrapper::new(action));
+            *state.write().unwrap() = new_state;
+        });
+        assert_eq!(result, Ok("foo".into()));
+        assert_eq!(steps, 2);
+    }
 }
@@ -30,10 +30,19 @@ pub(crate) fn get_entry_from_cas(
     Ok(entry)
 }
 
+#[cfg(test)]
+thread_local! {
+    /// How often this thread looked through the agent's chain, so that tests can check
+    /// which code paths skip the scan
+    pub(crate) static AGENT_CHAIN_LOOKUPS: std::cell::Cell<usize> = std::cell::Cell::new(0);
+}
+
 pub fn get_entry_from_agent_chain(
     context: &Arc<Context>,
     address: &Address,
 ) -> Result<Option<Entry>, HolochainError> {
+    #[cfg(test)]
+    AGENT_CHAIN_LOOKUPS.with(|lookups| lookups.set(lookups.get() + 1));
     let agent = context.state().unwrap().agent();
     let top_header = agent.top_chain_header();
     let maybe_header = &agent
@@ -207,9 +207,7 @@ impl Future for InitializationFuture {
         if Instant::now().duration_since(self.created_at)
             > Duration::from_secs(INITIALIZATION_TIMEOUT)
         {
-            return Poll::Ready(Err(HolochainError::ErrorGeneric(
-                "Timeout while initializing".to_string(),
-            )));
+            return Poll::Ready(Err(HolochainError::Timeout));
         }
         if let Some(state) = self.context.state() {
             match state.nucleus().status {
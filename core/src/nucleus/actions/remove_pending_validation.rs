@@ -17,3 +17,17 @@ pub fn remove_pending_validation(
         ActionWrapper::new(Action::RemovePendingValidation((address, workflow))),
     );
 }
+
+/// Like `remove_pending_validation`, but for when validating the entry failed,
+/// so that the failure shows up in consistency signals
+pub fn fail_pending_validation(
+    address: Address,
+    workflow: ValidatingWorkflow,
+    reason: String,
+    context: &Arc<Context>,
+) {
+    dispatch_action(
+        context.action_channel(),
+        ActionWrapper::new(Action::FailPendingValidation((address, workflow, reason))),
+    );
+}
@@ -15,14 +15,18 @@ pub use crate::{
         reducers::reduce,
     },
 };
-use holochain_core_types::{dna::capabilities::CapabilityRequest, error::HcResult};
+use holochain_core_types::{
+    dna::capabilities::CapabilityRequest,
+    error::{HcResult, HolochainError},
+};
 
 use holochain_persistence_api::cas::content::Address;
 
 use holochain_json_api::json::JsonString;
 
+use serde::Serialize;
 use snowflake;
-use std::sync::Arc;
+use std::{fmt::Debug, hash::Hash, sync::Arc};
 
 /// Struct holding data for requesting the execution of a Zome function (ExecutionZomeFunction Action)
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize)]
@@ -32,6 +36,18 @@ pub struct ZomeFnCall {
     pub cap: CapabilityRequest,
     pub fn_name: String,
     pub parameters: JsonString,
+    /// Optional id supplied by the caller, echoed back with the result so that
+    /// clients multiplexing calls over one connection can correlate responses
+    pub client_request_id: Option<String>,
+    /// The agent address of the calling instance if the call came in over a bridge.
+    /// Bridge calls can only be authorized by bridge grants assigned to that caller.
+    pub bridge_caller: Option<Address>,
+    /// Further capability requests the call can be authorized by,
+    /// for callers holding several tokens that each grant different functions
+    pub additional_caps: Vec<CapabilityRequest>,
+    /// Hash of the DNA the caller expects to be talking to.
+    /// If given, the call is rejected unless the instance runs exactly that DNA.
+    pub expected_dna_hash: Option<Address>,
 }
 
 impl ZomeFnCall {
@@ -49,23 +65,72 @@ impl ZomeFnCall {
             cap: cap,
             fn_name: function.to_string(),
             parameters: parameters.into(),
+            client_request_id: None,
+            bridge_caller: None,
+            additional_caps: Vec::new(),
+            expected_dna_hash: None,
         }
     }
 
+    /// Like `new`, but takes the parameters as any serializable value and turns them into
+    /// JSON with serde, so that callers with typed parameters don't have to build the JSON
+    /// string themselves.
+    pub fn with_params<P: Serialize>(
+        zome: &str,
+        cap: CapabilityRequest,
+        function: &str,
+        params: &P,
+    ) -> HcResult<Self> {
+        let parameters = JsonString::from_json(&serde_json::to_string(params)?);
+        Ok(ZomeFnCall::new(zome, cap, function, parameters))
+    }
+
+    /// Tags this call with a caller-supplied request id.
+    /// The id plays no part in capability checks.
+    pub fn with_client_request_id<S: Into<String>>(mut self, client_request_id: S) -> Self {
+        self.client_request_id = Some(client_request_id.into());
+        self
+    }
+
+    /// Marks this call as coming in over a bridge from the instance identified by `caller`.
+    pub fn with_bridge_caller(mut self, caller: Address) -> Self {
+        self.bridge_caller = Some(caller);
+        self
+    }
+
+    /// Adds another capability request the call may be authorized by.
+    /// The call goes through if any one of its requests is granted the function.
+    pub fn with_additional_cap(mut self, cap: CapabilityRequest) -> Self {
+        self.additional_caps.push(cap);
+        self
+    }
+
+    /// Makes the call fail with `HolochainError::DnaHashMismatch` unless the instance's DNA
+    /// has the given hash, for clients that depend on a specific version of a DNA.
+    pub fn with_expected_dna_hash(mut self, dna_hash: Address) -> Self {
+        self.expected_dna_hash = Some(dna_hash);
+        self
+    }
+
+    /// All capability requests of this call, starting with `cap`
+    pub fn caps(&self) -> impl Iterator<Item = &CapabilityRequest> {
+        std::iter::once(&self.cap).chain(self.additional_caps.iter())
+    }
+
     pub fn create<J: Into<JsonString>>(
         context: Arc<Context>,
         zome: &str,
         token: Address,
         function: &str,
         parameters: J,
-    ) -> Self {
+    ) -> HcResult<Self> {
         let params = parameters.into();
-        ZomeFnCall::new(
+        Ok(ZomeFnCall::new(
             zome,
-            make_cap_request_for_call(context, token, function, params.clone()),
+            make_cap_request_for_call(context, token, zome, function, params.clone())?,
             function,
             params,
-        )
+        ))
     }
 
     pub fn same_fn_as(&self, fn_call: &ZomeFnCall) -> bool {
@@ -78,6 +143,22 @@ impl ZomeFnCall {
         self.cap.cap_token.clone()
     }
 
+    /// Identifies the call by what it asks for, ignoring the per-call id, so that results
+    /// of pure functions can be memoized across logically identical calls.
+    pub fn cache_key(&self) -> impl Hash + Eq + Clone + Debug {
+        (
+            self.zome_name.clone(),
+            self.fn_name.clone(),
+            self.parameters.clone(),
+            self.caps()
+                .map(|cap| cap.cap_token.clone())
+                .collect::<Vec<_>>(),
+            // these change how the call gets authorized
+            self.bridge_caller.clone(),
+            self.expected_dna_hash.clone(),
+        )
+    }
+
     pub fn id(&self) -> snowflake::ProcessUniqueId {
         self.id.clone()
     }
@@ -85,6 +166,40 @@ impl ZomeFnCall {
 
 pub type ZomeFnResult = HcResult<JsonString>;
 
+/// A zome call's result with the zome's own errors told apart from calls that failed to run.
+/// Zome functions report errors by returning them as `{"Err": ...}`, which a `ZomeFnResult`
+/// can't distinguish from a successful value.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ZomeCallOutcome {
+    /// The zome function ran and returned this value
+    Ok(JsonString),
+    /// The zome function ran and returned this error, taken out of its `{"Err": ...}` envelope
+    AppError(JsonString),
+    /// The call couldn't be run or the zome function crashed
+    Failed(HolochainError),
+}
+
+impl From<ZomeFnResult> for ZomeCallOutcome {
+    fn from(result: ZomeFnResult) -> Self {
+        let json = match result {
+            Ok(json) => json,
+            Err(error) => return ZomeCallOutcome::Failed(error),
+        };
+        // only an object with the single key "Err" is an error envelope
+        match serde_json::from_str::<serde_json::Value>(&json.to_string()) {
+            Ok(serde_json::Value::Object(ref fields)) if fields.len() == 1 => {
+                match fields.get("Err") {
+                    Some(error) => {
+                        ZomeCallOutcome::AppError(JsonString::from_json(&error.to_string()))
+                    }
+                    None => ZomeCallOutcome::Ok(json),
+                }
+            }
+            _ => ZomeCallOutcome::Ok(json),
+        }
+    }
+}
+
 /// Struct holding data for requesting the execution of a callback function
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct CallbackFnCall {
@@ -111,6 +226,7 @@ impl CallbackFnCall {
 pub mod tests {
     use super::*;
     use crate::{
+        action::Action,
         instance::{
             tests::{test_context, test_instance_and_context},
             Instance,
@@ -159,29 +275,35 @@ pub mod tests {
     /// test capability call
     pub fn test_capability_request<J: Into<JsonString>>(
         context: Arc<Context>,
+        zome: &str,
         function: &str,
         parameters: J,
     ) -> CapabilityRequest {
         make_cap_request_for_call(
             context.clone(),
             dummy_capability_token(),
+            zome,
             function,
             parameters,
         )
+        .unwrap()
     }
 
     /// test self agent capability call
     pub fn test_agent_capability_request<J: Into<JsonString>>(
         context: Arc<Context>,
+        zome: &str,
         function: &str,
         parameters: J,
     ) -> CapabilityRequest {
         make_cap_request_for_call(
             context.clone(),
             Address::from(context.agent_id.address()),
+            zome,
             function,
             parameters,
         )
+        .unwrap()
     }
     /// dummy capability call
     pub fn dummy_capability_request() -> CapabilityRequest {
@@ -247,6 +369,47 @@ pub mod tests {
         assert_eq!(test_call_response().result(), Ok("foo".into()),);
     }
 
+    #[test]
+    /// tests that zome errors are told apart from successful values
+    fn test_call_outcome_app_error() {
+        let envelope = JsonString::from_json(r#"{"Err":{"Internal":"not allowed"}}"#);
+        assert_eq!(
+            ZomeCallOutcome::from(Ok(envelope)),
+            ZomeCallOutcome::AppError(JsonString::from_json(r#"{"Internal":"not allowed"}"#)),
+        );
+
+        let value = JsonString::from(RawString::from(1337));
+        assert_eq!(
+            ZomeCallOutcome::from(Ok(value.clone())),
+            ZomeCallOutcome::Ok(value)
+        );
+
+        // values that merely have an "Err" field aren't errors
+        let value = JsonString::from_json(r#"{"Err":"boom","Ok":null}"#);
+        assert_eq!(
+            ZomeCallOutcome::from(Ok(value.clone())),
+            ZomeCallOutcome::Ok(value)
+        );
+    }
+
+    #[test]
+    /// tests that calls which couldn't run are reported as failed
+    fn test_call_outcome_failed() {
+        let dna = test_utils::create_test_dna_with_wat("test_zome", None);
+        let (_instance, context) =
+            test_instance_and_context(dna, None).expect("Could not initialize test instance");
+
+        let call = ZomeFnCall::new("test_zome", dummy_capability_request(), "xxx", "{}");
+        let result = context.block_on(call_zome_function(call, context.clone()));
+
+        assert_eq!(
+            ZomeCallOutcome::from(result),
+            ZomeCallOutcome::Failed(HolochainError::Dna(DnaError::ZomeFunctionNotFound(
+                String::from("Zome function 'xxx' not found in Zome 'test_zome'")
+            ))),
+        );
+    }
+
     #[test]
     /// smoke test the init of a nucleus
     fn can_instantiate_nucleus_state() {
@@ -272,7 +435,7 @@ pub mod tests {
 
         // Create zome function call
         let zome_call =
-            ZomeFnCall::create(context.clone(), "test_zome", token, "public_test_fn", "");
+            ZomeFnCall::create(context.clone(), "test_zome", token, "public_test_fn", "").unwrap();
 
         let result = context.block_on(call_zome_function(zome_call, context.clone()));
 
@@ -281,7 +444,44 @@ pub mod tests {
     }
 
     #[test]
-    /// tests that calling an invalid DNA returns the correct error
+    /// tests that a caller-supplied request id is echoed back with the call's result
+    fn test_call_zome_function_with_client_request_id() {
+        let dna = test_utils::create_test_dna_with_wat("test_zome", None);
+        let test_setup = setup_test(dna, "test_call_zome_function_with_client_request_id");
+        let context = test_setup.context.clone();
+        let token = context.get_public_token().unwrap();
+
+        let zome_call =
+            ZomeFnCall::create(context.clone(), "test_zome", token, "public_test_fn", "")
+                .unwrap()
+                .with_client_request_id("req-42");
+        let call_id = zome_call.id();
+
+        let result = context.block_on(call_zome_function(zome_call, context.clone()));
+        assert_eq!(JsonString::from(RawString::from(1337)), result.unwrap());
+
+        // the response that was actually dispatched, not one rebuilt from the call
+        let response = context
+            .state()
+            .unwrap()
+            .history()
+            .into_iter()
+            .find_map(|action_wrapper| match action_wrapper.action() {
+                Action::ReturnZomeFunctionResult(response) if response.call().id() == call_id => {
+                    Some(response.clone())
+                }
+                _ => None,
+            })
+            .expect("the call's result should have been returned");
+        assert_eq!(response.client_request_id(), Some("req-42".to_string()));
+        assert_eq!(
+            response.result(),
+            Ok(JsonString::from(RawString::from(1337)))
+        );
+    }
+
+    #[test]
+    /// tests that calling an instance without DNA explains how to set one up
     fn call_ribosome_wrong_dna() {
         let netname = Some("call_ribosome_wrong_dna");
         let mut instance = Instance::new(test_context("janet", netname));
@@ -296,8 +496,11 @@ pub mod tests {
         let result = context.block_on(call_zome_function(call, context.clone()));
 
         match result {
-            Err(HolochainError::DnaMissing) => {}
-            _ => assert!(false),
+            Err(HolochainError::DnaMissing(message)) => {
+                assert!(message.contains("no DNA is installed"), message);
+                assert!(message.contains("initialize the instance"), message);
+            }
+            other => panic!("unexpected result: {:?}", other),
         }
     }
 
@@ -373,4 +576,64 @@ pub mod tests {
         assert!(!base.same_fn_as(&diff1));
         assert!(!base.same_fn_as(&diff2));
     }
+
+    #[test]
+    fn test_zomefncall_cache_key() {
+        let base = ZomeFnCall::new("yoyo", dummy_capability_request(), "fufu", "papa");
+        let copy = ZomeFnCall::new("yoyo", dummy_capability_request(), "fufu", "papa");
+        assert_ne!(base.id(), copy.id());
+        assert_eq!(base.cache_key(), copy.cache_key());
+
+        let other_params = ZomeFnCall::new("yoyo", dummy_capability_request(), "fufu", "papa1");
+        assert_ne!(base.cache_key(), other_params.cache_key());
+
+        let other_token = ZomeFnCall::new(
+            "yoyo",
+            CapabilityRequest::new(
+                Address::from("other_token"),
+                Address::from("dummy caller"),
+                Signature::fake(),
+            ),
+            "fufu",
+            "papa",
+        );
+        assert_ne!(base.cache_key(), other_token.cache_key());
+
+        let more_tokens = ZomeFnCall::new("yoyo", dummy_capability_request(), "fufu", "papa")
+            .with_additional_cap(other_token.cap.clone());
+        assert_ne!(base.cache_key(), more_tokens.cache_key());
+
+        let bridged = ZomeFnCall::new("yoyo", dummy_capability_request(), "fufu", "papa")
+            .with_bridge_caller(Address::from("caller instance"));
+        assert_ne!(base.cache_key(), bridged.cache_key());
+
+        let pinned = ZomeFnCall::new("yoyo", dummy_capability_request(), "fufu", "papa")
+            .with_expected_dna_hash(Address::from("dna hash"));
+        assert_ne!(base.cache_key(), pinned.cache_key());
+    }
+
+    #[test]
+    fn test_zomefncall_with_params() {
+        #[derive(Serialize)]
+        struct Params {
+            title: String,
+            count: u32,
+            tags: Vec<String>,
+        }
+
+        let params = Params {
+            title: "a \"quoted\" title".to_string(),
+            count: 3,
+            tags: vec!["x".to_string(), "y".to_string()],
+        };
+        let call =
+            ZomeFnCall::with_params("yoyo", dummy_capability_request(), "fufu", &params).unwrap();
+
+        assert_eq!(call.zome_name, "yoyo");
+        assert_eq!(call.fn_name, "fufu");
+        assert_eq!(
+            call.parameters,
+            JsonString::from_json(r#"{"title":"a \"quoted\" title","count":3,"tags":["x","y"]}"#),
+        );
+    }
 }
@@ -1,5 +1,7 @@
 mod add_pending_validation;
 pub mod init_application;
+mod record_cap_token_call;
+mod record_cap_token_use;
 mod remove_pending_validation;
 pub mod return_initialization_result;
 pub mod return_validation_package;
@@ -12,6 +14,8 @@ use crate::{
         reducers::{
             add_pending_validation::reduce_add_pending_validation,
             init_application::reduce_initialize_chain,
+            record_cap_token_call::reduce_record_cap_token_call,
+            record_cap_token_use::reduce_record_cap_token_use,
             remove_pending_validation::reduce_remove_pending_validation,
             return_initialization_result::reduce_return_initialization_result,
             return_validation_package::reduce_return_validation_package,
@@ -32,12 +36,15 @@ fn resolve_reducer(action_wrapper: &ActionWrapper) -> Option<NucleusReduceFn> {
     match action_wrapper.action() {
         Action::AddPendingValidation(_) => Some(reduce_add_pending_validation),
         Action::RemovePendingValidation(_) => Some(reduce_remove_pending_validation),
+        Action::FailPendingValidation(_) => Some(reduce_remove_pending_validation),
         Action::ReturnInitializationResult(_) => Some(reduce_return_initialization_result),
         Action::InitializeChain(_) => Some(reduce_initialize_chain),
         Action::ReturnZomeFunctionResult(_) => Some(reduce_return_zome_function_result),
         Action::ReturnValidationResult(_) => Some(reduce_return_validation_result),
         Action::ReturnValidationPackage(_) => Some(reduce_return_validation_package),
         Action::SignalZomeFunctionCall(_) => Some(reduce_signal_zome_function),
+        Action::RecordCapTokenCall(_) => Some(reduce_record_cap_token_call),
+        Action::RecordCapTokenUse(_) => Some(reduce_record_cap_token_use),
         _ => None,
     }
 }
@@ -0,0 +1,67 @@
+use crate::{
+    action::{Action, ActionWrapper},
+    nucleus::state::NucleusState,
+    state::State,
+};
+
+/// Reduce RecordCapTokenCall Action.
+/// Adds the call time to the token's list of recent calls, dropping those that
+/// are already outside of the rate limit's window.
+#[allow(unknown_lints)]
+#[allow(needless_pass_by_value)]
+pub fn reduce_record_cap_token_call(
+    state: &mut NucleusState,
+    _root_state: &State,
+    action_wrapper: &ActionWrapper,
+) {
+    let action = action_wrapper.action();
+    let (token, rate_limit, call_time) = unwrap_to!(action => Action::RecordCapTokenCall).clone();
+    let calls = state.cap_token_calls.entry(token).or_insert_with(Vec::new);
+    calls.retain(|previous_call| rate_limit.in_window(previous_call, call_time));
+    calls.push(call_time);
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::{
+        instance::tests::test_context, nucleus::state::tests::test_nucleus_state, state::test_store,
+    };
+    use holochain_core_types::entry::cap_entries::RateLimit;
+    use holochain_persistence_api::cas::content::Address;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn test_reduce_record_cap_token_call() {
+        let context = test_context("jimmy", None);
+        let mut nucleus_state = test_nucleus_state();
+        let state = test_store(context);
+
+        let token = Address::from("some_token");
+        let rate_limit = RateLimit::new(2, 60);
+        let start = SystemTime::now();
+
+        for call_time in vec![start, start + Duration::from_secs(1)] {
+            let action_wrapper = ActionWrapper::new(Action::RecordCapTokenCall((
+                token.clone(),
+                rate_limit.clone(),
+                call_time,
+            )));
+            reduce_record_cap_token_call(&mut nucleus_state, &state, &action_wrapper);
+        }
+        assert_eq!(nucleus_state.cap_token_calls[&token].len(), 2);
+
+        // a call after the window has passed clears out the stale first call
+        let late_call = start + Duration::from_secs(60);
+        let action_wrapper = ActionWrapper::new(Action::RecordCapTokenCall((
+            token.clone(),
+            rate_limit,
+            late_call,
+        )));
+        reduce_record_cap_token_call(&mut nucleus_state, &state, &action_wrapper);
+        assert_eq!(
+            nucleus_state.cap_token_calls[&token],
+            vec![start + Duration::from_secs(1), late_call]
+        );
+    }
+}
@@ -0,0 +1,43 @@
+use crate::{
+    action::{Action, ActionWrapper},
+    nucleus::state::NucleusState,
+    state::State,
+};
+
+/// Reduce RecordCapTokenUse Action.
+/// Counts one more use of the token.
+#[allow(unknown_lints)]
+#[allow(needless_pass_by_value)]
+pub fn reduce_record_cap_token_use(
+    state: &mut NucleusState,
+    _root_state: &State,
+    action_wrapper: &ActionWrapper,
+) {
+    let action = action_wrapper.action();
+    let token = unwrap_to!(action => Action::RecordCapTokenUse).clone();
+    *state.cap_token_uses.entry(token).or_insert(0) += 1;
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::{
+        instance::tests::test_context, nucleus::state::tests::test_nucleus_state, state::test_store,
+    };
+    use holochain_persistence_api::cas::content::Address;
+
+    #[test]
+    fn test_reduce_record_cap_token_use() {
+        let context = test_context("jimmy", None);
+        let mut nucleus_state = test_nucleus_state();
+        let state = test_store(context);
+
+        let token = Address::from("some_token");
+        assert_eq!(nucleus_state.cap_token_uses.get(&token), None);
+        for _ in 0..2 {
+            let action_wrapper = ActionWrapper::new(Action::RecordCapTokenUse(token.clone()));
+            reduce_record_cap_token_use(&mut nucleus_state, &state, &action_wrapper);
+        }
+        assert_eq!(nucleus_state.cap_token_uses[&token], 2);
+    }
+}
@@ -4,7 +4,7 @@ use crate::{
     state::State,
 };
 
-/// Reduce RemovePendingValidation Action.
+/// Reduce RemovePendingValidation and FailPendingValidation Actions.
 /// Removes boxed EntryWithHeader and dependencies from state, referenced with
 /// the entry's address.
 /// Corresponds to a prior AddPendingValidation Action.
@@ -15,8 +15,13 @@ pub fn reduce_remove_pending_validation(
     _root_state: &State,
     action_wrapper: &ActionWrapper,
 ) {
-    let action = action_wrapper.action();
-    let (address, workflow) = unwrap_to!(action => Action::RemovePendingValidation).clone();
+    let (address, workflow) = match action_wrapper.action() {
+        Action::RemovePendingValidation((address, workflow))
+        | Action::FailPendingValidation((address, workflow, _)) => {
+            (address.clone(), workflow.clone())
+        }
+        _ => unreachable!(),
+    };
     state
         .pending_validations
         .remove(&PendingValidationKey::new(address, workflow));
@@ -56,4 +56,22 @@ pub mod tests {
 
         assert!(state.zome_calls.contains_key(&fr.call()));
     }
+
+    #[test]
+    fn test_pending_zome_calls() {
+        let context = test_context("jimmy", None);
+        let mut state = test_nucleus_state();
+        let root_state = test_store(context);
+        let action_wrapper = test_action_wrapper_rzfr();
+        let action = action_wrapper.action();
+        let fr = unwrap_to!(action => Action::ReturnZomeFunctionResult);
+        assert!(state.pending_zome_calls().is_empty());
+
+        let signal = ActionWrapper::new(Action::SignalZomeFunctionCall(fr.call()));
+        reduce_signal_zome_function(&mut state, &root_state, &signal);
+        assert_eq!(state.pending_zome_calls(), vec![fr.call()]);
+
+        reduce_return_zome_function_result(&mut state, &root_state, &action_wrapper);
+        assert!(state.pending_zome_calls().is_empty());
+    }
 }
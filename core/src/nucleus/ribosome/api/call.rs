@@ -17,21 +17,22 @@ use wasmi::{RuntimeArgs, RuntimeValue};
 
 // ZomeFnCallArgs to ZomeFnCall
 impl ZomeFnCall {
-    fn from_args(context: Arc<Context>, args: ZomeFnCallArgs) -> Self {
+    fn from_args(context: Arc<Context>, args: ZomeFnCallArgs) -> Result<Self, HolochainError> {
         // TODO we are currently signing the call ourself.  This signature
         // should have happend at the client and be extracted from the args.
         let cap_call = make_cap_request_for_call(
             context.clone(),
             args.cap_token,
+            &args.zome_name,
             &args.fn_name,
             JsonString::from_json(&args.fn_args.clone()),
-        );
-        ZomeFnCall::new(
+        )?;
+        Ok(ZomeFnCall::new(
             &args.zome_name,
             cap_call,
             &args.fn_name,
             JsonString::from_json(&args.fn_args),
-        )
+        ))
     }
 }
 
@@ -61,7 +62,13 @@ pub fn invoke_call(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApiResult {
 
     let result = if input.instance_handle == String::from(THIS_INSTANCE) {
         // ZomeFnCallArgs to ZomeFnCall
-        let zome_call = ZomeFnCall::from_args(context.clone(), input.clone());
+        let zome_call = match ZomeFnCall::from_args(context.clone(), input.clone()) {
+            Ok(zome_call) => zome_call,
+            Err(error) => {
+                log_error!(context, "zome-to-zome-call/[{:?}]: {:?}", input, error);
+                return runtime.store_result::<JsonString>(Err(error));
+            }
+        };
 
         if let Ok(zome_call_data) = runtime.zome_call_data() {
             // Don't allow recursive calls
@@ -90,7 +97,7 @@ fn local_call(runtime: &mut Runtime, input: ZomeFnCallArgs) -> Result<JsonString
         )
     })?;
     // ZomeFnCallArgs to ZomeFnCall
-    let zome_call = ZomeFnCall::from_args(context.clone(), input);
+    let zome_call = ZomeFnCall::from_args(context.clone(), input)?;
     context.block_on(call_zome_function(zome_call, context.clone()))
 }
 
@@ -191,6 +198,7 @@ pub mod tests {
     use holochain_json_api::json::JsonString;
     use holochain_persistence_api::cas::content::{Address, AddressableContent};
     use holochain_wasm_utils::api_serialization::ZomeFnCallArgs;
+    use jsonrpc_core::IoHandler;
     use serde_json;
     use std::{
         collections::BTreeMap,
@@ -305,7 +313,9 @@ pub mod tests {
         let test_setup = setup_test(dna, "test_call_public");
         let token = test_setup.context.get_public_token().unwrap();
         let other_agent_context = test_context("other agent", None);
-        let cap_request = make_cap_request_for_call(other_agent_context, token, "test", "{}");
+        let cap_request =
+            make_cap_request_for_call(other_agent_context, token, "test_zome", "test", "{}")
+                .unwrap();
 
         // make the call with public token capability call
         test_reduce_call(&test_setup, cap_request, success_expected());
@@ -320,6 +330,35 @@ pub mod tests {
         test_reduce_call(&test_setup, cap_request, expected_failure);
     }
 
+    #[test]
+    fn test_call_fails_when_signing_fails() {
+        let dna = setup_dna_for_test(true);
+        let test_setup = setup_test(dna, "test_call_fails_when_signing_fails");
+        let token = test_setup.context.get_public_token().unwrap();
+        let args = JsonString::from_json(&String::from_utf8(test_args_bytes()).unwrap());
+        let caller = test_context("other agent", None);
+        let cap_request =
+            make_cap_request_for_call(caller, token, "test_zome", "test", args.clone()).unwrap();
+
+        // the zome calls "call", which needs the conductor to sign the call it makes
+        let mut failing_signer = IoHandler::new();
+        failing_signer.add_method("agent/sign", |_| {
+            Err::<jsonrpc_core::Value, _>(jsonrpc_core::Error::internal_error())
+        });
+        test_setup.context.conductor_api.reset(failing_signer);
+
+        let zome_call = ZomeFnCall::new("test_zome", cap_request, "test", args);
+        let result = test_setup
+            .context
+            .block_on(call_zome_function(zome_call, test_setup.context.clone()));
+        let outcome = format!("{:?}", result);
+        assert!(
+            outcome.contains("Could not sign call to test"),
+            "{}",
+            outcome
+        );
+    }
+
     #[test]
     fn test_call_transferable() {
         let dna = setup_dna_for_test(false);
@@ -335,12 +374,13 @@ pub mod tests {
         test_reduce_call(&test_setup, cap_request.clone(), expected_failure.clone());
 
         // make the call with an valid capability call from self
-        let cap_request = test_agent_capability_request(test_setup.context.clone(), "test", "{}");
+        let cap_request =
+            test_agent_capability_request(test_setup.context.clone(), "test_zome", "test", "{}");
         test_reduce_call(&test_setup, cap_request, success_expected());
 
         // make the call with an invalid valid capability call from self
         let cap_request =
-            test_agent_capability_request(test_setup.context.clone(), "some_fn", "{}");
+            test_agent_capability_request(test_setup.context.clone(), "test_zome", "some_fn", "{}");
         test_reduce_call(&test_setup, cap_request, expected_failure);
 
         let mut cap_functions = CapFunctions::new();
@@ -361,7 +401,8 @@ pub mod tests {
             .address();
         let other_agent_context = test_context("other agent", None);
         let cap_request =
-            make_cap_request_for_call(other_agent_context.clone(), addr, "test", "{}");
+            make_cap_request_for_call(other_agent_context.clone(), addr, "test_zome", "test", "{}")
+                .unwrap();
         test_reduce_call(&test_setup, cap_request, success_expected());
     }
 
@@ -382,9 +423,11 @@ pub mod tests {
         let cap_request = make_cap_request_for_call(
             test_setup.context.clone(),
             Address::from(agent_token_str.clone()),
+            "test_zome",
             "test",
             "{}",
-        );
+        )
+        .unwrap();
         test_reduce_call(&test_setup, cap_request, success_expected());
 
         // test assigned capability where the caller is someone else
@@ -413,14 +456,22 @@ pub mod tests {
         let cap_request = make_cap_request_for_call(
             test_context("random other agent", None),
             grant_addr.clone(),
+            "test_zome",
             "test",
             "{}",
-        );
+        )
+        .unwrap();
         test_reduce_call(&test_setup, cap_request, expected_failure.clone());
 
         // test assigned capability where the caller is someone else
-        let cap_request =
-            make_cap_request_for_call(other_agent_context.clone(), grant_addr, "test", "{}");
+        let cap_request = make_cap_request_for_call(
+            other_agent_context.clone(),
+            grant_addr,
+            "test_zome",
+            "test",
+            "{}",
+        )
+        .unwrap();
         test_reduce_call(&test_setup, cap_request, success_expected());
     }
 
@@ -468,9 +519,11 @@ pub mod tests {
             make_cap_request_for_call(
                 context.clone(),
                 Address::from(context.agent_id.address()), // <- agent token
+                "test_zome",
                 "foo_function",                            //<- not the function in the zome_call!
                 "{}",
-            ),
+            )
+            .unwrap(),
             "test",
             "{}",
         );
@@ -484,9 +537,11 @@ pub mod tests {
             make_cap_request_for_call(
                 context.clone(),
                 Address::from(context.agent_id.address()), // <- agent token
+                "test_zome",
                 "test",
                 "{}",
-            ),
+            )
+            .unwrap(),
             "test",
             "{}",
         );
@@ -530,9 +585,11 @@ pub mod tests {
             make_cap_request_for_call(
                 test_context("some_random_agent", None),
                 grant_addr,
+                "test_zome",
                 "test",
                 "{}",
-            ),
+            )
+            .unwrap(),
             "test",
             "{}",
         );
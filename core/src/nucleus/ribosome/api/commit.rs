@@ -42,11 +42,24 @@ pub fn invoke_commit_app_entry(runtime: &mut Runtime, args: &RuntimeArgs) -> Zom
 
 #[cfg(test)]
 pub mod tests {
-    use crate::nucleus::ribosome::{
-        api::{tests::test_zome_api_function, ZomeApiFunction},
-        Defn,
+    use crate::{
+        instance::tests::test_instance_and_context,
+        nucleus::{
+            actions::get_entry::get_entry_from_agent_chain,
+            ribosome::{
+                api::{
+                    tests::{
+                        test_function_name, test_zome_api_function, test_zome_api_function_call,
+                        test_zome_api_function_wasm, test_zome_name,
+                    },
+                    ZomeApiFunction,
+                },
+                Defn,
+            },
+        },
     };
     use holochain_core_types::{
+        dna::traits::{ReservedTraitNames, TraitFns},
         entry::test_entry,
         error::ZomeApiInternalResult,
         signature::{Provenance, Signature},
@@ -97,4 +110,30 @@ pub mod tests {
         );
     }
 
+    #[test]
+    fn test_read_only_fn_cant_commit() {
+        let wasm = test_zome_api_function_wasm(ZomeApiFunction::CommitAppEntry.as_str());
+        let mut dna = test_utils::create_test_dna_with_wasm(&test_zome_name(), wasm);
+        {
+            let zome = dna.zomes.get_mut(&test_zome_name()).unwrap();
+            let mut read_only_fns = TraitFns::new();
+            read_only_fns.functions = vec![test_function_name()];
+            zome.traits.insert(
+                ReservedTraitNames::ReadOnly.as_str().to_string(),
+                read_only_fns,
+            );
+        }
+        let (_instance, context) =
+            test_instance_and_context(dna, None).expect("Could not create test instance");
+
+        let call_result =
+            test_zome_api_function_call(context.clone(), test_commit_entry_args_bytes());
+        assert!(
+            String::from(call_result).contains("test is read-only and can't call hc_commit_entry")
+        );
+        assert_eq!(
+            get_entry_from_agent_chain(&context, &test_entry().address()),
+            Ok(None)
+        );
+    }
 }
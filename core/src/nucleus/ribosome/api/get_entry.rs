@@ -217,7 +217,12 @@ pub mod tests {
 
         let commit_call = ZomeFnCall::new(
             &test_zome_name(),
-            test_capability_request(context.clone(), "commit_dispatch", test_parameters()),
+            test_capability_request(
+                context.clone(),
+                &test_zome_name(),
+                "commit_dispatch",
+                test_parameters(),
+            ),
             "commit_dispatch",
             test_parameters(),
         );
@@ -238,7 +243,12 @@ pub mod tests {
 
         let get_call = ZomeFnCall::new(
             &test_zome_name(),
-            test_capability_request(context.clone(), "get_dispatch", test_parameters()),
+            test_capability_request(
+                context.clone(),
+                &test_zome_name(),
+                "get_dispatch",
+                test_parameters(),
+            ),
             "get_dispatch",
             test_parameters(),
         );
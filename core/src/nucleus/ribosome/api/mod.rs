@@ -156,6 +156,23 @@ link_zome_api! {
     "hc_meta",Meta,invoke_meta;
 }
 
+impl ZomeApiFunction {
+    /// whether the function commits to the source chain,
+    /// which zome functions declared read-only must not do
+    pub fn writes_to_chain(&self) -> bool {
+        match self {
+            ZomeApiFunction::CommitAppEntry
+            | ZomeApiFunction::UpdateEntry
+            | ZomeApiFunction::RemoveEntry
+            | ZomeApiFunction::LinkEntries
+            | ZomeApiFunction::RemoveLink
+            | ZomeApiFunction::CommitCapabilityGrant
+            | ZomeApiFunction::CommitCapabilityClaim => true,
+            _ => false,
+        }
+    }
+}
+
 #[cfg(test)]
 pub mod tests {
     use self::wabt::Wat2Wasm;
@@ -352,7 +369,12 @@ pub mod tests {
     pub fn test_zome_api_function_call(context: Arc<Context>, args_bytes: Vec<u8>) -> JsonString {
         let zome_call = ZomeFnCall::new(
             &test_zome_name(),
-            test_capability_request(context.clone(), &test_function_name(), test_parameters()),
+            test_capability_request(
+                context.clone(),
+                &test_zome_name(),
+                &test_function_name(),
+                test_parameters(),
+            ),
             &test_function_name(),
             test_parameters(),
         );
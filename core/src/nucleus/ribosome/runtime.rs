@@ -225,6 +225,22 @@ impl Runtime {
             Err(hc_err) => ZomeApiInternalResult::failure(core_error!(hc_err)),
         })
     }
+
+    /// whether the running zome function is declared read-only in the DNA
+    fn is_read_only_call(&self) -> bool {
+        match &self.data {
+            WasmCallData::ZomeCall(ref data) => data
+                .context
+                .get_dna()
+                .and_then(|dna| {
+                    dna.get_zome(&data.call.zome_name)
+                        .ok()
+                        .map(|zome| zome.is_fn_read_only(&data.call.fn_name))
+                })
+                .unwrap_or(false),
+            _ => false,
+        }
+    }
 }
 
 // Correlate the indexes of core API functions with a call to the actual function
@@ -234,6 +250,14 @@ impl Externals for Runtime {
         let zf = ZomeApiFunction::from_index(index);
         match zf {
             ZomeApiFunction::MissingNo => panic!("unknown function index"),
+            _ if zf.writes_to_chain() && self.is_read_only_call() => {
+                let error = HolochainError::ErrorGeneric(format!(
+                    "{} is read-only and can't call {}",
+                    self.data.fn_name(),
+                    zf.as_str()
+                ));
+                self.store_result::<JsonString>(Err(error))
+            }
             // convert the function to its callable form and call it with the given arguments
             _ => zf.as_fn()(self, &args),
         }
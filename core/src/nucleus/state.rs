@@ -15,7 +15,7 @@ use serde::{
     Deserialize, Deserializer, Serialize, Serializer,
 };
 use snowflake;
-use std::{collections::HashMap, convert::TryFrom, fmt};
+use std::{collections::HashMap, convert::TryFrom, fmt, time::SystemTime};
 
 #[derive(Clone, Debug, PartialEq, Deserialize, Serialize, DefaultJson)]
 pub enum NucleusStatus {
@@ -97,6 +97,12 @@ pub struct NucleusState {
     // Persisted fields:
     pub status: NucleusStatus,
     pub pending_validations: HashMap<PendingValidationKey, PendingValidation>,
+    /// How often each capability token that can be used up has been used.
+    /// Persisted so that restarting the instance doesn't give used up grants a fresh start.
+    pub cap_token_uses: HashMap<Address, u32>,
+    /// Times of the calls still inside the window of each rate limited capability token.
+    /// Persisted for the same reason, so that a restart doesn't reset rate limits.
+    pub cap_token_calls: HashMap<Address, Vec<SystemTime>>,
 
     // Transient fields:
     pub dna: Option<Dna>, //DNA is transient here because it is stored in the chain and gets
@@ -120,6 +126,8 @@ impl NucleusState {
             zome_calls: HashMap::new(),
             validation_results: HashMap::new(),
             validation_packages: HashMap::new(),
+            cap_token_calls: HashMap::new(),
+            cap_token_uses: HashMap::new(),
             pending_validations: HashMap::new(),
         }
     }
@@ -133,6 +141,16 @@ impl NucleusState {
             .and_then(|value| value.clone())
     }
 
+    /// Calls that have been signalled but have no result yet, in no particular order.
+    /// Meant for diagnosing calls that seem stuck.
+    pub fn pending_zome_calls(&self) -> Vec<ZomeFnCall> {
+        self.zome_calls
+            .iter()
+            .filter(|(_, result)| result.is_none())
+            .map(|(call, _)| call.clone())
+            .collect()
+    }
+
     pub fn has_initialized(&self) -> bool {
         match self.status {
             NucleusStatus::Initialized(_) => true,
@@ -167,6 +185,12 @@ impl NucleusState {
 pub struct NucleusStateSnapshot {
     pub status: NucleusStatus,
     pub pending_validations: HashMap<PendingValidationKey, PendingValidation>,
+    /// Snapshots taken before uses were counted have none
+    #[serde(default)]
+    pub cap_token_uses: HashMap<Address, u32>,
+    /// Likewise for snapshots taken before rate limited calls were kept
+    #[serde(default)]
+    pub cap_token_calls: HashMap<Address, Vec<SystemTime>>,
 }
 
 impl From<&StateWrapper> for NucleusStateSnapshot {
@@ -174,6 +198,8 @@ impl From<&StateWrapper> for NucleusStateSnapshot {
         NucleusStateSnapshot {
             status: state.nucleus().status(),
             pending_validations: state.nucleus().pending_validations.clone(),
+            cap_token_uses: state.nucleus().cap_token_uses.clone(),
+            cap_token_calls: state.nucleus().cap_token_calls.clone(),
         }
     }
 }
@@ -186,6 +212,8 @@ impl From<NucleusStateSnapshot> for NucleusState {
             zome_calls: HashMap::new(),
             validation_results: HashMap::new(),
             validation_packages: HashMap::new(),
+            cap_token_calls: snapshot.cap_token_calls,
+            cap_token_uses: snapshot.cap_token_uses,
             pending_validations: snapshot.pending_validations,
         }
     }
@@ -209,11 +237,34 @@ impl AddressableContent for NucleusStateSnapshot {
 #[cfg(test)]
 pub mod tests {
 
-    use super::NucleusState;
+    use super::*;
 
     /// dummy nucleus state
     pub fn test_nucleus_state() -> NucleusState {
         NucleusState::new()
     }
 
+    #[test]
+    fn test_snapshot_keeps_cap_token_uses_and_calls() {
+        let mut cap_token_uses = HashMap::new();
+        cap_token_uses.insert(Address::from("one-shot token"), 1);
+        let mut cap_token_calls = HashMap::new();
+        cap_token_calls.insert(Address::from("rate limited token"), vec![SystemTime::now()]);
+        let snapshot = NucleusStateSnapshot {
+            status: NucleusStatus::New,
+            pending_validations: HashMap::new(),
+            cap_token_uses: cap_token_uses.clone(),
+            cap_token_calls: cap_token_calls.clone(),
+        };
+        let restored = NucleusStateSnapshot::try_from(JsonString::from(snapshot)).unwrap();
+        let restored = NucleusState::from(restored);
+        assert_eq!(restored.cap_token_uses, cap_token_uses);
+        assert_eq!(restored.cap_token_calls, cap_token_calls);
+
+        // snapshots from before uses and calls were kept still load
+        let old_snapshot = JsonString::from_json(r#"{"status":"New","pending_validations":{}}"#);
+        let restored = NucleusState::from(NucleusStateSnapshot::try_from(old_snapshot).unwrap());
+        assert!(restored.cap_token_uses.is_empty());
+        assert!(restored.cap_token_calls.is_empty());
+    }
 }
@@ -30,3 +30,31 @@ pub fn validate_provenances(validation_data: &ValidationData) -> ValidationResul
         .collect::<Result<Vec<()>, ValidationError>>()?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use holochain_core_types::signature::{Provenance, Signature};
+    use holochain_dpki::utils::Verify;
+    use test_utils::mock_signing::mock_signature;
+
+    #[test]
+    fn mock_signatures_verify() {
+        let data = String::from("some entry address");
+        let (source, signature) = mock_signature("alice", data.clone());
+        assert_eq!(
+            mock_signature("alice", data.clone()),
+            (source.clone(), signature.clone())
+        );
+
+        let provenance = Provenance::new(source.clone(), signature);
+        assert_eq!(provenance.verify(data.clone()), Ok(true));
+        assert_eq!(provenance.verify(String::from("other data")), Ok(false));
+
+        let (_, bobs_signature) = mock_signature("bob", data.clone());
+        assert_eq!(
+            Provenance::new(source.clone(), bobs_signature).verify(data.clone()),
+            Ok(false)
+        );
+        assert!(Provenance::new(source, Signature::fake()).verify(data) != Ok(true));
+    }
+}
@@ -1,4 +1,5 @@
 pub mod pending_validations;
+pub mod query_chunks;
 pub mod state_dump;
 
 use crate::context::Context;
@@ -11,5 +12,6 @@ pub fn create_callback(context: Arc<Context>) -> impl 'static + FnMut() + Sync +
             state_dump::state_dump(context.clone());
         }
         pending_validations::run_pending_validations(context.clone());
+        query_chunks::time_out_query_chunks(context.clone());
     }
 }
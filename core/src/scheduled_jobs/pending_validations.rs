@@ -1,7 +1,9 @@
 use crate::{
     context::Context,
     network::entry_with_header::EntryWithHeader,
-    nucleus::actions::remove_pending_validation::remove_pending_validation,
+    nucleus::actions::remove_pending_validation::{
+        fail_pending_validation, remove_pending_validation,
+    },
     workflows::{hold_entry::hold_entry_workflow, hold_link::hold_link_workflow},
 };
 use holochain_core_types::error::HolochainError;
@@ -102,12 +104,16 @@ fn retry_validation(pending: PendingValidation, context: Arc<Context>) {
                     context.clone(),
                 )),
             };
-            if Err(HolochainError::ValidationPending) != result {
-                remove_pending_validation(
-                    pending.entry_with_header.entry.address(),
+            let address = pending.entry_with_header.entry.address();
+            match result {
+                Err(HolochainError::ValidationPending) => (),
+                Err(error) => fail_pending_validation(
+                    address,
                     pending.workflow.clone(),
+                    error.to_string(),
                     &context,
-                );
+                ),
+                Ok(_) => remove_pending_validation(address, pending.workflow.clone(), &context),
             }
         })
         .expect("Could not spawn thread for retry_validation");
@@ -0,0 +1,28 @@
+use crate::{
+    action::{Action, ActionWrapper},
+    context::Context,
+    instance::dispatch_action,
+    network::state::QUERY_CHUNK_TIMEOUT,
+};
+use std::{sync::Arc, time::SystemTime};
+
+/// Gives up on queries whose results stopped coming in chunk by chunk,
+/// resolving them like queries that got no response at all
+pub fn time_out_query_chunks(context: Arc<Context>) {
+    let timed_out = match context.state() {
+        Some(state) => state
+            .network()
+            .timed_out_query_chunks(QUERY_CHUNK_TIMEOUT, SystemTime::now()),
+        None => return,
+    };
+    for key in timed_out {
+        log_debug!(context,
+            "scheduled_jobs/time_out_query_chunks: chunks of {:?} stopped coming in",
+            key
+        );
+        dispatch_action(
+            context.action_channel(),
+            ActionWrapper::new(Action::QueryTimeout(key)),
+        );
+    }
+}
@@ -17,7 +17,11 @@ pub async fn initialize(
     context: Arc<Context>,
 ) -> HcResult<Arc<Context>> {
     let instance_context = instance.initialize_context(context.clone());
-    let dna = dna.ok_or(HolochainError::DnaMissing)?;
+    let dna = dna.ok_or_else(|| {
+        HolochainError::DnaMissing(String::from(
+            "none was given to initialize the instance with",
+        ))
+    })?;
     if let Err(err) = await!(get_dna_and_agent(&instance_context)) {
         log_warn!(context,
             "dna/initialize: Couldn't get DNA and agent from chain: {:?}",
@@ -1,11 +1,17 @@
 //! File holding all the structs for handling function declarations defined in DNA.
 
+use serde_json::Value;
+use std::hash::{Hash, Hasher};
+
 /// Represents the type declaration for zome function parameter
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Hash)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct FnParameter {
     #[serde(rename = "type")]
     pub parameter_type: String,
     pub name: String,
+    /// Value used for this parameter when a call leaves it out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<Value>,
 }
 
 impl FnParameter {
@@ -14,8 +20,24 @@ impl FnParameter {
         FnParameter {
             name: n.into(),
             parameter_type: t.into(),
+            default: None,
         }
     }
+
+    /// Makes the parameter optional for callers, falling back to `default` if they omit it
+    pub fn with_default(mut self, default: Value) -> FnParameter {
+        self.default = Some(default);
+        self
+    }
+}
+
+// serde_json::Value isn't Hash, so the default gets hashed as its JSON text
+impl Hash for FnParameter {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.parameter_type.hash(state);
+        self.name.hash(state);
+        self.default.as_ref().map(Value::to_string).hash(state);
+    }
 }
 
 /// Represents a zome function declaration
@@ -130,4 +152,25 @@ mod tests {
 
         assert_eq!(fixture, trt);
     }
+
+    #[test]
+    fn test_fn_parameter_default_roundtrip() {
+        let fixture: FnParameter = serde_json::from_str(
+            r#"{
+                "name": "count",
+                "type": "u32",
+                "default": 10
+            }"#,
+        )
+        .unwrap();
+        let parameter = FnParameter::new("count", "u32").with_default(json!(10));
+        assert_eq!(fixture, parameter);
+
+        let serialized = serde_json::to_string(&parameter).unwrap();
+        assert_eq!(serialized, r#"{"type":"u32","name":"count","default":10}"#);
+        assert_eq!(
+            serde_json::to_string(&FnParameter::new("count", "u32")).unwrap(),
+            r#"{"type":"u32","name":"count"}"#
+        );
+    }
 }
@@ -11,6 +11,9 @@ pub enum ReservedTraitNames {
 
     /// used for declaring functions that will auto-generate a public grant during init
     Public,
+
+    /// used for declaring functions that only read state and so can be called without a grant
+    ReadOnly,
 }
 
 impl FromStr for ReservedTraitNames {
@@ -18,6 +21,7 @@ impl FromStr for ReservedTraitNames {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "hc_public" => Ok(ReservedTraitNames::Public),
+            "hc_read_only" => Ok(ReservedTraitNames::ReadOnly),
             _ => Err("Cannot convert string to ReservedTraitNames"),
         }
     }
@@ -27,6 +31,7 @@ impl ReservedTraitNames {
     pub fn as_str(&self) -> &'static str {
         match *self {
             ReservedTraitNames::Public => "hc_public",
+            ReservedTraitNames::ReadOnly => "hc_read_only",
             ReservedTraitNames::MissingNo => "",
         }
     }
@@ -43,6 +48,10 @@ mod tests {
             Ok(ReservedTraitNames::Public),
             ReservedTraitNames::from_str("hc_public"),
         );
+        assert_eq!(
+            Ok(ReservedTraitNames::ReadOnly),
+            ReservedTraitNames::from_str("hc_read_only"),
+        );
         assert_eq!(
             Err("Cannot convert string to ReservedTraitNames"),
             ReservedTraitNames::from_str("foo"),
@@ -53,5 +62,6 @@ mod tests {
     /// test that a canonical string can be created from ReservedTraitNames
     fn test_reserved_traits_as_str() {
         assert_eq!(ReservedTraitNames::Public.as_str(), "hc_public");
+        assert_eq!(ReservedTraitNames::ReadOnly.as_str(), "hc_read_only");
     }
 }
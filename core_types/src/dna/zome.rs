@@ -147,6 +147,14 @@ impl Zome {
             trait_name == pub_trait && trait_fns.functions.contains(&fn_name.to_owned())
         })
     }
+
+    // Helper function for finding out if a given function is declared read-only
+    pub fn is_fn_read_only(&self, fn_name: &str) -> bool {
+        self.traits
+            .get(ReservedTraitNames::ReadOnly.as_str())
+            .map(|trait_fns| trait_fns.functions.contains(&fn_name.to_owned()))
+            .unwrap_or(false)
+    }
 }
 
 #[cfg(test)]
@@ -211,14 +219,11 @@ pub mod tests {
         zome.add_fn_declaration(
             String::from("hello"),
             vec![],
-            vec![FnParameter {
-                name: String::from("greeting"),
-                parameter_type: String::from("String"),
-            }],
+            vec![FnParameter::new("greeting", "String")],
         );
         assert_eq!(zome.fn_declarations.len(), 1);
 
-        let expected = "[FnDeclaration { name: \"hello\", inputs: [], outputs: [FnParameter { parameter_type: \"String\", name: \"greeting\" }] }]";
+        let expected = "[FnDeclaration { name: \"hello\", inputs: [], outputs: [FnParameter { parameter_type: \"String\", name: \"greeting\", default: None }] }]";
         assert_eq!(expected, format!("{:?}", zome.fn_declarations),);
     }
 
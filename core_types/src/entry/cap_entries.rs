@@ -4,7 +4,11 @@ use holochain_persistence_api::cas::content::{Address, AddressableContent};
 
 use holochain_json_api::{error::JsonError, json::JsonString};
 
-use std::{collections::BTreeMap, str::FromStr};
+use std::{
+    collections::BTreeMap,
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
 
 //--------------------------------------------------------------------------------------------------
 // CapabilityType
@@ -15,6 +19,8 @@ use std::{collections::BTreeMap, str::FromStr};
 /// this functions like a password in that you can give the token to someone else and it works.
 /// Assigned capabilities check the request's signature against the list of agents to which
 /// the capability has been granted.
+/// Bridge capabilities are only valid for calls coming in over a bridge, and check the
+/// calling instance against the list of bridge callers the capability has been granted to.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Hash)]
 pub enum CapabilityType {
     #[serde(rename = "public")]
@@ -23,6 +29,8 @@ pub enum CapabilityType {
     Transferable,
     #[serde(rename = "assigned")]
     Assigned,
+    #[serde(rename = "bridge")]
+    Bridge,
 }
 
 impl Default for CapabilityType {
@@ -62,6 +70,19 @@ pub type CapTokenValue = Address;
 /// a collection functions by zome name that are authorized within a capability
 pub type CapFunctions = BTreeMap<String, Vec<String>>;
 
+/// CapFunctions with the given functions of a single zome
+fn cap_functions_of(zome: &str, functions: &[&str]) -> CapFunctions {
+    let mut cap_functions = CapFunctions::new();
+    cap_functions.insert(
+        String::from(zome),
+        functions
+            .iter()
+            .map(|function| function.to_string())
+            .collect(),
+    );
+    cap_functions
+}
+
 /// System entry to hold a capability token claim for use as a caller
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, DefaultJson)]
 pub struct CapTokenClaim {
@@ -85,12 +106,65 @@ impl CapTokenClaim {
     }
 }
 
-/// System entry to hold a capabilities granted by the callee
+/// Upper bound on how often calls may be made under a single capability token
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RateLimit {
+    /// maximum number of calls allowed within one period
+    pub max_calls: u32,
+    /// length of the sliding window in seconds
+    pub period_secs: u64,
+}
+
+impl RateLimit {
+    pub fn new(max_calls: u32, period_secs: u64) -> Self {
+        RateLimit {
+            max_calls,
+            period_secs,
+        }
+    }
+
+    pub fn period(&self) -> Duration {
+        Duration::from_secs(self.period_secs)
+    }
+
+    /// true if a call made at `call_time` still counts against the limit at `now`
+    pub fn in_window(&self, call_time: &SystemTime, now: SystemTime) -> bool {
+        match now.duration_since(*call_time) {
+            Ok(elapsed) => elapsed < self.period(),
+            // calls from the future (i.e. clock adjustments) count conservatively
+            Err(_) => true,
+        }
+    }
+
+    /// checks whether one more call may be made at `now` given the times of previous calls
+    pub fn allows(&self, previous_calls: &[SystemTime], now: SystemTime) -> bool {
+        let recent_calls = previous_calls
+            .iter()
+            .filter(|call_time| self.in_window(call_time, now))
+            .count();
+        recent_calls < self.max_calls as usize
+    }
+}
+
+/// System entry to hold a capabilities granted by the callee.
+/// The token value is the address of this entry, so the optional restrictions set with
+/// `with_rate_limit`, `with_policy` and `with_max_uses` change it as well.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, DefaultJson)]
 pub struct CapTokenGrant {
     id: String,
     assignees: Option<Vec<Address>>,
     functions: CapFunctions,
+    // The fields below are left out of the entry while unset, so that grants which don't
+    // use them keep the token values they had before the fields were added.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rate_limit: Option<RateLimit>,
+    // set for bridge grants, whose assignees are bridge callers
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    bridge: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    policy: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_uses: Option<u32>,
 }
 
 impl CapTokenGrant {
@@ -99,6 +173,10 @@ impl CapTokenGrant {
             id: String::from(id),
             assignees,
             functions,
+            rate_limit: None,
+            bridge: false,
+            policy: None,
+            max_uses: None,
         }
     }
 
@@ -108,8 +186,49 @@ impl CapTokenGrant {
         assignees: Option<Vec<Address>>,
         functions: CapFunctions,
     ) -> Result<Self, HolochainError> {
+        let bridge = cap_type == CapabilityType::Bridge;
         let assignees = CapTokenGrant::valid(cap_type, assignees)?;
-        Ok(CapTokenGrant::new(id, assignees, functions))
+        let mut grant = CapTokenGrant::new(id, assignees, functions);
+        grant.bridge = bridge;
+        Ok(grant)
+    }
+
+    /// Public grant of the given functions of a single zome, e.g. of all its query functions
+    pub fn public_for(id: &str, zome: &str, functions: &[&str]) -> Self {
+        CapTokenGrant::create(
+            id,
+            CapabilityType::Public,
+            None,
+            cap_functions_of(zome, functions),
+        )
+        .expect("public grants have no assignees")
+    }
+
+    /// Transferable grant of the given functions of a single zome
+    pub fn transferable_for(id: &str, zome: &str, functions: &[&str]) -> Self {
+        CapTokenGrant::create(
+            id,
+            CapabilityType::Transferable,
+            None,
+            cap_functions_of(zome, functions),
+        )
+        .expect("transferable grants have no assignees")
+    }
+
+    /// Grant of the given functions of a single zome to the given assignees.
+    /// Fails if there are no assignees.
+    pub fn assigned_for(
+        id: &str,
+        zome: &str,
+        functions: &[&str],
+        assignees: Vec<Address>,
+    ) -> Result<Self, HolochainError> {
+        CapTokenGrant::create(
+            id,
+            CapabilityType::Assigned,
+            Some(assignees),
+            cap_functions_of(zome, functions),
+        )
     }
 
     // internal check that type and assignees are valid for create
@@ -133,6 +252,14 @@ impl CapTokenGrant {
                 }
                 Ok(assignees)
             }
+            CapabilityType::Bridge => {
+                if assignees.is_none() || assignees.clone().unwrap().is_empty() {
+                    return Err(HolochainError::new(
+                        "Bridge grant must have 1 or more bridge callers",
+                    ));
+                }
+                Ok(assignees)
+            }
             CapabilityType::Public => Ok(None),
             CapabilityType::Transferable => Ok(Some(Vec::new())),
         }
@@ -150,6 +277,9 @@ impl CapTokenGrant {
     }
 
     pub fn cap_type(&self) -> CapabilityType {
+        if self.bridge {
+            return CapabilityType::Bridge;
+        }
         match self.assignees() {
             None => CapabilityType::Public,
             Some(vec) => {
@@ -169,6 +299,38 @@ impl CapTokenGrant {
     pub fn functions(&self) -> CapFunctions {
         self.functions.clone()
     }
+
+    /// Limits how often this grant can be exercised.
+    pub fn with_rate_limit(mut self, rate_limit: RateLimit) -> Self {
+        self.rate_limit = Some(rate_limit);
+        self
+    }
+
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        self.rate_limit.clone()
+    }
+
+    /// Makes calls under this grant also subject to the custom capability policy registered
+    /// under the given name with the instance. Without such a policy the grant allows nothing.
+    pub fn with_policy(mut self, policy: &str) -> Self {
+        self.policy = Some(String::from(policy));
+        self
+    }
+
+    pub fn policy(&self) -> Option<String> {
+        self.policy.clone()
+    }
+
+    /// Makes this grant valid for the given number of calls only, after which it is used up.
+    /// Use 1 for a one-shot grant.
+    pub fn with_max_uses(mut self, max_uses: u32) -> Self {
+        self.max_uses = Some(max_uses);
+        self
+    }
+
+    pub fn max_uses(&self) -> Option<u32> {
+        self.max_uses
+    }
 }
 
 #[cfg(test)]
@@ -222,6 +384,55 @@ pub mod tests {
         assert_eq!(grant.assignees().unwrap()[0], test_address)
     }
 
+    #[test]
+    fn test_rate_limit_window() {
+        let rate_limit = RateLimit::new(2, 60);
+        let start = SystemTime::now();
+        let mut calls = Vec::new();
+
+        assert!(rate_limit.allows(&calls, start));
+        calls.push(start);
+        assert!(rate_limit.allows(&calls, start + Duration::from_secs(1)));
+        calls.push(start + Duration::from_secs(1));
+        assert!(!rate_limit.allows(&calls, start + Duration::from_secs(2)));
+
+        // once the first call has left the window there is room for one more
+        assert!(rate_limit.allows(&calls, start + Duration::from_secs(60)));
+        assert!(rate_limit.allows(&calls, start + Duration::from_secs(61)));
+    }
+
+    #[test]
+    fn test_restrictions_keep_plain_token() {
+        let mut functions = CapFunctions::new();
+        functions.insert("some_zome".to_string(), vec![String::from("some_fn")]);
+        let grant =
+            CapTokenGrant::create("foo", CapabilityType::Transferable, None, functions).unwrap();
+        assert_eq!(grant.rate_limit(), None);
+        assert_eq!(grant.policy(), None);
+        assert_eq!(grant.max_uses(), None);
+
+        let limited = grant.clone().with_rate_limit(RateLimit::new(5, 60));
+        assert_eq!(limited.rate_limit(), Some(RateLimit::new(5, 60)));
+        let with_policy = grant.clone().with_policy("office_hours");
+        assert_eq!(with_policy.policy(), Some(String::from("office_hours")));
+        let one_shot = grant.clone().with_max_uses(1);
+        assert_eq!(one_shot.max_uses(), Some(1));
+
+        for (field, restricted) in &[
+            ("rate_limit", limited),
+            ("policy", with_policy),
+            ("max_uses", one_shot),
+        ] {
+            // unset restrictions are left out of the entry and don't change the token
+            assert!(!JsonString::from(grant.clone()).to_string().contains(field));
+            assert!(JsonString::from(restricted.clone())
+                .to_string()
+                .contains(field));
+            assert_eq!(restricted.cap_type(), CapabilityType::Transferable);
+            assert_ne!(restricted.token(), grant.token(), "{}", field);
+        }
+    }
+
     #[test]
     fn test_cap_grant_valid() {
         assert!(CapTokenGrant::valid(CapabilityType::Public, None).is_ok());
@@ -235,6 +446,37 @@ pub mod tests {
         assert!(CapTokenGrant::valid(CapabilityType::Assigned, None).is_err());
         assert!(CapTokenGrant::valid(CapabilityType::Assigned, Some(Vec::new())).is_err());
         assert!(CapTokenGrant::valid(CapabilityType::Assigned, Some(vec![Address::new()])).is_ok());
+        assert!(CapTokenGrant::valid(CapabilityType::Bridge, None).is_err());
+        assert!(CapTokenGrant::valid(CapabilityType::Bridge, Some(Vec::new())).is_err());
+        assert!(CapTokenGrant::valid(CapabilityType::Bridge, Some(vec![Address::new()])).is_ok());
+    }
+
+    #[test]
+    fn test_create_bridge_grant() {
+        let mut functions = CapFunctions::new();
+        functions.insert("some_zome".to_string(), vec![String::from("some_fn")]);
+        let caller = Address::from("caller instance");
+        let grant = CapTokenGrant::create(
+            "foo",
+            CapabilityType::Bridge,
+            Some(vec![caller.clone()]),
+            functions.clone(),
+        )
+        .unwrap();
+        assert_eq!(grant.cap_type(), CapabilityType::Bridge);
+        assert_eq!(grant.assignees(), Some(vec![caller.clone()]));
+
+        // same assignees, but not a bridge grant: different type and token
+        let assigned = CapTokenGrant::create(
+            "foo",
+            CapabilityType::Assigned,
+            Some(vec![caller]),
+            functions,
+        )
+        .unwrap();
+        assert_eq!(assigned.cap_type(), CapabilityType::Assigned);
+        assert_ne!(assigned.token(), grant.token());
+        assert!(!JsonString::from(assigned).to_string().contains("bridge"));
     }
 
     #[test]
@@ -98,7 +98,7 @@ impl EntryType {
         // - must not contain any glob wildcards
         !entry_type_name.is_empty()
         // Must not have sys_prefix
-            && &entry_type_name[0..1] != "%"
+            && !entry_type_name.starts_with('%')
     }
 }
 
@@ -226,6 +226,7 @@ pub mod tests {
         assert!(!EntryType::has_valid_app_name(&String::new()));
         assert!(EntryType::has_valid_app_name("toto"));
         assert!(!EntryType::has_valid_app_name("%%"));
+        assert!(EntryType::has_valid_app_name("évent"));
         // TODO #445 - do a real regex test in has_valid_app_name()
         // assert!(EntryType::has_valid_app_name("\n"));
     }
@@ -101,18 +101,24 @@ pub enum HolochainError {
     CryptoError(CryptoError),
     NotImplemented(String),
     LoggingError,
-    DnaMissing,
+    /// The DNA to work with isn't there, for the given reason
+    DnaMissing(String),
     Dna(DnaError),
     IoError(String),
     SerializationError(String),
     InvalidOperationOnSysEntry,
     CapabilityCheckFailed,
+    RateLimitExceeded,
+    CapabilityExhausted,
     ValidationFailed(String),
     ValidationPending,
     Ribosome(RibosomeErrorCode),
     RibosomeFailed(String),
     ConfigError(String),
+    /// A keystore file can't be loaded because its contents are damaged, for the given reason
+    CorruptKeystore(String),
     Timeout,
+    Cancelled,
     InitializationFailed(String),
     LifecycleError(String),
     DnaHashMismatch(HashString, HashString),
@@ -142,7 +148,7 @@ impl fmt::Display for HolochainError {
             CryptoError(crypto_error) => write!(f, "{}", crypto_error),
             NotImplemented(description) => write!(f, "not implemented: {}", description),
             LoggingError => write!(f, "logging failed"),
-            DnaMissing => write!(f, "DNA is missing"),
+            DnaMissing(reason) => write!(f, "DNA is missing: {}", reason),
             Dna(dna_err) => write!(f, "{}", dna_err),
             IoError(err_msg) => write!(f, "{}", err_msg),
             SerializationError(err_msg) => write!(f, "{}", err_msg),
@@ -150,12 +156,16 @@ impl fmt::Display for HolochainError {
                 write!(f, "operation cannot be done on a system entry type")
             }
             CapabilityCheckFailed => write!(f, "Caller does not have Capability to make that call"),
+            RateLimitExceeded => write!(f, "Rate limit of the capability grant exceeded"),
+            CapabilityExhausted => write!(f, "Capability grant has been used up"),
             ValidationFailed(fail_msg) => write!(f, "{}", fail_msg),
             ValidationPending => write!(f, "Entry validation could not be completed"),
             Ribosome(err_code) => write!(f, "{}", err_code.as_str()),
             RibosomeFailed(fail_msg) => write!(f, "{}", fail_msg),
             ConfigError(err_msg) => write!(f, "{}", err_msg),
+            CorruptKeystore(reason) => write!(f, "Keystore file is corrupt: {}", reason),
             Timeout => write!(f, "timeout"),
+            Cancelled => write!(f, "cancelled"),
             InitializationFailed(err_msg) => write!(f, "{}", err_msg),
             LifecycleError(err_msg) => write!(f, "{}", err_msg),
             DnaHashMismatch(hash1, hash2) => write!(
@@ -314,6 +324,7 @@ impl ZomeApiInternalResult {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::convert::TryFrom;
     // a test function that returns our error result
     fn raises_holochain_error(yes: bool) -> Result<(), HolochainError> {
         if yes {
@@ -340,6 +351,14 @@ mod tests {
         );
     }
 
+    #[test]
+    /// timeouts are told apart by their variant rather than by an error message
+    fn timeout_json_roundtrip() {
+        let json = JsonString::from(HolochainError::Timeout);
+        assert_eq!(JsonString::from_json("\"Timeout\""), json);
+        assert_eq!(HolochainError::try_from(json), Ok(HolochainError::Timeout));
+    }
+
     #[test]
     /// smoke test new errors
     fn can_instantiate() {
@@ -377,8 +396,15 @@ mod tests {
                 "not implemented: reason",
             ),
             (HolochainError::LoggingError, "logging failed"),
-            (HolochainError::DnaMissing, "DNA is missing"),
+            (
+                HolochainError::DnaMissing(String::from("reason")),
+                "DNA is missing: reason",
+            ),
             (HolochainError::ConfigError(String::from("foo")), "foo"),
+            (
+                HolochainError::CorruptKeystore(String::from("foo")),
+                "Keystore file is corrupt: foo",
+            ),
             (
                 HolochainError::Dna(DnaError::ZomeNotFound(String::from("foo"))),
                 "foo",
@@ -404,7 +430,16 @@ mod tests {
                 HolochainError::CapabilityCheckFailed,
                 "Caller does not have Capability to make that call",
             ),
+            (
+                HolochainError::RateLimitExceeded,
+                "Rate limit of the capability grant exceeded",
+            ),
+            (
+                HolochainError::CapabilityExhausted,
+                "Capability grant has been used up",
+            ),
             (HolochainError::Timeout, "timeout"),
+            (HolochainError::Cancelled, "cancelled"),
             (
                 HolochainError::ValidationPending,
                 "Entry validation could not be completed",
@@ -189,7 +189,7 @@ impl From<HolochainError> for RibosomeErrorCode {
             HolochainError::CryptoError(_) => RibosomeErrorCode::Unspecified,
             HolochainError::NotImplemented(_) => RibosomeErrorCode::CallbackFailed,
             HolochainError::LoggingError => RibosomeErrorCode::Unspecified,
-            HolochainError::DnaMissing => RibosomeErrorCode::Unspecified,
+            HolochainError::DnaMissing(_) => RibosomeErrorCode::Unspecified,
             HolochainError::Dna(_) => RibosomeErrorCode::Unspecified,
             HolochainError::IoError(_) => RibosomeErrorCode::Unspecified,
             HolochainError::SerializationError(_) => {
@@ -197,12 +197,16 @@ impl From<HolochainError> for RibosomeErrorCode {
             }
             HolochainError::InvalidOperationOnSysEntry => RibosomeErrorCode::UnknownEntryType,
             HolochainError::CapabilityCheckFailed => RibosomeErrorCode::Unspecified,
+            HolochainError::RateLimitExceeded => RibosomeErrorCode::Unspecified,
+            HolochainError::CapabilityExhausted => RibosomeErrorCode::Unspecified,
             HolochainError::ValidationFailed(_) => RibosomeErrorCode::CallbackFailed,
             HolochainError::ValidationPending => RibosomeErrorCode::Unspecified,
             HolochainError::Ribosome(e) => e,
             HolochainError::RibosomeFailed(_) => RibosomeErrorCode::CallbackFailed,
             HolochainError::ConfigError(_) => RibosomeErrorCode::Unspecified,
+            HolochainError::CorruptKeystore(_) => RibosomeErrorCode::Unspecified,
             HolochainError::Timeout => RibosomeErrorCode::Unspecified,
+            HolochainError::Cancelled => RibosomeErrorCode::Unspecified,
             HolochainError::InitializationFailed(_) => RibosomeErrorCode::Unspecified,
             HolochainError::LifecycleError(_) => RibosomeErrorCode::Unspecified,
             HolochainError::DnaHashMismatch(_, _) => RibosomeErrorCode::Unspecified,
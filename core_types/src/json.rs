@@ -0,0 +1,401 @@
+//! Additions to the JSON types of holochain_json_api that are needed throughout core
+//! but can't live in that crate (yet).
+
+use error::{HcResult, HolochainError};
+use holochain_json_api::json::{JsonString, RawString};
+use rmp_serde;
+use serde::{ser::SerializeSeq, Serialize, Serializer};
+use serde_json::{self, Value};
+use std::{
+    fmt::{self, Write},
+    str::Utf8Error,
+};
+
+/// Extends RawString beyond the scalar primitives it can be built from directly
+pub trait RawStringExt {
+    /// Wraps an arbitrary JSON value (including arrays and objects) as is.
+    /// Converting the result into a JsonString yields the JSON of the value itself
+    /// rather than that JSON re-stringified as a JSON string.
+    fn from_value(value: Value) -> RawString;
+}
+
+impl RawStringExt for RawString {
+    fn from_value(value: Value) -> RawString {
+        // RawString is a newtype around Value, so this can't fail
+        serde_json::from_value(value).expect("any JSON value fits into a RawString")
+    }
+}
+
+/// Extends JsonString with constructors that don't exist in holochain_json_api
+pub trait JsonStringExt {
+    /// Builds a JSON array by serializing the elements one by one straight into the
+    /// backing string, without collecting them into a Vec first.
+    /// The result is identical to `JsonString::from(Vec<T>)` for the same elements.
+    fn from_iter<T: Serialize, I: IntoIterator<Item = T>>(iter: I) -> JsonString;
+
+    /// Builds a JSON array of the elements in sorted order, so that collections whose order
+    /// doesn't matter always serialize the same way.
+    fn from_sorted_vec<T: Serialize + Ord>(v: Vec<T>) -> JsonString;
+
+    /// Takes back the bytes of a JSON string, e.g. after they went over the network,
+    /// failing if they aren't valid UTF-8.
+    /// Whether they are valid JSON is left to whoever parses the result.
+    fn from_bytes(bytes: Vec<u8>) -> Result<JsonString, Utf8Error>;
+
+    /// Length of the JSON in bytes, computed without copying the string
+    fn byte_len(&self) -> usize;
+
+    /// Whether the JSON is an object with the given top-level key.
+    /// False for anything that isn't an object, including invalid JSON.
+    fn contains_key(&self, key: &str) -> bool;
+
+    /// Compares the JSON values structurally, skipping object keys named in `ignore_keys`
+    /// on both sides, however deeply nested. Handy for comparing outputs that contain
+    /// timestamps or generated ids.
+    /// Invalid JSON is only equal to the very same string.
+    fn deep_equal_ignoring(&self, other: &JsonString, ignore_keys: &[&str]) -> bool;
+
+    /// Encodes the JSON as MessagePack, for interfaces that exchange that instead.
+    /// Fails if the JsonString doesn't hold valid JSON.
+    fn to_msgpack(&self) -> HcResult<Vec<u8>>;
+
+    /// Decodes MessagePack into the equivalent JSON.
+    /// Fails for MessagePack that has no JSON equivalent, like binary data or non-string keys.
+    fn from_msgpack(bytes: &[u8]) -> HcResult<JsonString>;
+}
+
+/// fmt::Write sink that only counts what gets written to it
+struct ByteCounter(usize);
+
+impl fmt::Write for ByteCounter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0 += s.len();
+        Ok(())
+    }
+}
+
+impl JsonStringExt for JsonString {
+    fn from_iter<T: Serialize, I: IntoIterator<Item = T>>(iter: I) -> JsonString {
+        let mut buffer = Vec::new();
+        {
+            let mut serializer = serde_json::Serializer::new(&mut buffer);
+            let mut seq = serializer
+                .serialize_seq(None)
+                .expect("could not start JSON array");
+            for element in iter {
+                seq.serialize_element(&element)
+                    .expect("could not Jsonify element");
+            }
+            seq.end().expect("could not finish JSON array");
+        }
+        JsonString::from_json(
+            &String::from_utf8(buffer).expect("serde_json always produces valid UTF-8"),
+        )
+    }
+
+    fn from_sorted_vec<T: Serialize + Ord>(mut v: Vec<T>) -> JsonString {
+        v.sort();
+        JsonString::from_iter(v)
+    }
+
+    fn from_bytes(bytes: Vec<u8>) -> Result<JsonString, Utf8Error> {
+        let json = String::from_utf8(bytes).map_err(|error| error.utf8_error())?;
+        Ok(JsonString::from_json(&json))
+    }
+
+    fn byte_len(&self) -> usize {
+        let mut counter = ByteCounter(0);
+        write!(counter, "{}", self).expect("counting bytes can't fail");
+        counter.0
+    }
+
+    fn contains_key(&self, key: &str) -> bool {
+        match serde_json::from_str(&self.to_string()) {
+            Ok(Value::Object(map)) => map.contains_key(key),
+            _ => false,
+        }
+    }
+
+    fn deep_equal_ignoring(&self, other: &JsonString, ignore_keys: &[&str]) -> bool {
+        let parse = |json: &JsonString| serde_json::from_str::<Value>(&json.to_string());
+        match (parse(self), parse(other)) {
+            (Ok(a), Ok(b)) => values_equal_ignoring(&a, &b, ignore_keys),
+            _ => self == other,
+        }
+    }
+
+    fn to_msgpack(&self) -> HcResult<Vec<u8>> {
+        let value: Value = serde_json::from_str(&self.to_string())?;
+        rmp_serde::to_vec(&value).map_err(|e| HolochainError::SerializationError(e.to_string()))
+    }
+
+    fn from_msgpack(bytes: &[u8]) -> HcResult<JsonString> {
+        let value: Value = rmp_serde::from_slice(bytes)
+            .map_err(|e| HolochainError::SerializationError(e.to_string()))?;
+        Ok(JsonString::from_json(&value.to_string()))
+    }
+}
+
+fn values_equal_ignoring(a: &Value, b: &Value, ignore_keys: &[&str]) -> bool {
+    match (a, b) {
+        (Value::Object(a), Value::Object(b)) => {
+            let kept = |map: &serde_json::Map<String, Value>| {
+                map.iter()
+                    .filter(|(key, _)| !ignore_keys.contains(&key.as_str()))
+                    .count()
+            };
+            kept(a) == kept(b)
+                && a.iter()
+                    .filter(|(key, _)| !ignore_keys.contains(&key.as_str()))
+                    .all(|(key, value)| match b.get(key) {
+                        Some(other) => values_equal_ignoring(value, other, ignore_keys),
+                        None => false,
+                    })
+        }
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .zip(b.iter())
+                    .all(|(a, b)| values_equal_ignoring(a, b, ignore_keys))
+        }
+        (a, b) => a == b,
+    }
+}
+
+/// Reserializes the given JSON with the keys of every object, however deeply nested, sorted
+/// and without any insignificant whitespace.
+/// Equal values always come out as the same bytes, which is what hashing and signing need.
+pub fn canonicalize(input: &JsonString) -> Result<JsonString, serde_json::Error> {
+    let value: Value = serde_json::from_str(&input.to_string())?;
+    let mut canonical = String::new();
+    write_canonical(&value, &mut canonical)?;
+    Ok(JsonString::from_json(&canonical))
+}
+
+fn write_canonical(value: &Value, out: &mut String) -> Result<(), serde_json::Error> {
+    match value {
+        // serde_json keeps insertion order when preserve_order is enabled, so sort here
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            out.push('{');
+            for (index, (key, value)) in entries.into_iter().enumerate() {
+                if index > 0 {
+                    out.push(',');
+                }
+                out.push_str(&serde_json::to_string(key)?);
+                out.push(':');
+                write_canonical(value, out)?;
+            }
+            out.push('}');
+        }
+        Value::Array(values) => {
+            out.push('[');
+            for (index, value) in values.iter().enumerate() {
+                if index > 0 {
+                    out.push(',');
+                }
+                write_canonical(value, out)?;
+            }
+            out.push(']');
+        }
+        scalar => out.push_str(&serde_json::to_string(scalar)?),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    fn raw_string_from_value_test() {
+        assert_eq!(
+            JsonString::from(RawString::from("x")).to_string(),
+            "\"x\"".to_string(),
+        );
+
+        let array = json!(["x", 1, {"y": null}]);
+        assert_eq!(
+            JsonString::from(RawString::from_value(array)).to_string(),
+            "[\"x\",1,{\"y\":null}]".to_string(),
+        );
+
+        let object = json!({"x": [1, 2]});
+        assert_eq!(
+            JsonString::from(RawString::from_value(object)).to_string(),
+            "{\"x\":[1,2]}".to_string(),
+        );
+
+        // scalars behave exactly like the existing conversions
+        assert_eq!(
+            JsonString::from(RawString::from_value(json!("x"))),
+            JsonString::from(RawString::from("x")),
+        );
+    }
+
+    #[test]
+    fn json_string_from_iter_test() {
+        let elements = vec![
+            RawString::from("x"),
+            RawString::from(1),
+            RawString::from_value(json!({"y": [null, true]})),
+        ];
+        assert_eq!(
+            JsonString::from_iter(elements.clone()),
+            JsonString::from(elements),
+        );
+
+        let numbers = (0..5).map(|i| i * 2);
+        assert_eq!(
+            JsonString::from_iter(numbers),
+            JsonString::from(vec![0, 2, 4, 6, 8]),
+        );
+
+        let empty: Vec<String> = Vec::new();
+        assert_eq!(
+            JsonString::from_iter(empty.clone()),
+            JsonString::from(empty)
+        );
+    }
+
+    #[test]
+    fn json_string_from_sorted_vec_test() {
+        let a = JsonString::from_sorted_vec(vec!["b", "c", "a"]);
+        let b = JsonString::from_sorted_vec(vec!["c", "a", "b"]);
+        assert_eq!(a, b);
+        assert_eq!(a.to_string(), r#"["a","b","c"]"#.to_string());
+
+        // the unsorted conversion keeps the given order
+        assert_eq!(
+            JsonString::from(vec![3, 1, 2]).to_string(),
+            "[3,1,2]".to_string()
+        );
+        assert_eq!(
+            JsonString::from_sorted_vec(vec![3, 1, 2]).to_string(),
+            "[1,2,3]".to_string(),
+        );
+    }
+
+    #[test]
+    fn json_string_from_bytes_test() {
+        let json = JsonString::from_json(r#"{"text":"hëllo"}"#);
+        assert_eq!(
+            JsonString::from_bytes(json.to_string().into_bytes()),
+            Ok(json)
+        );
+
+        // a lone continuation byte and a truncated multi-byte sequence
+        let invalid = JsonString::from_bytes(vec![b'"', 0x80, b'"']).unwrap_err();
+        assert_eq!(invalid.valid_up_to(), 1);
+        let truncated = "\"ë\"".as_bytes()[..2].to_vec();
+        assert_eq!(
+            JsonString::from_bytes(truncated).unwrap_err().valid_up_to(),
+            1
+        );
+    }
+
+    #[test]
+    fn json_string_byte_len_test() {
+        let json = JsonString::from(RawString::from("hëllo"));
+        assert_eq!(json.byte_len(), json.to_string().len());
+        assert_eq!(json.byte_len(), 8);
+        assert_eq!(JsonString::null().byte_len(), 4);
+    }
+
+    #[test]
+    fn json_string_contains_key_test() {
+        let object = JsonString::from_json(r#"{"a": 1, "b": {"c": null}}"#);
+        assert!(object.contains_key("a"));
+        assert!(object.contains_key("b"));
+        // only top-level keys count
+        assert!(!object.contains_key("c"));
+        assert!(!object.contains_key("x"));
+
+        assert!(!JsonString::from_json(r#"["a"]"#).contains_key("a"));
+        assert!(!JsonString::from(RawString::from("a")).contains_key("a"));
+        assert!(!JsonString::null().contains_key("a"));
+        assert!(!JsonString::from_json("{not json").contains_key("not"));
+    }
+
+    #[test]
+    fn json_string_deep_equal_ignoring_test() {
+        let a = JsonString::from_json(r#"{"id": "abc", "post": {"title": "x", "id": 1}}"#);
+        let b = JsonString::from_json(r#"{"post": {"id": 2, "title": "x"}, "id": "def"}"#);
+        assert!(a.deep_equal_ignoring(&b, &["id"]));
+        assert!(!a.deep_equal_ignoring(&b, &[]));
+
+        // differences in fields that aren't ignored still count
+        let c = JsonString::from_json(r#"{"id": "abc", "post": {"title": "y", "id": 1}}"#);
+        assert!(!a.deep_equal_ignoring(&c, &["id"]));
+        let d = JsonString::from_json(r#"{"id": "abc", "post": {"title": "x"}, "extra": true}"#);
+        assert!(!a.deep_equal_ignoring(&d, &["id"]));
+
+        // ignored keys may be missing on one side, also within arrays
+        let e = JsonString::from_json(r#"[{"id": 1, "v": 1}, {"v": 2}]"#);
+        let f = JsonString::from_json(r#"[{"v": 1}, {"id": 3, "v": 2}]"#);
+        assert!(e.deep_equal_ignoring(&f, &["id"]));
+        assert!(!e.deep_equal_ignoring(&JsonString::from_json(r#"[{"v": 1}]"#), &["id"]));
+
+        let invalid = JsonString::from_json("{not json");
+        assert!(invalid.deep_equal_ignoring(&invalid, &["id"]));
+        assert!(!invalid.deep_equal_ignoring(&a, &["id"]));
+    }
+
+    #[test]
+    fn json_string_msgpack_roundtrip_test() {
+        let roundtrip = |json: &str| {
+            let msgpack = JsonString::from_json(json).to_msgpack().unwrap();
+            JsonString::from_msgpack(&msgpack)
+        };
+
+        for json in &[
+            r#"{"a":1,"b":{"c":[true,null]},"d":"ë"}"#,
+            r#"{}"#,
+            r#"[1,-2,3.5,"x",[],{"y":false}]"#,
+            r#"[]"#,
+            r#""text""#,
+            r#"42"#,
+            r#"-7"#,
+            r#"0.25"#,
+            r#"true"#,
+            r#"null"#,
+        ] {
+            assert_eq!(roundtrip(json), Ok(JsonString::from_json(json)));
+        }
+
+        // it really is MessagePack: a fixmap with one entry, "a" => 1
+        assert_eq!(
+            JsonString::from_json(r#"{"a":1}"#).to_msgpack(),
+            Ok(vec![0x81, 0xa1, b'a', 0x01])
+        );
+
+        assert!(JsonString::from_json("{not json").to_msgpack().is_err());
+        // binary data has no JSON equivalent
+        assert!(JsonString::from_msgpack(&[0xc4, 0x01, 0x00]).is_err());
+    }
+
+    #[test]
+    fn canonicalize_test() {
+        let a =
+            JsonString::from_json(r#"{"b": {"z": 1, "a": [ {"y": true, "x": null} ]}, "a": "s"}"#);
+        let b = JsonString::from_json(
+            r#"{
+                "a": "s",
+                "b": {"a": [{"x": null, "y": true}], "z": 1}
+            }"#,
+        );
+        let expected = JsonString::from_json(r#"{"a":"s","b":{"a":[{"x":null,"y":true}],"z":1}}"#);
+        assert_eq!(canonicalize(&a).unwrap(), expected);
+        assert_eq!(canonicalize(&b).unwrap(), expected);
+
+        // canonical JSON stays as it is and array order is significant
+        assert_eq!(canonicalize(&expected).unwrap(), expected);
+        assert_eq!(
+            canonicalize(&JsonString::from_json("[2, 1]")).unwrap(),
+            JsonString::from_json("[2,1]"),
+        );
+
+        assert!(canonicalize(&JsonString::from_json("{not json")).is_err());
+    }
+}
@@ -25,6 +25,7 @@ extern crate holochain_json_api;
 extern crate holochain_persistence_api;
 extern crate lib3h_crypto_api;
 extern crate regex;
+extern crate rmp_serde;
 #[cfg(test)]
 #[macro_use]
 extern crate maplit;
@@ -42,6 +43,7 @@ pub mod bits_n_pieces;
 pub mod chain_migrate;
 pub mod dna;
 pub mod hdk_version;
+pub mod json;
 pub mod link;
 pub mod signature;
 pub mod time;
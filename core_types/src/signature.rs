@@ -6,6 +6,9 @@ use holochain_persistence_api::cas::content::Address;
 
 use holochain_json_api::{error::JsonError, json::JsonString};
 
+use base64;
+use error::HcResult;
+
 /// Provenance is a tuple of initiating agent public key and signature of some item being signed
 /// this type is used in headers and in capability requests where the item being signed
 /// is implicitly known by context
@@ -37,6 +40,16 @@ impl Signature {
     pub fn fake() -> Signature {
         test_signature()
     }
+
+    /// The raw bytes of the signature, decoded from the base64 it is stored as
+    pub fn to_bytes(&self) -> HcResult<Vec<u8>> {
+        Ok(base64::decode(&self.0)?)
+    }
+
+    /// Wraps raw signature bytes, encoding them as base64 like all signatures are stored
+    pub fn from_bytes(bytes: &[u8]) -> Signature {
+        Signature(base64::encode(bytes))
+    }
 }
 
 impl From<&'static str> for Signature {
@@ -72,3 +85,18 @@ impl From<Signature> for String {
         s.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signature_bytes_roundtrip_test() {
+        let bytes: Vec<u8> = (0..64).collect();
+        let signature = Signature::from_bytes(&bytes);
+        assert_eq!(String::from(signature.clone()), base64::encode(&bytes));
+        assert_eq!(signature.to_bytes(), Ok(bytes));
+
+        assert!(Signature::fake().to_bytes().is_err());
+    }
+}
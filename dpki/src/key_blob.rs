@@ -117,8 +117,8 @@ impl Blobbable for Seed {
     ) -> HcResult<Self> {
         // Retrieve data buf from blob
         let mut seed_buf = Self::unblob(blob, passphrase, config)?;
-        // Construct
-        Ok(Seed::new(seed_buf, blob.seed_type.clone()))
+        // Construct, which fails for seeds that are not SEED_SIZE bytes long
+        Seed::new(seed_buf, blob.seed_type.clone())
     }
 
     ///  generate a persistence bundle with hint info
@@ -554,7 +554,7 @@ mod tests {
     fn it_should_blob_seed() {
         let mut passphrase = generate_random_seed_buf();
         let mut seed_buf = generate_random_seed_buf();
-        let mut initial_seed = Seed::new(seed_buf, SeedType::Root);
+        let mut initial_seed = Seed::new(seed_buf, SeedType::Root).unwrap();
 
         let blob = initial_seed
             .as_blob(&mut passphrase, "hint".to_string(), TEST_CONFIG)
@@ -569,7 +569,7 @@ mod tests {
     fn it_should_blob_device_pin_seed() {
         let mut passphrase = generate_random_seed_buf();
         let mut seed_buf = generate_random_seed_buf();
-        let mut initial_device_pin_seed = DevicePinSeed::new(seed_buf);
+        let mut initial_device_pin_seed = DevicePinSeed::new(seed_buf).unwrap();
 
         let blob = initial_device_pin_seed
             .seed_mut()
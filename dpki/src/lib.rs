@@ -9,6 +9,7 @@ extern crate lazy_static;
 pub const CONTEXT_SIZE: usize = 8;
 pub const SEED_SIZE: usize = 32;
 pub const AGENT_ID_CTX: [u8; 8] = *b"HCAGNTID";
+pub const DEVICE_CTX: [u8; 8] = *b"HCDEVICE";
 pub(crate) const SIGNATURE_SIZE: usize = 64;
 
 lazy_static! {
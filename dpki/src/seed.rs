@@ -1,11 +1,15 @@
 use crate::{
     key_bundle::KeyBundle,
+    keypair::{KeyPair, SigningKeyPair},
     password_encryption::*,
     utils::{generate_derived_seed_buf, SeedContext},
     AGENT_ID_CTX, SEED_SIZE,
 };
 use bip39::{Language, Mnemonic, MnemonicType};
-use holochain_core_types::error::{HcResult, HolochainError};
+use holochain_core_types::{
+    agent::Base32,
+    error::{HcResult, HolochainError},
+};
 use lib3h_sodium::{kdf, pwhash, secbuf::SecBuf};
 use serde_derive::{Deserialize, Serialize};
 use std::str;
@@ -81,6 +85,18 @@ where
 // Seed
 //--------------------------------------------------------------------------------------------------
 
+/// Fails unless `size` is the size all seeds have, `SEED_SIZE`.
+/// Deriving from a seed of any other size doesn't work.
+pub fn check_seed_size(size: usize) -> HcResult<()> {
+    if size != SEED_SIZE {
+        return Err(HolochainError::ErrorGeneric(format!(
+            "Invalid seed size: expected {} bytes, got {}",
+            SEED_SIZE, size
+        )));
+    }
+    Ok(())
+}
+
 // Data of a seed
 #[derive(Debug)]
 pub struct Seed {
@@ -89,30 +105,32 @@ pub struct Seed {
 }
 
 impl Seed {
-    pub fn new(seed_buf: SecBuf, seed_type: SeedType) -> Self {
-        assert_eq!(seed_buf.len(), SEED_SIZE);
-        Seed {
+    pub fn new(seed_buf: SecBuf, seed_type: SeedType) -> HcResult<Self> {
+        check_seed_size(seed_buf.len())?;
+        Ok(Seed {
             kind: seed_type,
             buf: seed_buf,
-        }
+        })
     }
 
     ///  Construct this seed struct from a SeedInitializer
     ///  @param {string} seed_type -
     ///  @param {SecBuf|string} initializer - data (buffer or mnemonic) for constructing the Seed
-    pub fn new_with_initializer(initializer: SeedInitializer, seed_type: SeedType) -> Self {
+    pub fn new_with_initializer(
+        initializer: SeedInitializer,
+        seed_type: SeedType,
+    ) -> HcResult<Self> {
         match initializer {
             SeedInitializer::Seed(seed_buf) => Seed::new(seed_buf, seed_type),
-            SeedInitializer::Mnemonic(phrase) => Seed::new_with_mnemonic(phrase, seed_type)
-                .expect("Invalid Mnemonic Seed initializer"),
+            SeedInitializer::Mnemonic(phrase) => Seed::new_with_mnemonic(phrase, seed_type),
         }
     }
 
     pub fn into_typed(self) -> HcResult<TypedSeed> {
         match self.kind {
-            SeedType::Root => Ok(TypedSeed::Root(RootSeed::new(self.buf))),
-            SeedType::Device => Ok(TypedSeed::Device(DeviceSeed::new(self.buf))),
-            SeedType::DevicePin => Ok(TypedSeed::DevicePin(DevicePinSeed::new(self.buf))),
+            SeedType::Root => Ok(TypedSeed::Root(RootSeed::new(self.buf)?)),
+            SeedType::Device => Ok(TypedSeed::Device(DeviceSeed::new(self.buf)?)),
+            SeedType::DevicePin => Ok(TypedSeed::DevicePin(DevicePinSeed::new(self.buf)?)),
             _ => Err(HolochainError::ErrorGeneric(
                 "Seed does have specific behavior for its type".to_string(),
             )),
@@ -128,7 +146,7 @@ impl MnemonicableSeed for Seed {
         })?;
 
         let entropy = mnemonic.entropy().to_owned();
-        assert_eq!(entropy.len(), SEED_SIZE);
+        check_seed_size(entropy.len())?;
         let mut seed_buf = SecBuf::with_secure(entropy.len());
         seed_buf.from_array(entropy.as_slice())?;
         // Done
@@ -169,11 +187,11 @@ impl SeedTrait for RootSeed {
 }
 
 impl RootSeed {
-    /// Construct from a 32 bytes seed buffer
-    pub fn new(seed_buf: SecBuf) -> Self {
-        RootSeed {
-            inner: Seed::new_with_initializer(SeedInitializer::Seed(seed_buf), SeedType::Root),
-        }
+    /// Construct from a 32 bytes seed buffer, failing for buffers of any other size
+    pub fn new(seed_buf: SecBuf) -> HcResult<Self> {
+        Ok(RootSeed {
+            inner: Seed::new_with_initializer(SeedInitializer::Seed(seed_buf), SeedType::Root)?,
+        })
     }
 
     /// Generate Device Seed
@@ -185,10 +203,27 @@ impl RootSeed {
     ) -> HcResult<DeviceSeed> {
         let device_seed_buf =
             generate_derived_seed_buf(&mut self.inner.buf, seed_context, index, SEED_SIZE)?;
-        Ok(DeviceSeed::new(device_seed_buf))
+        DeviceSeed::new(device_seed_buf)
     }
 }
 
+/// Check that a public signing key was derived from a root seed
+/// @param {RootSeed} seed - the root seed the key supposedly stems from
+/// @param {number} index - the index the key was derived at, must not be zero
+/// @param {Base32} claimed - the public signing key to check
+/// @return true if deriving at `index` with `context` yields the claimed key
+pub fn verify_derivation(
+    seed: &mut RootSeed,
+    context: &SeedContext,
+    index: u64,
+    claimed: &Base32,
+) -> HcResult<bool> {
+    let mut derived_seed_buf =
+        generate_derived_seed_buf(&mut seed.inner.buf, context, index, SEED_SIZE)?;
+    let key_pair = SigningKeyPair::new_from_seed(&mut derived_seed_buf)?;
+    Ok(&key_pair.public == claimed)
+}
+
 //--------------------------------------------------------------------------------------------------
 // DeviceSeed
 //--------------------------------------------------------------------------------------------------
@@ -208,11 +243,11 @@ impl SeedTrait for DeviceSeed {
 }
 
 impl DeviceSeed {
-    /// Construct from a 32 bytes seed buffer
-    pub fn new(seed_buf: SecBuf) -> Self {
-        DeviceSeed {
-            inner: Seed::new_with_initializer(SeedInitializer::Seed(seed_buf), SeedType::Device),
-        }
+    /// Construct from a 32 bytes seed buffer, failing for buffers of any other size
+    pub fn new(seed_buf: SecBuf) -> HcResult<Self> {
+        Ok(DeviceSeed {
+            inner: Seed::new_with_initializer(SeedInitializer::Seed(seed_buf), SeedType::Device)?,
+        })
     }
 
     /// generate a device pin seed by applying pwhash of pin with this seed as the salt
@@ -225,7 +260,7 @@ impl DeviceSeed {
     ) -> HcResult<DevicePinSeed> {
         let mut hash = SecBuf::with_secure(pwhash::HASHBYTES);
         pw_hash(pin, &mut self.inner.buf, &mut hash, config)?;
-        Ok(DevicePinSeed::new(hash))
+        DevicePinSeed::new(hash)
     }
 }
 
@@ -248,11 +283,14 @@ impl SeedTrait for DevicePinSeed {
 }
 
 impl DevicePinSeed {
-    /// Construct from a 32 bytes seed buffer
-    pub fn new(seed_buf: SecBuf) -> Self {
-        DevicePinSeed {
-            inner: Seed::new_with_initializer(SeedInitializer::Seed(seed_buf), SeedType::DevicePin),
-        }
+    /// Construct from a 32 bytes seed buffer, failing for buffers of any other size
+    pub fn new(seed_buf: SecBuf) -> HcResult<Self> {
+        Ok(DevicePinSeed {
+            inner: Seed::new_with_initializer(
+                SeedInitializer::Seed(seed_buf),
+                SeedType::DevicePin,
+            )?,
+        })
     }
 
     /// generate a DNA agent KeyBundle given an index based on this seed
@@ -293,10 +331,8 @@ impl EncryptedSeed {
         let mut passphrase_buf = SecBuf::with_insecure_from_string(passphrase);
         let mut decrypted_data = SecBuf::with_secure(SEED_SIZE);
         pw_dec(&self.data, &mut passphrase_buf, &mut decrypted_data, config)?;
-        Ok(
-            Seed::new_with_initializer(SeedInitializer::Seed(decrypted_data), self.kind.clone())
-                .into_typed()?,
-        )
+        Seed::new_with_initializer(SeedInitializer::Seed(decrypted_data), self.kind.clone())?
+            .into_typed()
     }
 }
 
@@ -375,22 +411,40 @@ mod tests {
     fn it_should_create_a_new_seed() {
         let seed_buf = utils::generate_random_seed_buf();
         let seed_type = SeedType::OneShot;
-        let seed = Seed::new_with_initializer(SeedInitializer::Seed(seed_buf), seed_type.clone());
+        let seed =
+            Seed::new_with_initializer(SeedInitializer::Seed(seed_buf), seed_type.clone()).unwrap();
         assert_eq!(seed_type, seed.kind);
     }
 
     #[test]
     fn it_should_create_a_new_root_seed() {
         let seed_buf = generate_random_seed_buf();
-        let root_seed = RootSeed::new(seed_buf);
+        let root_seed = RootSeed::new(seed_buf).unwrap();
         assert_eq!(SeedType::Root, root_seed.seed().kind);
     }
 
+    #[test]
+    fn it_should_reject_seeds_of_the_wrong_size() {
+        let too_short = SecBuf::with_insecure(SEED_SIZE - 1);
+        assert_eq!(
+            RootSeed::new(too_short).unwrap_err(),
+            HolochainError::ErrorGeneric(
+                "Invalid seed size: expected 32 bytes, got 31".to_string()
+            )
+        );
+        let too_long = SecBuf::with_insecure(SEED_SIZE + 1);
+        assert!(Seed::new(too_long, SeedType::Device).is_err());
+
+        // 12 words only encode half a seed
+        let mnemonic = Mnemonic::new(MnemonicType::Words12, Language::English).into_phrase();
+        assert!(Seed::new_with_mnemonic(mnemonic, SeedType::Root).is_err());
+    }
+
     #[test]
     fn it_should_create_a_device_seed() {
         let seed_buf = generate_random_seed_buf();
         let context = SeedContext::new(*b"HCDEVICE");
-        let mut root_seed = RootSeed::new(seed_buf);
+        let mut root_seed = RootSeed::new(seed_buf).unwrap();
 
         let mut device_seed_3 = root_seed.generate_device_seed(&context, 3).unwrap();
         assert_eq!(SeedType::Device, device_seed_3.seed().kind);
@@ -413,13 +467,35 @@ mod tests {
         );
     }
 
+    #[test]
+    fn it_should_verify_derivation() {
+        let context = SeedContext::new(*b"HCDEVICE");
+        let mut root_seed = RootSeed::new(generate_random_seed_buf()).unwrap();
+        let mut derived_seed_buf =
+            generate_derived_seed_buf(&mut root_seed.inner.buf, &context, 3, SEED_SIZE).unwrap();
+        let key = SigningKeyPair::new_from_seed(&mut derived_seed_buf)
+            .unwrap()
+            .public;
+
+        assert!(verify_derivation(&mut root_seed, &context, 3, &key).unwrap());
+
+        // another index, another context or another seed lead to another key
+        assert!(!verify_derivation(&mut root_seed, &context, 4, &key).unwrap());
+        let other_context = SeedContext::new(*b"OTHERCTX");
+        assert!(!verify_derivation(&mut root_seed, &other_context, 3, &key).unwrap());
+        let mut other_seed = RootSeed::new(generate_random_seed_buf()).unwrap();
+        assert!(!verify_derivation(&mut other_seed, &context, 3, &key).unwrap());
+
+        assert!(verify_derivation(&mut root_seed, &context, 0, &key).is_err());
+    }
+
     #[test]
     fn it_should_create_a_device_pin_seed() {
         let seed_buf = generate_random_seed_buf();
         let mut pin = generate_random_seed_buf();
 
         let context = SeedContext::new(*b"HCDEVICE");
-        let mut root_seed = RootSeed::new(seed_buf);
+        let mut root_seed = RootSeed::new(seed_buf).unwrap();
         let mut device_seed = root_seed.generate_device_seed(&context, 3).unwrap();
         let device_pin_seed = device_seed
             .generate_device_pin_seed(&mut pin, TEST_CONFIG)
@@ -433,7 +509,7 @@ mod tests {
         let mut pin = generate_random_seed_buf();
 
         let context = SeedContext::new(*b"HCDEVICE");
-        let mut rs = RootSeed::new(seed_buf);
+        let mut rs = RootSeed::new(seed_buf).unwrap();
         let mut ds = rs.generate_device_seed(&context, 3).unwrap();
         let mut dps = ds.generate_device_pin_seed(&mut pin, TEST_CONFIG).unwrap();
         let mut keybundle_5 = dps.generate_dna_key(5).unwrap();
@@ -459,7 +535,7 @@ mod tests {
             seed_buf[1] = 70;
             seed_buf[2] = 88;
         }
-        let mut seed = Seed::new(seed_buf, SeedType::Root);
+        let mut seed = Seed::new(seed_buf, SeedType::Root).unwrap();
         let mnemonic = seed.get_mnemonic().unwrap();
         println!("mnemonic: {:?}", mnemonic);
         assert_eq!(mnemonic.split(" ").count(), 24);
@@ -473,7 +549,7 @@ mod tests {
     fn it_should_change_into_typed() {
         // Root
         let seed_buf = generate_random_seed_buf();
-        let seed = Seed::new(seed_buf, SeedType::Root);
+        let seed = Seed::new(seed_buf, SeedType::Root).unwrap();
         let unknown_seed = seed.into_typed().unwrap();
         let _ = match unknown_seed {
             TypedSeed::Root(typed_seed) => typed_seed,
@@ -481,7 +557,7 @@ mod tests {
         };
         // Device
         let seed_buf = generate_random_seed_buf();
-        let seed = Seed::new(seed_buf, SeedType::Device);
+        let seed = Seed::new(seed_buf, SeedType::Device).unwrap();
         let unknown_seed = seed.into_typed().unwrap();
         let _ = match unknown_seed {
             TypedSeed::Device(typed_seed) => typed_seed,
@@ -489,7 +565,7 @@ mod tests {
         };
         // DevicePin
         let seed_buf = generate_random_seed_buf();
-        let seed = Seed::new(seed_buf, SeedType::DevicePin);
+        let seed = Seed::new(seed_buf, SeedType::DevicePin).unwrap();
         let unknown_seed = seed.into_typed().unwrap();
         let _ = match unknown_seed {
             TypedSeed::DevicePin(typed_seed) => typed_seed,
@@ -497,7 +573,7 @@ mod tests {
         };
         // App
         let seed_buf = generate_random_seed_buf();
-        let seed = Seed::new(seed_buf, SeedType::DNA);
+        let seed = Seed::new(seed_buf, SeedType::DNA).unwrap();
         let maybe_seed = seed.into_typed();
         assert!(maybe_seed.is_err());
     }
@@ -505,7 +581,11 @@ mod tests {
     #[test]
     fn it_should_encrypt_and_decrypt_seed() {
         let seed_buf = generate_random_seed_buf();
-        let mut seed = match Seed::new(seed_buf, SeedType::Root).into_typed().unwrap() {
+        let mut seed = match Seed::new(seed_buf, SeedType::Root)
+            .unwrap()
+            .into_typed()
+            .unwrap()
+        {
             TypedSeed::Root(s) => s,
             _ => unreachable!(),
         };
@@ -524,7 +604,11 @@ mod tests {
     #[test]
     fn it_should_roundtrip_encrypted_seed_mnemonic() {
         let seed_buf = generate_random_seed_buf();
-        let mut seed = match Seed::new(seed_buf, SeedType::Root).into_typed().unwrap() {
+        let mut seed = match Seed::new(seed_buf, SeedType::Root)
+            .unwrap()
+            .into_typed()
+            .unwrap()
+        {
             TypedSeed::Root(s) => s,
             _ => unreachable!(),
         };
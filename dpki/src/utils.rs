@@ -162,7 +162,10 @@ pub fn decrypt_with_passphrase_buf(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::SIGNATURE_SIZE;
+    use crate::{
+        keypair::{KeyPair, SigningKeyPair},
+        SIGNATURE_SIZE,
+    };
     use lib3h_sodium::{secbuf::SecBuf, sign};
 
     #[test]
@@ -199,6 +202,20 @@ mod tests {
         assert!(res.unwrap());
     }
 
+    #[test]
+    fn it_should_verify_signatures_round_tripped_through_bytes() {
+        let mut key_pair = SigningKeyPair::new_from_seed(&mut generate_random_seed_buf()).unwrap();
+        let data = "some data".to_string();
+        let mut data_buf = SecBuf::with_insecure_from_string(data.clone());
+        let signature_buf = key_pair.sign(&mut data_buf).unwrap();
+        let signature_bytes = signature_buf.read_lock().to_vec();
+
+        let signature = Signature::from_bytes(&signature_bytes);
+        assert_eq!(signature.to_bytes().unwrap(), signature_bytes);
+        let source = Address::from(key_pair.public.clone());
+        assert!(verify(source, data, signature).unwrap());
+    }
+
     #[test]
     fn it_should_round_trip_passphrase_encryption() {
         let data_size = 32;
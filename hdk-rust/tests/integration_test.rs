@@ -414,9 +414,10 @@ fn make_test_call(hc: &mut Holochain, fn_name: &str, params: &str) -> HolochainR
         make_cap_request_for_call(
             context.clone(),
             token,
+            "test_zome",
             fn_name,
             JsonString::from_json(params),
-        )
+        )?
     };
     hc.call("test_zome", cap_call, fn_name, params)
 }
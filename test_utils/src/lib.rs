@@ -303,9 +303,10 @@ pub fn hc_setup_and_call_zome_fn<J: Into<JsonString>>(
     let cap_request = make_cap_request_for_call(
         context.clone(),
         context.clone().agent_id.address(),
+        "test_zome",
         fn_name,
         JsonString::from_json(&params_string.clone()),
-    );
+    )?;
 
     // Run the holochain instance
     hc.start().expect("couldn't start");
@@ -1,6 +1,4 @@
-use holochain_core_types::{
-    agent::AgentId,
-    };
+use holochain_core_types::{agent::AgentId, signature::Signature};
 
 use holochain_persistence_api::cas::content::{Address, AddressableContent};
 
@@ -20,21 +18,29 @@ lazy_static! {
         = Mutex::new(HashMap::new());
 }
 
-pub fn registered_test_agent<S: Into<String>>(nick: S) -> AgentId {
-    let nick = nick.into();
-    // Create deterministic seed from nick:
+/// Creates a deterministic seed from the bytes of `seed_phrase`
+fn deterministic_seed(seed_phrase: &str) -> SecBuf {
     let mut seed = SecBuf::with_insecure(SEED_SIZE);
-    let nick_bytes = nick.as_bytes();
-    let seed_bytes: Vec<u8> = (1..SEED_SIZE).map(|num| {
-        if num <= nick_bytes.len(){
-            nick_bytes[num-1]
-        } else {
-            num as u8
-        }
-    }).collect();
+    let phrase_bytes = seed_phrase.as_bytes();
+    let seed_bytes: Vec<u8> = (1..SEED_SIZE)
+        .map(|num| {
+            if num <= phrase_bytes.len() {
+                phrase_bytes[num - 1]
+            } else {
+                num as u8
+            }
+        })
+        .collect();
 
     seed.write(0, seed_bytes.as_slice())
         .expect("SecBuf must be writeable");
+    seed
+}
+
+pub fn registered_test_agent<S: Into<String>>(nick: S) -> AgentId {
+    let nick = nick.into();
+    // Create deterministic seed from nick:
+    let mut seed = deterministic_seed(&nick);
 
     // Create KeyBundle from seed
     let keybundle = KeyBundle::new_from_seed_buf(&mut seed).unwrap();
@@ -72,6 +78,20 @@ pub fn mock_signer(payload: String, agent_id: &AgentId) -> String { TEST_AGENT_K
         .unwrap()
 }
 
+/// Signs `payload` with a key generated from the deterministic seed of `seed_phrase`,
+/// returning the address of that key and the signature.
+/// Unlike `Signature::fake()`, the signature passes verification, and unlike `mock_signer`
+/// no agent needs to be registered first, so tests can cover the happy path of checking
+/// provenances without a keystore or conductor. The same inputs always give the same result.
+pub fn mock_signature(seed_phrase: &str, payload: String) -> (Address, Signature) {
+    let mut seed = deterministic_seed(seed_phrase);
+    let mut keybundle = KeyBundle::new_from_seed_buf(&mut seed).unwrap();
+    let mut message = SecBuf::with_insecure_from_string(payload);
+    let mut signature_buf = keybundle.sign(&mut message).expect("Mock signing failed.");
+    let signature = Signature::from_bytes(&signature_buf.read_lock().to_vec());
+    (Address::from(keybundle.get_id()), signature)
+}
+
 /// This is a local mock for the `agent/encrypt` conductor API function.
 /// It creates a syntactically equivalent signature using dpki::Keypair
 /// but with key generated from a static/deterministic mock seed.